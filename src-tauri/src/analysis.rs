@@ -0,0 +1,272 @@
+//! Background analysis of imported media
+//!
+//! Everything the editor needs to show a new clip - its duration, a strip
+//! of thumbnails, a waveform, and where its keyframes are - is worked out
+//! as soon as the file is imported, with the four steps running side by
+//! side on the blocking pool. Each result is emitted as "media-analysis" the
+//! moment it's ready, so the clip fills in piece by piece instead of waiting
+//! on the slowest step, and "media-analysis-finished" follows the last one.
+//!
+//! Thumbnail strips and waveforms are kept in the shared cache, so opening
+//! the same file again skips straight to the results.
+
+use crate::cache::{self, CacheKind};
+use crate::error::{ClipFlowError, Result};
+use crate::output::PartialOutput;
+use crate::{probe, process, settings};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Semaphore;
+
+/// Frames in a thumbnail strip
+const STRIP_FRAMES: u32 = 10;
+
+/// Width of each frame in the strip; height follows the aspect ratio
+const STRIP_FRAME_WIDTH: u32 = 160;
+
+/// Peaks in a waveform, however long the file
+const WAVEFORM_POINTS: usize = 2000;
+
+/// Sample rate audio is decoded at for the waveform; peaks only need to be
+/// roughly right, and this keeps an hour of audio around 14MB in memory
+const WAVEFORM_SAMPLE_RATE: u32 = 2000;
+
+/// Files analyzed at once; each runs four ffmpeg/ffprobe processes, so a
+/// folder of imports shouldn't start them all together
+static FILE_SLOTS: Semaphore = Semaphore::const_new(2);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalysisStep {
+    Duration,
+    Thumbnails,
+    Waveform,
+    Keyframes,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AnalysisResult {
+    Duration {
+        seconds: f64,
+    },
+    /// One image with the frames side by side, evenly spaced through the file
+    Thumbnails {
+        path: String,
+        frames: u32,
+        frame_width: u32,
+    },
+    /// Peak levels from 0 to 1, evenly spaced through the file
+    Waveform {
+        peaks: Vec<f32>,
+    },
+    /// Keyframe timestamps of the first video stream, in seconds
+    Keyframes {
+        times: Vec<f64>,
+    },
+    Failed {
+        step: AnalysisStep,
+        error: String,
+    },
+}
+
+#[derive(Serialize, Clone)]
+struct AnalysisEvent {
+    path: String,
+    result: AnalysisResult,
+}
+
+#[derive(Serialize, Clone)]
+struct FinishedEvent {
+    path: String,
+    /// Steps that failed
+    failed: usize,
+}
+
+/// Thumbnail strip of `input_path`, from the cache or freshly rendered
+fn thumbnail_strip(app: &AppHandle, input_path: &str, key: &str) -> Result<AnalysisResult> {
+    let file_name = format!("{}.jpg", key);
+    let path = match cache::lookup(app, CacheKind::Thumbnails, &file_name)? {
+        Some(path) => path,
+        None => {
+            let duration = probe::media_duration(input_path)?;
+            if duration <= 0.0 {
+                return Err(ClipFlowError::invalid(format!("{} has no duration to take thumbnails from", input_path)));
+            }
+            let partial = PartialOutput::new(&cache::entry_path(app, CacheKind::Thumbnails, &file_name)?);
+            // Non-square pixels are resampled first so frames aren't squished
+            let square = probe::video_geometry(input_path)
+                .ok()
+                .and_then(|geometry| probe::square_pixel_filter(&geometry))
+                .map(|filter| format!("{},", filter))
+                .unwrap_or_default();
+            let filter = format!(
+                "fps={}/{:.3},{}scale={}:-2,tile={}x1",
+                STRIP_FRAMES, duration, square, STRIP_FRAME_WIDTH, STRIP_FRAMES
+            );
+            let partial_path = partial.path_str();
+            process::run_ffmpeg(
+                &[
+                    "-i", input_path,
+                    "-vf", filter.as_str(),
+                    "-frames:v", "1",
+                    "-q:v", "4",
+                    partial_path.as_str(),
+                    "-y",
+                ],
+                "thumbnail strip",
+            )?;
+            partial.commit()?;
+            cache::insert(app, CacheKind::Thumbnails, &file_name, input_path)?
+        }
+    };
+    Ok(AnalysisResult::Thumbnails {
+        path: path.to_string_lossy().into_owned(),
+        frames: STRIP_FRAMES,
+        frame_width: STRIP_FRAME_WIDTH,
+    })
+}
+
+/// Bucket 16-bit mono samples into at most `points` peaks scaled to 0..1
+fn peaks(samples: &[u8], points: usize) -> Vec<f32> {
+    let levels: Vec<u16> = samples
+        .chunks_exact(2)
+        .map(|s| i16::from_le_bytes([s[0], s[1]]).unsigned_abs())
+        .collect();
+    if levels.is_empty() {
+        return Vec::new();
+    }
+    let per_point = levels.len().div_ceil(points);
+    levels
+        .chunks(per_point)
+        .map(|chunk| *chunk.iter().max().unwrap_or(&0) as f32 / i16::MAX as f32)
+        .map(|peak| peak.min(1.0))
+        .collect()
+}
+
+/// Waveform peaks of `input_path`'s first audio track, from the cache or
+/// freshly decoded
+fn waveform(app: &AppHandle, input_path: &str, key: &str) -> Result<AnalysisResult> {
+    let file_name = format!("{}.json", key);
+    if let Some(path) = cache::lookup(app, CacheKind::Waveforms, &file_name)? {
+        let cached = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Vec<f32>>(&content).ok());
+        if let Some(peaks) = cached {
+            return Ok(AnalysisResult::Waveform { peaks });
+        }
+    }
+
+    let ffmpeg = settings::ffmpeg_bin();
+    let rate = WAVEFORM_SAMPLE_RATE.to_string();
+    let output = process::output(Command::new(&ffmpeg).args([
+        "-v", "error",
+        "-i", input_path,
+        "-map", "0:a:0",
+        "-ac", "1",
+        "-ar", rate.as_str(),
+        "-f", "s16le",
+        "-",
+    ]))
+    .map_err(|e| process::spawn_error(&ffmpeg, e))?;
+    process::check_ffmpeg(&output, "waveform")?;
+    let peaks = peaks(&output.stdout, WAVEFORM_POINTS);
+
+    let partial = PartialOutput::new(&cache::entry_path(app, CacheKind::Waveforms, &file_name)?);
+    let json = serde_json::to_string(&peaks).map_err(|e| ClipFlowError::io("Failed to serialize waveform", e))?;
+    fs::write(partial.path_str(), json).map_err(|e| ClipFlowError::io("Failed to write waveform", e))?;
+    partial.commit()?;
+    cache::insert(app, CacheKind::Waveforms, &file_name, input_path)?;
+    Ok(AnalysisResult::Waveform { peaks })
+}
+
+/// Keyframe timestamps, read from packet flags so nothing is decoded
+fn keyframes(input_path: &str) -> Result<AnalysisResult> {
+    let ffprobe = settings::ffprobe_bin();
+    let output = process::output(Command::new(&ffprobe).args([
+        "-v", "error",
+        "-select_streams", "v:0",
+        "-show_entries", "packet=pts_time,flags",
+        "-of", "csv=p=0",
+        input_path,
+    ]))
+    .map_err(|e| process::spawn_error(&ffprobe, e))?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(ClipFlowError::tool("ffprobe", format!("couldn't list keyframes: {}", error.trim())));
+    }
+    let mut times: Vec<f64> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (time, flags) = line.split_once(',')?;
+            if !flags.starts_with('K') {
+                return None;
+            }
+            time.parse().ok()
+        })
+        .collect();
+    // Packets come in decode order, which isn't presentation order with B-frames
+    times.sort_by(|a, b| a.total_cmp(b));
+    times.dedup();
+    Ok(AnalysisResult::Keyframes { times })
+}
+
+fn run_step(app: &AppHandle, input_path: &str, step: AnalysisStep) -> Result<AnalysisResult> {
+    match step {
+        AnalysisStep::Duration => Ok(AnalysisResult::Duration { seconds: probe::media_duration(input_path)? }),
+        AnalysisStep::Thumbnails => thumbnail_strip(app, input_path, &cache::source_key(Path::new(input_path))?),
+        AnalysisStep::Waveform => waveform(app, input_path, &cache::source_key(Path::new(input_path))?),
+        AnalysisStep::Keyframes => keyframes(input_path),
+    }
+}
+
+/// Run one step on the blocking pool and emit its result; true if it failed
+async fn emit_step(app: AppHandle, path: String, step: AnalysisStep) -> bool {
+    let (step_app, step_path) = (app.clone(), path.clone());
+    let result = tauri::async_runtime::spawn_blocking(move || run_step(&step_app, &step_path, step))
+        .await
+        .map_err(|e| ClipFlowError::io("Analysis thread failed", e))
+        .and_then(|result| result);
+    let result = result.unwrap_or_else(|e| {
+        tracing::warn!(file = %path, step = ?step, error = %e, "media analysis step failed");
+        AnalysisResult::Failed { step, error: e.to_string() }
+    });
+    let failed = matches!(result, AnalysisResult::Failed { .. });
+    let _ = app.emit("media-analysis", AnalysisEvent { path, result });
+    failed
+}
+
+async fn analyze(app: AppHandle, path: String) {
+    let _slot = FILE_SLOTS.acquire().await;
+    let results = tokio::join!(
+        emit_step(app.clone(), path.clone(), AnalysisStep::Duration),
+        emit_step(app.clone(), path.clone(), AnalysisStep::Thumbnails),
+        emit_step(app.clone(), path.clone(), AnalysisStep::Waveform),
+        emit_step(app.clone(), path.clone(), AnalysisStep::Keyframes),
+    );
+    let failed = [results.0, results.1, results.2, results.3].iter().filter(|f| **f).count();
+    tracing::info!(file = %path, failed, "media analysis finished");
+    let _ = app.emit("media-analysis-finished", FinishedEvent { path, failed });
+}
+
+/// Queue analysis of `paths` in the background
+pub fn start(app: &AppHandle, paths: Vec<String>) {
+    for path in paths {
+        tauri::async_runtime::spawn(analyze(app.clone(), path));
+    }
+}
+
+/// Re-run analysis on files already in the library, e.g. after the cache
+/// was cleared
+/// Results arrive as "media-analysis" and "media-analysis-finished" events.
+#[tauri::command]
+pub async fn analyze_media(app: AppHandle, paths: Vec<String>) -> Result<()> {
+    if let Some(missing) = paths.iter().find(|p| !Path::new(p).is_file()) {
+        return Err(ClipFlowError::not_found(format!("File not found: {}", missing)));
+    }
+    start(&app, paths);
+    Ok(())
+}
@@ -0,0 +1,483 @@
+//! Audio processing commands (fades, mixing, channel operations)
+
+use crate::error::{ClipFlowError, Result};
+use crate::output::{self, OverwritePolicy};
+use crate::{probe, process, settings, transitions};
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+/// Named voice-sweetening chains for spoken-word audio
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum VoicePolish {
+    /// Rumble cut, gentle de-essing and compression
+    Light,
+    /// Adds mud cut, presence lift, and firmer compression
+    Podcast,
+    /// Dense, loud, radio-style voice with a limiter on top
+    Broadcast,
+}
+
+impl VoicePolish {
+    /// Audio filter chain: high-pass, de-esser, EQ, compressor
+    pub fn filter(&self) -> &'static str {
+        match self {
+            VoicePolish::Light => concat!(
+                "highpass=f=80,",
+                "deesser=i=0.3,",
+                "acompressor=threshold=-20dB:ratio=2:attack=10:release=200"
+            ),
+            VoicePolish::Podcast => concat!(
+                "highpass=f=90,",
+                "deesser=i=0.4,",
+                "equalizer=f=250:t=q:w=1:g=-2,",
+                "equalizer=f=4000:t=q:w=1.5:g=3,",
+                "acompressor=threshold=-22dB:ratio=3:attack=5:release=150:makeup=2"
+            ),
+            VoicePolish::Broadcast => concat!(
+                "highpass=f=100,",
+                "deesser=i=0.5,",
+                "equalizer=f=200:t=q:w=1:g=-3,",
+                "equalizer=f=3500:t=q:w=1.5:g=4,",
+                "acompressor=threshold=-24dB:ratio=4:attack=3:release=120:makeup=3,",
+                "alimiter=limit=0.9"
+            ),
+        }
+    }
+}
+
+/// Fade the audio in at the start and/or out at the end; video is copied
+#[tauri::command]
+pub async fn fade_audio(
+    input_path: &str,
+    output_path: &str,
+    fade_in_s: f64,
+    fade_out_s: f64,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    if fade_in_s < 0.0 || fade_out_s < 0.0 {
+        return Err(ClipFlowError::invalid("Fade durations must not be negative"));
+    }
+
+    let duration = probe::media_duration(input_path)?;
+    if fade_in_s + fade_out_s > duration {
+        return Err(ClipFlowError::invalid(format!(
+            "Fades ({}s + {}s) are longer than the clip ({:.2}s)",
+            fade_in_s, fade_out_s, duration
+        )));
+    }
+    if probe::audio_tracks(input_path)?.is_empty() {
+        return Err(ClipFlowError::invalid("Input has no audio to fade"));
+    }
+
+    let mut filters = Vec::new();
+    if fade_in_s > 0.0 {
+        filters.push(format!("afade=t=in:st=0:d={}", fade_in_s));
+    }
+    if fade_out_s > 0.0 {
+        filters.push(format!("afade=t=out:st={}:d={}", duration - fade_out_s, fade_out_s));
+    }
+    if filters.is_empty() {
+        return Err(ClipFlowError::invalid("No fade requested"));
+    }
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-af".to_string(), filters.join(","),
+        "-c:v".to_string(), "copy".to_string(),
+    ];
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "audio fade").await
+    })
+    .await
+}
+
+/// Input args for a separately recorded audio file, shifted by `offset`
+/// seconds (positive delays the audio, negative skips into it)
+fn offset_audio_input(audio_input: &str, offset: f64) -> Vec<String> {
+    let mut args = Vec::new();
+    if offset > 0.0 {
+        args.push("-itsoffset".to_string());
+        args.push(offset.to_string());
+    } else if offset < 0.0 {
+        args.push("-ss".to_string());
+        args.push((-offset).to_string());
+    }
+    args.push("-i".to_string());
+    args.push(audio_input.to_string());
+    args
+}
+
+/// Swap a video's audio for an external recording, e.g. a cleaned-up mix
+/// `offset` (seconds) corrects sync; the output keeps the video's length
+#[tauri::command]
+pub async fn replace_audio(
+    video_input: &str,
+    audio_input: &str,
+    output_path: &str,
+    offset: f64,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let duration = probe::media_duration(video_input)?;
+    if probe::audio_tracks(audio_input)?.is_empty() {
+        return Err(ClipFlowError::invalid("Audio file has no audio stream"));
+    }
+
+    let mut args: Vec<String> = vec!["-i".to_string(), video_input.to_string()];
+    args.extend(offset_audio_input(audio_input, offset));
+    args.extend([
+        "-map".to_string(), "0:v".to_string(),
+        "-map".to_string(), "1:a:0".to_string(),
+        "-c:v".to_string(), "copy".to_string(),
+        "-c:a".to_string(), "aac".to_string(),
+        "-b:a".to_string(), "192k".to_string(),
+        // A delayed track would otherwise start with nothing; pad it with silence
+        "-af".to_string(), "apad".to_string(),
+        "-t".to_string(), duration.to_string(),
+    ]);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "audio replace").await
+    })
+    .await
+}
+
+/// Add an external recording as an extra audio track, keeping the originals
+#[tauri::command]
+pub async fn add_audio_track(
+    video_input: &str,
+    audio_input: &str,
+    output_path: &str,
+    offset: f64,
+    title: Option<String>,
+    language: Option<String>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let duration = probe::media_duration(video_input)?;
+    let existing = probe::audio_tracks(video_input)?.len();
+    if probe::audio_tracks(audio_input)?.is_empty() {
+        return Err(ClipFlowError::invalid("Audio file has no audio stream"));
+    }
+
+    let new_track = format!("a:{}", existing);
+    let mut args: Vec<String> = vec!["-i".to_string(), video_input.to_string()];
+    args.extend(offset_audio_input(audio_input, offset));
+    args.extend([
+        "-map".to_string(), "0:v".to_string(),
+        "-map".to_string(), "0:a?".to_string(),
+        "-map".to_string(), "1:a:0".to_string(),
+        "-c".to_string(), "copy".to_string(),
+        format!("-c:{}", new_track), "aac".to_string(),
+        format!("-b:{}", new_track), "192k".to_string(),
+        format!("-filter:{}", new_track), "apad".to_string(),
+        "-t".to_string(), duration.to_string(),
+    ]);
+    if let Some(title) = title {
+        args.push(format!("-metadata:s:{}", new_track));
+        args.push(format!("title={}", title));
+    }
+    if let Some(language) = language {
+        args.push(format!("-metadata:s:{}", new_track));
+        args.push(format!("language={}", language));
+    }
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "add audio track").await
+    })
+    .await
+}
+
+/// Mix a music bed under the video's own audio
+///
+/// The music loops to cover the whole video and is set to `music_gain_db`.
+/// With `duck`, a sidechain compressor keyed on the voice track pulls the
+/// music down whenever someone is speaking.
+#[tauri::command]
+pub async fn mix_music(
+    input_path: &str,
+    music_path: &str,
+    output_path: &str,
+    music_gain_db: f64,
+    duck: bool,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    if probe::audio_tracks(music_path)?.is_empty() {
+        return Err(ClipFlowError::invalid("Music file has no audio stream"));
+    }
+    let has_voice = !probe::audio_tracks(input_path)?.is_empty();
+    if duck && !has_voice {
+        return Err(ClipFlowError::invalid("Ducking needs a voice track, but the input has no audio"));
+    }
+
+    let music = format!("[1:a]{},volume={}dB[music]", transitions::normalize_audio_filter(), music_gain_db);
+    let graph = if !has_voice {
+        format!("{};[music]anull[outa]", music)
+    } else if duck {
+        format!(
+            "{};[0:a]{},asplit=2[voice][key];\
+             [music][key]sidechaincompress=threshold=0.03:ratio=8:attack=20:release=400[ducked];\
+             [voice][ducked]amix=inputs=2:duration=first:normalize=0[outa]",
+            music,
+            transitions::normalize_audio_filter()
+        )
+    } else {
+        format!(
+            "{};[0:a]{}[voice];[voice][music]amix=inputs=2:duration=first:normalize=0[outa]",
+            music,
+            transitions::normalize_audio_filter()
+        )
+    };
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-stream_loop".to_string(), "-1".to_string(),
+        "-i".to_string(), music_path.to_string(),
+        "-filter_complex".to_string(), graph,
+        "-map".to_string(), "0:v?".to_string(),
+        "-map".to_string(), "[outa]".to_string(),
+        "-c:v".to_string(), "copy".to_string(),
+        "-c:a".to_string(), "aac".to_string(),
+        "-b:a".to_string(), "192k".to_string(),
+        // The looped music never ends on its own
+        "-t".to_string(), probe::media_duration(input_path)?.to_string(),
+    ];
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "music mix").await
+    })
+    .await
+}
+
+/// Look up an audio track by its index among the file's audio streams
+fn find_audio_track(input_path: &str, audio_track: u32) -> Result<probe::AudioTrack> {
+    let tracks = probe::audio_tracks(input_path)?;
+    let count = tracks.len();
+    tracks
+        .into_iter()
+        .nth(audio_track as usize)
+        .ok_or_else(|| ClipFlowError::invalid(format!("Audio track {} doesn't exist (file has {})", audio_track, count)))
+}
+
+fn require_stereo(track: &probe::AudioTrack) -> Result<()> {
+    if track.channels != 2 {
+        return Err(ClipFlowError::invalid(format!("Audio track {} has {} channels, expected stereo", track.audio_index, track.channels)));
+    }
+    Ok(())
+}
+
+/// Fold an audio track down to a single channel; video is copied
+#[tauri::command]
+pub async fn downmix_to_mono(
+    input_path: &str,
+    output_path: &str,
+    audio_track: Option<u32>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let track = find_audio_track(input_path, audio_track.unwrap_or(0))?;
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-map".to_string(), "0:v?".to_string(),
+        "-map".to_string(), format!("0:a:{}", track.audio_index),
+        "-c:v".to_string(), "copy".to_string(),
+        "-ac".to_string(), "1".to_string(),
+    ];
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "mono downmix").await
+    })
+    .await
+}
+
+/// Split a stereo track into two mono files, e.g. a two-mic interview
+/// recorded one speaker per channel
+/// Returns the left and right paths written.
+#[tauri::command]
+pub async fn split_stereo(
+    input_path: &str,
+    left_output: &str,
+    right_output: &str,
+    audio_track: Option<u32>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<Vec<String>> {
+    let track = find_audio_track(input_path, audio_track.unwrap_or(0))?;
+    require_stereo(&track)?;
+
+    let policy = overwrite.unwrap_or_default();
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-filter_complex".to_string(),
+        format!("[0:a:{}]channelsplit=channel_layout=stereo[left][right]", track.audio_index),
+        "-map".to_string(), "[left]".to_string(),
+    ];
+
+    // Both files are partials until ffmpeg finishes; the right one is moved
+    // into place first, inside the left one's write
+    let mut right_written = String::new();
+    let right = &mut right_written;
+    let left = output::write_atomically_async(left_output, policy, |left_partial| async move {
+        *right = output::write_atomically_async(right_output, policy, |right_partial| async move {
+            args.push(left_partial);
+            args.extend(["-map".to_string(), "[right]".to_string(), right_partial, "-y".to_string()]);
+            process::run_ffmpeg_async(&args, "stereo split").await
+        })
+        .await?;
+        Ok(())
+    })
+    .await?;
+    Ok(vec![left, right_written])
+}
+
+/// Swap the left and right channels of a stereo track; video is copied
+#[tauri::command]
+pub async fn swap_channels(
+    input_path: &str,
+    output_path: &str,
+    audio_track: Option<u32>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let track = find_audio_track(input_path, audio_track.unwrap_or(0))?;
+    require_stereo(&track)?;
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-map".to_string(), "0:v?".to_string(),
+        "-map".to_string(), format!("0:a:{}", track.audio_index),
+        "-c:v".to_string(), "copy".to_string(),
+        "-af".to_string(), "pan=stereo|c0=c1|c1=c0".to_string(),
+    ];
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "channel swap").await
+    })
+    .await
+}
+
+/// Keep only one audio track of a multi-track recording (e.g. just the mic
+/// from an OBS capture with desktop audio on another track)
+#[tauri::command]
+pub async fn select_audio_track(
+    input_path: &str,
+    output_path: &str,
+    audio_track: u32,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let track = find_audio_track(input_path, audio_track)?;
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-map".to_string(), "0:v?".to_string(),
+        "-map".to_string(), format!("0:a:{}", track.audio_index),
+        "-c".to_string(), "copy".to_string(),
+    ];
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "audio track selection").await
+    })
+    .await
+}
+
+/// Filter chain (for `[0:a]`) producing `duration` seconds of room tone by
+/// looping the quiet region [tone_start, tone_end] of the source
+pub fn room_tone_filter(tone_start: f64, tone_end: f64, duration: f64, sample_rate: u32) -> String {
+    let loop_samples = ((tone_end - tone_start) * sample_rate as f64).round().max(1.0) as u64;
+    format!(
+        "atrim=start={}:end={},asetpts=PTS-STARTPTS,aloop=loop=-1:size={},atrim=duration={},asetpts=PTS-STARTPTS",
+        tone_start, tone_end, loop_samples, duration
+    )
+}
+
+/// Loudness target for the two-pass loudnorm filter
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct LoudnessTarget {
+    /// Integrated loudness, LUFS
+    pub integrated: f64,
+    /// Max true peak, dBTP
+    pub true_peak: f64,
+    /// Loudness range, LU
+    pub range: f64,
+}
+
+impl LoudnessTarget {
+    /// -16 LUFS / -1.5 dBTP, what Apple Podcasts and Spotify recommend for
+    /// spoken word
+    pub const PODCAST: LoudnessTarget = LoudnessTarget { integrated: -16.0, true_peak: -1.5, range: 11.0 };
+}
+
+/// First-pass loudnorm measurement of a file
+#[derive(Serialize, Clone, Debug)]
+pub struct LoudnessMeasurement {
+    pub input_i: f64,
+    pub input_tp: f64,
+    pub input_lra: f64,
+    pub input_thresh: f64,
+    pub target_offset: f64,
+}
+
+/// Measure a file's loudness with loudnorm's analysis pass
+/// loudnorm prints its stats as a JSON block at the end of stderr, with
+/// the numbers as strings.
+pub async fn measure_loudness(input_path: &str, target: LoudnessTarget) -> Result<LoudnessMeasurement> {
+    let ffmpeg = settings::ffmpeg_bin();
+    let output = process::run(Command::new(&ffmpeg).args([
+        "-hide_banner",
+        "-i", input_path,
+        "-vn",
+        "-af", &format!("loudnorm=I={}:TP={}:LRA={}:print_format=json", target.integrated, target.true_peak, target.range),
+        "-f", "null",
+        "-",
+    ]), None, |_| {})
+    .await
+    .map_err(|e| process::spawn_error(&ffmpeg, e))?;
+    process::check_ffmpeg(&output, "loudness measurement")?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stats: serde_json::Value = stderr
+        .rfind('{')
+        .and_then(|start| serde_json::from_str(&stderr[start..]).ok())
+        .ok_or_else(|| ClipFlowError::tool("loudnorm", "no loudness stats in ffmpeg output"))?;
+    let field = |name: &str| -> Result<f64> {
+        stats[name]
+            .as_str()
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| ClipFlowError::tool("loudnorm", format!("missing or silent {} in loudness stats", name)))
+    };
+    Ok(LoudnessMeasurement {
+        input_i: field("input_i")?,
+        input_tp: field("input_tp")?,
+        input_lra: field("input_lra")?,
+        input_thresh: field("input_thresh")?,
+        target_offset: field("target_offset")?,
+    })
+}
+
+/// Second-pass loudnorm filter applying `measured` to hit `target`
+/// Linear mode keeps the dynamics intact, unlike loudnorm's one-pass guess.
+pub fn loudnorm_filter(target: LoudnessTarget, measured: &LoudnessMeasurement) -> String {
+    format!(
+        "loudnorm=I={}:TP={}:LRA={}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true",
+        target.integrated,
+        target.true_peak,
+        target.range,
+        measured.input_i,
+        measured.input_tp,
+        measured.input_lra,
+        measured.input_thresh,
+        measured.target_offset
+    )
+}
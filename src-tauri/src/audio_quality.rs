@@ -0,0 +1,147 @@
+//! Recording health checks for the audio of a file
+//!
+//! Catches the problems that can't be fixed in the edit - clipping, a mic
+//! with a DC bias, audio that cuts out - so a bad take is noticed before
+//! any time goes into cutting it. Sample statistics and dropouts come from
+//! one decode (astats and silencedetect), loudness from a loudnorm pass run
+//! alongside it.
+
+use crate::audio::{self, LoudnessTarget};
+use crate::error::{ClipFlowError, Result};
+use crate::{parse_silence_output, probe, process, settings};
+use serde::Serialize;
+use tokio::process::Command;
+
+/// Level below which audio counts as cut out rather than quiet; real room
+/// tone sits well above this
+const DROPOUT_LEVEL_DB: f64 = -80.0;
+
+/// Shortest gap reported as a dropout
+const DROPOUT_MIN_SECONDS: f64 = 0.5;
+
+/// Peak level at which samples are treated as clipped
+const CLIP_LEVEL_DB: f64 = -0.01;
+
+/// Share of clipped samples worth warning about
+const CLIPPED_WARN_PERCENT: f64 = 0.01;
+
+/// |DC offset| worth warning about, as a fraction of full scale
+const DC_OFFSET_WARN: f64 = 0.01;
+
+#[derive(Serialize, Clone)]
+pub struct Dropout {
+    pub start: f64,
+    pub end: f64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct AudioQualityReport {
+    pub clipped_samples: u64,
+    pub clipped_percent: f64,
+    /// dBTP
+    pub true_peak: f64,
+    /// LUFS
+    pub integrated_loudness: f64,
+    /// Mean sample value as a fraction of full scale, ideally 0
+    pub dc_offset: f64,
+    pub dropouts: Vec<Dropout>,
+    /// Plain-language problems found, empty for a clean recording
+    pub warnings: Vec<String>,
+}
+
+/// Overall sample statistics from astats' summary
+#[derive(Default)]
+struct SampleStats {
+    dc_offset: f64,
+    peak_db: f64,
+    peak_count: u64,
+    samples: u64,
+}
+
+/// Read astats' "Overall" block; the per-channel blocks before it use the
+/// same keys
+fn parse_astats(stderr: &str) -> SampleStats {
+    let mut stats = SampleStats::default();
+    let overall = match stderr.rfind("] Overall") {
+        Some(at) => &stderr[at..],
+        None => return stats,
+    };
+    for line in overall.lines() {
+        let (key, value) = match line.split_once("] ").and_then(|(_, rest)| rest.split_once(": ")) {
+            Some(pair) => pair,
+            None => continue,
+        };
+        let value = value.trim();
+        match key.trim() {
+            "DC offset" => stats.dc_offset = value.parse().unwrap_or(0.0),
+            "Peak level dB" => stats.peak_db = value.parse().unwrap_or(f64::NEG_INFINITY),
+            "Peak count" => stats.peak_count = value.parse::<f64>().unwrap_or(0.0) as u64,
+            "Number of samples" => stats.samples = value.parse::<f64>().unwrap_or(0.0) as u64,
+            _ => {}
+        }
+    }
+    stats
+}
+
+async fn sample_stats(file_path: &str) -> Result<(SampleStats, Vec<Dropout>)> {
+    let ffmpeg = settings::ffmpeg_bin();
+    let output = process::run(Command::new(&ffmpeg).args([
+        "-hide_banner",
+        "-i", file_path,
+        "-map", "0:a:0",
+        "-af", &format!("astats=measure_perchannel=none,silencedetect=noise={}dB:d={}", DROPOUT_LEVEL_DB, DROPOUT_MIN_SECONDS),
+        "-f", "null",
+        "-",
+    ]), None, |_| {})
+    .await
+    .map_err(|e| process::spawn_error(&ffmpeg, e))?;
+    process::check_ffmpeg(&output, "audio statistics")?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let dropouts = parse_silence_output(&stderr)
+        .into_iter()
+        .map(|s| Dropout { start: s.start, end: s.end })
+        .collect();
+    Ok((parse_astats(&stderr), dropouts))
+}
+
+/// Check a recording for clipping, peaks, loudness, DC offset, and dropouts
+#[tauri::command]
+pub async fn analyze_audio_quality(file_path: &str) -> Result<AudioQualityReport> {
+    if probe::audio_tracks(file_path)?.is_empty() {
+        return Err(ClipFlowError::invalid("Input has no audio to check"));
+    }
+    let ((stats, dropouts), loudness) =
+        tokio::try_join!(sample_stats(file_path), audio::measure_loudness(file_path, LoudnessTarget::PODCAST))?;
+
+    // astats counts how often the peak was hit; only a full-scale peak means clipping
+    let clipped_samples = if stats.peak_db >= CLIP_LEVEL_DB { stats.peak_count } else { 0 };
+    let clipped_percent = if stats.samples == 0 { 0.0 } else { clipped_samples as f64 * 100.0 / stats.samples as f64 };
+
+    let mut warnings = Vec::new();
+    if clipped_percent >= CLIPPED_WARN_PERCENT {
+        warnings.push(format!("{} samples are clipped ({:.3}%) - the input gain was too high", clipped_samples, clipped_percent));
+    }
+    if loudness.input_tp > 0.0 {
+        warnings.push(format!("True peak is {:.1} dBTP, above full scale; expect distortion after encoding", loudness.input_tp));
+    }
+    if stats.dc_offset.abs() >= DC_OFFSET_WARN {
+        warnings.push(format!("DC offset of {:.3} - the mic or interface may be faulty", stats.dc_offset));
+    }
+    if loudness.input_i < -35.0 {
+        warnings.push(format!("Very quiet at {:.1} LUFS; raising it will bring up noise too", loudness.input_i));
+    }
+    if !dropouts.is_empty() {
+        warnings.push(format!("Audio cuts out {} time(s)", dropouts.len()));
+    }
+
+    Ok(AudioQualityReport {
+        clipped_samples,
+        clipped_percent,
+        true_peak: loudness.input_tp,
+        integrated_loudness: loudness.input_i,
+        dc_offset: stats.dc_offset,
+        dropouts,
+        warnings,
+    })
+}
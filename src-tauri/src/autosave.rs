@@ -0,0 +1,157 @@
+//! Periodic autosave of the open project into the app data dir, so work
+//! survives a crash of the webview or the whole app
+
+use crate::error::{ClipFlowError, Result};
+use crate::project::{self, Project};
+use crate::settings;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, State};
+
+/// How often the open project is written out if it changed
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Number of recovery points kept before the oldest are deleted
+const MAX_RECOVERY_POINTS: usize = 10;
+
+const RECOVERY_PREFIX: &str = "autosave-";
+
+/// Latest project state pushed by the frontend, waiting to be autosaved
+#[derive(Default)]
+pub struct AutosaveState {
+    inner: Mutex<PendingProject>,
+}
+
+#[derive(Default)]
+struct PendingProject {
+    project: Option<Project>,
+    dirty: bool,
+}
+
+#[derive(Serialize)]
+pub struct RecoveryPoint {
+    id: String,
+    created_at: u64,
+    project_name: String,
+    clip_count: usize,
+    size_bytes: u64,
+}
+
+fn recovery_dir(app: &AppHandle) -> Result<PathBuf> {
+    settings::data_dir(app, "recovery")
+}
+
+/// Recovery point files, newest first
+fn recovery_files(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut files: Vec<(String, PathBuf)> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter_map(|path| {
+                    let stem = path.file_stem()?.to_str()?.to_string();
+                    if stem.starts_with(RECOVERY_PREFIX) && path.extension()? == "json" {
+                        Some((stem, path))
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Ids embed a millisecond timestamp, so sorting by id sorts by age
+    files.sort_by(|a, b| b.0.cmp(&a.0));
+    files
+}
+
+/// Write the pending project to a new recovery point if it changed
+fn autosave_now(app: &AppHandle) -> Result<()> {
+    let project = {
+        let state = app.state::<AutosaveState>();
+        let mut pending = state.inner.lock().unwrap();
+        if !pending.dirty {
+            return Ok(());
+        }
+        pending.dirty = false;
+        match &pending.project {
+            Some(project) => project.clone(),
+            None => return Ok(()),
+        }
+    };
+
+    let dir = recovery_dir(app)?;
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let path = dir.join(format!("{}{:013}.json", RECOVERY_PREFIX, millis));
+    project::write_project(&path, &project)?;
+
+    // Rotate: keep only the newest N recovery points
+    for (_, old) in recovery_files(&dir).into_iter().skip(MAX_RECOVERY_POINTS) {
+        let _ = fs::remove_file(old);
+    }
+
+    Ok(())
+}
+
+/// Start the background autosave loop
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(AUTOSAVE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = autosave_now(&app) {
+                tracing::warn!(error = %e, "autosave failed");
+            }
+        }
+    });
+}
+
+/// Called by the frontend whenever the editing state changes
+#[tauri::command]
+pub async fn update_autosave_project(state: State<'_, AutosaveState>, project: Project) -> Result<()> {
+    let mut pending = state.inner.lock().unwrap();
+    pending.project = Some(project);
+    pending.dirty = true;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_recovery_points(app: AppHandle) -> Result<Vec<RecoveryPoint>> {
+    let dir = recovery_dir(&app)?;
+
+    Ok(recovery_files(&dir)
+        .into_iter()
+        .filter_map(|(id, path)| {
+            let project = project::read_project(&path).ok()?;
+            let size_bytes = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            let created_at = id[RECOVERY_PREFIX.len()..].parse::<u64>().unwrap_or(0) / 1000;
+            Some(RecoveryPoint {
+                id,
+                created_at,
+                project_name: project.name,
+                clip_count: project.clips.len(),
+                size_bytes,
+            })
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn restore_recovery_point(app: AppHandle, id: &str) -> Result<Project> {
+    if !id.starts_with(RECOVERY_PREFIX) || id.contains(['/', '\\', '.']) {
+        return Err(ClipFlowError::invalid(format!("Invalid recovery point id: {}", id)));
+    }
+
+    let path = recovery_dir(&app)?.join(format!("{}.json", id));
+    if !path.exists() {
+        return Err(ClipFlowError::not_found(format!("Recovery point not found: {}", id)));
+    }
+
+    project::read_project(&path)
+}
@@ -0,0 +1,233 @@
+//! Disk cache for derived media: proxies, thumbnails, waveforms, and
+//! transcription output
+//!
+//! Everything in here can be regenerated from its source, so the cache is
+//! capped (`cache_limit_mb` in settings) and the least recently used entries
+//! are evicted whenever a new one pushes it over. Entries are plain files
+//! under `<app cache dir>/<kind>/`, tracked in a single index recording
+//! their size and last use.
+
+use crate::error::{ClipFlowError, Result};
+use crate::{now_secs, settings};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+/// Bytes sampled from each of the start, middle, and end of a source for
+/// its content key
+const KEY_SAMPLE_BYTES: u64 = 1024 * 1024;
+
+/// Serializes read-modify-write cycles on the cache index
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheKind {
+    Proxies,
+    Thumbnails,
+    Waveforms,
+    Transcriptions,
+}
+
+impl CacheKind {
+    const ALL: [CacheKind; 4] = [CacheKind::Proxies, CacheKind::Thumbnails, CacheKind::Waveforms, CacheKind::Transcriptions];
+
+    fn dir_name(&self) -> &'static str {
+        match self {
+            CacheKind::Proxies => "proxies",
+            CacheKind::Thumbnails => "thumbnails",
+            CacheKind::Waveforms => "waveforms",
+            CacheKind::Transcriptions => "transcriptions",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CacheEntry {
+    kind: CacheKind,
+    file_name: String,
+    /// Source the entry was derived from; informational only
+    source_path: String,
+    size_bytes: u64,
+    created_at: u64,
+    last_used: u64,
+}
+
+#[derive(Serialize)]
+pub struct KindStats {
+    pub kind: CacheKind,
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+#[derive(Serialize)]
+pub struct CacheStats {
+    pub path: String,
+    pub limit_bytes: u64,
+    pub total_bytes: u64,
+    pub kinds: Vec<KindStats>,
+}
+
+fn limit_bytes() -> u64 {
+    settings::current().cache_limit_mb * 1024 * 1024
+}
+
+fn cache_root(app: &AppHandle) -> Result<PathBuf> {
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| ClipFlowError::io("Failed to resolve cache dir", e))?;
+    fs::create_dir_all(&dir).map_err(|e| ClipFlowError::io("Failed to create cache dir", e))?;
+    Ok(dir)
+}
+
+fn entry_file(root: &Path, entry: &CacheEntry) -> PathBuf {
+    root.join(entry.kind.dir_name()).join(&entry.file_name)
+}
+
+fn read_index(root: &Path) -> Vec<CacheEntry> {
+    fs::read_to_string(root.join("cache_index.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_index(root: &Path, entries: &[CacheEntry]) -> Result<()> {
+    let json = serde_json::to_string_pretty(entries).map_err(|e| ClipFlowError::io("Failed to serialize cache index", e))?;
+    let tmp = root.join("cache_index.json.tmp");
+    fs::write(&tmp, json).map_err(|e| ClipFlowError::io("Failed to write cache index", e))?;
+    fs::rename(&tmp, root.join("cache_index.json")).map_err(|e| ClipFlowError::io("Failed to save cache index", e))
+}
+
+/// Content key for a source: BLAKE3 over its size and samples from the
+/// start, middle, and end
+/// Cheap enough to compute on every lookup; hashing whole camera files
+/// would take longer than most of what gets cached for them.
+pub fn source_key(path: &Path) -> Result<String> {
+    let context = || format!("Failed to read {}", path.display());
+    let mut file = File::open(path).map_err(|e| ClipFlowError::io(&context(), e))?;
+    let size = file.metadata().map_err(|e| ClipFlowError::io(&context(), e))?.len();
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&size.to_le_bytes());
+    let mut buffer = vec![0u8; KEY_SAMPLE_BYTES as usize];
+    for offset in [0, size.saturating_sub(KEY_SAMPLE_BYTES) / 2, size.saturating_sub(KEY_SAMPLE_BYTES)] {
+        file.seek(SeekFrom::Start(offset)).map_err(|e| ClipFlowError::io(&context(), e))?;
+        let n = file.read(&mut buffer).map_err(|e| ClipFlowError::io(&context(), e))?;
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher.finalize().to_hex()[..32].to_string())
+}
+
+/// Path of a cached file, if there is one, marking it as just used
+pub fn lookup(app: &AppHandle, kind: CacheKind, file_name: &str) -> Result<Option<PathBuf>> {
+    let root = cache_root(app)?;
+    let _guard = INDEX_LOCK.lock().unwrap();
+    let mut entries = read_index(&root);
+    let position = match entries.iter().position(|e| e.kind == kind && e.file_name == file_name) {
+        Some(position) => position,
+        None => return Ok(None),
+    };
+    let path = entry_file(&root, &entries[position]);
+    if path.is_file() {
+        entries[position].last_used = now_secs();
+    } else {
+        // Deleted behind our back
+        entries.remove(position);
+    }
+    write_index(&root, &entries)?;
+    Ok(path.is_file().then_some(path))
+}
+
+/// Where to write a new entry; `insert` it once the file is complete
+pub fn entry_path(app: &AppHandle, kind: CacheKind, file_name: &str) -> Result<PathBuf> {
+    let dir = cache_root(app)?.join(kind.dir_name());
+    fs::create_dir_all(&dir).map_err(|e| ClipFlowError::io("Failed to create cache dir", e))?;
+    Ok(dir.join(file_name))
+}
+
+/// Record a file written at `entry_path`, then evict least recently used
+/// entries until the cache is back under its limit
+/// The new entry itself is never evicted, even if it alone is over the limit.
+pub fn insert(app: &AppHandle, kind: CacheKind, file_name: &str, source_path: &str) -> Result<PathBuf> {
+    let root = cache_root(app)?;
+    let path = root.join(kind.dir_name()).join(file_name);
+    let size_bytes = fs::metadata(&path)
+        .map_err(|e| ClipFlowError::io(&format!("Cache entry {} wasn't written", path.display()), e))?
+        .len();
+
+    let _guard = INDEX_LOCK.lock().unwrap();
+    let mut entries = read_index(&root);
+    entries.retain(|e| !(e.kind == kind && e.file_name == file_name));
+    let now = now_secs();
+
+    let limit = limit_bytes();
+    let mut total: u64 = entries.iter().map(|e| e.size_bytes).sum::<u64>() + size_bytes;
+    entries.sort_by_key(|e| e.last_used);
+    let mut kept = Vec::with_capacity(entries.len() + 1);
+    for entry in entries {
+        if total > limit {
+            let _ = fs::remove_file(entry_file(&root, &entry));
+            total -= entry.size_bytes;
+            tracing::debug!(kind = ?entry.kind, file = %entry.file_name, "evicted cache entry");
+        } else {
+            kept.push(entry);
+        }
+    }
+    kept.push(CacheEntry {
+        kind,
+        file_name: file_name.to_string(),
+        source_path: source_path.to_string(),
+        size_bytes,
+        created_at: now,
+        last_used: now,
+    });
+    write_index(&root, &kept)?;
+    Ok(path)
+}
+
+#[tauri::command]
+pub async fn get_cache_stats(app: AppHandle) -> Result<CacheStats> {
+    let root = cache_root(&app)?;
+    let entries = {
+        let _guard = INDEX_LOCK.lock().unwrap();
+        read_index(&root)
+    };
+    let kinds: Vec<KindStats> = CacheKind::ALL
+        .iter()
+        .map(|kind| {
+            let of_kind = entries.iter().filter(|e| e.kind == *kind);
+            KindStats {
+                kind: *kind,
+                entries: of_kind.clone().count(),
+                bytes: of_kind.map(|e| e.size_bytes).sum(),
+            }
+        })
+        .collect();
+    Ok(CacheStats {
+        path: root.to_string_lossy().into_owned(),
+        limit_bytes: limit_bytes(),
+        total_bytes: kinds.iter().map(|k| k.bytes).sum(),
+        kinds,
+    })
+}
+
+/// Delete cached files of one kind, or everything; returns bytes freed
+#[tauri::command]
+pub async fn clear_cache(app: AppHandle, kind: Option<CacheKind>) -> Result<u64> {
+    let root = cache_root(&app)?;
+    let _guard = INDEX_LOCK.lock().unwrap();
+    let (cleared, kept): (Vec<CacheEntry>, Vec<CacheEntry>) =
+        read_index(&root).into_iter().partition(|e| kind.map(|k| e.kind == k).unwrap_or(true));
+    let mut freed = 0;
+    for entry in &cleared {
+        if fs::remove_file(entry_file(&root, entry)).is_ok() {
+            freed += entry.size_bytes;
+        }
+    }
+    write_index(&root, &kept)?;
+    tracing::info!(kind = ?kind, freed_bytes = freed, "cleared cache");
+    Ok(freed)
+}
@@ -0,0 +1,229 @@
+//! Transcript to caption file conversion (SRT/WebVTT) with line breaking
+//! that follows the conventions of the transcript's language
+//!
+//! Space-separated languages wrap between words and prefer to break after
+//! punctuation. Chinese and Japanese have no spaces, so they wrap between
+//! characters, but never start a line with closing punctuation or a
+//! grammatical particle, which readers find jarring.
+//!
+//! Diarized transcripts can mark who's speaking: SRT cues are colored per
+//! speaker, and WebVTT cues get a voice span naming them.
+
+use crate::error::{ClipFlowError, Result};
+use crate::format_srt_timestamp;
+use crate::project::Transcript;
+use crate::transcripts;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct CaptionOptions {
+    /// Characters per line; defaults to 42, or 16 for Chinese/Japanese
+    pub max_chars_per_line: Option<usize>,
+    /// Lines per cue, 2 unless set
+    pub max_lines: Option<usize>,
+    /// Mark each cue with its speaker, when the transcript has them
+    pub speaker_colors: bool,
+}
+
+/// Caption colors handed out to speakers in order of first appearance
+const SPEAKER_COLORS: [&str; 6] = ["#FFFFFF", "#FFE066", "#7FDBFF", "#FF9F80", "#A5E887", "#D9A5FF"];
+
+/// Characters that must not start a line (closing punctuation, small kana)
+const NO_LINE_START: &str = "、。，．・：；？！)）]」』】〕〉》”’ゃゅょっぁぃぅぇぉャュョッァィゥェォー々";
+
+/// Characters that must not end a line (opening punctuation)
+const NO_LINE_END: &str = "(（[「『【〔〈《“‘";
+
+/// Single-character particles that belong to the preceding word
+const JA_PARTICLES: &str = "はがをにでとのもへやね";
+const ZH_PARTICLES: &str = "的了吗呢吧啊着过地得";
+
+/// Punctuation that makes a good place to break a line or cue
+const BREAK_AFTER: &str = ".,;:?!、。，；：？！";
+
+fn is_unspaced(language: &str) -> bool {
+    let lang = language.to_ascii_lowercase();
+    lang.starts_with("ja") || lang.starts_with("zh")
+}
+
+fn particles_for(language: &str) -> &'static str {
+    if language.to_ascii_lowercase().starts_with("ja") {
+        JA_PARTICLES
+    } else {
+        ZH_PARTICLES
+    }
+}
+
+/// Split text into unbreakable units: words for spaced languages, and for
+/// unspaced ones single characters glued to their neighbours where a break
+/// would be wrong
+fn units(text: &str, language: &str) -> Vec<String> {
+    if !is_unspaced(language) {
+        return text.split_whitespace().map(|w| w.to_string()).collect();
+    }
+
+    let particles = particles_for(language);
+    let mut units: Vec<String> = Vec::new();
+    let mut glue_next = false;
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        let attach = NO_LINE_START.contains(c) || particles.contains(c);
+        match units.last_mut() {
+            Some(last) if glue_next || attach => last.push(c),
+            _ => units.push(c.to_string()),
+        }
+        glue_next = NO_LINE_END.contains(c);
+    }
+    units
+}
+
+fn width(s: &str) -> usize {
+    s.chars().count()
+}
+
+fn ends_with_break(unit: &str) -> bool {
+    unit.chars().last().is_some_and(|c| BREAK_AFTER.contains(c))
+}
+
+/// Wrap units into lines of at most `cpl` characters
+/// A line that is already past 60% full ends early at punctuation
+fn wrap_lines(units: &[String], cpl: usize, separator: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for unit in units {
+        let added = if line.is_empty() { width(unit) } else { width(separator) + width(unit) };
+        if !line.is_empty() && width(&line) + added > cpl {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push_str(separator);
+        }
+        line.push_str(unit);
+        if ends_with_break(unit) && width(&line) * 10 >= cpl * 6 {
+            lines.push(std::mem::take(&mut line));
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+struct Cue {
+    start: f64,
+    end: f64,
+    text: String,
+    speaker: Option<String>,
+}
+
+/// Break a transcript into cues of at most `max_lines` wrapped lines,
+/// splitting each segment's time in proportion to the text in each cue
+fn build_cues(transcript: &Transcript, options: &CaptionOptions) -> Vec<Cue> {
+    let unspaced = is_unspaced(&transcript.language);
+    let cpl = options.max_chars_per_line.unwrap_or(if unspaced { 16 } else { 42 }).max(1);
+    let max_lines = options.max_lines.unwrap_or(2).max(1);
+    let separator = if unspaced { "" } else { " " };
+
+    let mut cues = Vec::new();
+    for segment in &transcript.segments {
+        let lines = wrap_lines(&units(&segment.text, &transcript.language), cpl, separator);
+        let total: usize = lines.iter().map(|l| width(l)).sum();
+        if total == 0 {
+            continue;
+        }
+
+        let duration = segment.end - segment.start;
+        let mut start = segment.start;
+        let mut consumed = 0;
+        for chunk in lines.chunks(max_lines) {
+            consumed += chunk.iter().map(|l| width(l)).sum::<usize>();
+            let end = segment.start + duration * consumed as f64 / total as f64;
+            let speaker = if options.speaker_colors { segment.speaker.clone() } else { None };
+            cues.push(Cue { start, end, text: chunk.join("\n"), speaker });
+            start = end;
+        }
+    }
+    cues
+}
+
+fn to_srt(cues: &[Cue]) -> String {
+    let mut speakers: Vec<&str> = Vec::new();
+    cues.iter()
+        .enumerate()
+        .map(|(i, cue)| {
+            let text = match &cue.speaker {
+                Some(speaker) => {
+                    let n = speakers.iter().position(|s| *s == speaker.as_str()).unwrap_or_else(|| {
+                        speakers.push(speaker);
+                        speakers.len() - 1
+                    });
+                    format!("<font color=\"{}\">{}</font>", SPEAKER_COLORS[n % SPEAKER_COLORS.len()], cue.text)
+                }
+                None => cue.text.clone(),
+            };
+            format!(
+                "{}\n{} --> {}\n{}\n\n",
+                i + 1,
+                format_srt_timestamp(cue.start),
+                format_srt_timestamp(cue.end),
+                text
+            )
+        })
+        .collect()
+}
+
+fn to_vtt(cues: &[Cue]) -> String {
+    let body: String = cues
+        .iter()
+        .map(|cue| {
+            let text = match &cue.speaker {
+                Some(speaker) => format!("<v {}>{}", speaker, cue.text),
+                None => cue.text.clone(),
+            };
+            format!(
+                "{} --> {}\n{}\n\n",
+                format_srt_timestamp(cue.start).replace(',', "."),
+                format_srt_timestamp(cue.end).replace(',', "."),
+                text
+            )
+        })
+        .collect();
+    format!("WEBVTT\n\n{}", body)
+}
+
+/// Caption file contents for a transcript; `format` is "srt" or "vtt"
+pub fn render_captions(transcript: &Transcript, format: &str, options: &CaptionOptions) -> Result<String> {
+    let cues = build_cues(transcript, options);
+    match format {
+        "srt" => Ok(to_srt(&cues)),
+        "vtt" => Ok(to_vtt(&cues)),
+        other => Err(ClipFlowError::invalid(format!("Unknown caption format: {}", other))),
+    }
+}
+
+/// Write a transcript as captions; the format follows the output extension
+/// Without an explicit `transcript`, the stored (and possibly corrected)
+/// transcript of `source_path` is used
+#[tauri::command]
+pub async fn export_captions(
+    app: AppHandle,
+    transcript: Option<Transcript>,
+    source_path: Option<String>,
+    output_path: &str,
+    options: Option<CaptionOptions>,
+) -> Result<()> {
+    let transcript = match (transcript, source_path) {
+        (Some(transcript), _) => transcript,
+        (None, Some(source)) => transcripts::require(&app, &source)?.transcript,
+        (None, None) => return Err(ClipFlowError::invalid("Pass a transcript or the source file it belongs to")),
+    };
+    let format = Path::new(output_path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let content = render_captions(&transcript, &format, &options.unwrap_or_default())?;
+    fs::write(output_path, content).map_err(|e| ClipFlowError::io("Failed to write captions", e))
+}
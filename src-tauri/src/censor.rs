@@ -0,0 +1,159 @@
+//! Profanity detection from transcripts, and muting or bleeping it out
+//!
+//! Transcripts only time whole segments, so a flagged word's range is
+//! estimated from where it sits in its segment's text and padded a little
+//! either side. Review the ranges before censoring a final export.
+
+use crate::error::{ClipFlowError, Result};
+use crate::output::{self, OverwritePolicy};
+use crate::{probe, process, settings, transcripts};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+/// Words flagged out of the box; a trailing `*` matches any ending
+const DEFAULT_WORDS: [&str; 20] = [
+    "fuck*", "motherfuck*", "shit*", "bullshit", "bitch*", "cunt*", "asshole*", "bastard*", "dick", "dickhead*",
+    "cock", "pussy", "whore*", "slut*", "twat*", "wank*", "piss", "pissed", "goddamn*", "damn",
+];
+
+/// Seconds added either side of a word's estimated range
+const PADDING: f64 = 0.15;
+
+/// Level of the bleep tone, low enough not to be painful
+const BEEP_VOLUME: f64 = 0.3;
+const BEEP_FREQUENCY: u32 = 1000;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct CensorRange {
+    pub start: f64,
+    pub end: f64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct FlaggedWord {
+    pub word: String,
+    pub segment_id: usize,
+    pub start: f64,
+    pub end: f64,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum CensorMode {
+    /// Silence the range
+    Mute,
+    /// Silence the range and play a tone over it
+    Beep,
+}
+
+fn matches(word: &str, pattern: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => word.starts_with(prefix),
+        None => word == pattern,
+    }
+}
+
+/// Lowercased word list from settings or the caller, plus the built-ins
+fn word_list(words: Option<Vec<String>>) -> Vec<String> {
+    let extra = words.unwrap_or_else(|| settings::current().profanity_words);
+    DEFAULT_WORDS
+        .iter()
+        .map(|w| w.to_string())
+        .chain(extra)
+        .map(|w| w.trim().to_lowercase())
+        .filter(|w| !w.is_empty() && w != "*")
+        .collect()
+}
+
+/// Sorted ranges with overlapping and touching ones joined
+fn merge_ranges(mut ranges: Vec<CensorRange>) -> Vec<CensorRange> {
+    ranges.sort_by(|a, b| a.start.total_cmp(&b.start));
+    let mut merged: Vec<CensorRange> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Flag profanity in the stored transcript of `file_path`
+/// `words` replaces the extra words from settings for this call; the
+/// built-in list always applies.
+#[tauri::command]
+pub async fn detect_profanity(app: AppHandle, file_path: &str, words: Option<Vec<String>>) -> Result<Vec<FlaggedWord>> {
+    let patterns = word_list(words);
+    let transcript = transcripts::require(&app, file_path)?.transcript;
+    let mut flagged = Vec::new();
+    for segment in &transcript.segments {
+        let text = segment.text.to_lowercase();
+        let length = text.chars().count().max(1) as f64;
+        let duration = segment.end - segment.start;
+        let mut offset = 0;
+        for token in text.split_inclusive(char::is_whitespace) {
+            let position = offset;
+            offset += token.chars().count();
+            let word = token.trim().trim_matches(|c: char| !c.is_alphanumeric());
+            if word.is_empty() || !patterns.iter().any(|p| matches(word, p)) {
+                continue;
+            }
+            let word_end = position + token.trim_end().chars().count();
+            flagged.push(FlaggedWord {
+                word: word.to_string(),
+                segment_id: segment.id,
+                start: (segment.start + duration * position as f64 / length - PADDING).max(0.0),
+                end: segment.start + duration * word_end as f64 / length + PADDING,
+            });
+        }
+    }
+    Ok(flagged)
+}
+
+/// Mute or bleep `ranges` of the audio; video is copied
+#[tauri::command]
+pub async fn censor_audio(
+    input_path: &str,
+    output_path: &str,
+    ranges: Vec<CensorRange>,
+    mode: CensorMode,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    if ranges.iter().any(|r| r.start.is_nan() || r.end.is_nan() || r.start < 0.0 || r.end <= r.start) {
+        return Err(ClipFlowError::invalid("Each range must end after it starts"));
+    }
+    let ranges = merge_ranges(ranges);
+    if ranges.is_empty() {
+        return Err(ClipFlowError::invalid("Nothing to censor"));
+    }
+    let sample_rate = probe::audio_tracks(input_path)?
+        .first()
+        .map(|track| track.sample_rate)
+        .ok_or_else(|| ClipFlowError::invalid("Input has no audio to censor"))?;
+
+    let censored = ranges.iter().map(|r| format!("between(t,{},{})", r.start, r.end)).collect::<Vec<_>>().join("+");
+    let graph = match mode {
+        CensorMode::Mute => format!("[0:a:0]volume='if({},0,1)':eval=frame[a]", censored),
+        CensorMode::Beep => format!(
+            "[0:a:0]volume='if({c},0,1)':eval=frame[muted];\
+             sine=frequency={f}:sample_rate={r},volume='if({c},{v},0)':eval=frame[beep];\
+             [muted][beep]amix=inputs=2:duration=first:normalize=0[a]",
+            c = censored, f = BEEP_FREQUENCY, r = sample_rate, v = BEEP_VOLUME
+        ),
+    };
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-filter_complex".to_string(), graph,
+        "-map".to_string(), "0:v?".to_string(),
+        "-map".to_string(), "[a]".to_string(),
+        "-c:v".to_string(), "copy".to_string(),
+    ];
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "audio censoring").await
+    })
+    .await
+}
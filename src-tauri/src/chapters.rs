@@ -0,0 +1,245 @@
+//! Chapter markers: generating them, embedding them in a file, and writing
+//! them out as a YouTube description list
+//!
+//! Chapters can come from cuts in the picture, from pauses in the stored
+//! transcript, or from a timestamp list typed by hand ("0:00 Intro").
+
+use crate::error::{ClipFlowError, Result};
+use crate::metadata::{self, Chapter};
+use crate::output::{self, OverwritePolicy};
+use crate::{probe, process, settings, transcripts};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tokio::process::Command;
+use tauri::AppHandle;
+
+/// Default scene-change score (0-1) that counts as a cut
+const DEFAULT_SCENE_THRESHOLD: f64 = 0.4;
+
+/// Default pause in speech, in seconds, that may start a new chapter
+const DEFAULT_MIN_GAP: f64 = 2.0;
+
+/// YouTube ignores chapter lists with any chapter shorter than this
+const DEFAULT_MIN_LENGTH: f64 = 10.0;
+
+/// Words of a transcript segment used as its chapter title
+const TITLE_WORDS: usize = 6;
+
+#[derive(Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum ChapterSource {
+    /// A chapter at every hard cut in the picture
+    Scenes { threshold: Option<f64> },
+    /// A chapter at every long pause in the stored transcript, titled with
+    /// the words that follow it
+    Transcript { min_gap: Option<f64> },
+    /// "mm:ss Title" or "h:mm:ss Title" lines
+    Manual { text: String },
+}
+
+/// Times of hard cuts, from ffmpeg's scene-change score
+pub async fn scene_changes(input_path: &str, threshold: f64) -> Result<Vec<f64>> {
+    let ffmpeg = settings::ffmpeg_bin();
+    let output = process::run(Command::new(&ffmpeg).args([
+        "-hide_banner",
+        "-i", input_path,
+        "-an",
+        "-vf", &format!("select='gt(scene,{})',showinfo", threshold),
+        "-f", "null",
+        "-",
+    ]), None, |_| {})
+    .await
+    .map_err(|e| process::spawn_error(&ffmpeg, e))?;
+    process::check_ffmpeg(&output, "scene detection")?;
+
+    Ok(String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .filter(|line| line.contains("Parsed_showinfo"))
+        .filter_map(|line| line.split("pts_time:").nth(1))
+        .filter_map(|rest| rest.split_whitespace().next()?.parse::<f64>().ok())
+        .collect())
+}
+
+/// Parse "1:23" / "01:02:03" into seconds
+pub fn parse_timestamp(text: &str) -> Option<f64> {
+    let parts: Vec<&str> = text.split(':').collect();
+    if !(2..=3).contains(&parts.len()) {
+        return None;
+    }
+    parts.iter().try_fold(0.0, |total, part| Some(total * 60.0 + part.parse::<f64>().ok()?))
+}
+
+/// Chapters from "mm:ss Title" lines, in the order written
+pub fn parse_manual(text: &str) -> Result<Vec<Chapter>> {
+    let mut chapters = Vec::new();
+    for (n, line) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let line = line.trim();
+        let (timestamp, title) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let start = parse_timestamp(timestamp)
+            .ok_or_else(|| ClipFlowError::invalid(format!("Line {}: \"{}\" doesn't start with a timestamp", n + 1, line)))?;
+        let title = title.trim_start_matches(['-', '–', ' ']).trim();
+        chapters.push(Chapter { title: title.to_string(), start, end: None });
+    }
+    Ok(chapters)
+}
+
+fn title_from(text: &str) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let title = words[..words.len().min(TITLE_WORDS)].join(" ");
+    let title = title.trim_end_matches([',', '.', '?', '!', ';', ':']);
+    if words.len() > TITLE_WORDS {
+        format!("{}…", title)
+    } else {
+        title.to_string()
+    }
+}
+
+fn transcript_chapters(app: &AppHandle, input_path: &str, min_gap: f64) -> Result<Vec<Chapter>> {
+    let stored = transcripts::require(app, input_path)?;
+    let segments = &stored.transcript.segments;
+    let mut chapters = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let after_pause = i == 0 || segment.start - segments[i - 1].end >= min_gap;
+        if after_pause && !segment.text.trim().is_empty() {
+            // The first chapter always opens the video
+            let start = if chapters.is_empty() { 0.0 } else { segment.start };
+            chapters.push(Chapter { title: title_from(&segment.text), start, end: None });
+        }
+    }
+    Ok(chapters)
+}
+
+/// Fold chapters shorter than `min_length` into the one before them
+fn merge_short(chapters: Vec<Chapter>, min_length: f64) -> Vec<Chapter> {
+    let mut merged: Vec<Chapter> = Vec::new();
+    for chapter in chapters {
+        match merged.last_mut() {
+            Some(last) if last.end.unwrap_or(last.start) - last.start < min_length => last.end = chapter.end,
+            _ => merged.push(chapter),
+        }
+    }
+    // A too-short tail goes into the chapter before it
+    if merged.len() > 1 {
+        let last = &merged[merged.len() - 1];
+        if last.end.unwrap_or(last.start) - last.start < min_length {
+            let end = last.end;
+            merged.pop();
+            if let Some(previous) = merged.last_mut() {
+                previous.end = end;
+            }
+        }
+    }
+    merged
+}
+
+/// Format seconds as YouTube expects: "mm:ss", or "h:mm:ss" past an hour
+fn youtube_timestamp(seconds: f64, with_hours: bool) -> String {
+    let total = seconds.floor() as u64;
+    let (h, m, s) = (total / 3600, total % 3600 / 60, total % 60);
+    if with_hours {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{:02}:{:02}", m, s)
+    }
+}
+
+/// Chapters for a file from `source`, sorted and with every end filled in
+/// Generated chapters shorter than `min_length` (default 10s, YouTube's
+/// minimum) are merged into their neighbours; manual ones are kept as typed.
+#[tauri::command]
+pub async fn generate_chapters(
+    app: AppHandle,
+    input_path: &str,
+    source: ChapterSource,
+    min_length: Option<f64>,
+) -> Result<Vec<Chapter>> {
+    let duration = probe::media_duration(input_path)?;
+    let min_length = min_length.unwrap_or(DEFAULT_MIN_LENGTH);
+    let (chapters, generated) = match source {
+        ChapterSource::Scenes { threshold } => {
+            let cuts = scene_changes(input_path, threshold.unwrap_or(DEFAULT_SCENE_THRESHOLD).clamp(0.05, 1.0)).await?;
+            let starts = std::iter::once(0.0).chain(cuts.into_iter().filter(|t| *t > 0.0));
+            let chapters = starts
+                .enumerate()
+                .map(|(i, start)| Chapter { title: format!("Scene {}", i + 1), start, end: None })
+                .collect();
+            (chapters, true)
+        }
+        ChapterSource::Transcript { min_gap } => {
+            (transcript_chapters(&app, input_path, min_gap.unwrap_or(DEFAULT_MIN_GAP))?, true)
+        }
+        ChapterSource::Manual { text } => (parse_manual(&text)?, false),
+    };
+    if chapters.is_empty() {
+        return Err(ClipFlowError::invalid("No chapters found"));
+    }
+
+    let chapters = metadata::normalize_chapters(&chapters, duration)?;
+    Ok(if generated { merge_short(chapters, min_length) } else { chapters })
+}
+
+/// Write `chapters` into a copy of `input_path`, keeping its streams and
+/// other metadata as they are; returns the path written
+#[tauri::command]
+pub async fn embed_chapters(
+    input_path: &str,
+    output_path: &str,
+    chapters: Vec<Chapter>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let ext = Path::new(output_path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if !matches!(ext.as_str(), "mp4" | "m4v" | "m4a" | "mov" | "mkv" | "mka") {
+        return Err(ClipFlowError::invalid("Chapters can be embedded in MP4, MOV, and MKV files"));
+    }
+    let duration = probe::media_duration(input_path)?;
+    let chapters = metadata::normalize_chapters(&chapters, duration)?;
+    let metadata_file = metadata::write_ffmetadata(&BTreeMap::new(), &chapters)?;
+    let metadata_path = metadata_file.path_str();
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        process::run_ffmpeg_async(&[
+            "-i", input_path,
+            "-i", metadata_path.as_str(),
+            "-map", "0",
+            "-map_metadata", "0",
+            "-map_chapters", "1",
+            "-c", "copy",
+            partial.as_str(),
+            "-y",
+        ], "chapter embedding").await
+    })
+    .await
+}
+
+/// Write `chapters` as a YouTube description chapter list ("00:00 Intro")
+/// YouTube requires the list to start at 0:00, so an "Intro" chapter is
+/// added when the first one starts later. Returns the list so it can be
+/// pasted straight into a description.
+#[tauri::command]
+pub async fn export_chapter_list(chapters: Vec<Chapter>, output_path: &str) -> Result<String> {
+    let mut chapters = chapters;
+    chapters.sort_by(|a, b| a.start.total_cmp(&b.start));
+    match chapters.first() {
+        None => return Err(ClipFlowError::invalid("No chapters to export")),
+        Some(first) if first.start >= 1.0 => {
+            chapters.insert(0, Chapter { title: "Intro".to_string(), start: 0.0, end: None })
+        }
+        _ => {}
+    }
+    let with_hours = chapters.last().map(|c| c.start >= 3600.0).unwrap_or(false);
+    let list: String = chapters
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let start = if i == 0 { 0.0 } else { c.start };
+            format!("{} {}\n", youtube_timestamp(start, with_hours), c.title.trim())
+        })
+        .collect();
+    fs::write(output_path, &list).map_err(|e| ClipFlowError::io("Failed to write chapter list", e))?;
+    Ok(list)
+}
@@ -0,0 +1,112 @@
+//! Paste-to-import: turn whatever is on the clipboard (file paths, file://
+//! URIs, or web URLs) into library items
+
+use crate::error::{ClipFlowError, Result};
+use crate::{download, library};
+use serde::Serialize;
+use std::path::PathBuf;
+use tauri::AppHandle;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+#[derive(Serialize)]
+pub struct PasteImportResult {
+    imported: Vec<library::LibraryItem>,
+    /// Clipboard entries that couldn't be imported, with the reason
+    skipped: Vec<(String, String)>,
+}
+
+enum PastedItem {
+    File(PathBuf),
+    Url(String),
+}
+
+/// Decode %XX escapes in a file:// URI path
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(b) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Interpret one line of clipboard text
+fn parse_entry(line: &str) -> Option<PastedItem> {
+    // File managers and terminals often wrap paths in quotes
+    let entry = line.trim().trim_matches(|c| c == '"' || c == '\'');
+    if entry.is_empty() {
+        return None;
+    }
+
+    if let Some(rest) = entry.strip_prefix("file://") {
+        // file:///C:/x on Windows, file:///home/x elsewhere
+        let path = percent_decode(rest.strip_prefix("localhost").unwrap_or(rest));
+        let path = if cfg!(windows) { path.trim_start_matches('/').to_string() } else { path };
+        return Some(PastedItem::File(PathBuf::from(path)));
+    }
+    if entry.starts_with("http://") || entry.starts_with("https://") {
+        return Some(PastedItem::Url(entry.to_string()));
+    }
+    Some(PastedItem::File(PathBuf::from(entry)))
+}
+
+/// Import the clipboard's files and URLs into the library
+///
+/// URLs are downloaded first (into `destination_dir`, or the app's downloads
+/// folder). Plain text that isn't an existing file is reported as skipped.
+#[tauri::command]
+pub async fn paste_import(app: AppHandle, destination_dir: Option<String>) -> Result<PasteImportResult> {
+    let text = app
+        .clipboard()
+        .read_text()
+        .map_err(|e| ClipFlowError::io("Failed to read clipboard", e))?;
+
+    let mut paths = Vec::new();
+    let mut skipped = Vec::new();
+    for line in text.lines() {
+        match parse_entry(line) {
+            Some(PastedItem::File(path)) => {
+                if path.is_file() {
+                    paths.push(path.to_string_lossy().into_owned());
+                } else {
+                    skipped.push((line.trim().to_string(), "Not a file or URL".to_string()));
+                }
+            }
+            Some(PastedItem::Url(url)) => {
+                let dir = match &destination_dir {
+                    Some(dir) => PathBuf::from(dir),
+                    None => download::downloads_dir(&app)?,
+                };
+                match download::download_to_dir(&app, &url, &dir).await {
+                    Ok(path) => paths.push(path.to_string_lossy().into_owned()),
+                    Err(e) => skipped.push((url, e.to_string())),
+                }
+            }
+            None => {}
+        }
+    }
+
+    if paths.is_empty() && skipped.is_empty() {
+        return Err(ClipFlowError::invalid("Clipboard doesn't contain a file path or URL"));
+    }
+
+    let mut imported = Vec::new();
+    for path in paths {
+        match library::add_paths(&app, std::slice::from_ref(&path)) {
+            Ok(items) => imported.extend(items),
+            Err(e) => skipped.push((path, e.to_string())),
+        }
+    }
+
+    Ok(PasteImportResult { imported, skipped })
+}
@@ -0,0 +1,422 @@
+//! Archiving and sharing exports in S3-compatible object storage (AWS S3,
+//! Backblaze B2, Cloudflare R2, MinIO, ...)
+//!
+//! Destinations are saved in the config dir; their keys live in the OS
+//! keychain as an S3 credential ("access_key_id:secret_access_key").
+//! Requests are signed with AWS Signature V4, which every S3-compatible
+//! service accepts.
+//!
+//! Files larger than one part go up as a multipart upload. The upload id
+//! and the ETags of finished parts are saved as a transfer checkpoint, so
+//! an interrupted upload continues with the next part, even after a
+//! restart, as long as the file hasn't changed in the meantime.
+
+use crate::credentials::{self, CredentialProvider};
+use crate::error::{ClipFlowError, Result};
+use crate::retry::{self, NetworkError, TransferCheckpoint};
+use crate::settings;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Emitter};
+
+/// Bytes per multipart part; S3 wants at least 5 MiB for all but the last
+const PART_SIZE: u64 = 16 * 1024 * 1024;
+
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CloudDestination {
+    pub id: String,
+    pub name: String,
+    /// Service URL, e.g. "https://s3.us-west-002.backblazeb2.com"
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    /// Prepended to the file name to make the object key, e.g. "exports/"
+    #[serde(default)]
+    pub prefix: String,
+    /// Address the bucket in the path ("endpoint/bucket/key") rather than
+    /// the host name; most non-AWS services expect this
+    #[serde(default = "default_path_style")]
+    pub path_style: bool,
+    /// Account of the S3 credential to sign with
+    pub account: String,
+}
+
+fn default_path_style() -> bool {
+    true
+}
+
+#[derive(Serialize, Clone)]
+struct CloudUploadProgress {
+    file_path: String,
+    destination: String,
+    bytes_done: u64,
+    bytes_total: u64,
+}
+
+fn destinations_path(app: &AppHandle) -> Result<PathBuf> {
+    settings::config_file(app, "cloud_destinations.json")
+}
+
+fn read_destinations(app: &AppHandle) -> Result<Vec<CloudDestination>> {
+    let path = destinations_path(app)?;
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content = fs::read_to_string(&path).map_err(|e| ClipFlowError::io("Failed to read cloud destinations", e))?;
+    serde_json::from_str(&content).map_err(|e| ClipFlowError::io("Invalid cloud destinations file", e))
+}
+
+fn write_destinations(app: &AppHandle, destinations: &[CloudDestination]) -> Result<()> {
+    let json = serde_json::to_string_pretty(destinations)
+        .map_err(|e| ClipFlowError::io("Failed to serialize cloud destinations", e))?;
+    fs::write(destinations_path(app)?, json).map_err(|e| ClipFlowError::io("Failed to write cloud destinations", e))
+}
+
+/// Percent-encode as SigV4 wants: everything but unreserved characters,
+/// and `/` too unless it separates key segments
+fn uri_encode(text: &str, keep_slash: bool) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            b'/' if keep_slash => "/".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Text between the first `<tag>` and `</tag>` of an XML reply
+fn xml_value(body: &str, tag: &str) -> Option<String> {
+    let start = body.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = start + body[start..].find(&format!("</{}>", tag))?;
+    Some(body[start..end].to_string())
+}
+
+/// Signs requests for one destination
+struct Signer<'a> {
+    destination: &'a CloudDestination,
+    access_key_id: String,
+    secret_access_key: String,
+}
+
+impl<'a> Signer<'a> {
+    fn new(destination: &'a CloudDestination) -> Result<Self> {
+        let secret = credentials::get_secret(CredentialProvider::S3, &destination.account)?.ok_or_else(|| {
+            ClipFlowError::not_found(format!("No S3 credential stored for account \"{}\"", destination.account))
+        })?;
+        match secret.split_once(':') {
+            Some((id, key)) if !id.is_empty() && !key.is_empty() => Ok(Signer {
+                destination,
+                access_key_id: id.to_string(),
+                secret_access_key: key.to_string(),
+            }),
+            _ => Err(ClipFlowError::invalid("S3 credential must be \"access_key_id:secret_access_key\"")),
+        }
+    }
+
+    /// Host and path of an object, by the destination's addressing style
+    fn locate(&self, key: &str) -> Result<(String, String, String)> {
+        let endpoint = reqwest::Url::parse(&self.destination.endpoint)
+            .map_err(|e| ClipFlowError::invalid(format!("Invalid endpoint {}: {}", self.destination.endpoint, e)))?;
+        let host = endpoint
+            .host_str()
+            .ok_or_else(|| ClipFlowError::invalid(format!("Endpoint {} has no host", self.destination.endpoint)))?;
+        let host = match endpoint.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+        let bucket = &self.destination.bucket;
+        Ok(if self.destination.path_style {
+            (endpoint.scheme().to_string(), host, format!("/{}/{}", uri_encode(bucket, false), uri_encode(key, true)))
+        } else {
+            (endpoint.scheme().to_string(), format!("{}.{}", bucket, host), format!("/{}", uri_encode(key, true)))
+        })
+    }
+
+    /// Public URL of an object; whether it can be fetched depends on the
+    /// bucket's policy
+    fn object_url(&self, key: &str) -> Result<String> {
+        let (scheme, host, path) = self.locate(key)?;
+        Ok(format!("{}://{}{}", scheme, host, path))
+    }
+
+    /// A request for `key`, signed with SigV4
+    fn request(
+        &self,
+        client: &reqwest::Client,
+        method: reqwest::Method,
+        key: &str,
+        query: &[(&str, &str)],
+        body: Vec<u8>,
+    ) -> Result<reqwest::RequestBuilder> {
+        let (scheme, host, path) = self.locate(key)?;
+        let mut params: Vec<(String, String)> =
+            query.iter().map(|(k, v)| (uri_encode(k, false), uri_encode(v, false))).collect();
+        params.sort();
+        let canonical_query = params.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("&");
+
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = sha256_hex(&body);
+        let canonical_request = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method.as_str(),
+            path,
+            canonical_query,
+            host,
+            payload_hash,
+            amz_date,
+            SIGNED_HEADERS,
+            payload_hash
+        );
+        let region = &self.destination.region;
+        let scope = format!("{}/{}/s3/aws4_request", date, region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            sha256_hex(canonical_request.as_bytes())
+        );
+        let signing_key = ["s3", "aws4_request"].iter().fold(
+            hmac_sha256(&hmac_sha256(format!("AWS4{}", self.secret_access_key).as_bytes(), &date), region),
+            |key, part| hmac_sha256(&key, part),
+        );
+        let signature = hex::encode(hmac_sha256(&signing_key, &string_to_sign));
+
+        let url = if canonical_query.is_empty() {
+            format!("{}://{}{}", scheme, host, path)
+        } else {
+            format!("{}://{}{}?{}", scheme, host, path, canonical_query)
+        };
+        Ok(client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key_id, scope, SIGNED_HEADERS, signature
+                ),
+            )
+            .body(body))
+    }
+}
+
+fn read_range(file_path: &str, offset: u64, len: u64) -> Result<Vec<u8>> {
+    let mut data = vec![0u8; len as usize];
+    let mut file = File::open(file_path).map_err(|e| ClipFlowError::io("Failed to open file to upload", e))?;
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.read_exact(&mut data))
+        .map_err(|e| ClipFlowError::io("Failed to read file to upload", e))?;
+    Ok(data)
+}
+
+async fn send(request: Result<reqwest::RequestBuilder>) -> std::result::Result<reqwest::Response, NetworkError> {
+    let request = request.map_err(|e| NetworkError::Fatal(e.to_string()))?;
+    retry::check_response(request.send().await?).await
+}
+
+/// ETag of an uploaded object or part, quotes included as S3 wants them back
+fn etag(response: &reqwest::Response) -> std::result::Result<String, NetworkError> {
+    response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(str::to_string)
+        .ok_or_else(|| NetworkError::Fatal("Storage service didn't return an ETag".to_string()))
+}
+
+/// Upload `file_path` to `destination`, returning the object's URL
+pub async fn upload_file(app: &AppHandle, file_path: &str, destination: &CloudDestination) -> Result<String> {
+    let metadata = fs::metadata(file_path)
+        .map_err(|_| ClipFlowError::not_found(format!("File not found: {}", file_path)))?;
+    let size = metadata.len();
+    // Parts from a previous run only belong to this upload if the file
+    // hasn't been re-exported since
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_nanos() as u64);
+    let file_name = Path::new(file_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| ClipFlowError::invalid(format!("Not a file: {}", file_path)))?;
+    let key = format!("{}{}", destination.prefix, file_name);
+    let signer = Signer::new(destination)?;
+    let client = reqwest::Client::new();
+    let policy = settings::current().network_retry;
+    let progress = |bytes_done: u64| {
+        let _ = app.emit("cloud-upload-progress", CloudUploadProgress {
+            file_path: file_path.to_string(),
+            destination: destination.id.clone(),
+            bytes_done,
+            bytes_total: size,
+        });
+    };
+
+    if size <= PART_SIZE {
+        let data = read_range(file_path, 0, size)?;
+        retry::with_retry(&policy, "cloud upload", |_| {
+            send(signer.request(&client, reqwest::Method::PUT, &key, &[], data.clone()))
+        })
+        .await?;
+        progress(size);
+        return signer.object_url(&key);
+    }
+
+    // Resume the multipart upload a previous run started, if there is one
+    let checkpoint_id = format!("cloud-{}-{}", destination.id, file_path);
+    let mut checkpoint = match TransferCheckpoint::load(app, &checkpoint_id) {
+        Some(checkpoint)
+            if checkpoint.session.is_some()
+                && checkpoint.total_bytes == size
+                && checkpoint.extra["modified"].as_u64() == Some(modified) =>
+        {
+            checkpoint
+        }
+        _ => {
+            let response = retry::with_retry(&policy, "cloud upload start", |_| {
+                send(signer.request(&client, reqwest::Method::POST, &key, &[("uploads", "")], Vec::new()))
+            })
+            .await?;
+            let body = response.text().await.map_err(|e| ClipFlowError::Network(e.to_string()))?;
+            let upload_id = xml_value(&body, "UploadId")
+                .ok_or_else(|| ClipFlowError::Network("Storage service didn't return an upload id".to_string()))?;
+            TransferCheckpoint {
+                id: checkpoint_id.clone(),
+                session: Some(upload_id),
+                total_bytes: size,
+                extra: serde_json::json!({ "etags": [], "modified": modified }),
+                ..Default::default()
+            }
+        }
+    };
+    let upload_id = checkpoint.session.clone().unwrap_or_default();
+    let mut etags: Vec<String> = serde_json::from_value(checkpoint.extra["etags"].clone()).unwrap_or_default();
+
+    let parts = size.div_ceil(PART_SIZE);
+    for part in etags.len() as u64..parts {
+        let offset = part * PART_SIZE;
+        let data = read_range(file_path, offset, PART_SIZE.min(size - offset))?;
+        let part_number = (part + 1).to_string();
+        let query = [("partNumber", part_number.as_str()), ("uploadId", upload_id.as_str())];
+        let response = retry::with_retry(&policy, "cloud upload part", |_| {
+            send(signer.request(&client, reqwest::Method::PUT, &key, &query, data.clone()))
+        })
+        .await
+        .inspect_err(|e| {
+            // The service dropped the upload (expired or aborted); start a
+            // fresh one next time rather than resuming into a dead id
+            if e.to_string().contains("NoSuchUpload") {
+                TransferCheckpoint::clear(app, &checkpoint_id);
+            }
+        })?;
+        etags.push(etag(&response).map_err(|e| ClipFlowError::Network(e.message().to_string()))?);
+
+        checkpoint.bytes_done = (offset + PART_SIZE).min(size);
+        checkpoint.extra = serde_json::json!({ "etags": etags, "modified": modified });
+        checkpoint.save(app)?;
+        progress(checkpoint.bytes_done);
+    }
+
+    let manifest: String = etags
+        .iter()
+        .enumerate()
+        .map(|(i, etag)| format!("<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>", i + 1, etag))
+        .collect();
+    let manifest = format!("<CompleteMultipartUpload>{}</CompleteMultipartUpload>", manifest);
+    let query = [("uploadId", upload_id.as_str())];
+    let response = retry::with_retry(&policy, "cloud upload finish", |_| {
+        send(signer.request(&client, reqwest::Method::POST, &key, &query, manifest.clone().into_bytes()))
+    })
+    .await?;
+    // S3 can report a failed completion in a 200 reply
+    let body = response.text().await.unwrap_or_default();
+    if let Some(message) = xml_value(&body, "Message").filter(|_| body.contains("<Error>")) {
+        return Err(ClipFlowError::Network(format!("Storage service couldn't finish the upload: {}", message)));
+    }
+    TransferCheckpoint::clear(app, &checkpoint_id);
+    signer.object_url(&key)
+}
+
+/// Saved destinations that sign with the S3 credential of `account`
+pub fn destinations_using(app: &AppHandle, account: &str) -> Result<Vec<CloudDestination>> {
+    Ok(read_destinations(app)?.into_iter().filter(|d| d.account == account).collect())
+}
+
+/// A signed listing of at most one object in the destination's bucket, to
+/// check that its credential is accepted
+pub fn list_request(client: &reqwest::Client, destination: &CloudDestination) -> Result<reqwest::RequestBuilder> {
+    Signer::new(destination)?.request(
+        client,
+        reqwest::Method::GET,
+        "",
+        &[("list-type", "2"), ("max-keys", "1")],
+        Vec::new(),
+    )
+}
+
+/// Look up a saved destination by id
+pub fn find_destination(app: &AppHandle, id: &str) -> Result<CloudDestination> {
+    read_destinations(app)?
+        .into_iter()
+        .find(|d| d.id == id)
+        .ok_or_else(|| ClipFlowError::not_found(format!("Cloud destination not found: {}", id)))
+}
+
+#[tauri::command]
+pub async fn list_cloud_destinations(app: AppHandle) -> Result<Vec<CloudDestination>> {
+    read_destinations(&app)
+}
+
+/// Add a destination, or replace the one with the same id
+#[tauri::command]
+pub async fn save_cloud_destination(app: AppHandle, destination: CloudDestination) -> Result<()> {
+    if destination.id.trim().is_empty() || destination.bucket.trim().is_empty() || destination.region.trim().is_empty() {
+        return Err(ClipFlowError::invalid("Destination id, bucket, and region must not be empty"));
+    }
+    let endpoint = reqwest::Url::parse(&destination.endpoint)
+        .map_err(|e| ClipFlowError::invalid(format!("Invalid endpoint {}: {}", destination.endpoint, e)))?;
+    if !matches!(endpoint.scheme(), "http" | "https") {
+        return Err(ClipFlowError::invalid(format!("Unsupported endpoint scheme: {}", endpoint.scheme())));
+    }
+    let mut destinations = read_destinations(&app)?;
+    destinations.retain(|d| d.id != destination.id);
+    destinations.push(destination);
+    write_destinations(&app, &destinations)
+}
+
+#[tauri::command]
+pub async fn remove_cloud_destination(app: AppHandle, id: String) -> Result<()> {
+    let mut destinations = read_destinations(&app)?;
+    destinations.retain(|d| d.id != id);
+    write_destinations(&app, &destinations)
+}
+
+/// Upload a finished render to a saved destination, with
+/// `cloud-upload-progress` events as parts finish
+/// Returns the object's URL.
+#[tauri::command]
+pub async fn upload_export(app: AppHandle, file: String, destination: String) -> Result<String> {
+    let destination = find_destination(&app, &destination)?;
+    upload_file(&app, &file, &destination).await
+}
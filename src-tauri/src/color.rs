@@ -0,0 +1,200 @@
+//! Source color space tracking and conversion to the BT.709 delivery space
+//!
+//! Mixed SD/HD/phone sources carry different matrices and ranges. If we let
+//! ffmpeg guess, untagged or full-range inputs come out washed out or
+//! oversaturated, so every re-encode converts explicitly and tags its output.
+//!
+//! HDR sources (PQ or HLG, e.g. HDR screen recordings and iPhone footage)
+//! are tone mapped down to SDR BT.709 rather than just converted, since
+//! squeezing their brightness range into SDR without it is what makes them
+//! look grey and washed out.
+
+use crate::error::{ClipFlowError, Result};
+use crate::output::{self, OverwritePolicy};
+use crate::{probe, process};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ColorInfo {
+    /// YUV matrix, e.g. "bt709", "smpte170m", "bt2020nc"
+    pub matrix: String,
+    pub primaries: String,
+    pub transfer: String,
+    /// "tv" (limited) or "pc" (full)
+    pub range: String,
+    pub pix_fmt: String,
+    /// True when the file didn't say and we inferred from resolution
+    pub guessed: bool,
+    /// PQ or HLG transfer - needs tone mapping, not just a matrix conversion
+    pub hdr: bool,
+}
+
+fn tag(value: &serde_json::Value) -> Option<String> {
+    value
+        .as_str()
+        .filter(|v| !v.is_empty() && *v != "unknown" && *v != "reserved")
+        .map(|v| v.to_string())
+}
+
+pub fn source_color(file_path: &str) -> Result<ColorInfo> {
+    let json = probe::ffprobe_json(file_path)?;
+    let video = json["streams"]
+        .as_array()
+        .and_then(|streams| streams.iter().find(|s| s["codec_type"] == "video"))
+        .ok_or_else(|| ClipFlowError::invalid(format!("No video stream found. Path: {}", file_path)))?;
+
+    let height = video["height"].as_u64().unwrap_or(0);
+    let pix_fmt = video["pix_fmt"].as_str().unwrap_or("yuv420p").to_string();
+
+    let matrix = tag(&video["color_space"]);
+    let guessed = matrix.is_none();
+    // Untagged: assume SD is BT.601 and everything else BT.709, which is
+    // what players do
+    let default_matrix = if height > 0 && height < 720 { "smpte170m" } else { "bt709" };
+    let matrix = matrix.unwrap_or_else(|| default_matrix.to_string());
+
+    let primaries = tag(&video["color_primaries"]).unwrap_or_else(|| match matrix.as_str() {
+        "bt2020nc" | "bt2020c" => "bt2020".to_string(),
+        "bt470bg" => "bt470bg".to_string(),
+        "smpte170m" => "smpte170m".to_string(),
+        _ => "bt709".to_string(),
+    });
+    let transfer = tag(&video["color_transfer"]).unwrap_or_else(|| "bt709".to_string());
+
+    // JPEG-style pixel formats (yuvj*) are always full range
+    let range = tag(&video["color_range"]).unwrap_or_else(|| {
+        if pix_fmt.starts_with("yuvj") || pix_fmt.starts_with("rgb") || pix_fmt.starts_with("bgr") {
+            "pc".to_string()
+        } else {
+            "tv".to_string()
+        }
+    });
+
+    let hdr = matches!(transfer.as_str(), "smpte2084" | "arib-std-b67");
+
+    Ok(ColorInfo { matrix, primaries, transfer, range, pix_fmt, guessed, hdr })
+}
+
+/// Name of a matrix/primaries set as the colorspace filter spells it
+fn colorspace_filter_name(matrix: &str) -> Option<&'static str> {
+    match matrix {
+        "bt709" => Some("bt709"),
+        "smpte170m" => Some("bt601-6-525"),
+        "bt470bg" => Some("bt601-6-625"),
+        "smpte240m" => Some("smpte240m"),
+        "bt2020nc" | "bt2020c" => Some("bt2020"),
+        _ => None,
+    }
+}
+
+/// Filter converting the source to BT.709 limited range, or None if it's
+/// already there (or is HDR, which needs tone mapping instead)
+pub fn to_bt709_filter(info: &ColorInfo) -> Option<String> {
+    if info.hdr {
+        return None;
+    }
+    if info.matrix == "bt709" && info.primaries == "bt709" && info.range == "tv" {
+        return None;
+    }
+
+    let source = colorspace_filter_name(&info.matrix)?;
+    let input_range = if info.range == "pc" { "pc" } else { "tv" };
+
+    Some(format!(
+        "colorspace=all=bt709:iall={}:irange={}:range=tv:format=yuv420p",
+        source, input_range
+    ))
+}
+
+/// Curve used to compress HDR highlights into SDR
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ToneMapping {
+    /// Filmic curve keeping highlight detail; a safe default
+    #[default]
+    Hable,
+    /// Leaves in-range colors alone, compressing only the brightest parts
+    Mobius,
+    Reinhard,
+    /// ITU-R BT.2390 EETF, the broadcast reference; needs an ffmpeg built
+    /// with libplacebo (and Vulkan)
+    Bt2390,
+}
+
+/// Filter chain tone mapping HDR to BT.709 limited-range 4:2:0
+///
+/// The zscale path linearizes at 100 nits nominal peak, converts primaries
+/// in linear light, applies the curve, then encodes BT.709. Desaturation is
+/// off: tonemap's default fades bright colors to white.
+pub fn tonemap_filter(mapping: ToneMapping) -> String {
+    let curve = match mapping {
+        ToneMapping::Hable => "hable",
+        ToneMapping::Mobius => "mobius",
+        ToneMapping::Reinhard => "reinhard",
+        ToneMapping::Bt2390 => {
+            return "libplacebo=tonemapping=bt.2390:colorspace=bt709:color_primaries=bt709:color_trc=bt709:range=tv:format=yuv420p"
+                .to_string()
+        }
+    };
+    format!(
+        "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,tonemap=tonemap={}:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p",
+        curve
+    )
+}
+
+/// Output flags tagging a stream as BT.709 limited range
+pub fn bt709_output_args() -> Vec<String> {
+    [
+        "-colorspace", "bt709",
+        "-color_primaries", "bt709",
+        "-color_trc", "bt709",
+        "-color_range", "tv",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+/// Conversion filter (if needed) plus output tags for a re-encode of `file_path`
+/// HDR sources are tone mapped to SDR. Probe failures fall back to tagging
+/// only, so an odd file never blocks an export
+pub fn export_color_args(file_path: &str) -> (Option<String>, Vec<String>) {
+    match source_color(file_path) {
+        Ok(info) if info.hdr => (Some(tonemap_filter(ToneMapping::default())), bt709_output_args()),
+        Ok(info) => (to_bt709_filter(&info), bt709_output_args()),
+        Err(_) => (None, bt709_output_args()),
+    }
+}
+
+#[tauri::command]
+pub async fn get_color_info(file_path: &str) -> Result<ColorInfo> {
+    source_color(file_path)
+}
+
+/// Convert HDR footage to SDR BT.709 with the chosen tone mapping curve
+/// Audio is copied. Returns the path written.
+#[tauri::command]
+pub async fn tonemap_video(
+    input_path: &str,
+    output_path: &str,
+    mapping: Option<ToneMapping>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let info = source_color(input_path)?;
+    if !info.hdr {
+        return Err(ClipFlowError::invalid(format!("{} isn't HDR (transfer is {})", input_path, info.transfer)));
+    }
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-vf".to_string(), tonemap_filter(mapping.unwrap_or_default()),
+        "-c:a".to_string(), "copy".to_string(),
+    ];
+    args.extend(bt709_output_args());
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "tone mapping").await
+    })
+    .await
+}
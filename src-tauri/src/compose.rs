@@ -0,0 +1,165 @@
+//! Split-screen composition: two or four clips on screen at once, for
+//! reaction and comparison videos
+//!
+//! Every input is conformed to its cell (letterboxed, never stretched) and
+//! to the first input's frame rate, since the stacking filters refuse
+//! inputs that differ. The result ends with the shortest input.
+
+use crate::error::{ClipFlowError, Result};
+use crate::filtergraph::{Filter, FilterGraph};
+use crate::output::{self, OverwritePolicy};
+use crate::transitions::{normalize_audio_filter, normalize_filter};
+use crate::{color, probe, process};
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitLayout {
+    /// Two clips left and right
+    SideBySide,
+    /// Two clips above one another, suited to vertical video
+    TopBottom,
+    /// Three or four clips in a 2x2 grid, filled left to right, top to
+    /// bottom; a missing fourth cell is black
+    Grid,
+}
+
+/// Which sound the composition keeps
+#[derive(Deserialize, Clone, Copy, Default, Debug)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SplitAudio {
+    /// All inputs that have audio, mixed at equal level
+    #[default]
+    Mix,
+    /// Only this input's audio, e.g. the reaction over the video reacted to
+    Input { index: usize },
+    Silent,
+}
+
+impl SplitLayout {
+    fn default_size(&self) -> (u32, u32) {
+        match self {
+            SplitLayout::TopBottom => (1080, 1920),
+            SplitLayout::SideBySide | SplitLayout::Grid => (1920, 1080),
+        }
+    }
+
+    /// Size of one cell of a `width` x `height` frame, rounded down to even
+    fn cell_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let even = |n: u32| n / 2 * 2;
+        match self {
+            SplitLayout::SideBySide => (even(width / 2), height),
+            SplitLayout::TopBottom => (width, even(height / 2)),
+            SplitLayout::Grid => (even(width / 2), even(height / 2)),
+        }
+    }
+
+    fn input_range(&self) -> (usize, usize) {
+        match self {
+            SplitLayout::SideBySide | SplitLayout::TopBottom => (2, 2),
+            SplitLayout::Grid => (3, 4),
+        }
+    }
+}
+
+/// Put clips side by side, stacked, or in a grid
+/// `width`/`height` are the whole frame, 1920x1080 unless set (1080x1920
+/// for top/bottom). Returns the path written.
+#[tauri::command]
+pub async fn compose_split(
+    inputs: Vec<String>,
+    output_path: &str,
+    layout: SplitLayout,
+    audio: Option<SplitAudio>,
+    width: Option<u32>,
+    height: Option<u32>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let (min, max) = layout.input_range();
+    if !(min..=max).contains(&inputs.len()) {
+        return Err(ClipFlowError::invalid(format!("This layout takes {} to {} clips, got {}", min, max, inputs.len())));
+    }
+    let (default_width, default_height) = layout.default_size();
+    let (width, height) = (width.unwrap_or(default_width), height.unwrap_or(default_height));
+    if width < 16 || height < 16 || width % 2 != 0 || height % 2 != 0 {
+        return Err(ClipFlowError::invalid("Width and height must be even numbers of at least 16"));
+    }
+    let (cell_width, cell_height) = layout.cell_size(width, height);
+    let fps = probe::frame_rate(&inputs[0])?;
+
+    let mut graph = FilterGraph::new();
+    let mut cells: Vec<String> = Vec::new();
+    for (i, input) in inputs.iter().enumerate() {
+        let mut filters: Vec<Filter> = color::export_color_args(input).0.into_iter().map(Filter::raw).collect();
+        filters.push(Filter::raw(normalize_filter(cell_width, cell_height, fps)));
+        let cell = graph.label("cell");
+        graph.chain(&[format!("{}:v", i).as_str()], filters, &[cell.as_str()]);
+        cells.push(cell);
+    }
+    if layout == SplitLayout::Grid && inputs.len() == 3 {
+        let blank = graph.label("cell");
+        let black = Filter::new("color").opt("c", "black").opt("s", format!("{}x{}", cell_width, cell_height)).opt("r", fps);
+        graph.chain(&[], vec![black, Filter::new("format").arg("yuv420p"), Filter::new("settb").arg("AVTB")], &[blank.as_str()]);
+        cells.push(blank);
+    }
+    let stack = match layout {
+        SplitLayout::SideBySide => Filter::new("hstack").opt("inputs", 2),
+        SplitLayout::TopBottom => Filter::new("vstack").opt("inputs", 2),
+        SplitLayout::Grid => Filter::new("xstack").opt("inputs", 4).opt("layout", "0_0|w0_0|0_h0|w0_h0"),
+    };
+    let cell_refs: Vec<&str> = cells.iter().map(String::as_str).collect();
+    graph.chain(&cell_refs, vec![stack.opt("shortest", 1)], &["outv"]);
+
+    let with_audio: Vec<usize> = inputs
+        .iter()
+        .enumerate()
+        .filter(|(_, input)| probe::audio_tracks(input).map(|tracks| !tracks.is_empty()).unwrap_or(false))
+        .map(|(i, _)| i)
+        .collect();
+    let mixed: Vec<usize> = match audio.unwrap_or_default() {
+        SplitAudio::Mix => with_audio,
+        SplitAudio::Input { index } if index >= inputs.len() => {
+            return Err(ClipFlowError::invalid(format!("There's no input {} to take audio from", index)))
+        }
+        SplitAudio::Input { index } if !with_audio.contains(&index) => {
+            return Err(ClipFlowError::invalid(format!("{} has no audio", inputs[index])))
+        }
+        SplitAudio::Input { index } => vec![index],
+        SplitAudio::Silent => Vec::new(),
+    };
+    match mixed.as_slice() {
+        [] => {}
+        [only] => {
+            graph.chain(&[format!("{}:a", only).as_str()], vec![Filter::raw(normalize_audio_filter())], &["outa"]);
+        }
+        several => {
+            let mut pads = Vec::new();
+            for i in several {
+                let pad = graph.label("a");
+                graph.chain(&[format!("{}:a", i).as_str()], vec![Filter::raw(normalize_audio_filter())], &[pad.as_str()]);
+                pads.push(pad);
+            }
+            let pad_refs: Vec<&str> = pads.iter().map(String::as_str).collect();
+            let mix = Filter::new("amix").opt("inputs", several.len()).opt("duration", "shortest");
+            graph.chain(&pad_refs, vec![mix], &["outa"]);
+        }
+    }
+    let maps: &[&str] = if mixed.is_empty() { &["[outv]"] } else { &["[outv]", "[outa]"] };
+
+    let mut args: Vec<String> = Vec::new();
+    for input in &inputs {
+        args.extend(["-i".to_string(), input.clone()]);
+    }
+    args.extend(["-filter_complex".to_string(), graph.build(maps)?]);
+    for map in maps {
+        args.extend(["-map".to_string(), map.to_string()]);
+    }
+    args.extend(color::bt709_output_args());
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "split screen").await
+    })
+    .await
+}
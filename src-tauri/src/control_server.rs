@@ -0,0 +1,338 @@
+//! Local control server: an HTTP API and WebSocket event stream so scripts
+//! and external tools can queue renders and follow them without the UI
+//!
+//! The server is off unless `control_server_enabled` is set, listens on
+//! 127.0.0.1 only, and starts, stops, or moves port as settings change.
+//! Every request must carry the token from the config dir, either as
+//! `Authorization: Bearer <token>` or, for WebSocket clients that can't set
+//! headers, a `?token=` query parameter.
+//!
+//! Endpoints mirror the job commands:
+//! - `GET /api/jobs`, `POST /api/jobs` (`{ spec, priority }`)
+//! - `GET /api/jobs/{id}`, `GET /api/jobs/{id}/log`
+//! - `POST /api/jobs/{id}/cancel`, `/pause`, `/resume`
+//! - `GET /api/events`: WebSocket of `{ event, payload }` messages for job,
+//!   publish, and upload events
+
+use crate::error::{ClipFlowError, Result};
+use crate::jobs::{self, Job, JobPriority, JobSpec};
+use crate::settings;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Listener, Manager};
+use tokio::sync::{broadcast, oneshot, watch};
+
+/// App events passed on to WebSocket clients
+const FORWARDED_EVENTS: [&str; 8] = [
+    "job-updated",
+    "job-progress",
+    "publish-updated",
+    "download-progress",
+    "youtube-upload-progress",
+    "cloud-upload-progress",
+    "watch-file-ready",
+    "watch-pipeline-finished",
+];
+
+/// Events buffered per WebSocket client before a slow one starts missing some
+const EVENT_BUFFER: usize = 256;
+
+pub struct ControlServer {
+    /// Port of the running server and the sender that stops it
+    running: Mutex<Option<(u16, oneshot::Sender<()>)>>,
+    /// Token loaded from the config dir, once it's been read
+    token: Mutex<Option<String>>,
+    events: broadcast::Sender<String>,
+    /// Bumped when the token is replaced, closing streams opened with the old one
+    revoked: watch::Sender<u64>,
+}
+
+impl Default for ControlServer {
+    fn default() -> Self {
+        ControlServer {
+            running: Mutex::new(None),
+            token: Mutex::new(None),
+            events: broadcast::channel(EVENT_BUFFER).0,
+            revoked: watch::channel(0).0,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ControlServerInfo {
+    pub enabled: bool,
+    pub running: bool,
+    pub url: String,
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+struct SubmitJob {
+    spec: JobSpec,
+    priority: Option<JobPriority>,
+}
+
+fn token_path(app: &AppHandle) -> Result<PathBuf> {
+    settings::config_file(app, "control_server_token")
+}
+
+fn write_new_token(app: &AppHandle) -> Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| ClipFlowError::io("Failed to generate token", e))?;
+    let token = hex::encode(bytes);
+    let path = token_path(app)?;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // Only the user running ClipFlow may read the token
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&path).map_err(|e| ClipFlowError::io("Failed to write control server token", e))?;
+    // `mode` only applies when the file is created; tighten an older one too
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))
+            .map_err(|e| ClipFlowError::io("Failed to restrict control server token", e))?;
+    }
+    file.write_all(token.as_bytes()).map_err(|e| ClipFlowError::io("Failed to write control server token", e))?;
+    Ok(token)
+}
+
+/// The API token, created on first use
+fn token(app: &AppHandle) -> Result<String> {
+    let server = app.state::<ControlServer>();
+    let mut cached = server.token.lock().unwrap();
+    if let Some(token) = cached.as_ref() {
+        return Ok(token.clone());
+    }
+    let token = match fs::read_to_string(token_path(app)?) {
+        Ok(token) if !token.trim().is_empty() => token.trim().to_string(),
+        _ => write_new_token(app)?,
+    };
+    *cached = Some(token.clone());
+    Ok(token)
+}
+
+/// Compare without stopping at the first differing byte, so response times
+/// don't give the token away
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len() && given.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// A command error as an HTTP response, with the same JSON body the UI gets
+struct ApiError(ClipFlowError);
+
+impl From<ClipFlowError> for ApiError {
+    fn from(error: ClipFlowError) -> Self {
+        ApiError(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match self.0 {
+            ClipFlowError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            ClipFlowError::NotFound(_) => StatusCode::NOT_FOUND,
+            ClipFlowError::PreflightFailed(_) => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(self.0)).into_response()
+    }
+}
+
+type ApiResult<T> = std::result::Result<Json<T>, ApiError>;
+
+async fn authorize(
+    State(app): State<AppHandle>,
+    Query(params): Query<HashMap<String, String>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let given = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or(params.get("token").map(String::as_str));
+    let authorized = match (given, token(&app)) {
+        (Some(given), Ok(expected)) => token_matches(given.trim(), &expected),
+        _ => false,
+    };
+    if !authorized {
+        let body = serde_json::json!({ "kind": "unauthorized", "message": "Missing or wrong control server token" });
+        return (StatusCode::UNAUTHORIZED, Json(body)).into_response();
+    }
+    next.run(request).await
+}
+
+async fn list_jobs(State(app): State<AppHandle>) -> ApiResult<Vec<Job>> {
+    Ok(Json(jobs::list_jobs(app.state()).await?))
+}
+
+async fn submit_job(State(app): State<AppHandle>, Json(body): Json<SubmitJob>) -> ApiResult<Job> {
+    Ok(Json(jobs::submit_job(app.clone(), app.state(), body.spec, body.priority).await?))
+}
+
+async fn get_job(State(app): State<AppHandle>, Path(id): Path<String>) -> ApiResult<Job> {
+    Ok(Json(jobs::get_job(app.state(), id).await?))
+}
+
+async fn get_job_log(State(app): State<AppHandle>, Path(id): Path<String>) -> std::result::Result<String, ApiError> {
+    Ok(jobs::get_job_log(app, id).await?)
+}
+
+async fn cancel_job(State(app): State<AppHandle>, Path(id): Path<String>) -> ApiResult<Job> {
+    jobs::cancel_job(app.clone(), id.clone()).await?;
+    Ok(Json(jobs::get_job(app.state(), id).await?))
+}
+
+async fn pause_job(State(app): State<AppHandle>, Path(id): Path<String>) -> ApiResult<Job> {
+    Ok(Json(jobs::pause_job(app, id).await?))
+}
+
+async fn resume_job(State(app): State<AppHandle>, Path(id): Path<String>) -> ApiResult<Job> {
+    Ok(Json(jobs::resume_job(app, id).await?))
+}
+
+async fn events(State(app): State<AppHandle>, upgrade: WebSocketUpgrade) -> Response {
+    let server = app.state::<ControlServer>();
+    let events = server.events.subscribe();
+    let revoked = server.revoked.subscribe();
+    upgrade.on_upgrade(|socket| stream_events(socket, events, revoked))
+}
+
+async fn stream_events(mut socket: WebSocket, mut events: broadcast::Receiver<String>, mut revoked: watch::Receiver<u64>) {
+    loop {
+        tokio::select! {
+            _ = revoked.changed() => {
+                let _ = socket.send(Message::Close(None)).await;
+                break;
+            }
+            event = events.recv() => match event {
+                Ok(text) => {
+                    if socket.send(Message::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+                // The client fell behind and missed some; carry on from here
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::debug!(skipped, "control server client missed events");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = socket.recv() => {
+                if matches!(message, Some(Ok(Message::Close(_))) | Some(Err(_)) | None) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+async fn serve(app: AppHandle, port: u16, stop: oneshot::Receiver<()>) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .await
+        .map_err(|e| ClipFlowError::io(&format!("Failed to listen on port {}", port), e))?;
+    let router = Router::new()
+        .route("/api/jobs", get(list_jobs).post(submit_job))
+        .route("/api/jobs/:id", get(get_job))
+        .route("/api/jobs/:id/log", get(get_job_log))
+        .route("/api/jobs/:id/cancel", post(cancel_job))
+        .route("/api/jobs/:id/pause", post(pause_job))
+        .route("/api/jobs/:id/resume", post(resume_job))
+        .route("/api/events", get(events))
+        .layer(middleware::from_fn_with_state(app.clone(), authorize))
+        .with_state(app);
+    tracing::info!(port, "control server listening");
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async {
+            let _ = stop.await;
+        })
+        .await
+        .map_err(|e| ClipFlowError::io("Control server failed", e))
+}
+
+/// Start, stop, or restart the server to match the current settings
+fn apply(app: &AppHandle) {
+    let current = settings::current();
+    let wanted = current.control_server_enabled.then_some(current.control_server_port);
+    let server = app.state::<ControlServer>();
+    let mut running = server.running.lock().unwrap();
+    if running.as_ref().map(|(port, _)| *port) == wanted {
+        return;
+    }
+    if let Some((port, stop)) = running.take() {
+        let _ = stop.send(());
+        tracing::info!(port, "control server stopped");
+    }
+    let port = match wanted {
+        Some(port) => port,
+        None => return,
+    };
+    let (stop, stopped) = oneshot::channel();
+    *running = Some((port, stop));
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = serve(app.clone(), port, stopped).await {
+            tracing::warn!(error = %e, port, "control server not running");
+            let server = app.state::<ControlServer>();
+            let mut running = server.running.lock().unwrap();
+            if running.as_ref().is_some_and(|(p, _)| *p == port) {
+                *running = None;
+            }
+        }
+    });
+}
+
+/// Forward app events to WebSocket clients, and run the server whenever
+/// settings have it enabled
+pub fn start(app: AppHandle) {
+    for name in FORWARDED_EVENTS {
+        let handle = app.clone();
+        app.listen_any(name, move |event| {
+            let server = handle.state::<ControlServer>();
+            if server.events.receiver_count() > 0 {
+                let _ = server.events.send(format!("{{\"event\":\"{}\",\"payload\":{}}}", name, event.payload()));
+            }
+        });
+    }
+    let handle = app.clone();
+    app.listen_any("settings-changed", move |_| apply(&handle));
+    apply(&app);
+}
+
+/// Whether the server is up, where, and the token clients need
+#[tauri::command]
+pub async fn get_control_server_info(app: AppHandle) -> Result<ControlServerInfo> {
+    let current = settings::current();
+    let running = app.state::<ControlServer>().running.lock().unwrap().is_some();
+    Ok(ControlServerInfo {
+        enabled: current.control_server_enabled,
+        running,
+        url: format!("http://127.0.0.1:{}", current.control_server_port),
+        token: token(&app)?,
+    })
+}
+
+/// Replace the token, locking out every client that has the old one and
+/// closing event streams they already have open
+#[tauri::command]
+pub async fn regenerate_control_server_token(app: AppHandle) -> Result<String> {
+    let token = write_new_token(&app)?;
+    let server = app.state::<ControlServer>();
+    *server.token.lock().unwrap() = Some(token.clone());
+    server.revoked.send_modify(|generation| *generation += 1);
+    Ok(token)
+}
@@ -0,0 +1,193 @@
+//! API keys and upload credentials stored in the OS keychain
+//!
+//! Secrets never touch the settings files. We only keep an index of which
+//! (provider, account) pairs exist, since keychains can't be enumerated
+//! portably.
+
+use crate::error::{ClipFlowError, Result};
+use crate::{cloud, now_secs, settings, youtube};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+/// Service name credentials are filed under in the keychain
+const KEYCHAIN_SERVICE: &str = "com.clipflow.app";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialProvider {
+    YouTube,
+    S3,
+    OpenAI,
+}
+
+impl CredentialProvider {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CredentialProvider::YouTube => "youtube",
+            CredentialProvider::S3 => "s3",
+            CredentialProvider::OpenAI => "openai",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CredentialInfo {
+    provider: CredentialProvider,
+    account: String,
+    added_at: u64,
+}
+
+#[derive(Serialize)]
+pub struct CredentialTestResult {
+    ok: bool,
+    message: String,
+}
+
+fn keychain_entry(provider: CredentialProvider, account: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, &format!("{}:{}", provider.as_str(), account))
+        .map_err(|e| ClipFlowError::io("Failed to access keychain", e))
+}
+
+fn index_path(app: &AppHandle) -> Result<PathBuf> {
+    settings::config_file(app, "credentials.json")
+}
+
+fn read_index(app: &AppHandle) -> Result<Vec<CredentialInfo>> {
+    let path = index_path(app)?;
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content = fs::read_to_string(&path).map_err(|e| ClipFlowError::io("Failed to read credential index", e))?;
+    serde_json::from_str(&content).map_err(|e| ClipFlowError::io("Invalid credential index", e))
+}
+
+fn write_index(app: &AppHandle, index: &[CredentialInfo]) -> Result<()> {
+    let json = serde_json::to_string_pretty(index)
+        .map_err(|e| ClipFlowError::io("Failed to serialize credential index", e))?;
+    fs::write(index_path(app)?, json).map_err(|e| ClipFlowError::io("Failed to write credential index", e))
+}
+
+/// Look up a stored secret for use by the publishing subsystems
+pub fn get_secret(provider: CredentialProvider, account: &str) -> Result<Option<String>> {
+    match keychain_entry(provider, account)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(ClipFlowError::tool("keychain", format!("read failed: {}", e))),
+    }
+}
+
+/// Store or replace a secret without touching the index
+/// Used when a subsystem refreshes a token it already owns
+pub fn set_secret(provider: CredentialProvider, account: &str, secret: &str) -> Result<()> {
+    keychain_entry(provider, account)?
+        .set_password(secret)
+        .map_err(|e| ClipFlowError::tool("keychain", format!("store failed: {}", e)))
+}
+
+#[tauri::command]
+pub async fn list_credentials(app: AppHandle) -> Result<Vec<CredentialInfo>> {
+    read_index(&app)
+}
+
+#[tauri::command]
+pub async fn add_credential(
+    app: AppHandle,
+    provider: CredentialProvider,
+    account: String,
+    secret: String,
+) -> Result<()> {
+    if account.trim().is_empty() || secret.is_empty() {
+        return Err(ClipFlowError::invalid("Account and secret must not be empty"));
+    }
+
+    set_secret(provider, &account, &secret)?;
+
+    let mut index = read_index(&app)?;
+    index.retain(|c| !(c.provider == provider && c.account == account));
+    index.push(CredentialInfo {
+        provider,
+        account,
+        added_at: now_secs(),
+    });
+    write_index(&app, &index)
+}
+
+#[tauri::command]
+pub async fn remove_credential(app: AppHandle, provider: CredentialProvider, account: String) -> Result<()> {
+    match keychain_entry(provider, &account)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => {}
+        Err(e) => return Err(ClipFlowError::tool("keychain", format!("remove failed: {}", e))),
+    }
+
+    let mut index = read_index(&app)?;
+    index.retain(|c| !(c.provider == provider && c.account == account));
+    write_index(&app, &index)
+}
+
+/// Check that a stored credential is accepted by its service
+#[tauri::command]
+pub async fn test_credential(app: AppHandle, provider: CredentialProvider, account: String) -> Result<CredentialTestResult> {
+    let secret = match get_secret(provider, &account)? {
+        Some(secret) => secret,
+        None => {
+            return Ok(CredentialTestResult {
+                ok: false,
+                message: "No credential stored for this account".to_string(),
+            })
+        }
+    };
+
+    let client = reqwest::Client::new();
+    let response = match provider {
+        CredentialProvider::OpenAI => {
+            client
+                .get("https://api.openai.com/v1/models")
+                .bearer_auth(&secret)
+                .send()
+                .await
+        }
+        CredentialProvider::YouTube => {
+            client
+                .get("https://www.googleapis.com/oauth2/v3/tokeninfo")
+                .query(&[("access_token", youtube::stored_access_token(&secret))])
+                .send()
+                .await
+        }
+        CredentialProvider::S3 => {
+            // S3 secrets are stored as "access_key_id:secret_access_key"
+            if !secret.split_once(':').is_some_and(|(id, key)| !id.is_empty() && !key.is_empty()) {
+                return Ok(CredentialTestResult {
+                    ok: false,
+                    message: "Expected \"access_key_id:secret_access_key\"".to_string(),
+                });
+            }
+            // The endpoint lives with the upload destination, so list a
+            // bucket that signs with this account
+            let destination = match cloud::destinations_using(&app, &account)?.into_iter().next() {
+                Some(destination) => destination,
+                None => {
+                    return Ok(CredentialTestResult {
+                        ok: false,
+                        message: "No cloud destination uses this credential, so there's nothing to test it against"
+                            .to_string(),
+                    })
+                }
+            };
+            cloud::list_request(&client, &destination)?.send().await
+        }
+    };
+
+    match response {
+        Ok(response) if response.status().is_success() => Ok(CredentialTestResult {
+            ok: true,
+            message: "Credential accepted".to_string(),
+        }),
+        Ok(response) => Ok(CredentialTestResult {
+            ok: false,
+            message: format!("Service rejected credential (HTTP {})", response.status().as_u16()),
+        }),
+        Err(e) => Err(ClipFlowError::Network(format!("Failed to reach service: {}", e))),
+    }
+}
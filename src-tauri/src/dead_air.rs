@@ -0,0 +1,146 @@
+//! Black and frozen picture detection, for finding dead air in recordings
+//!
+//! Screen recordings pick up long stretches where nothing is shown (a
+//! minimized capture window) or nothing moves (a paused game, a stalled
+//! capture). Each command returns the ranges found, to jump to, and the
+//! segments around them in the shape `cut_video_remove` takes, to cut them.
+
+use crate::error::{ClipFlowError, Result};
+use crate::{probe, process, settings};
+use serde::Serialize;
+use tokio::process::Command;
+
+const DEFAULT_MIN_DURATION: f64 = 2.0;
+
+/// blackdetect's share of near-black pixels for a frame to count as black
+const DEFAULT_BLACK_RATIO: f64 = 0.98;
+
+/// freezedetect's noise tolerance; compression noise on a still frame
+/// stays under this
+const DEFAULT_FREEZE_NOISE_DB: f64 = -60.0;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DeadAirRange {
+    pub start: f64,
+    pub end: f64,
+    pub duration: f64,
+}
+
+/// A stretch between dead air ranges, named like `cut_video_remove`'s input
+#[derive(Serialize, Clone, Debug)]
+pub struct KeepSegment {
+    pub keep_start: f64,
+    pub keep_end: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DeadAirReport {
+    pub ranges: Vec<DeadAirRange>,
+    /// Everything else, for cutting the ranges out
+    pub keep: Vec<KeepSegment>,
+}
+
+/// Run a detection filter over the first video stream, returning stderr
+async fn run_filter(file_path: &str, filter: &str) -> Result<String> {
+    let ffmpeg = settings::ffmpeg_bin();
+    let output = process::run(Command::new(&ffmpeg).args([
+        "-hide_banner",
+        "-i", file_path,
+        "-map", "0:v:0",
+        "-an",
+        "-vf", filter,
+        "-f", "null",
+        "-",
+    ]), None, |_| {})
+    .await
+    .map_err(|e| process::spawn_error(&ffmpeg, e))?;
+    process::check_ffmpeg(&output, "dead air detection")?;
+    Ok(String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+/// Number after `key` on a line, e.g. `black_start:` or `freeze_end: `
+fn value_after(line: &str, key: &str) -> Option<f64> {
+    line.split(key).nth(1)?.split_whitespace().next()?.parse().ok()
+}
+
+/// blackdetect's "black_start:1.2 black_end:3.4 ..." lines
+fn parse_blackdetect(stderr: &str) -> Vec<DeadAirRange> {
+    stderr
+        .lines()
+        .filter_map(|line| {
+            let start = value_after(line, "black_start:")?;
+            let end = value_after(line, "black_end:")?;
+            Some(DeadAirRange { start, end, duration: end - start })
+        })
+        .collect()
+}
+
+/// freezedetect's freeze_start/freeze_end metadata lines in order; a freeze
+/// running to the end of the file has no end line
+fn parse_freezedetect(stderr: &str, duration: f64) -> Vec<DeadAirRange> {
+    let mut ranges = Vec::new();
+    let mut pending: Option<f64> = None;
+    for line in stderr.lines() {
+        if let Some(start) = value_after(line, "freeze_start:") {
+            pending = Some(start);
+        } else if let Some(end) = value_after(line, "freeze_end:") {
+            if let Some(start) = pending.take() {
+                ranges.push(DeadAirRange { start, end, duration: end - start });
+            }
+        }
+    }
+    if let Some(start) = pending {
+        ranges.push(DeadAirRange { start, end: duration, duration: duration - start });
+    }
+    ranges
+}
+
+fn report(ranges: Vec<DeadAirRange>, duration: f64) -> DeadAirReport {
+    let mut keep = Vec::new();
+    let mut cursor = 0.0;
+    for range in &ranges {
+        if range.start > cursor {
+            keep.push(KeepSegment { keep_start: cursor, keep_end: range.start });
+        }
+        cursor = range.end.max(cursor);
+    }
+    if duration > cursor {
+        keep.push(KeepSegment { keep_start: cursor, keep_end: duration });
+    }
+    DeadAirReport { ranges, keep }
+}
+
+fn check_min_duration(min_duration: Option<f64>) -> Result<f64> {
+    let min_duration = min_duration.unwrap_or(DEFAULT_MIN_DURATION);
+    if min_duration.is_nan() || min_duration <= 0.0 {
+        return Err(ClipFlowError::invalid("Minimum duration must be positive"));
+    }
+    Ok(min_duration)
+}
+
+/// Find stretches of black picture at least `min_duration` seconds long
+/// `black_ratio` (0-1) is how much of the frame must be black, 0.98 unless set.
+#[tauri::command]
+pub async fn detect_black_frames(file_path: &str, min_duration: Option<f64>, black_ratio: Option<f64>) -> Result<DeadAirReport> {
+    let min_duration = check_min_duration(min_duration)?;
+    let black_ratio = black_ratio.unwrap_or(DEFAULT_BLACK_RATIO);
+    if !(0.0..=1.0).contains(&black_ratio) {
+        return Err(ClipFlowError::invalid(format!("Black ratio must be between 0 and 1, got {}", black_ratio)));
+    }
+    let duration = probe::media_duration(file_path)?;
+    let stderr = run_filter(file_path, &format!("blackdetect=d={}:pic_th={}", min_duration, black_ratio)).await?;
+    Ok(report(parse_blackdetect(&stderr), duration))
+}
+
+/// Find stretches where the picture doesn't change for at least
+/// `min_duration` seconds
+/// `noise_db` is how much change still counts as frozen, -60dB unless set;
+/// raise it for noisy or heavily compressed sources.
+#[tauri::command]
+pub async fn detect_frozen_frames(file_path: &str, min_duration: Option<f64>, noise_db: Option<f64>) -> Result<DeadAirReport> {
+    let min_duration = check_min_duration(min_duration)?;
+    let noise_db = noise_db.unwrap_or(DEFAULT_FREEZE_NOISE_DB);
+    let duration = probe::media_duration(file_path)?;
+    let stderr = run_filter(file_path, &format!("freezedetect=n={}dB:d={}", noise_db, min_duration)).await?;
+    Ok(report(parse_freezedetect(&stderr, duration), duration))
+}
@@ -0,0 +1,150 @@
+//! Exact-duplicate detection by content hash
+//!
+//! Recording libraries pick up byte-identical copies - a card offloaded
+//! twice, a file dragged into two folders. Only files sharing a size can be
+//! identical, so sizes are compared first and just the colliding files get
+//! hashed. Hashing runs on a blocking thread and reports progress, since a
+//! few hundred GB of footage takes minutes to read.
+
+use crate::error::{ClipFlowError, Result};
+use crate::{ingest, library};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, Emitter};
+
+/// Bytes read between progress events
+const PROGRESS_STEP: u64 = 64 * 1024 * 1024;
+
+#[derive(Serialize, Clone)]
+pub struct FileHash {
+    pub path: String,
+    pub size_bytes: u64,
+    /// BLAKE3, hex
+    pub hash: String,
+}
+
+#[derive(Serialize, Clone)]
+struct HashProgress {
+    file: String,
+    file_index: usize,
+    file_count: usize,
+    bytes_done: u64,
+    bytes_total: u64,
+}
+
+#[derive(Serialize)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub size_bytes: u64,
+    /// Sorted; the first is a reasonable one to keep
+    pub paths: Vec<String>,
+    /// Space freed by keeping only one copy
+    pub wasted_bytes: u64,
+}
+
+/// Hash `files` (path, size) in order, emitting "hash-progress" as it goes
+/// Unreadable files are logged and left out.
+fn hash_all(app: &AppHandle, files: &[(String, u64)]) -> Vec<FileHash> {
+    let bytes_total: u64 = files.iter().map(|(_, size)| size).sum();
+    let mut bytes_done = 0u64;
+    let mut last_emitted = 0u64;
+    let mut hashes = Vec::with_capacity(files.len());
+
+    for (index, (path, size)) in files.iter().enumerate() {
+        let progress = |bytes_done| HashProgress {
+            file: path.clone(),
+            file_index: index,
+            file_count: files.len(),
+            bytes_done,
+            bytes_total,
+        };
+        let _ = app.emit("hash-progress", progress(bytes_done));
+        let result = ingest::hash_file_with_progress(Path::new(path), |n| {
+            bytes_done += n;
+            if bytes_done - last_emitted >= PROGRESS_STEP {
+                last_emitted = bytes_done;
+                let _ = app.emit("hash-progress", progress(bytes_done));
+            }
+        });
+        match result {
+            Ok(hash) => hashes.push(FileHash { path: path.clone(), size_bytes: *size, hash }),
+            Err(e) => tracing::warn!(path = %path, error = %e, "skipping unreadable file while hashing"),
+        }
+    }
+
+    let _ = app.emit("hash-progress", HashProgress {
+        file: String::new(),
+        file_index: files.len(),
+        file_count: files.len(),
+        bytes_done,
+        bytes_total,
+    });
+    hashes
+}
+
+fn sized(paths: &[String]) -> Vec<(String, u64)> {
+    paths
+        .iter()
+        .filter_map(|p| fs::metadata(p).ok().filter(|m| m.is_file()).map(|m| (p.clone(), m.len())))
+        .collect()
+}
+
+/// Content hashes of files, computed off the async runtime
+/// Emits "hash-progress" while reading.
+#[tauri::command]
+pub async fn hash_files(app: AppHandle, paths: Vec<String>) -> Result<Vec<FileHash>> {
+    let files = sized(&paths);
+    if files.is_empty() {
+        return Err(ClipFlowError::invalid("No readable files to hash"));
+    }
+    tauri::async_runtime::spawn_blocking(move || hash_all(&app, &files))
+        .await
+        .map_err(|e| ClipFlowError::io("Hashing thread failed", e))
+}
+
+/// Byte-identical files among `paths`, or across the whole library when
+/// `paths` is omitted
+#[tauri::command]
+pub async fn find_duplicates(app: AppHandle, paths: Option<Vec<String>>) -> Result<Vec<DuplicateGroup>> {
+    let paths = match paths {
+        Some(paths) => paths,
+        None => library::read_library(&app)?.into_iter().map(|item| item.path).collect(),
+    };
+
+    let mut by_size: HashMap<u64, Vec<String>> = HashMap::new();
+    for (path, size) in sized(&paths) {
+        by_size.entry(size).or_default().push(path);
+    }
+    // Empty files all match each other and aren't worth reporting
+    let candidates: Vec<(String, u64)> = by_size
+        .into_iter()
+        .filter(|(size, paths)| *size > 0 && paths.len() > 1)
+        .flat_map(|(size, paths)| paths.into_iter().map(move |p| (p, size)))
+        .collect();
+    if candidates.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let hashes = tauri::async_runtime::spawn_blocking(move || hash_all(&app, &candidates))
+        .await
+        .map_err(|e| ClipFlowError::io("Hashing thread failed", e))?;
+
+    let mut by_hash: HashMap<String, Vec<FileHash>> = HashMap::new();
+    for file in hashes {
+        by_hash.entry(file.hash.clone()).or_default().push(file);
+    }
+    let mut groups: Vec<DuplicateGroup> = by_hash
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .map(|(hash, files)| {
+            let size_bytes = files[0].size_bytes;
+            let mut paths: Vec<String> = files.into_iter().map(|f| f.path).collect();
+            paths.sort();
+            DuplicateGroup { wasted_bytes: size_bytes * (paths.len() as u64 - 1), hash, size_bytes, paths }
+        })
+        .collect();
+    groups.sort_by(|a, b| b.wasted_bytes.cmp(&a.wasted_bytes));
+    Ok(groups)
+}
@@ -0,0 +1,170 @@
+//! Deinterlacing and inverse telecine for camcorder and capture-card footage
+//!
+//! What the container says about field order is often wrong (capture cards
+//! tag progressive video as interlaced and vice versa), so detection also
+//! runs ffmpeg's idet filter over a stretch of frames and goes by what the
+//! picture shows. Telecined film - progressive frames spread over fields
+//! with 3:2 pulldown - shows up as repeated fields, and is restored to its
+//! original frames instead of being deinterlaced, which would blur it.
+
+use crate::error::{ClipFlowError, Result};
+use crate::output::{self, OverwritePolicy};
+use crate::{color, probe, process, settings};
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+/// Frames idet looks at; enough to get past an opening title or black
+const DETECT_FRAMES: u32 = 600;
+
+/// Share of frames that must look interlaced to call the file interlaced
+const INTERLACED_SHARE: f64 = 0.1;
+
+/// Share of repeated fields typical of pulldown (3:2 repeats 2 fields in
+/// every 10)
+const TELECINE_SHARE: f64 = 0.1;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DeinterlaceMode {
+    /// Pick from detection: IVTC for telecine, bwdif for interlaced, and
+    /// nothing for progressive footage
+    Auto,
+    /// Fast, a little softer
+    Yadif,
+    /// Sharper than yadif at similar speed
+    Bwdif,
+    /// Inverse telecine: rebuild the original film frames
+    Ivtc,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct InterlaceInfo {
+    /// What the container says: "progressive", "tt", "bb", "tb", "bt", or
+    /// "unknown"
+    pub field_order: String,
+    pub interlaced_frames: u64,
+    pub progressive_frames: u64,
+    pub repeated_fields: u64,
+    /// "progressive", "interlaced", or "telecined"
+    pub verdict: String,
+    /// Top field first, going by the picture when it's interlaced
+    pub top_field_first: bool,
+    /// None when there's nothing to fix
+    pub suggested_mode: Option<DeinterlaceMode>,
+}
+
+/// Sum of the counts after `key` in idet's multi-frame summary line
+fn idet_count(line: &str, key: &str) -> u64 {
+    line.split(key).nth(1).and_then(|rest| rest.split_whitespace().next()).and_then(|n| n.parse().ok()).unwrap_or(0)
+}
+
+/// Probe field order and run idet over the first `DETECT_FRAMES` frames
+async fn detect(input_path: &str) -> Result<InterlaceInfo> {
+    let json = probe::ffprobe_json(input_path)?;
+    let field_order = json["streams"]
+        .as_array()
+        .and_then(|streams| streams.iter().find(|s| s["codec_type"] == "video"))
+        .ok_or_else(|| ClipFlowError::invalid(format!("No video stream found. Path: {}", input_path)))?["field_order"]
+        .as_str()
+        .unwrap_or("unknown")
+        .to_string();
+
+    let ffmpeg = settings::ffmpeg_bin();
+    let frames = DETECT_FRAMES.to_string();
+    let output = process::run(Command::new(&ffmpeg).args([
+        "-hide_banner",
+        "-i", input_path,
+        "-map", "0:v:0",
+        "-frames:v", frames.as_str(),
+        "-vf", "idet",
+        "-an",
+        "-f", "null",
+        "-",
+    ]), None, |_| {})
+    .await
+    .map_err(|e| process::spawn_error(&ffmpeg, e))?;
+    process::check_ffmpeg(&output, "interlace detection")?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let multi = stderr.lines().rev().find(|l| l.contains("Multi frame detection:")).unwrap_or("");
+    let repeated = stderr.lines().rev().find(|l| l.contains("Repeated Fields:")).unwrap_or("");
+    let (tff, bff) = (idet_count(multi, "TFF:"), idet_count(multi, "BFF:"));
+    let progressive_frames = idet_count(multi, "Progressive:");
+    let interlaced_frames = tff + bff;
+    let repeated_fields = idet_count(repeated, "Top:") + idet_count(repeated, "Bottom:");
+
+    let total = (interlaced_frames + progressive_frames + idet_count(multi, "Undetermined:")).max(1) as f64;
+    let (verdict, suggested_mode) = if repeated_fields as f64 / total >= TELECINE_SHARE {
+        ("telecined", Some(DeinterlaceMode::Ivtc))
+    } else if interlaced_frames as f64 / total >= INTERLACED_SHARE {
+        ("interlaced", Some(DeinterlaceMode::Bwdif))
+    } else {
+        ("progressive", None)
+    };
+    let top_field_first = if interlaced_frames > 0 { tff >= bff } else { matches!(field_order.as_str(), "tt" | "tb") };
+
+    Ok(InterlaceInfo {
+        field_order,
+        interlaced_frames,
+        progressive_frames,
+        repeated_fields,
+        verdict: verdict.to_string(),
+        top_field_first,
+        suggested_mode,
+    })
+}
+
+/// Whether a file is progressive, interlaced, or telecined, and what to do
+/// about it
+#[tauri::command]
+pub async fn detect_interlacing(input_path: &str) -> Result<InterlaceInfo> {
+    detect(input_path).await
+}
+
+/// Deinterlace or inverse-telecine footage
+/// `double_rate` outputs one frame per field (e.g. 50i to 50p) for smoother
+/// motion; it doesn't apply to IVTC. Audio is copied. Returns the path written.
+#[tauri::command]
+pub async fn deinterlace(
+    input_path: &str,
+    output_path: &str,
+    mode: DeinterlaceMode,
+    double_rate: Option<bool>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let info = detect(input_path).await?;
+    let mode = match mode {
+        DeinterlaceMode::Auto => info
+            .suggested_mode
+            .ok_or_else(|| ClipFlowError::invalid(format!("{} is already progressive", input_path)))?,
+        other => other,
+    };
+    let parity = if info.top_field_first { "tff" } else { "bff" };
+    let rate = if double_rate.unwrap_or(false) { "send_field" } else { "send_frame" };
+    // deint=all: footage tagged progressive can still be interlaced, which
+    // is why detection looks at the picture
+    let filter = match mode {
+        DeinterlaceMode::Yadif => format!("yadif=mode={}:parity={}:deint=all", rate, parity),
+        DeinterlaceMode::Bwdif | DeinterlaceMode::Auto => format!("bwdif=mode={}:parity={}:deint=all", rate, parity),
+        // Match fields back into frames, clean up any leftover combing,
+        // then drop the duplicate frame pulldown leaves in every five
+        DeinterlaceMode::Ivtc => format!("fieldmatch=order={},yadif=deint=interlaced,decimate", parity),
+    };
+
+    let (color_filter, color_tags) = color::export_color_args(input_path);
+    let video_filters: Vec<String> = std::iter::once(filter).chain(color_filter).collect();
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-vf".to_string(), video_filters.join(","),
+        "-c:a".to_string(), "copy".to_string(),
+        "-field_order".to_string(), "progressive".to_string(),
+    ];
+    args.extend(color_tags);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "deinterlace").await
+    })
+    .await
+}
@@ -0,0 +1,96 @@
+//! Turning ffmpeg's stderr into something a user can act on
+//!
+//! ffmpeg reports every problem as free text on stderr and exit code 1;
+//! this maps the common failures onto typed errors with a plain-language
+//! explanation, keeping the offending line for the details view.
+
+use serde::Serialize;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FfmpegFailure {
+    /// Encoder/decoder missing from this ffmpeg build, or a codec the
+    /// container can't hold
+    UnsupportedCodec { detail: String },
+    /// GPU encoder present in ffmpeg but unusable on this machine
+    HardwareEncoderUnavailable { detail: String },
+    DiskFull,
+    PermissionDenied { detail: String },
+    FileNotFound { detail: String },
+    /// Broken or truncated input (missing moov atom, garbage data)
+    CorruptInput { detail: String },
+    /// Timestamps ffmpeg couldn't make sense of, usually from a bad trim
+    /// point or a variable-frame-rate source
+    InvalidTimestamp { detail: String },
+    /// A filter graph ffmpeg rejected
+    FilterError { detail: String },
+    Unknown { detail: String },
+}
+
+impl FfmpegFailure {
+    /// One-sentence explanation for the UI
+    pub fn message(&self) -> String {
+        match self {
+            FfmpegFailure::UnsupportedCodec { detail } => {
+                format!("A codec isn't supported by this ffmpeg build or output format ({})", detail)
+            }
+            FfmpegFailure::HardwareEncoderUnavailable { .. } => {
+                "The hardware encoder couldn't be started - switch hardware acceleration off in settings".to_string()
+            }
+            FfmpegFailure::DiskFull => "The disk is full - free up space or choose another output folder".to_string(),
+            FfmpegFailure::PermissionDenied { detail } => format!("Permission denied ({})", detail),
+            FfmpegFailure::FileNotFound { detail } => format!("File not found ({})", detail),
+            FfmpegFailure::CorruptInput { detail } => {
+                format!("The input file is damaged or incomplete ({})", detail)
+            }
+            FfmpegFailure::InvalidTimestamp { detail } => {
+                format!("The source has timestamps ffmpeg can't process ({})", detail)
+            }
+            FfmpegFailure::FilterError { detail } => format!("A video/audio filter failed ({})", detail),
+            FfmpegFailure::Unknown { detail } => detail.clone(),
+        }
+    }
+}
+
+/// Patterns checked in order; the first one found in stderr wins
+const PATTERNS: &[(&str, fn(String) -> FfmpegFailure)] = &[
+    ("No space left on device", |_| FfmpegFailure::DiskFull),
+    ("Cannot load nvcuda", |d| FfmpegFailure::HardwareEncoderUnavailable { detail: d }),
+    ("No NVENC capable devices", |d| FfmpegFailure::HardwareEncoderUnavailable { detail: d }),
+    ("OpenEncodeSessionEx failed", |d| FfmpegFailure::HardwareEncoderUnavailable { detail: d }),
+    ("Error creating a MFX session", |d| FfmpegFailure::HardwareEncoderUnavailable { detail: d }),
+    ("Error while opening encoder", |d| FfmpegFailure::HardwareEncoderUnavailable { detail: d }),
+    ("Permission denied", |d| FfmpegFailure::PermissionDenied { detail: d }),
+    ("No such file or directory", |d| FfmpegFailure::FileNotFound { detail: d }),
+    ("Unknown encoder", |d| FfmpegFailure::UnsupportedCodec { detail: d }),
+    ("Encoder not found", |d| FfmpegFailure::UnsupportedCodec { detail: d }),
+    ("Decoder not found", |d| FfmpegFailure::UnsupportedCodec { detail: d }),
+    ("Could not find tag for codec", |d| FfmpegFailure::UnsupportedCodec { detail: d }),
+    ("not currently supported in container", |d| FfmpegFailure::UnsupportedCodec { detail: d }),
+    ("moov atom not found", |d| FfmpegFailure::CorruptInput { detail: d }),
+    ("Invalid data found when processing input", |d| FfmpegFailure::CorruptInput { detail: d }),
+    ("Invalid timestamp", |d| FfmpegFailure::InvalidTimestamp { detail: d }),
+    ("non monotonically increasing dts", |d| FfmpegFailure::InvalidTimestamp { detail: d }),
+    ("Non-monotonous DTS", |d| FfmpegFailure::InvalidTimestamp { detail: d }),
+    ("Invalid duration specification", |d| FfmpegFailure::InvalidTimestamp { detail: d }),
+    ("No such filter", |d| FfmpegFailure::FilterError { detail: d }),
+    ("Error initializing filter", |d| FfmpegFailure::FilterError { detail: d }),
+    ("Error reinitializing filters", |d| FfmpegFailure::FilterError { detail: d }),
+];
+
+/// Classify a failed ffmpeg run from its stderr
+pub fn classify(stderr: &str) -> FfmpegFailure {
+    for (needle, make) in PATTERNS {
+        if let Some(line) = stderr.lines().find(|l| l.contains(needle)) {
+            return make(line.trim().to_string());
+        }
+    }
+    let last_line = stderr
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or("ffmpeg exited with an error")
+        .to_string();
+    FfmpegFailure::Unknown { detail: last_line }
+}
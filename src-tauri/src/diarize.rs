@@ -0,0 +1,92 @@
+//! Speaker diarization: working out who speaks when
+//!
+//! Diarization runs through an external pyannote-compatible tool -
+//! `diarize_path` in settings, or `diarize` on PATH - called as
+//! `<tool> <audio.wav> --output <file.rttm> [--num-speakers N]`. RTTM is
+//! the standard "who spoke when" format every diarization toolkit writes,
+//! so any model can sit behind that command. Each transcript segment is
+//! labelled with the speaker heard most during it.
+
+use crate::error::{ClipFlowError, Result};
+use crate::process;
+use crate::settings;
+use crate::temp::TempFile;
+use std::fs;
+use tokio::process::Command;
+
+/// One stretch of a single speaker talking
+#[derive(Clone, Debug)]
+pub struct SpeakerTurn {
+    pub speaker: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Turns from RTTM `SPEAKER` lines, with the tool's labels (e.g.
+/// "SPEAKER_00") renamed "Speaker 1", "Speaker 2"... in order of first
+/// appearance
+fn parse_rttm(text: &str) -> Vec<SpeakerTurn> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut turns: Vec<SpeakerTurn> = text
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 8 || fields[0] != "SPEAKER" {
+                return None;
+            }
+            let start: f64 = fields[3].parse().ok()?;
+            let duration: f64 = fields[4].parse().ok()?;
+            Some((fields[7].to_string(), start, start + duration))
+        })
+        .map(|(label, start, end)| {
+            let n = match labels.iter().position(|l| *l == label) {
+                Some(n) => n,
+                None => {
+                    labels.push(label);
+                    labels.len() - 1
+                }
+            };
+            SpeakerTurn { speaker: format!("Speaker {}", n + 1), start, end }
+        })
+        .collect();
+    turns.sort_by(|a, b| a.start.total_cmp(&b.start));
+    turns
+}
+
+/// Who speaks when in `wav_path`; `num_speakers` helps the model when the
+/// count is known
+pub async fn speaker_turns(wav_path: &str, num_speakers: Option<u32>) -> Result<Vec<SpeakerTurn>> {
+    let tool = settings::current().diarize_path.unwrap_or_else(|| "diarize".to_string());
+    let rttm = TempFile::new("diarize", "rttm")?;
+    let rttm_path = rttm.path_str();
+    let mut command = Command::new(&tool);
+    command.args([wav_path, "--output", rttm_path.as_str()]);
+    if let Some(n) = num_speakers {
+        command.args(["--num-speakers", &n.to_string()]);
+    }
+    let output = process::run(&mut command, None, |_| {})
+        .await
+        .map_err(|e| process::spawn_error(&tool, e))?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(ClipFlowError::tool("diarization", error.trim()));
+    }
+    let text = fs::read_to_string(rttm.path()).map_err(|e| ClipFlowError::io("Failed to read diarization output", e))?;
+    Ok(parse_rttm(&text))
+}
+
+/// Speaker with the most overlap with `start..end`, if anyone speaks then
+pub fn speaker_for(turns: &[SpeakerTurn], start: f64, end: f64) -> Option<String> {
+    let mut overlaps: Vec<(&str, f64)> = Vec::new();
+    for turn in turns.iter().filter(|t| t.end > start && t.start < end) {
+        let overlap = turn.end.min(end) - turn.start.max(start);
+        match overlaps.iter_mut().find(|(speaker, _)| *speaker == turn.speaker) {
+            Some((_, total)) => *total += overlap,
+            None => overlaps.push((&turn.speaker, overlap)),
+        }
+    }
+    overlaps
+        .into_iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(speaker, _)| speaker.to_string())
+}
@@ -0,0 +1,369 @@
+//! Downloading remote media into a local folder
+//!
+//! Direct links to media files go to a `.part` file and resume with a Range
+//! request after a dropped connection, using the shared retry policy and a
+//! transfer checkpoint so a restart of the app can pick up where it left
+//! off. Video pages (YouTube, Twitch VODs, and anything else it supports)
+//! go through yt-dlp (`ytdlp_path` in settings, or `yt-dlp` on PATH).
+
+use crate::error::{ClipFlowError, Result};
+use crate::library::{self, VIDEO_EXTENSIONS};
+use crate::retry::{self, NetworkError, TransferCheckpoint};
+use crate::{process, settings};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, State};
+use tokio::process::Command;
+use tokio::sync::Notify;
+
+/// Prefixes of the lines yt-dlp is asked to print, told apart from its
+/// other output
+const PROGRESS_PREFIX: &str = "clipflow-progress";
+const FILE_PREFIX: &str = "clipflow-file ";
+
+/// Audio-only downloads worth fetching directly rather than through yt-dlp
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "m4a", "wav", "flac", "aac", "ogg", "opus"];
+
+#[derive(Serialize, Clone)]
+struct DownloadProgress {
+    url: String,
+    bytes_done: u64,
+    bytes_total: u64,
+}
+
+/// Default folder for downloaded media
+pub fn downloads_dir(app: &AppHandle) -> Result<PathBuf> {
+    settings::data_dir(app, "downloads")
+}
+
+/// File name for a URL: the last path segment, or a fallback for bare hosts
+fn file_name_for(url: &reqwest::Url) -> String {
+    url.path_segments()
+        .and_then(|mut segments| segments.rfind(|s| !s.is_empty()))
+        .map(|s| s.chars().map(|c| if "<>:\"/\\|?*".contains(c) { '_' } else { c }).collect())
+        .unwrap_or_else(|| "download".to_string())
+}
+
+/// Part file a download to `destination` is written to until it's complete
+fn part_path_for(destination: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.part", destination.to_string_lossy()))
+}
+
+/// Pick a destination in `dir` that doesn't clobber an existing file and
+/// reserve it by creating its part file, so a concurrent download of
+/// another URL with the same file name picks a different one
+/// Returns the part file's path.
+fn reserve_destination(dir: &Path, name: &str) -> Result<PathBuf> {
+    let path = Path::new(name);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    std::iter::once(dir.join(name))
+        .chain((1..).map(|n| dir.join(format!("{} ({}){}", stem, n, ext))))
+        .filter(|candidate| !candidate.exists())
+        .find_map(|candidate| {
+            let part = part_path_for(&candidate);
+            match OpenOptions::new().write(true).create_new(true).open(&part) {
+                Ok(_) => Some(Ok(part)),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => None,
+                Err(e) => Some(Err(ClipFlowError::io("Failed to create download file", e))),
+            }
+        })
+        .unwrap()
+}
+
+/// Fetch whatever is left of the download, appending to `part_path`
+async fn fetch_remaining(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    url: &str,
+    part_path: &Path,
+    checkpoint_id: &str,
+) -> std::result::Result<(), NetworkError> {
+    let offset = fs::metadata(part_path).map(|m| m.len()).unwrap_or(0);
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+    }
+    let mut response = retry::check_response(request.send().await?).await?;
+
+    // A server that ignores Range sends the whole file again
+    let resumed = offset > 0 && response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(part_path)
+        .map_err(|e| NetworkError::Fatal(format!("Failed to open download file: {}", e)))?;
+    let mut bytes_done = if resumed { offset } else { 0 };
+    let bytes_total = response.content_length().map(|len| len + bytes_done).unwrap_or(0);
+
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk)
+            .map_err(|e| NetworkError::Fatal(format!("Failed to write download: {}", e)))?;
+        bytes_done += chunk.len() as u64;
+        let _ = app.emit("download-progress", DownloadProgress {
+            url: url.to_string(),
+            bytes_done,
+            bytes_total,
+        });
+    }
+
+    if bytes_total > 0 && bytes_done < bytes_total {
+        let checkpoint = TransferCheckpoint {
+            id: checkpoint_id.to_string(),
+            session: Some(part_path.to_string_lossy().into_owned()),
+            bytes_done,
+            total_bytes: bytes_total,
+            ..Default::default()
+        };
+        let _ = checkpoint.save(app);
+        return Err(NetworkError::Transient(format!(
+            "Connection closed at {} of {} bytes",
+            bytes_done, bytes_total
+        )));
+    }
+    Ok(())
+}
+
+/// Download `url` into `dest_dir`, returning the saved file's path
+pub async fn download_to_dir(app: &AppHandle, url: &str, dest_dir: &Path) -> Result<PathBuf> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| ClipFlowError::invalid(format!("Invalid URL {}: {}", url, e)))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(ClipFlowError::invalid(format!("Unsupported URL scheme: {}", parsed.scheme())));
+    }
+    fs::create_dir_all(dest_dir).map_err(|e| ClipFlowError::io("Failed to create destination dir", e))?;
+
+    // The part file path is remembered so a resumed download keeps its name
+    let checkpoint_id = format!("download-{}", url);
+    let part_path = match TransferCheckpoint::load(app, &checkpoint_id).and_then(|c| c.session) {
+        Some(path) if Path::new(&path).exists() => PathBuf::from(path),
+        _ => {
+            let part = reserve_destination(dest_dir, &file_name_for(&parsed))?;
+            TransferCheckpoint {
+                id: checkpoint_id.clone(),
+                session: Some(part.to_string_lossy().into_owned()),
+                ..Default::default()
+            }
+            .save(app)?;
+            part
+        }
+    };
+
+    let client = reqwest::Client::new();
+    let policy = settings::current().network_retry;
+    retry::with_retry(&policy, "download", |_| fetch_remaining(app, &client, url, &part_path, &checkpoint_id)).await?;
+
+    let final_path = part_path.with_extension("");
+    fs::rename(&part_path, &final_path).map_err(|e| ClipFlowError::io("Failed to finish download", e))?;
+    TransferCheckpoint::clear(app, &checkpoint_id);
+    Ok(final_path)
+}
+
+/// Which of the formats a site offers to download
+#[derive(Deserialize, Clone, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FormatChoice {
+    /// Best video and audio, merged
+    #[default]
+    Best,
+    /// Best video no taller than `height` pixels, with the best audio
+    MaxHeight { height: u32 },
+    AudioOnly,
+    /// A yt-dlp format selector, e.g. "137+140"
+    Custom { selector: String },
+}
+
+impl FormatChoice {
+    fn selector(&self) -> String {
+        match self {
+            FormatChoice::Best => "bv*+ba/b".to_string(),
+            FormatChoice::MaxHeight { height } => format!("bv*[height<={h}]+ba/b[height<={h}]", h = height),
+            FormatChoice::AudioOnly => "ba/b".to_string(),
+            FormatChoice::Custom { selector } => selector.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct DownloadOptions {
+    /// Folder to save into, or None for the app's downloads folder
+    pub destination_dir: Option<String>,
+    pub format: FormatChoice,
+    /// Add the downloaded file to the media library
+    pub import: bool,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        DownloadOptions { destination_dir: None, format: FormatChoice::Best, import: true }
+    }
+}
+
+/// Downloads in progress, by URL, so they can be cancelled
+#[derive(Default)]
+pub struct DownloadState {
+    running: Mutex<HashMap<String, Arc<Notify>>>,
+}
+
+/// Removes a download from `DownloadState` when it ends, however it ends
+struct Running<'a> {
+    state: &'a DownloadState,
+    url: String,
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.state.running.lock().unwrap().remove(&self.url);
+    }
+}
+
+/// Direct links to a media file don't need yt-dlp
+fn is_direct_media(url: &reqwest::Url) -> bool {
+    let name = file_name_for(url).to_lowercase();
+    match name.rsplit_once('.') {
+        Some((_, ext)) => VIDEO_EXTENSIONS.contains(&ext) || AUDIO_EXTENSIONS.contains(&ext),
+        None => false,
+    }
+}
+
+/// Progress from a "clipflow-progress <done> <total> <estimate>" line;
+/// yt-dlp writes "NA" for sizes it doesn't know
+fn parse_progress(line: &str) -> Option<(u64, u64)> {
+    let mut fields = line.strip_prefix(PROGRESS_PREFIX)?.split_whitespace();
+    let done = fields.next()?.parse::<f64>().ok()? as u64;
+    let total = fields
+        .map(|field| field.parse::<f64>().ok())
+        .find_map(|size| size.filter(|size| *size > 0.0))
+        .unwrap_or(0.0) as u64;
+    Some((done, total))
+}
+
+/// Download `url` with yt-dlp into `dest_dir`, returning the saved file's path
+async fn download_with_ytdlp(
+    app: &AppHandle,
+    url: &str,
+    dest_dir: &Path,
+    format: &FormatChoice,
+    cancel: &Notify,
+) -> Result<PathBuf> {
+    fs::create_dir_all(dest_dir).map_err(|e| ClipFlowError::io("Failed to create destination dir", e))?;
+    let current = settings::current();
+    let tool = current.ytdlp_path.clone().unwrap_or_else(|| "yt-dlp".to_string());
+
+    let mut cmd = Command::new(&tool);
+    cmd.args(["--no-playlist", "--newline", "--progress", "--windows-filenames"])
+        .args(["-f", &format.selector()])
+        // Prefer formats that mux into mp4 when the quality is the same
+        .args(["-S", "res,ext:mp4:m4a"])
+        .args(["--progress-template", &format!(
+            "download:{} %(progress.downloaded_bytes)s %(progress.total_bytes)s %(progress.total_bytes_estimate)s",
+            PROGRESS_PREFIX
+        )])
+        .args(["--print", &format!("after_move:{}%(filepath)s", FILE_PREFIX)])
+        .arg("-o")
+        .arg(dest_dir.join("%(title)s [%(id)s].%(ext)s"));
+    if let Some(ffmpeg) = &current.ffmpeg_path {
+        cmd.args(["--ffmpeg-location", ffmpeg]);
+    }
+    // The URL can't be taken for an option, whatever it starts with
+    cmd.arg("--").arg(url);
+
+    let mut saved: Option<PathBuf> = None;
+    let mut on_stdout = |line: &str| {
+        if let Some((bytes_done, bytes_total)) = parse_progress(line) {
+            let _ = app.emit("download-progress", DownloadProgress {
+                url: url.to_string(),
+                bytes_done,
+                bytes_total,
+            });
+        } else if let Some(path) = line.strip_prefix(FILE_PREFIX) {
+            saved = Some(PathBuf::from(path.trim()));
+        }
+    };
+    // Dropping the running command when cancelled kills yt-dlp
+    let output = tokio::select! {
+        output = process::run_streaming(&mut cmd, None, Some(&mut on_stdout), |_| {}) => {
+            output.map_err(|e| process::spawn_error(&tool, e))?
+        }
+        _ = cancel.notified() => return Err(ClipFlowError::Cancelled("Download was cancelled".to_string())),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr
+            .lines()
+            .rev()
+            .find(|line| line.starts_with("ERROR:"))
+            .unwrap_or_else(|| stderr.trim());
+        return Err(ClipFlowError::tool("yt-dlp", message.trim_start_matches("ERROR:").trim()));
+    }
+    saved.ok_or_else(|| ClipFlowError::tool("yt-dlp", "finished without reporting the downloaded file"))
+}
+
+/// Download a media URL, into the app's downloads folder unless a
+/// destination is given, and add it to the library
+/// Video pages go through yt-dlp, with progress as `download-progress`
+/// events either way; `cancel_download` stops one. Returns the saved file's
+/// path.
+#[tauri::command]
+pub async fn download_media(
+    app: AppHandle,
+    state: State<'_, DownloadState>,
+    url: String,
+    options: Option<DownloadOptions>,
+) -> Result<String> {
+    let options = options.unwrap_or_default();
+    let parsed = reqwest::Url::parse(&url).map_err(|e| ClipFlowError::invalid(format!("Invalid URL {}: {}", url, e)))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(ClipFlowError::invalid(format!("Unsupported URL scheme: {}", parsed.scheme())));
+    }
+    let dir = match &options.destination_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => downloads_dir(&app)?,
+    };
+
+    let cancel = Arc::new(Notify::new());
+    {
+        match state.running.lock().unwrap().entry(url.clone()) {
+            Entry::Occupied(_) => return Err(ClipFlowError::invalid(format!("{} is already downloading", url))),
+            Entry::Vacant(entry) => {
+                entry.insert(cancel.clone());
+            }
+        }
+    }
+    let _running = Running { state: &state, url: url.clone() };
+    let path = if is_direct_media(&parsed) {
+        // A cancelled direct download keeps its part file and checkpoint,
+        // so asking for it again resumes it
+        tokio::select! {
+            path = download_to_dir(&app, &url, &dir) => path?,
+            _ = cancel.notified() => return Err(ClipFlowError::Cancelled("Download was cancelled".to_string())),
+        }
+    } else {
+        download_with_ytdlp(&app, &url, &dir, &options.format, &cancel).await?
+    };
+
+    let path = path.to_string_lossy().into_owned();
+    if options.import {
+        library::add_paths(&app, std::slice::from_ref(&path))?;
+    }
+    Ok(path)
+}
+
+/// Stop a download started by `download_media`
+#[tauri::command]
+pub async fn cancel_download(state: State<'_, DownloadState>, url: String) -> Result<()> {
+    match state.running.lock().unwrap().get(&url) {
+        Some(cancel) => {
+            cancel.notify_one();
+            Ok(())
+        }
+        None => Err(ClipFlowError::not_found(format!("No download of {} is running", url))),
+    }
+}
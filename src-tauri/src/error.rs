@@ -0,0 +1,109 @@
+//! The error type every command returns
+//!
+//! Errors cross the Tauri boundary as `{ kind, message, ...details }` so the
+//! frontend can branch on `kind` and still show `message` as-is.
+
+use crate::diagnostics::FfmpegFailure;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ClipFlowError {
+    /// The ffmpeg/ffprobe binary couldn't be started
+    FfmpegNotFound { program: String },
+    /// ffmpeg ran and exited with an error
+    FfmpegFailed {
+        operation: String,
+        code: Option<i32>,
+        stderr: String,
+        diagnosis: FfmpegFailure,
+    },
+    /// Another external tool (whisper, the OS keychain) failed
+    ToolFailed { tool: String, message: String },
+    /// Bad arguments from the caller; fix the request, not the environment
+    InvalidInput(String),
+    NotFound(String),
+    /// A render was refused before starting (no disk space, no permission)
+    PreflightFailed(String),
+    Io(String),
+    Network(String),
+    /// The user stopped the operation; nothing to report as a failure
+    Cancelled(String),
+}
+
+pub type Result<T> = std::result::Result<T, ClipFlowError>;
+
+impl ClipFlowError {
+    pub fn invalid(message: impl Into<String>) -> Self {
+        ClipFlowError::InvalidInput(message.into())
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        ClipFlowError::NotFound(message.into())
+    }
+
+    /// I/O failure with what was being attempted, e.g. "Failed to write library"
+    pub fn io(context: &str, error: impl fmt::Display) -> Self {
+        ClipFlowError::Io(format!("{}: {}", context, error))
+    }
+
+    pub fn tool(tool: &str, message: impl Into<String>) -> Self {
+        ClipFlowError::ToolFailed { tool: tool.to_string(), message: message.into() }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            ClipFlowError::FfmpegNotFound { .. } => "ffmpeg_not_found",
+            ClipFlowError::FfmpegFailed { .. } => "ffmpeg_failed",
+            ClipFlowError::ToolFailed { .. } => "tool_failed",
+            ClipFlowError::InvalidInput(_) => "invalid_input",
+            ClipFlowError::NotFound(_) => "not_found",
+            ClipFlowError::PreflightFailed(_) => "preflight_failed",
+            ClipFlowError::Io(_) => "io",
+            ClipFlowError::Network(_) => "network",
+            ClipFlowError::Cancelled(_) => "cancelled",
+        }
+    }
+}
+
+impl fmt::Display for ClipFlowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClipFlowError::FfmpegNotFound { program } => {
+                write!(f, "{} was not found - install ffmpeg or set its path in settings", program)
+            }
+            ClipFlowError::FfmpegFailed { operation, diagnosis, .. } => {
+                write!(f, "ffmpeg {} failed: {}", operation, diagnosis.message())
+            }
+            ClipFlowError::ToolFailed { tool, message } => write!(f, "{} failed: {}", tool, message),
+            ClipFlowError::InvalidInput(message)
+            | ClipFlowError::NotFound(message)
+            | ClipFlowError::PreflightFailed(message)
+            | ClipFlowError::Io(message)
+            | ClipFlowError::Network(message)
+            | ClipFlowError::Cancelled(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ClipFlowError {}
+
+impl Serialize for ClipFlowError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("message", &self.to_string())?;
+        match self {
+            ClipFlowError::FfmpegNotFound { program } => map.serialize_entry("program", program)?,
+            ClipFlowError::FfmpegFailed { operation, code, stderr, diagnosis } => {
+                map.serialize_entry("operation", operation)?;
+                map.serialize_entry("code", code)?;
+                map.serialize_entry("stderr", stderr)?;
+                map.serialize_entry("diagnosis", diagnosis)?;
+            }
+            ClipFlowError::ToolFailed { tool, .. } => map.serialize_entry("tool", tool)?,
+            _ => {}
+        }
+        map.end()
+    }
+}
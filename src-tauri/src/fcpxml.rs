@@ -0,0 +1,192 @@
+//! Final Cut Pro XML: the project's edit as an FCPXML document for Mac
+//! finishing workflows
+//!
+//! FCPXML times are rational seconds, so every time is a whole number of
+//! frames of the clip's frame duration (1001/30000s at 29.97) rather than
+//! a rounded decimal.
+
+use crate::error::{ClipFlowError, Result};
+use crate::interchange::{self, Edit};
+use crate::project::Project;
+use crate::{probe, timecode};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const FCPXML_VERSION: &str = "1.10";
+
+/// Length of one frame in seconds, as (numerator, denominator): 1001/30000
+/// for 29.97, 1/25 for 25
+fn frame_duration(fps: f64) -> (u64, u64) {
+    let ntsc = (fps * 1.001).round();
+    if (fps - fps.round()).abs() > 0.001 && (fps - ntsc / 1.001).abs() < 0.001 {
+        (1001, ntsc as u64 * 1000)
+    } else {
+        (1, fps.round().max(1.0) as u64)
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// `frames` frames as an FCPXML time, reduced: "0s", "5s", "1001/6000s"
+fn rational(frames: u64, (num, den): (u64, u64)) -> String {
+    let total = frames * num;
+    if total == 0 {
+        return "0s".to_string();
+    }
+    let divisor = gcd(total, den);
+    match (total / divisor, den / divisor) {
+        (seconds, 1) => format!("{}s", seconds),
+        (num, den) => format!("{}/{}s", num, den),
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn tc_format(drop_frame: bool) -> &'static str {
+    if drop_frame {
+        "DF"
+    } else {
+        "NDF"
+    }
+}
+
+/// An FCPXML document with one asset per source file and the kept pieces,
+/// with their markers, on the primary storyline
+pub fn write_fcpxml(project: &Project, edit: &Edit) -> Result<String> {
+    let mut resources = String::new();
+    // (frame duration, width, height) of each format, and the path of each
+    // asset; ids are positions in these lists
+    let mut formats: Vec<((u64, u64), u32, u32)> = Vec::new();
+    let mut assets: Vec<&str> = Vec::new();
+    let mut asset_formats: Vec<usize> = Vec::new();
+
+    for event in &edit.events {
+        let path = event.clip.path.as_str();
+        if assets.contains(&path) {
+            continue;
+        }
+        let geometry = probe::video_geometry(path)?;
+        let rate = frame_duration(event.timecode.fps);
+        let format = (rate, geometry.display_width, geometry.display_height);
+        let format_index = match formats.iter().position(|f| *f == format) {
+            Some(index) => index,
+            None => {
+                resources.push_str(&format!(
+                    "        <format id=\"f{}\" frameDuration=\"{}\" width=\"{}\" height=\"{}\"/>\n",
+                    formats.len() + 1,
+                    rational(1, rate),
+                    format.1,
+                    format.2
+                ));
+                formats.push(format);
+                formats.len() - 1
+            }
+        };
+
+        let tc = &event.timecode;
+        let start = timecode::timecode_to_frames(&tc.start_timecode, tc.fps, tc.drop_frame).unwrap_or(0);
+        let audio = match probe::audio_tracks(path)?.first() {
+            Some(track) => format!(
+                " hasAudio=\"1\" audioSources=\"1\" audioChannels=\"{}\" audioRate=\"{}\"",
+                track.channels, track.sample_rate
+            ),
+            None => String::new(),
+        };
+        resources.push_str(&format!(
+            "        <asset id=\"a{}\" name=\"{}\" start=\"{}\" duration=\"{}\" hasVideo=\"1\" format=\"f{}\"{}>\n\
+             \x20           <media-rep kind=\"original-media\" src=\"{}\"/>\n\
+             \x20       </asset>\n",
+            assets.len() + 1,
+            xml_escape(&interchange::file_name(path)),
+            rational(start, rate),
+            rational((event.clip.duration * tc.fps).round() as u64, rate),
+            format_index + 1,
+            audio,
+            xml_escape(&interchange::file_url(path))
+        ));
+        assets.push(path);
+        asset_formats.push(format_index);
+    }
+
+    let sequence_rate = frame_duration(edit.fps);
+    let mut spine = String::new();
+    for event in &edit.events {
+        let asset = assets.iter().position(|p| *p == event.clip.path).unwrap_or(0);
+        let clip_rate = frame_duration(event.timecode.fps);
+        let clip = format!(
+            "                        <asset-clip ref=\"a{}\" name=\"{}\" offset=\"{}\" start=\"{}\" duration=\"{}\" tcFormat=\"{}\"",
+            asset + 1,
+            xml_escape(&event.clip.name),
+            rational(event.record_in, sequence_rate),
+            rational(event.source_in, clip_rate),
+            rational(event.record_out - event.record_in, sequence_rate),
+            tc_format(event.timecode.drop_frame)
+        );
+        if event.markers.is_empty() {
+            spine.push_str(&format!("{}/>\n", clip));
+            continue;
+        }
+        // Marker times are in the clip's source time, like its start
+        spine.push_str(&format!("{}>\n", clip));
+        for (frame, marker) in &event.markers {
+            spine.push_str(&format!(
+                "                            <marker start=\"{}\" duration=\"{}\" value=\"{}\"/>\n",
+                rational(*frame, clip_rate),
+                rational(1, clip_rate),
+                xml_escape(&marker.name)
+            ));
+        }
+        spine.push_str("                        </asset-clip>\n");
+    }
+
+    let duration = edit.events.last().map(|event| event.record_out).unwrap_or(0);
+    let name = xml_escape(&project.name);
+    Ok(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE fcpxml>\n\n\
+         <fcpxml version=\"{version}\">\n\
+         \x20   <resources>\n\
+         {resources}\
+         \x20   </resources>\n\
+         \x20   <library>\n\
+         \x20       <event name=\"{name}\">\n\
+         \x20           <project name=\"{name}\">\n\
+         \x20               <sequence format=\"f{format}\" duration=\"{duration}\" tcStart=\"0s\" tcFormat=\"{tc_format}\">\n\
+         \x20                   <spine>\n\
+         {spine}\
+         \x20                   </spine>\n\
+         \x20               </sequence>\n\
+         \x20           </project>\n\
+         \x20       </event>\n\
+         \x20   </library>\n\
+         </fcpxml>\n",
+        version = FCPXML_VERSION,
+        resources = resources,
+        name = name,
+        format = asset_formats.first().map(|index| index + 1).unwrap_or(1),
+        duration = rational(duration, sequence_rate),
+        tc_format = tc_format(edit.drop_frame),
+        spine = spine,
+    ))
+}
+
+/// Where to write the document: inside the bundle for an .fcpxmld path,
+/// which Final Cut opens as a folder holding Info.fcpxml
+pub fn document_path(path: &str) -> Result<PathBuf> {
+    if !path.ends_with(".fcpxmld") {
+        return Ok(PathBuf::from(path));
+    }
+    fs::create_dir_all(path).map_err(|e| ClipFlowError::io("Failed to create FCPXML bundle", e))?;
+    Ok(Path::new(path).join("Info.fcpxml"))
+}
@@ -0,0 +1,463 @@
+//! A builder for ffmpeg filter graphs, checked before ffmpeg sees them
+//!
+//! A graph is a list of chains, each reading labelled pads (or input
+//! streams such as "0:v"), running filters in order, and writing labelled
+//! pads. Option values are escaped for both levels of ffmpeg's filtergraph
+//! syntax, so text and paths with quotes, colons, or commas go through
+//! as written. `build` checks the wiring - every pad read is written by
+//! some chain or is an input stream, no pad is read twice, and every pad
+//! left over is mapped to the output - so mistakes come back as a clear
+//! error instead of ffmpeg's "Invalid argument".
+
+use crate::error::{ClipFlowError, Result};
+use crate::output::{self, OverwritePolicy};
+use crate::{presets, process};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt;
+use tauri::AppHandle;
+
+/// Characters escaped in an option value, first for the filter's own option
+/// parser and then for the graph parser around it
+const OPTION_SPECIAL: &[char] = &['\\', '\'', ':'];
+const GRAPH_SPECIAL: &[char] = &['\\', '\'', '[', ']', ',', ';'];
+
+fn escape_level(value: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Escape an option value for use inside a filtergraph
+pub fn escape_value(value: &str) -> String {
+    escape_level(&escape_level(value, OPTION_SPECIAL), GRAPH_SPECIAL)
+}
+
+fn is_name(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// An input stream specifier such as "0:v", "1:a:0", or "2"
+fn is_stream(s: &str) -> bool {
+    let mut parts = s.split(':');
+    let file = parts.next().unwrap_or("");
+    !file.is_empty() && file.chars().all(|c| c.is_ascii_digit()) && parts.all(is_name)
+}
+
+/// One filter with its options
+#[derive(Clone, Debug)]
+pub struct Filter {
+    name: String,
+    /// Unnamed options first, in order, then named ones
+    args: Vec<(Option<String>, String)>,
+    /// Already in filtergraph syntax; written out as is
+    raw: bool,
+}
+
+impl Filter {
+    pub fn new(name: &str) -> Filter {
+        Filter { name: name.to_string(), args: Vec::new(), raw: false }
+    }
+
+    /// A filter, or a chain of them, already written in filtergraph syntax
+    /// Nothing in it is escaped or checked.
+    pub fn raw(text: impl Into<String>) -> Filter {
+        Filter { name: text.into(), args: Vec::new(), raw: true }
+    }
+
+    /// A named option, e.g. `.opt("w", 1280)`
+    pub fn opt(mut self, key: &str, value: impl fmt::Display) -> Filter {
+        self.args.push((Some(key.to_string()), value.to_string()));
+        self
+    }
+
+    /// An unnamed option, taken by position
+    pub fn arg(mut self, value: impl fmt::Display) -> Filter {
+        self.args.push((None, value.to_string()));
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.raw {
+            return match self.name.trim().is_empty() {
+                true => Err(ClipFlowError::invalid("Empty filter")),
+                false => Ok(()),
+            };
+        }
+        if !is_name(&self.name) {
+            return Err(ClipFlowError::invalid(format!("Invalid filter name: \"{}\"", self.name)));
+        }
+        if let Some((Some(key), _)) = self.args.iter().find(|(key, _)| key.as_deref().is_some_and(|k| !is_name(k))) {
+            return Err(ClipFlowError::invalid(format!("Invalid option name \"{}\" on {}", key, self.name)));
+        }
+        let mut named = false;
+        for (key, _) in &self.args {
+            match key {
+                Some(_) => named = true,
+                None if named => {
+                    return Err(ClipFlowError::invalid(format!("Unnamed options of {} must come before named ones", self.name)))
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if self.raw || self.args.is_empty() {
+            return Ok(());
+        }
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|(key, value)| match key {
+                Some(key) => format!("{}={}", key, escape_value(value)),
+                None => escape_value(value),
+            })
+            .collect();
+        write!(f, "={}", args.join(":"))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Chain {
+    inputs: Vec<String>,
+    filters: Vec<Filter>,
+    outputs: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FilterGraph {
+    chains: Vec<Chain>,
+    next_label: usize,
+}
+
+impl FilterGraph {
+    pub fn new() -> FilterGraph {
+        FilterGraph::default()
+    }
+
+    /// A pad label not used anywhere else in this graph, e.g. "v3"
+    pub fn label(&mut self, prefix: &str) -> String {
+        self.next_label += 1;
+        format!("{}{}", prefix, self.next_label)
+    }
+
+    /// Add a chain reading `inputs` (pad labels or input streams) and
+    /// writing `outputs`
+    pub fn chain(&mut self, inputs: &[&str], filters: Vec<Filter>, outputs: &[&str]) -> &mut FilterGraph {
+        self.chains.push(Chain {
+            inputs: inputs.iter().map(|s| s.to_string()).collect(),
+            filters,
+            outputs: outputs.iter().map(|s| s.to_string()).collect(),
+        });
+        self
+    }
+
+    /// Pads written but not read by any chain; these must be mapped
+    pub fn open_outputs(&self) -> Vec<String> {
+        let read: HashSet<&str> = self.chains.iter().flat_map(|c| &c.inputs).map(String::as_str).collect();
+        self.chains.iter().flat_map(|c| &c.outputs).filter(|label| !read.contains(label.as_str())).cloned().collect()
+    }
+
+    /// Check the wiring, with `maps` being what's passed to -map (pad
+    /// labels, with or without brackets, or input streams)
+    pub fn validate(&self, maps: &[&str]) -> Result<()> {
+        if self.chains.is_empty() {
+            return Err(ClipFlowError::invalid("The filter graph is empty"));
+        }
+        let mut written = HashSet::new();
+        for chain in &self.chains {
+            if chain.filters.is_empty() {
+                return Err(ClipFlowError::invalid("Every chain needs at least one filter"));
+            }
+            for filter in &chain.filters {
+                filter.validate()?;
+            }
+            for label in &chain.outputs {
+                if !is_name(label) {
+                    return Err(ClipFlowError::invalid(format!("Invalid pad label: \"{}\"", label)));
+                }
+                if !written.insert(label.as_str()) {
+                    return Err(ClipFlowError::invalid(format!("Pad [{}] is written twice", label)));
+                }
+            }
+        }
+
+        let mut read = HashSet::new();
+        for label in self.chains.iter().flat_map(|c| &c.inputs) {
+            if is_stream(label) {
+                continue;
+            }
+            if !written.contains(label.as_str()) {
+                return Err(ClipFlowError::invalid(format!("Pad [{}] is read but never written", label)));
+            }
+            if !read.insert(label.as_str()) {
+                return Err(ClipFlowError::invalid(format!("Pad [{}] is read twice; split it first", label)));
+            }
+        }
+
+        let mapped: HashSet<&str> = maps.iter().map(|m| m.trim_start_matches('[').trim_end_matches(']')).collect();
+        for map in &mapped {
+            if !is_stream(map) && !(written.contains(map) && !read.contains(map)) {
+                return Err(ClipFlowError::invalid(format!("Mapped pad [{}] isn't an output of the graph", map)));
+            }
+        }
+        if let Some(unmapped) = self.open_outputs().iter().find(|label| !mapped.contains(label.as_str())) {
+            return Err(ClipFlowError::invalid(format!("Pad [{}] is never used or mapped", unmapped)));
+        }
+        Ok(())
+    }
+
+    /// Check the graph against `maps` and write it in filtergraph syntax
+    pub fn build(&self, maps: &[&str]) -> Result<String> {
+        self.validate(maps)?;
+        Ok(self.to_string())
+    }
+}
+
+impl fmt::Display for FilterGraph {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let chains: Vec<String> = self
+            .chains
+            .iter()
+            .map(|chain| {
+                let pads = |labels: &[String]| labels.iter().map(|l| format!("[{}]", l)).collect::<String>();
+                let filters: Vec<String> = chain.filters.iter().map(Filter::to_string).collect();
+                format!("{}{}{}", pads(&chain.inputs), filters.join(","), pads(&chain.outputs))
+            })
+            .collect();
+        write!(f, "{}", chains.join(";"))
+    }
+}
+
+/// A filter as sent from the frontend
+#[derive(Deserialize, Clone)]
+pub struct FilterSpec {
+    pub name: String,
+    /// Unnamed options, in order
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Named options as [key, value] pairs, in order
+    #[serde(default)]
+    pub options: Vec<(String, String)>,
+}
+
+/// A chain as sent from the frontend
+#[derive(Deserialize, Clone)]
+pub struct ChainSpec {
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    pub filters: Vec<FilterSpec>,
+    #[serde(default)]
+    pub outputs: Vec<String>,
+}
+
+/// Run a filter graph of your own over one or more inputs
+/// `maps` picks what goes into the output: graph pads ("outv" or "[outv]")
+/// or input streams ("0:a"). Encoded with an export preset (default
+/// "medium"). Returns the path written.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn run_custom_filtergraph(
+    app: AppHandle,
+    inputs: Vec<String>,
+    chains: Vec<ChainSpec>,
+    maps: Vec<String>,
+    output_path: &str,
+    preset: Option<String>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    if inputs.is_empty() {
+        return Err(ClipFlowError::invalid("No inputs given"));
+    }
+    if maps.is_empty() {
+        return Err(ClipFlowError::invalid("Map at least one stream to the output"));
+    }
+    let mut graph = FilterGraph::new();
+    for chain in &chains {
+        let filters = chain
+            .filters
+            .iter()
+            .map(|spec| {
+                let filter = spec.args.iter().fold(Filter::new(&spec.name), |f, value| f.arg(value));
+                spec.options.iter().fold(filter, |f, (key, value)| f.opt(key, value))
+            })
+            .collect();
+        let inputs: Vec<&str> = chain.inputs.iter().map(String::as_str).collect();
+        let outputs: Vec<&str> = chain.outputs.iter().map(String::as_str).collect();
+        graph.chain(&inputs, filters, &outputs);
+    }
+    let map_refs: Vec<&str> = maps.iter().map(String::as_str).collect();
+    let graph = graph.build(&map_refs)?;
+    // Streams read by number must exist among the inputs
+    let highest = chains
+        .iter()
+        .flat_map(|c| &c.inputs)
+        .chain(&maps)
+        .filter(|label| is_stream(label))
+        .filter_map(|label| label.split(':').next()?.parse::<usize>().ok())
+        .max();
+    if let Some(index) = highest.filter(|i| *i >= inputs.len()) {
+        return Err(ClipFlowError::invalid(format!("Input {} doesn't exist ({} given)", index, inputs.len())));
+    }
+
+    let name = preset.unwrap_or_else(|| "medium".to_string());
+    let preset = presets::resolve(&app, &name)?
+        .ok_or_else(|| ClipFlowError::not_found(format!("No export preset named \"{}\"", name)))?;
+    presets::validate(&preset)?;
+
+    let mut args: Vec<String> = Vec::new();
+    for input in &inputs {
+        args.extend(["-i".to_string(), input.clone()]);
+    }
+    args.extend(["-filter_complex".to_string(), graph]);
+    for map in &maps {
+        let label = map.trim_start_matches('[').trim_end_matches(']');
+        let target = if is_stream(label) { label.to_string() } else { format!("[{}]", label) };
+        args.extend(["-map".to_string(), target]);
+    }
+    args.extend(presets::codec_args(&preset, true));
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "custom filter graph").await
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_invalid(result: Result<String>) -> bool {
+        matches!(result, Err(ClipFlowError::InvalidInput(_)))
+    }
+
+    #[test]
+    fn builds_a_simple_chain() {
+        let mut graph = FilterGraph::new();
+        graph.chain(&["0:v"], vec![Filter::new("scale").opt("w", 1280).opt("h", -2), Filter::new("hflip")], &["out"]);
+        assert_eq!(graph.build(&["[out]"]).unwrap(), "[0:v]scale=w=1280:h=-2,hflip[out]");
+    }
+
+    #[test]
+    fn unnamed_options_come_first() {
+        let filter = Filter::new("fade").arg("in").opt("d", 1.5);
+        assert_eq!(filter.to_string(), "fade=in:d=1.5");
+        let mut graph = FilterGraph::new();
+        graph.chain(&["0:v"], vec![Filter::new("fade").opt("d", 1).arg("in")], &["out"]);
+        assert!(is_invalid(graph.build(&["out"])));
+    }
+
+    #[test]
+    fn escapes_special_characters_for_both_levels() {
+        assert_eq!(escape_value("plain"), "plain");
+        assert_eq!(escape_value("a:b"), "a\\\\:b");
+        assert_eq!(escape_value("it's"), "it\\\\\\'s");
+        assert_eq!(escape_value("one, two"), "one\\, two");
+        assert_eq!(escape_value("[x];y"), "\\[x\\]\\;y");
+        assert_eq!(escape_value("C:\\dir"), "C\\\\:\\\\\\\\dir");
+    }
+
+    #[test]
+    fn joins_chains_with_labels() {
+        let mut graph = FilterGraph::new();
+        let (a, b) = (graph.label("v"), graph.label("v"));
+        assert_ne!(a, b);
+        graph
+            .chain(&["0:v"], vec![Filter::new("split")], &[a.as_str(), b.as_str()])
+            .chain(&[a.as_str(), b.as_str()], vec![Filter::new("hstack")], &["out"]);
+        assert_eq!(graph.build(&["out"]).unwrap(), format!("[0:v]split[{a}][{b}];[{a}][{b}]hstack[out]", a = a, b = b));
+    }
+
+    #[test]
+    fn raw_filters_pass_through() {
+        let mut graph = FilterGraph::new();
+        graph.chain(&["0:v"], vec![Filter::raw("scale=640:-2,setsar=1")], &["out"]);
+        assert_eq!(graph.build(&["out"]).unwrap(), "[0:v]scale=640:-2,setsar=1[out]");
+    }
+
+    #[test]
+    fn rejects_pads_read_but_never_written() {
+        let mut graph = FilterGraph::new();
+        graph.chain(&["missing"], vec![Filter::new("null")], &["out"]);
+        assert!(is_invalid(graph.build(&["out"])));
+    }
+
+    #[test]
+    fn rejects_pads_written_or_read_twice() {
+        let mut graph = FilterGraph::new();
+        graph.chain(&["0:v"], vec![Filter::new("null")], &["a"]).chain(&["1:v"], vec![Filter::new("null")], &["a"]);
+        assert!(is_invalid(graph.build(&["a"])));
+
+        let mut graph = FilterGraph::new();
+        graph
+            .chain(&["0:v"], vec![Filter::new("null")], &["a"])
+            .chain(&["a"], vec![Filter::new("null")], &["b"])
+            .chain(&["a"], vec![Filter::new("null")], &["c"]);
+        assert!(is_invalid(graph.build(&["b", "c"])));
+    }
+
+    #[test]
+    fn requires_every_open_output_to_be_mapped() {
+        let mut graph = FilterGraph::new();
+        graph.chain(&["0:v"], vec![Filter::new("split")], &["a", "b"]);
+        assert!(is_invalid(graph.build(&["a"])));
+        assert_eq!(graph.open_outputs(), vec!["a".to_string(), "b".to_string()]);
+        assert!(graph.build(&["a", "b"]).is_ok());
+    }
+
+    #[test]
+    fn rejects_maps_that_are_not_outputs() {
+        let mut graph = FilterGraph::new();
+        graph
+            .chain(&["0:v"], vec![Filter::new("null")], &["a"])
+            .chain(&["a"], vec![Filter::new("null")], &["out"]);
+        assert!(is_invalid(graph.build(&["out", "a"])));
+        assert!(is_invalid(graph.build(&["out", "nowhere"])));
+        assert!(graph.build(&["out", "0:a"]).is_ok());
+    }
+
+    #[test]
+    fn rejects_bad_names() {
+        let mut graph = FilterGraph::new();
+        graph.chain(&["0:v"], vec![Filter::new("scale;rm")], &["out"]);
+        assert!(is_invalid(graph.build(&["out"])));
+
+        let mut graph = FilterGraph::new();
+        graph.chain(&["0:v"], vec![Filter::new("null")], &["bad label"]);
+        assert!(is_invalid(graph.build(&["bad label"])));
+
+        let mut graph = FilterGraph::new();
+        graph.chain(&["0:v"], vec![Filter::new("scale").opt("w=1:h", 2)], &["out"]);
+        assert!(is_invalid(graph.build(&["out"])));
+    }
+
+    #[test]
+    fn rejects_empty_graphs_and_chains() {
+        assert!(is_invalid(FilterGraph::new().build(&[])));
+        let mut graph = FilterGraph::new();
+        graph.chain(&["0:v"], vec![], &["out"]);
+        assert!(is_invalid(graph.build(&["out"])));
+    }
+
+    #[test]
+    fn recognises_stream_specifiers() {
+        assert!(is_stream("0"));
+        assert!(is_stream("0:v"));
+        assert!(is_stream("12:a:1"));
+        assert!(!is_stream("v"));
+        assert!(!is_stream("0:"));
+        assert!(!is_stream(":v"));
+    }
+}
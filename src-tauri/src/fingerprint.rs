@@ -0,0 +1,188 @@
+//! Perceptual frame hashing for finding repeated footage
+//!
+//! Frames are sampled a few times a second, shrunk to 9x8 grayscale, and
+//! reduced to a 64-bit difference hash (each bit says whether a pixel is
+//! brighter than its right neighbour). Re-encodes, scaling, and mild color
+//! changes barely move the hash, so the same shot used twice - a duplicate
+//! take, re-used B-roll - shows up as a run of near-identical hashes.
+
+use crate::error::{ClipFlowError, Result};
+use crate::{library, process, settings};
+use serde::Serialize;
+use tokio::process::Command;
+use tauri::AppHandle;
+
+/// Frames hashed per second of video
+const SAMPLE_RATE: f64 = 2.0;
+
+/// dHash input size: one extra column for the horizontal differences
+const HASH_WIDTH: usize = 9;
+const HASH_HEIGHT: usize = 8;
+
+/// Frames this flat (brightness spread across the thumbnail) carry no
+/// picture to match on, e.g. black or title-card backgrounds
+const MIN_CONTRAST: u8 = 12;
+
+/// Default max differing bits for two frames to count as the same
+const DEFAULT_THRESHOLD: u32 = 8;
+
+/// Default shortest run reported as a duplicate, in seconds
+const DEFAULT_MIN_DURATION: f64 = 2.0;
+
+/// Hashes of a file's sampled frames; None for frames too flat to compare
+pub struct Fingerprint {
+    pub path: String,
+    pub hashes: Vec<Option<u64>>,
+}
+
+fn dhash(pixels: &[u8]) -> Option<u64> {
+    let min = *pixels.iter().min()?;
+    let max = *pixels.iter().max()?;
+    if max - min < MIN_CONTRAST {
+        return None;
+    }
+    let mut hash = 0u64;
+    for row in pixels.chunks(HASH_WIDTH) {
+        for pair in row.windows(2) {
+            hash = (hash << 1) | (pair[0] > pair[1]) as u64;
+        }
+    }
+    Some(hash)
+}
+
+/// Sample and hash the frames of one file
+pub async fn fingerprint(file_path: &str) -> Result<Fingerprint> {
+    let ffmpeg = settings::ffmpeg_bin();
+    let output = process::run(Command::new(&ffmpeg).args([
+        "-i", file_path,
+        "-an",
+        "-vf", &format!("fps={},scale={}:{}:flags=area,format=gray", SAMPLE_RATE, HASH_WIDTH, HASH_HEIGHT),
+        "-f", "rawvideo",
+        "-",
+    ]), None, |_| {})
+    .await
+    .map_err(|e| process::spawn_error(&ffmpeg, e))?;
+    process::check_ffmpeg(&output, "frame hashing")?;
+
+    let hashes = output.stdout.chunks_exact(HASH_WIDTH * HASH_HEIGHT).map(dhash).collect();
+    Ok(Fingerprint { path: file_path.to_string(), hashes })
+}
+
+fn similar(a: Option<u64>, b: Option<u64>, threshold: u32) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => (a ^ b).count_ones() <= threshold,
+        _ => false,
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct SegmentRef {
+    pub path: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DuplicateMatch {
+    /// Earlier occurrence (first in input order, or earlier in the same file)
+    pub original: SegmentRef,
+    pub duplicate: SegmentRef,
+    /// Mean fraction of matching hash bits over the run, 0-1
+    pub similarity: f64,
+}
+
+/// Runs of matching frames between two fingerprints, walking each diagonal
+/// (fixed offset between the two files) and keeping runs of `min_frames`
+/// or longer
+/// Comparing a fingerprint with itself skips offsets shorter than a run,
+/// where a static shot would trivially match itself.
+fn match_runs(a: &Fingerprint, b: &Fingerprint, same_file: bool, threshold: u32, min_frames: usize) -> Vec<DuplicateMatch> {
+    let (len_a, len_b) = (a.hashes.len() as isize, b.hashes.len() as isize);
+    let offsets = if same_file { min_frames as isize..len_b } else { -(len_a - 1)..len_b };
+    let segment = |fp: &Fingerprint, start: usize, end: usize| SegmentRef {
+        path: fp.path.clone(),
+        start: start as f64 / SAMPLE_RATE,
+        end: end as f64 / SAMPLE_RATE,
+    };
+
+    let mut matches = Vec::new();
+    for offset in offsets {
+        let mut run_start: Option<usize> = None;
+        let mut bits_differing = 0u32;
+        let first = (-offset).max(0) as usize;
+        let last = (len_a.min(len_b - offset)).max(0) as usize;
+        for i in first..=last {
+            let hit = i < last && similar(a.hashes[i], b.hashes[(i as isize + offset) as usize], threshold);
+            match (hit, run_start) {
+                (true, None) => {
+                    run_start = Some(i);
+                    bits_differing = 0;
+                }
+                (false, Some(start)) => {
+                    let frames = i - start;
+                    if frames >= min_frames {
+                        let j = (start as isize + offset) as usize;
+                        matches.push(DuplicateMatch {
+                            original: segment(a, start, i),
+                            duplicate: segment(b, j, j + frames),
+                            similarity: 1.0 - bits_differing as f64 / (frames as f64 * 64.0),
+                        });
+                    }
+                    run_start = None;
+                }
+                _ => {}
+            }
+            if hit {
+                let j = (i as isize + offset) as usize;
+                bits_differing += (a.hashes[i].unwrap_or(0) ^ b.hashes[j].unwrap_or(0)).count_ones();
+            }
+        }
+    }
+    matches
+}
+
+/// Find segments that appear more than once within and across `fingerprints`
+pub fn find_duplicates(fingerprints: &[Fingerprint], threshold: u32, min_duration: f64) -> Vec<DuplicateMatch> {
+    let min_frames = ((min_duration * SAMPLE_RATE).ceil() as usize).max(1);
+    let mut matches = Vec::new();
+    for (i, a) in fingerprints.iter().enumerate() {
+        for (j, b) in fingerprints.iter().enumerate().skip(i) {
+            matches.extend(match_runs(a, b, i == j, threshold, min_frames));
+        }
+    }
+    matches.sort_by(|x, y| y.similarity.total_cmp(&x.similarity));
+    matches
+}
+
+/// Find repeated footage in the given files, or across the whole library
+/// when `paths` is omitted
+///
+/// `threshold` is the number of differing hash bits (of 64) still counted
+/// as the same frame; `min_duration` is the shortest run reported.
+#[tauri::command]
+pub async fn find_duplicate_segments(
+    app: AppHandle,
+    paths: Option<Vec<String>>,
+    threshold: Option<u32>,
+    min_duration: Option<f64>,
+) -> Result<Vec<DuplicateMatch>> {
+    let paths = match paths {
+        Some(paths) => paths,
+        None => library::read_library(&app)?.into_iter().map(|item| item.path).collect(),
+    };
+    if paths.is_empty() {
+        return Err(ClipFlowError::invalid("No files to compare"));
+    }
+    let threshold = threshold.unwrap_or(DEFAULT_THRESHOLD).min(32);
+
+    let mut fingerprints = Vec::new();
+    for path in &paths {
+        match fingerprint(path).await {
+            Ok(fp) => fingerprints.push(fp),
+            // One unreadable file shouldn't sink a library-wide scan
+            Err(e) if paths.len() > 1 => tracing::warn!(path = %path, error = %e, "skipping file in duplicate scan"),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(find_duplicates(&fingerprints, threshold, min_duration.unwrap_or(DEFAULT_MIN_DURATION)))
+}
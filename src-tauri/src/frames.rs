@@ -0,0 +1,303 @@
+//! Still frames out of video: single grabs for thumbnails and posters, and
+//! whole image sequences for analysis or thumbnail design - and video made
+//! back from stills, for timelapses and static-image podcast uploads
+//!
+//! Frames go through the same pixel-aspect and color handling as exports,
+//! so a still from anamorphic or HDR footage looks like the video does.
+
+use crate::error::{ClipFlowError, Result};
+use crate::output::{self, OverwritePolicy};
+use crate::temp::TempFile;
+use crate::{color, probe, process};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Sequences longer than this are almost always a mistaken fps
+const MAX_SEQUENCE_FRAMES: f64 = 100_000.0;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Png,
+    Jpg,
+    Webp,
+    Tiff,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpg => "jpg",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Tiff => "tiff",
+        }
+    }
+
+    /// Encoder quality flags; the lossless formats need none
+    fn quality_args(&self) -> Vec<String> {
+        let args: &[&str] = match self {
+            ImageFormat::Jpg => &["-q:v", "2"],
+            ImageFormat::Webp => &["-quality", "90"],
+            ImageFormat::Png | ImageFormat::Tiff => &[],
+        };
+        args.iter().map(|s| s.to_string()).collect()
+    }
+}
+
+/// Images to turn into video
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum ImageSource {
+    /// A numbered sequence, e.g. "shots/img_%04d.jpg"
+    Pattern(String),
+    /// Files in the order they should appear
+    List(Vec<String>),
+}
+
+/// H.264 in 4:2:0 at even dimensions, which every player and upload site
+/// takes; camera stills are often odd-sized
+fn still_video_args() -> Vec<String> {
+    [
+        "-vf", "scale=trunc(iw/2)*2:trunc(ih/2)*2,setsar=1,format=yuv420p",
+        "-c:v", "libx264",
+        "-crf", "18",
+        "-movflags", "+faststart",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+/// An ffconcat list showing each image for one frame at `fps`
+fn concat_list(images: &[String], fps: f64) -> String {
+    let quote = |path: &str| format!("'{}'", path.replace('\'', "'\\''"));
+    let mut list = String::from("ffconcat version 1.0\n");
+    for image in images {
+        list.push_str(&format!("file {}\nduration {}\n", quote(image), 1.0 / fps));
+    }
+    // The concat demuxer ignores the last entry's duration unless the file
+    // is listed again
+    if let Some(last) = images.last() {
+        list.push_str(&format!("file {}\n", quote(last)));
+    }
+    list
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ImageSequence {
+    /// ffmpeg-style pattern of the files written, e.g. "clip_%06d.png"
+    pub pattern: String,
+    pub frames: usize,
+}
+
+/// Square-pixel and color conversion filters for a still of `input_path`
+fn still_filters(input_path: &str) -> Vec<String> {
+    let mut filters: Vec<String> = color::export_color_args(input_path).0.into_iter().collect();
+    if let Ok(geometry) = probe::video_geometry(input_path) {
+        filters.extend(probe::square_pixel_filter(&geometry));
+    }
+    filters
+}
+
+/// Save the frame at `timestamp` as an image
+/// Returns the path written.
+#[tauri::command]
+pub async fn extract_frame(
+    input_path: &str,
+    timestamp: f64,
+    output_path: &str,
+    format: ImageFormat,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let duration = probe::media_duration(input_path)?;
+    if timestamp.is_nan() || timestamp < 0.0 || timestamp >= duration {
+        return Err(ClipFlowError::invalid(format!("{}s is outside the clip ({:.2}s)", timestamp, duration)));
+    }
+    let extension = Path::new(output_path).extension().map(|e| e.to_string_lossy().to_ascii_lowercase());
+    let matches = match (format, extension.as_deref()) {
+        (ImageFormat::Jpg, Some("jpg" | "jpeg")) | (ImageFormat::Tiff, Some("tiff" | "tif")) => true,
+        (_, Some(ext)) => ext == format.extension(),
+        (_, None) => false,
+    };
+    if !matches {
+        return Err(ClipFlowError::invalid(format!("Output path must end in .{}", format.extension())));
+    }
+
+    let mut args: Vec<String> = vec![
+        "-ss".to_string(), timestamp.to_string(),
+        "-i".to_string(), input_path.to_string(),
+        "-frames:v".to_string(), "1".to_string(),
+    ];
+    let filters = still_filters(input_path);
+    if !filters.is_empty() {
+        args.extend(["-vf".to_string(), filters.join(",")]);
+    }
+    args.extend(format.quality_args());
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        // The partial name doesn't end in a number, so tell the image muxer
+        // it's one file rather than a sequence pattern
+        args.extend(["-update".to_string(), "1".to_string(), partial, "-y".to_string()]);
+        process::run_ffmpeg_async(&args, "frame extraction").await
+    })
+    .await
+}
+
+/// Write frames as numbered images in `output_dir`, named after the input
+/// `fps` samples that many frames per second (e.g. 0.1 for one every ten
+/// seconds); without it every frame is written.
+#[tauri::command]
+pub async fn export_image_sequence(
+    input_path: &str,
+    output_dir: &str,
+    fps: Option<f64>,
+    format: ImageFormat,
+) -> Result<ImageSequence> {
+    let duration = probe::media_duration(input_path)?;
+    let rate = match fps {
+        Some(fps) if fps.is_nan() || fps <= 0.0 => {
+            return Err(ClipFlowError::invalid(format!("Frame rate must be positive, got {}", fps)))
+        }
+        Some(fps) => fps,
+        None => probe::frame_rate(input_path)?,
+    };
+    if duration * rate > MAX_SEQUENCE_FRAMES {
+        return Err(ClipFlowError::invalid(format!(
+            "That would write about {:.0} images; lower the frame rate (limit is {})",
+            duration * rate,
+            MAX_SEQUENCE_FRAMES
+        )));
+    }
+    let planning = process::PlanRecorder::current().is_some();
+    if !planning {
+        fs::create_dir_all(output_dir).map_err(|e| ClipFlowError::io("Failed to create image sequence dir", e))?;
+    }
+
+    let stem = Path::new(input_path).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let prefix = format!("{}_", stem);
+    let pattern = format!("{}%06d.{}", prefix, format.extension());
+    let mut filters = Vec::new();
+    if let Some(fps) = fps {
+        filters.push(format!("fps={}", fps));
+    }
+    filters.extend(still_filters(input_path));
+
+    let mut args: Vec<String> = vec!["-i".to_string(), input_path.to_string(), "-an".to_string()];
+    if !filters.is_empty() {
+        args.extend(["-vf".to_string(), filters.join(",")]);
+    }
+    // Without a frame rate, keep every decoded frame rather than letting
+    // the muxer duplicate or drop to a constant rate
+    if fps.is_none() {
+        args.extend(["-fps_mode".to_string(), "passthrough".to_string()]);
+    }
+    args.extend(format.quality_args());
+    args.extend([Path::new(output_dir).join(&pattern).to_string_lossy().into_owned(), "-y".to_string()]);
+    process::run_ffmpeg_async(&args, "image sequence export").await?;
+    if planning {
+        return Ok(ImageSequence { pattern, frames: 0 });
+    }
+
+    let frames = fs::read_dir(output_dir)
+        .map_err(|e| ClipFlowError::io("Failed to read image sequence dir", e))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.starts_with(&prefix) && name.ends_with(&format!(".{}", format.extension()))
+        })
+        .count();
+    Ok(ImageSequence { pattern, frames })
+}
+
+/// Make a video from a numbered image sequence or a list of images, one
+/// image per frame at `fps`
+/// Returns the path written.
+#[tauri::command]
+pub async fn images_to_video(
+    images: ImageSource,
+    output_path: &str,
+    fps: f64,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    if fps.is_nan() || !(0.1..=240.0).contains(&fps) {
+        return Err(ClipFlowError::invalid(format!("Frame rate must be between 0.1 and 240, got {}", fps)));
+    }
+    // Kept alive until the render is done
+    let list_file = TempFile::new("images", "ffconcat")?;
+    let mut args: Vec<String> = match &images {
+        ImageSource::Pattern(pattern) => {
+            if !pattern.contains('%') {
+                return Err(ClipFlowError::invalid("Image pattern needs a number placeholder, e.g. img_%04d.jpg"));
+            }
+            vec!["-framerate".to_string(), fps.to_string(), "-i".to_string(), pattern.clone()]
+        }
+        ImageSource::List(list) => {
+            if list.is_empty() {
+                return Err(ClipFlowError::invalid("No images given"));
+            }
+            if let Some(missing) = list.iter().find(|p| !Path::new(p).is_file()) {
+                return Err(ClipFlowError::not_found(format!("Image not found: {}", missing)));
+            }
+            fs::write(list_file.path(), concat_list(list, fps))
+                .map_err(|e| ClipFlowError::io("Failed to write image list", e))?;
+            vec![
+                "-f".to_string(), "concat".to_string(),
+                "-safe".to_string(), "0".to_string(),
+                "-i".to_string(), list_file.path_str(),
+            ]
+        }
+    };
+    args.extend(still_video_args());
+    args.extend(["-r".to_string(), fps.to_string()]);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "images to video").await
+    })
+    .await
+}
+
+/// Make a video of one still image for the length of an audio file, e.g.
+/// a podcast episode with its cover art for YouTube
+/// Returns the path written.
+#[tauri::command]
+pub async fn image_plus_audio(
+    image_path: &str,
+    audio_path: &str,
+    output_path: &str,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    if !Path::new(image_path).is_file() {
+        return Err(ClipFlowError::not_found(format!("Image not found: {}", image_path)));
+    }
+    if probe::audio_tracks(audio_path)?.is_empty() {
+        return Err(ClipFlowError::invalid(format!("{} has no audio", audio_path)));
+    }
+    // A picture that never changes needs very few frames; stillimage
+    // tuning keeps the file close to the size of the audio alone
+    let mut args: Vec<String> = vec![
+        "-loop".to_string(), "1".to_string(),
+        "-framerate".to_string(), "1".to_string(),
+        "-i".to_string(), image_path.to_string(),
+        "-i".to_string(), audio_path.to_string(),
+        "-map".to_string(), "0:v".to_string(),
+        "-map".to_string(), "1:a:0".to_string(),
+    ];
+    args.extend(still_video_args());
+    args.extend([
+        "-tune", "stillimage",
+        "-c:a", "aac",
+        "-b:a", "192k",
+        "-shortest",
+    ].map(str::to_string));
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "image and audio video").await
+    })
+    .await
+}
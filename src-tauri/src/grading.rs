@@ -0,0 +1,262 @@
+//! Color correction: basic picture adjustments, with single-frame previews
+//!
+//! Adjustments map onto ffmpeg's eq filter (brightness, contrast,
+//! saturation, gamma) and colortemperature (white balance in Kelvin). A
+//! preview renders just one adjusted frame, so the UI can show the result
+//! of a slider change in well under a second before anything is rendered.
+//!
+//! Looks are applied as standard .cube 3D LUTs, picked from the LUT folder
+//! (set in settings) or given by path, and can be faded in below full
+//! strength.
+
+use crate::error::{ClipFlowError, Result};
+use crate::output::{self, OverwritePolicy};
+use crate::{color, escape_filter_path, probe, process, settings};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::ipc::Response;
+use tauri::AppHandle;
+use tokio::process::Command;
+
+/// Neutral white balance; colortemperature leaves 6500K untouched
+const NEUTRAL_TEMPERATURE: f64 = 6500.0;
+
+/// Width previews are scaled down to; plenty for a UI panel
+const PREVIEW_WIDTH: u32 = 960;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Lut {
+    /// File name without the extension
+    pub name: String,
+    pub path: String,
+    /// Points per axis, e.g. 33
+    pub size: u32,
+}
+
+/// Picture adjustments, each neutral when unset
+#[derive(Clone, Copy, Debug, Default)]
+struct ColorAdjustment {
+    /// -1 to 1, 0 is unchanged
+    brightness: Option<f64>,
+    /// 0 to 4, 1 is unchanged
+    contrast: Option<f64>,
+    /// 0 (grayscale) to 3, 1 is unchanged
+    saturation: Option<f64>,
+    /// 0.1 to 10, 1 is unchanged
+    gamma: Option<f64>,
+    /// White balance in Kelvin, 1000 to 40000; lower is warmer
+    temperature: Option<f64>,
+}
+
+fn check_range(name: &str, value: Option<f64>, min: f64, max: f64) -> Result<()> {
+    match value {
+        Some(v) if v.is_nan() || !(min..=max).contains(&v) => {
+            Err(ClipFlowError::invalid(format!("{} must be between {} and {}, got {}", name, min, max, v)))
+        }
+        _ => Ok(()),
+    }
+}
+
+impl ColorAdjustment {
+    /// Filters applying the adjustment, empty when it changes nothing
+    fn filters(&self) -> Result<Vec<String>> {
+        check_range("Brightness", self.brightness, -1.0, 1.0)?;
+        check_range("Contrast", self.contrast, 0.0, 4.0)?;
+        check_range("Saturation", self.saturation, 0.0, 3.0)?;
+        check_range("Gamma", self.gamma, 0.1, 10.0)?;
+        check_range("Temperature", self.temperature, 1000.0, 40000.0)?;
+
+        let eq: Vec<String> = [
+            ("brightness", self.brightness, 0.0),
+            ("contrast", self.contrast, 1.0),
+            ("saturation", self.saturation, 1.0),
+            ("gamma", self.gamma, 1.0),
+        ]
+        .iter()
+        .filter_map(|(name, value, neutral)| value.filter(|v| v != neutral).map(|v| format!("{}={}", name, v)))
+        .collect();
+
+        let mut filters = Vec::new();
+        if !eq.is_empty() {
+            filters.push(format!("eq={}", eq.join(":")));
+        }
+        if let Some(kelvin) = self.temperature.filter(|t| *t != NEUTRAL_TEMPERATURE) {
+            filters.push(format!("colortemperature=temperature={}", kelvin));
+        }
+        Ok(filters)
+    }
+}
+
+/// Adjust brightness, contrast, saturation, gamma, and white balance
+/// Audio is copied. Returns the path written.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn adjust_color(
+    input_path: &str,
+    output_path: &str,
+    brightness: Option<f64>,
+    contrast: Option<f64>,
+    saturation: Option<f64>,
+    gamma: Option<f64>,
+    temperature: Option<f64>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let adjustment = ColorAdjustment { brightness, contrast, saturation, gamma, temperature };
+    let adjust = adjustment.filters()?;
+    if adjust.is_empty() {
+        return Err(ClipFlowError::invalid("No color adjustment requested"));
+    }
+
+    // Adjust in the delivery color space, so previews and renders agree
+    let (color_filter, color_tags) = color::export_color_args(input_path);
+    let video_filters: Vec<String> = color_filter.into_iter().chain(adjust).collect();
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-vf".to_string(), video_filters.join(","),
+        "-c:a".to_string(), "copy".to_string(),
+    ];
+    args.extend(color_tags);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "color adjustment").await
+    })
+    .await
+}
+
+/// One frame at `timestamp` with the adjustment applied, as JPEG bytes
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn preview_color_adjustment(
+    input_path: &str,
+    timestamp: f64,
+    brightness: Option<f64>,
+    contrast: Option<f64>,
+    saturation: Option<f64>,
+    gamma: Option<f64>,
+    temperature: Option<f64>,
+) -> Result<Response> {
+    let duration = probe::media_duration(input_path)?;
+    if timestamp.is_nan() || timestamp < 0.0 || timestamp >= duration {
+        return Err(ClipFlowError::invalid(format!("{}s is outside the clip ({:.2}s)", timestamp, duration)));
+    }
+    let adjustment = ColorAdjustment { brightness, contrast, saturation, gamma, temperature };
+    let (color_filter, _) = color::export_color_args(input_path);
+    let mut video_filters: Vec<String> = color_filter.into_iter().chain(adjustment.filters()?).collect();
+    video_filters.push(format!("scale='min({},iw)':-2", PREVIEW_WIDTH));
+
+    let ffmpeg = settings::ffmpeg_bin();
+    let output = process::run(Command::new(&ffmpeg).args([
+        "-v", "error",
+        "-ss", &timestamp.to_string(),
+        "-i", input_path,
+        "-vf", &video_filters.join(","),
+        "-frames:v", "1",
+        "-q:v", "3",
+        "-f", "image2pipe",
+        "-c:v", "mjpeg",
+        "-",
+    ]), None, |_| {})
+    .await
+    .map_err(|e| process::spawn_error(&ffmpeg, e))?;
+    process::check_ffmpeg(&output, "color preview")?;
+    if output.stdout.is_empty() {
+        return Err(ClipFlowError::tool("ffmpeg", format!("no frame decoded at {}s", timestamp)));
+    }
+    Ok(Response::new(output.stdout))
+}
+
+/// Folder LUTs are listed from, created on first use
+fn lut_dir(app: &AppHandle) -> Result<PathBuf> {
+    let Some(dir) = settings::current().lut_dir else {
+        return settings::data_dir(app, "luts");
+    };
+    let dir = PathBuf::from(dir);
+    fs::create_dir_all(&dir).map_err(|e| ClipFlowError::io("Failed to create LUT dir", e))?;
+    Ok(dir)
+}
+
+/// Grid size of a 3D .cube LUT, from its LUT_3D_SIZE header
+fn cube_size(path: &Path) -> Result<u32> {
+    let content = fs::read_to_string(path).map_err(|e| ClipFlowError::io(&format!("Failed to read {}", path.display()), e))?;
+    let mut lines = content.lines().map(str::trim);
+    if lines.clone().any(|l| l.starts_with("LUT_1D_SIZE")) {
+        return Err(ClipFlowError::invalid(format!("{} is a 1D LUT; only 3D LUTs are supported", path.display())));
+    }
+    lines
+        .find_map(|l| l.strip_prefix("LUT_3D_SIZE"))
+        .and_then(|size| size.trim().parse::<u32>().ok())
+        .filter(|size| (2..=256).contains(size))
+        .ok_or_else(|| ClipFlowError::invalid(format!("{} isn't a valid .cube LUT", path.display())))
+}
+
+/// The .cube LUTs in the LUT folder, by name
+/// Files that aren't valid 3D LUTs are skipped.
+#[tauri::command]
+pub async fn list_luts(app: AppHandle) -> Result<Vec<Lut>> {
+    let dir = lut_dir(&app)?;
+    let entries = fs::read_dir(&dir).map_err(|e| ClipFlowError::io("Failed to read LUT dir", e))?;
+    let mut luts: Vec<Lut> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|e| e.eq_ignore_ascii_case("cube")))
+        .filter_map(|path| match cube_size(&path) {
+            Ok(size) => Some(Lut {
+                name: path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default(),
+                path: path.to_string_lossy().into_owned(),
+                size,
+            }),
+            Err(e) => {
+                tracing::debug!(path = %path.display(), error = %e, "skipping LUT");
+                None
+            }
+        })
+        .collect();
+    luts.sort_by_key(|lut| lut.name.to_lowercase());
+    Ok(luts)
+}
+
+/// Grade footage with a .cube LUT
+/// `intensity` (0-1, default 1) blends the graded picture over the
+/// original. Audio is copied. Returns the path written.
+#[tauri::command]
+pub async fn apply_lut(
+    input_path: &str,
+    output_path: &str,
+    cube_path: &str,
+    intensity: Option<f64>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let intensity = intensity.unwrap_or(1.0);
+    if intensity.is_nan() || !(0.0..=1.0).contains(&intensity) {
+        return Err(ClipFlowError::invalid(format!("Intensity must be between 0 and 1, got {}", intensity)));
+    }
+    cube_size(Path::new(cube_path))?;
+
+    let lut = format!("lut3d=file='{}':interp=tetrahedral", escape_filter_path(cube_path));
+    let (color_filter, color_tags) = color::export_color_args(input_path);
+    let graded = if intensity < 1.0 {
+        // blend lays its first input over its second at this opacity
+        format!("split[original][tograde];[tograde]{}[graded];[graded][original]blend=all_mode=normal:all_opacity={}", lut, intensity)
+    } else {
+        lut
+    };
+    let video_filter = match color_filter {
+        Some(convert) => format!("{},{}", convert, graded),
+        None => graded,
+    };
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-vf".to_string(), video_filter,
+        "-c:a".to_string(), "copy".to_string(),
+    ];
+    args.extend(color_tags);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "LUT grading").await
+    })
+    .await
+}
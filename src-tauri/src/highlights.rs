@@ -0,0 +1,201 @@
+//! Highlight detection: proposing the moments worth clipping for shorts
+//!
+//! Three signals are scored second by second and combined:
+//! - audio energy well above the recording's typical level (shouting,
+//!   cheering, a sudden reaction)
+//! - laughter and exclamations in the stored transcript, when there is one
+//! - scene density, since fast cutting usually marks something happening
+//!
+//! Each candidate is the best-scoring window of the requested length that
+//! doesn't overlap a better one; its confidence is the window's average
+//! combined score, from 0 to 1.
+
+use crate::error::{ClipFlowError, Result};
+use crate::{chapters, process, settings, transcripts};
+use serde::Serialize;
+use tauri::AppHandle;
+use tokio::process::Command;
+
+/// Rate audio is decoded at for energy; loudness doesn't need more
+const ENERGY_SAMPLE_RATE: usize = 4000;
+
+/// Scene score above which a frame counts as a cut
+const SCENE_THRESHOLD: f64 = 0.3;
+
+/// Seconds either side of a moment that cuts are counted over
+const SCENE_WINDOW: f64 = 5.0;
+
+/// Weights of the audio, transcript, and scene signals
+const WEIGHTS: (f64, f64, f64) = (0.5, 0.3, 0.2);
+
+/// A signal averaging above this over a window is named as a reason
+const REASON_THRESHOLD: f64 = 0.3;
+
+const DEFAULT_CLIP_LENGTH: f64 = 30.0;
+const DEFAULT_MAX_RESULTS: usize = 10;
+
+/// Words and sounds transcripts use for laughter and excitement
+const KEYWORDS: [&str; 16] = [
+    "haha", "lol", "lmao", "[laughter]", "(laughs)", "[laughs]", "oh my god", "no way", "wow", "let's go", "what the",
+    "holy", "insane", "crazy", "amazing", "yes!",
+];
+
+#[derive(Serialize, Clone)]
+pub struct Highlight {
+    pub start: f64,
+    pub end: f64,
+    /// 0 to 1
+    pub confidence: f64,
+    /// e.g. "loud audio", "laughter or exclamations", "fast cutting"
+    pub reasons: Vec<String>,
+}
+
+/// RMS level of each second of the first audio track, in dBFS
+async fn energy_per_second(file_path: &str) -> Result<Vec<f64>> {
+    let ffmpeg = settings::ffmpeg_bin();
+    let rate = ENERGY_SAMPLE_RATE.to_string();
+    let output = process::run(Command::new(&ffmpeg).args([
+        "-v", "error",
+        "-i", file_path,
+        "-map", "0:a:0",
+        "-ac", "1",
+        "-ar", rate.as_str(),
+        "-f", "s16le",
+        "-",
+    ]), None, |_| {})
+    .await
+    .map_err(|e| process::spawn_error(&ffmpeg, e))?;
+    process::check_ffmpeg(&output, "audio energy")?;
+
+    Ok(output
+        .stdout
+        .chunks(ENERGY_SAMPLE_RATE * 2)
+        .map(|second| {
+            let samples = second.chunks_exact(2).map(|s| i16::from_le_bytes([s[0], s[1]]) as f64 / 32768.0);
+            let (sum, count) = samples.fold((0.0, 0usize), |(sum, n), s| (sum + s * s, n + 1));
+            let rms = if count == 0 { 0.0 } else { (sum / count as f64).sqrt() };
+            (20.0 * rms.max(1e-5).log10()).max(-100.0)
+        })
+        .collect())
+}
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    sorted.get(sorted.len() / 2).copied().unwrap_or(0.0)
+}
+
+/// How far each second stands out above the typical level, 0 to 1
+fn audio_scores(levels: &[f64]) -> Vec<f64> {
+    let typical = median(levels);
+    let deviations: Vec<f64> = levels.iter().map(|l| (l - typical).abs()).collect();
+    // Median absolute deviation, as a robust spread; +1dB keeps a flat
+    // recording from turning tiny bumps into highlights
+    let spread = median(&deviations) * 1.4826 + 1.0;
+    levels.iter().map(|l| ((l - typical) / spread / 3.0).clamp(0.0, 1.0)).collect()
+}
+
+/// Laughter and exclamations per second of the stored transcript, 0 to 1
+fn transcript_scores(app: &AppHandle, file_path: &str, seconds: usize) -> Vec<f64> {
+    let mut scores = vec![0.0; seconds];
+    let stored = match transcripts::load(app, file_path) {
+        Ok(Some(stored)) if !stored.stale => stored,
+        _ => return scores,
+    };
+    for segment in &stored.transcript.segments {
+        let text = segment.text.to_lowercase();
+        let hits = KEYWORDS.iter().map(|k| text.matches(k).count()).sum::<usize>() + text.matches('!').count();
+        if hits == 0 {
+            continue;
+        }
+        let first = segment.start.max(0.0) as usize;
+        let last = (segment.end.ceil() as usize).min(seconds);
+        for score in scores.iter_mut().take(last).skip(first) {
+            *score = (*score + hits as f64 * 0.5).min(1.0);
+        }
+    }
+    scores
+}
+
+/// Cuts near each second, 0 to 1 (one cut per second or more is 1)
+fn scene_scores(cuts: &[f64], seconds: usize) -> Vec<f64> {
+    (0..seconds)
+        .map(|t| {
+            let t = t as f64 + 0.5;
+            let near = cuts.iter().filter(|c| (*c - t).abs() <= SCENE_WINDOW).count();
+            (near as f64 / (SCENE_WINDOW * 2.0)).min(1.0)
+        })
+        .collect()
+}
+
+fn window_mean(prefix: &[f64], start: usize, len: usize) -> f64 {
+    (prefix[start + len] - prefix[start]) / len as f64
+}
+
+fn prefix_sums(values: &[f64]) -> Vec<f64> {
+    let mut prefix = vec![0.0; values.len() + 1];
+    for (i, v) in values.iter().enumerate() {
+        prefix[i + 1] = prefix[i] + v;
+    }
+    prefix
+}
+
+/// Up to `max_results` non-overlapping clip ranges of `clip_length`
+/// seconds, best first
+pub async fn find_highlights(app: &AppHandle, file_path: &str, max_results: usize, clip_length: f64) -> Result<Vec<Highlight>> {
+    let levels = energy_per_second(file_path).await?;
+    let seconds = levels.len();
+    let length = (clip_length.round() as usize).max(1);
+    if seconds < length {
+        return Err(ClipFlowError::invalid(format!("{} is too short to find {}s highlights in", file_path, clip_length)));
+    }
+    let audio = audio_scores(&levels);
+    let words = transcript_scores(app, file_path, seconds);
+    let scenes = scene_scores(&chapters::scene_changes(file_path, SCENE_THRESHOLD).await?, seconds);
+
+    let combined: Vec<f64> = (0..seconds)
+        .map(|t| audio[t] * WEIGHTS.0 + words[t] * WEIGHTS.1 + scenes[t] * WEIGHTS.2)
+        .collect();
+    let (combined, audio, words, scenes) = (prefix_sums(&combined), prefix_sums(&audio), prefix_sums(&words), prefix_sums(&scenes));
+
+    let mut windows: Vec<(usize, f64)> = (0..=seconds - length).map(|s| (s, window_mean(&combined, s, length))).collect();
+    windows.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut picked: Vec<(usize, f64)> = Vec::new();
+    for (start, score) in windows {
+        if picked.len() >= max_results || score <= 0.0 {
+            break;
+        }
+        if picked.iter().all(|(other, _)| start + length <= *other || *other + length <= start) {
+            picked.push((start, score));
+        }
+    }
+
+    Ok(picked
+        .into_iter()
+        .map(|(start, score)| {
+            let reasons = [(&audio, "loud audio"), (&words, "laughter or exclamations"), (&scenes, "fast cutting")]
+                .iter()
+                .filter(|(signal, _)| window_mean(signal, start, length) > REASON_THRESHOLD)
+                .map(|(_, reason)| reason.to_string())
+                .collect();
+            Highlight { start: start as f64, end: (start + length) as f64, confidence: score.min(1.0), reasons }
+        })
+        .collect())
+}
+
+/// Propose clip ranges for shorts, best first
+/// The transcript signal is used when the file has an up-to-date stored
+/// transcript.
+#[tauri::command]
+pub async fn detect_highlights(
+    app: AppHandle,
+    file_path: &str,
+    max_results: Option<usize>,
+    clip_length: Option<f64>,
+) -> Result<Vec<Highlight>> {
+    let clip_length = clip_length.unwrap_or(DEFAULT_CLIP_LENGTH);
+    if clip_length.is_nan() || clip_length < 1.0 {
+        return Err(ClipFlowError::invalid(format!("Clip length must be at least 1 second, got {}", clip_length)));
+    }
+    find_highlights(&app, file_path, max_results.unwrap_or(DEFAULT_MAX_RESULTS), clip_length).await
+}
@@ -0,0 +1,197 @@
+//! Verified-copy ingest for offloading camera cards
+//!
+//! Each file is hashed while it's read from the source, then the written
+//! copy is read back and hashed independently, so a flaky card reader or
+//! cable can't silently corrupt footage.
+
+use crate::error::{ClipFlowError, Result};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+#[derive(Serialize, Clone)]
+pub struct IngestFileResult {
+    source: String,
+    destination: String,
+    size_bytes: u64,
+    source_hash: String,
+    destination_hash: Option<String>,
+    verified: bool,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct IngestReport {
+    files: Vec<IngestFileResult>,
+    total_bytes: u64,
+    verified_count: usize,
+    mismatch_count: usize,
+    error_count: usize,
+}
+
+#[derive(Serialize, Clone)]
+struct IngestProgress {
+    file: String,
+    file_index: usize,
+    file_count: usize,
+    bytes_done: u64,
+    bytes_total: u64,
+}
+
+/// Copy `src` to `dst`, hashing the bytes as they stream through
+fn copy_with_hash(src: &Path, dst: &Path) -> Result<(u64, String)> {
+    let mut reader = BufReader::with_capacity(BUFFER_SIZE, File::open(src).map_err(|e| ClipFlowError::io("Failed to open source", e))?);
+    let mut writer = BufWriter::with_capacity(BUFFER_SIZE, File::create(dst).map_err(|e| ClipFlowError::io("Failed to create destination", e))?);
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut total = 0u64;
+
+    loop {
+        let n = reader.read(&mut buffer).map_err(|e| ClipFlowError::io("Failed to read source", e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+        writer.write_all(&buffer[..n]).map_err(|e| ClipFlowError::io("Failed to write destination", e))?;
+        total += n as u64;
+    }
+
+    let file = writer.into_inner().map_err(|e| ClipFlowError::io("Failed to flush destination", e))?;
+    // Make sure the read-back below hits the disk, not just the page cache
+    file.sync_all().map_err(|e| ClipFlowError::io("Failed to sync destination", e))?;
+
+    Ok((total, hasher.finalize().to_hex().to_string()))
+}
+
+/// BLAKE3 hash of a file's contents
+pub fn hash_file(path: &Path) -> Result<String> {
+    hash_file_with_progress(path, |_| {})
+}
+
+/// Like `hash_file`, calling `on_read` with the byte count of each chunk
+pub fn hash_file_with_progress(path: &Path, mut on_read: impl FnMut(u64)) -> Result<String> {
+    let mut reader = BufReader::with_capacity(BUFFER_SIZE, File::open(path).map_err(|e| ClipFlowError::io(&format!("Failed to open {}", path.display()), e))?);
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    loop {
+        let n = reader.read(&mut buffer).map_err(|e| ClipFlowError::io(&format!("Failed to read {}", path.display()), e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+        on_read(n as u64);
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Copy files into `destination_dir`, verifying every copy against its source
+///
+/// Existing destination files are never overwritten. A mismatched copy is
+/// left in place (renamed with a `.mismatch` suffix) so it can be inspected.
+#[tauri::command]
+pub async fn verified_ingest(app: AppHandle, sources: Vec<String>, destination_dir: String) -> Result<IngestReport> {
+    let dest_dir = PathBuf::from(&destination_dir);
+    fs::create_dir_all(&dest_dir).map_err(|e| ClipFlowError::io("Failed to create destination dir", e))?;
+
+    let bytes_total: u64 = sources.iter().filter_map(|s| fs::metadata(s).ok()).map(|m| m.len()).sum();
+    let mut bytes_done = 0u64;
+    let mut results = Vec::new();
+
+    for (index, source) in sources.iter().enumerate() {
+        let src = Path::new(source);
+        let file_name = match src.file_name() {
+            Some(name) => name.to_owned(),
+            None => {
+                results.push(IngestFileResult {
+                    source: source.clone(),
+                    destination: String::new(),
+                    size_bytes: 0,
+                    source_hash: String::new(),
+                    destination_hash: None,
+                    verified: false,
+                    error: Some("Not a file path".to_string()),
+                });
+                continue;
+            }
+        };
+        let dst = dest_dir.join(&file_name);
+
+        let _ = app.emit("ingest-progress", IngestProgress {
+            file: source.clone(),
+            file_index: index,
+            file_count: sources.len(),
+            bytes_done,
+            bytes_total,
+        });
+
+        let mut result = IngestFileResult {
+            source: source.clone(),
+            destination: dst.to_string_lossy().into_owned(),
+            size_bytes: 0,
+            source_hash: String::new(),
+            destination_hash: None,
+            verified: false,
+            error: None,
+        };
+
+        if dst.exists() {
+            result.error = Some("Destination file already exists".to_string());
+            results.push(result);
+            continue;
+        }
+
+        match copy_with_hash(src, &dst) {
+            Ok((size, source_hash)) => {
+                result.size_bytes = size;
+                result.source_hash = source_hash;
+                bytes_done += size;
+
+                match hash_file(&dst) {
+                    Ok(dest_hash) => {
+                        result.verified = dest_hash == result.source_hash;
+                        if !result.verified {
+                            let quarantined = dst.with_extension(format!(
+                                "{}.mismatch",
+                                dst.extension().map(|e| e.to_string_lossy().into_owned()).unwrap_or_default()
+                            ));
+                            let _ = fs::rename(&dst, &quarantined);
+                            result.destination = quarantined.to_string_lossy().into_owned();
+                            result.error = Some("Checksum mismatch between source and copy".to_string());
+                        }
+                        result.destination_hash = Some(dest_hash);
+                    }
+                    Err(e) => result.error = Some(e.to_string()),
+                }
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&dst);
+                result.error = Some(e.to_string());
+            }
+        }
+
+        if let Some(error) = &result.error {
+            tracing::warn!(source = %result.source, error = %error, "ingest verification failed");
+        }
+        results.push(result);
+    }
+
+    let _ = app.emit("ingest-progress", IngestProgress {
+        file: String::new(),
+        file_index: sources.len(),
+        file_count: sources.len(),
+        bytes_done,
+        bytes_total,
+    });
+
+    Ok(IngestReport {
+        total_bytes: bytes_done,
+        verified_count: results.iter().filter(|r| r.verified).count(),
+        mismatch_count: results.iter().filter(|r| r.destination_hash.is_some() && !r.verified).count(),
+        error_count: results.iter().filter(|r| r.error.is_some()).count(),
+        files: results,
+    })
+}
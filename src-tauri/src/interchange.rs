@@ -0,0 +1,425 @@
+//! Edit interchange: the project's cut written out as a CMX3600 EDL, an
+//! OpenTimelineIO document, or FCPXML, so a rough cut can be finished in
+//! Resolve, Premiere, or Final Cut
+//!
+//! Each clip contributes its in/out range minus the cuts on it, and the
+//! kept pieces play back to back. Source times are written in each file's
+//! own timecode so the NLE relinks against the camera originals.
+//!
+//! Cut lists made elsewhere (EDL, CSV, YouTube chapters) come back in as
+//! keep-segments.
+
+use crate::error::{ClipFlowError, Result};
+use crate::project::{Clip, Marker, MarkerColor, Project};
+use crate::timecode::{self, SourceTimecode};
+use crate::{chapters, fcpxml, probe, CutSegment};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Record timecode of the first frame, the usual program start
+const RECORD_START: &str = "01:00:00:00";
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum EditFormat {
+    /// CMX3600 edit decision list
+    Edl,
+    /// OpenTimelineIO JSON (.otio)
+    Otio,
+    /// Final Cut Pro XML, as an .fcpxml file or an .fcpxmld bundle
+    Fcpxml,
+}
+
+/// A kept piece of a clip, in frames
+pub struct EditEvent<'a> {
+    pub clip: &'a Clip,
+    pub timecode: SourceTimecode,
+    /// Source in/out as absolute frames of the clip's timecode, at its own rate
+    pub source_in: u64,
+    pub source_out: u64,
+    /// Record in/out from the start of the edit, at the edit rate
+    pub record_in: u64,
+    pub record_out: u64,
+    /// Markers inside the piece, with their source frame
+    pub markers: Vec<(u64, &'a Marker)>,
+}
+
+/// The project's edit, conformed to frames
+pub struct Edit<'a> {
+    pub fps: f64,
+    pub drop_frame: bool,
+    pub events: Vec<EditEvent<'a>>,
+}
+
+/// Ranges of a clip that survive its cuts, in seconds
+fn kept_ranges(clip: &Clip, project: &Project) -> Vec<(f64, f64)> {
+    let mut cuts: Vec<(f64, f64)> = project
+        .cuts
+        .iter()
+        .filter(|cut| cut.clip_id == clip.id)
+        .map(|cut| (cut.start, cut.end))
+        .collect();
+    cuts.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut ranges = Vec::new();
+    let mut position = clip.in_point;
+    for (start, end) in cuts {
+        if start > position {
+            ranges.push((position, start.min(clip.out_point)));
+        }
+        position = position.max(end);
+    }
+    ranges.push((position, clip.out_point));
+    ranges.retain(|(start, end)| end > start);
+    ranges
+}
+
+/// The project's kept pieces in playback order, timed in frames
+/// The edit runs at the first clip's frame rate.
+pub fn conform(project: &Project) -> Result<Edit<'_>> {
+    let mut timecodes: HashMap<&str, SourceTimecode> = HashMap::new();
+    for clip in &project.clips {
+        if let Entry::Vacant(entry) = timecodes.entry(clip.path.as_str()) {
+            entry.insert(timecode::source_timecode(&clip.path)?);
+        }
+    }
+    let first = match project.clips.first() {
+        Some(clip) => &timecodes[clip.path.as_str()],
+        None => return Err(ClipFlowError::invalid("The project has no clips to export")),
+    };
+    let (fps, drop_frame) = (first.fps, first.drop_frame);
+
+    let mut events = Vec::new();
+    let mut record = 0;
+    for clip in &project.clips {
+        let tc = &timecodes[clip.path.as_str()];
+        let start = timecode::timecode_to_frames(&tc.start_timecode, tc.fps, tc.drop_frame).unwrap_or(0);
+        for (from, to) in kept_ranges(clip, project) {
+            let (source_in, source_out) = ((from * tc.fps).round() as u64, (to * tc.fps).round() as u64);
+            if source_out <= source_in {
+                continue;
+            }
+            // Counting record time in whole frames keeps the events butted
+            // together with no drift
+            let length = ((source_out - source_in) as f64 * fps / tc.fps).round().max(1.0) as u64;
+            let markers = project
+                .markers
+                .iter()
+                .filter(|marker| marker.clip_id == clip.id)
+                .map(|marker| ((marker.time * tc.fps).round() as u64, marker))
+                .filter(|(frame, _)| (source_in..source_out).contains(frame))
+                .map(|(frame, marker)| (start + frame, marker))
+                .collect();
+            events.push(EditEvent {
+                clip,
+                timecode: tc.clone(),
+                source_in: start + source_in,
+                source_out: start + source_out,
+                record_in: record,
+                record_out: record + length,
+                markers,
+            });
+            record += length;
+        }
+    }
+    if events.is_empty() {
+        return Err(ClipFlowError::invalid("Every clip in the project is cut out"));
+    }
+    Ok(Edit { fps, drop_frame, events })
+}
+
+pub fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// file:// URL for a local path, with the characters URLs reserve escaped
+pub fn file_url(path: &str) -> String {
+    let encoded: String = path
+        .replace('\\', "/")
+        .chars()
+        .map(|c| match c {
+            ' ' => "%20".to_string(),
+            '%' => "%25".to_string(),
+            '#' => "%23".to_string(),
+            '?' => "%3F".to_string(),
+            c => c.to_string(),
+        })
+        .collect();
+    if encoded.starts_with('/') {
+        format!("file://{}", encoded)
+    } else {
+        format!("file:///{}", encoded)
+    }
+}
+
+/// A CMX3600 EDL with one A/V event per kept piece
+/// The clip name comment is what Resolve and Premiere relink by.
+fn write_edl(project: &Project, edit: &Edit) -> String {
+    let record_start = timecode::timecode_to_frames(RECORD_START, edit.fps, edit.drop_frame).unwrap_or(0);
+    let mut edl = format!(
+        "TITLE: {}\nFCM: {}\n\n",
+        project.name.lines().next().unwrap_or(""),
+        if edit.drop_frame { "DROP FRAME" } else { "NON-DROP FRAME" }
+    );
+    for (i, event) in edit.events.iter().enumerate() {
+        let source = |frames| timecode::frames_to_timecode(frames, event.timecode.fps, event.timecode.drop_frame);
+        let record = |frames| timecode::frames_to_timecode(record_start + frames, edit.fps, edit.drop_frame);
+        edl.push_str(&format!(
+            "{:03}  AX       AA/V  C        {} {} {} {}\n* FROM CLIP NAME: {}\n* SOURCE FILE: {}\n\n",
+            i + 1,
+            source(event.source_in),
+            source(event.source_out),
+            record(event.record_in),
+            record(event.record_out),
+            file_name(&event.clip.path),
+            event.clip.path
+        ));
+    }
+    edl
+}
+
+/// Seconds into the edit where `time` in clip `clip_id` plays, or None
+/// when that moment is cut out
+pub fn edit_time(project: &Project, clip_id: &str, time: f64) -> Option<f64> {
+    let mut record = 0.0;
+    for clip in &project.clips {
+        for (from, to) in kept_ranges(clip, project) {
+            if clip.id == clip_id && (from..to).contains(&time) {
+                return Some(record + time - from);
+            }
+            record += to - from;
+        }
+    }
+    None
+}
+
+fn rational_time(value: u64, rate: f64) -> Value {
+    json!({ "OTIO_SCHEMA": "RationalTime.1", "rate": rate, "value": value as f64 })
+}
+
+fn time_range(start: u64, duration: u64, rate: f64) -> Value {
+    json!({
+        "OTIO_SCHEMA": "TimeRange.1",
+        "start_time": rational_time(start, rate),
+        "duration": rational_time(duration, rate),
+    })
+}
+
+/// OTIO's marker color names
+fn otio_color(color: MarkerColor) -> &'static str {
+    match color {
+        MarkerColor::Red => "RED",
+        MarkerColor::Orange => "ORANGE",
+        MarkerColor::Yellow => "YELLOW",
+        MarkerColor::Green => "GREEN",
+        MarkerColor::Cyan => "CYAN",
+        MarkerColor::Blue => "BLUE",
+        MarkerColor::Purple => "PURPLE",
+        MarkerColor::Pink => "PINK",
+    }
+}
+
+fn otio_clip(event: &EditEvent, with_markers: bool) -> Value {
+    let tc = &event.timecode;
+    let start = timecode::timecode_to_frames(&tc.start_timecode, tc.fps, tc.drop_frame).unwrap_or(0);
+    let markers: Vec<Value> = if with_markers {
+        event
+            .markers
+            .iter()
+            .map(|(frame, marker)| {
+                json!({
+                    "OTIO_SCHEMA": "Marker.2",
+                    "name": marker.name,
+                    "color": otio_color(marker.color),
+                    "marked_range": time_range(*frame, 0, tc.fps),
+                    "comment": "",
+                    "metadata": {},
+                })
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    json!({
+        "OTIO_SCHEMA": "Clip.1",
+        "name": event.clip.name,
+        "source_range": time_range(event.source_in, event.source_out - event.source_in, tc.fps),
+        "media_reference": {
+            "OTIO_SCHEMA": "ExternalReference.1",
+            "name": file_name(&event.clip.path),
+            "target_url": file_url(&event.clip.path),
+            "available_range": time_range(start, (event.clip.duration * tc.fps).round() as u64, tc.fps),
+            "metadata": {},
+        },
+        "effects": [],
+        "markers": markers,
+        "metadata": {},
+    })
+}
+
+/// Markers go on the video track only, so the NLE doesn't show them twice
+fn otio_track(name: &str, kind: &str, edit: &Edit) -> Value {
+    json!({
+        "OTIO_SCHEMA": "Track.1",
+        "name": name,
+        "kind": kind,
+        "source_range": null,
+        "children": edit.events.iter().map(|event| otio_clip(event, kind == "Video")).collect::<Vec<_>>(),
+        "effects": [],
+        "markers": [],
+        "metadata": {},
+    })
+}
+
+/// An OpenTimelineIO timeline with matching video and audio tracks
+fn write_otio(project: &Project, edit: &Edit) -> Result<String> {
+    let record_start = timecode::timecode_to_frames(RECORD_START, edit.fps, edit.drop_frame).unwrap_or(0);
+    let timeline = json!({
+        "OTIO_SCHEMA": "Timeline.1",
+        "name": project.name,
+        "global_start_time": rational_time(record_start, edit.fps),
+        "tracks": {
+            "OTIO_SCHEMA": "Stack.1",
+            "name": "tracks",
+            "source_range": null,
+            "children": [otio_track("V1", "Video", edit), otio_track("A1", "Audio", edit)],
+            "effects": [],
+            "markers": [],
+            "metadata": {},
+        },
+        "metadata": {},
+    });
+    serde_json::to_string_pretty(&timeline).map_err(|e| ClipFlowError::io("Failed to serialize timeline", e))
+}
+
+/// Write the project's edit to `path` as an EDL, OpenTimelineIO, or FCPXML file
+#[tauri::command]
+pub async fn export_edit(project: Project, format: EditFormat, path: &str) -> Result<()> {
+    let edit = conform(&project)?;
+    let (contents, path) = match format {
+        EditFormat::Edl => (write_edl(&project, &edit), PathBuf::from(path)),
+        EditFormat::Otio => (write_otio(&project, &edit)?, PathBuf::from(path)),
+        EditFormat::Fcpxml => (fcpxml::write_fcpxml(&project, &edit)?, fcpxml::document_path(path)?),
+    };
+    fs::write(path, contents).map_err(|e| ClipFlowError::io("Failed to write edit", e))
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CutListFormat {
+    /// CMX3600 EDL; each event's source range is kept
+    Edl,
+    /// "start,end" rows in seconds or h:mm:ss, with an optional header
+    Csv,
+    /// A YouTube description chapter list; each chapter is kept whole
+    YoutubeChapters,
+}
+
+/// Sort ranges and merge the ones that overlap or touch, dropping empties
+fn merge_ranges(mut ranges: Vec<(f64, f64)>) -> Vec<CutSegment> {
+    ranges.retain(|(start, end)| end > start);
+    ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut merged: Vec<CutSegment> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.keep_end => last.keep_end = last.keep_end.max(end),
+            _ => merged.push(CutSegment { keep_start: start, keep_end: end }),
+        }
+    }
+    merged
+}
+
+/// Source ranges of an EDL's events, in seconds from the start of the file
+/// Split edits list video and audio separately; their ranges are merged.
+fn parse_edl(text: &str, source: &SourceTimecode) -> Result<Vec<(f64, f64)>> {
+    let drop_frame = text.lines().any(|line| line.trim() == "FCM: DROP FRAME") || source.drop_frame;
+    let start = timecode::timecode_to_frames(&source.start_timecode, source.fps, source.drop_frame).unwrap_or(0);
+    let mut ranges = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        // Event lines start with the event number; titles, FCM, and
+        // comments don't
+        if !fields.first().is_some_and(|f| f.parse::<u32>().is_ok()) {
+            continue;
+        }
+        if fields.len() < 8 {
+            return Err(ClipFlowError::invalid(format!("Line {}: EDL event is missing timecodes", n + 1)));
+        }
+        let times = &fields[fields.len() - 4..];
+        let frames = |tc: &str| timecode::timecode_to_frames(tc, source.fps, drop_frame);
+        let (source_in, source_out) = (frames(times[0])?, frames(times[1])?);
+        let seconds = |frames: u64| frames.saturating_sub(start) as f64 / source.fps;
+        ranges.push((seconds(source_in), seconds(source_out)));
+    }
+    Ok(ranges)
+}
+
+/// "start,end" rows; a first row that isn't times is taken as a header
+fn parse_csv(text: &str) -> Result<Vec<(f64, f64)>> {
+    let time = |field: &str| {
+        let field = field.trim().trim_matches('"');
+        field.parse::<f64>().ok().or_else(|| chapters::parse_timestamp(field))
+    };
+    let mut ranges = Vec::new();
+    for (n, line) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let fields: Vec<&str> = line.split([',', ';', '\t']).collect();
+        match (fields.first().and_then(|f| time(f)), fields.get(1).and_then(|f| time(f))) {
+            (Some(start), Some(end)) => ranges.push((start, end)),
+            _ if n == 0 => continue,
+            _ => return Err(ClipFlowError::invalid(format!("Line {}: expected \"start,end\"", n + 1))),
+        }
+    }
+    Ok(ranges)
+}
+
+/// Each chapter runs to the next one's start; the last runs to `duration`
+fn parse_chapter_list(text: &str, duration: Option<f64>) -> Result<Vec<(f64, f64)>> {
+    let mut chapters = chapters::parse_manual(text)?;
+    chapters.sort_by(|a, b| a.start.total_cmp(&b.start));
+    let mut ranges: Vec<(f64, f64)> = chapters.windows(2).map(|pair| (pair[0].start, pair[1].start)).collect();
+    if let Some(last) = chapters.last() {
+        let end = duration
+            .ok_or_else(|| ClipFlowError::invalid("The last chapter's end needs the media file's duration"))?;
+        ranges.push((last.start, end));
+    }
+    Ok(ranges)
+}
+
+/// Read a cut list made elsewhere as keep-segments for `cut_video_remove`
+/// `media_path` is the file the list applies to: its timecode places EDL
+/// source times, and its duration ends the last YouTube chapter.
+#[tauri::command]
+pub async fn import_cuts(path: &str, format: CutListFormat, media_path: Option<String>) -> Result<Vec<CutSegment>> {
+    let text = fs::read_to_string(path).map_err(|e| ClipFlowError::io("Failed to read cut list", e))?;
+    let ranges = match format {
+        CutListFormat::Edl => {
+            let source = match &media_path {
+                Some(media_path) => timecode::source_timecode(media_path)?,
+                None => SourceTimecode {
+                    start_timecode: "00:00:00:00".to_string(),
+                    fps: 30.0,
+                    drop_frame: false,
+                    embedded: false,
+                },
+            };
+            parse_edl(&text, &source)?
+        }
+        CutListFormat::Csv => parse_csv(&text)?,
+        CutListFormat::YoutubeChapters => {
+            let duration = media_path.as_deref().map(probe::media_duration).transpose()?;
+            parse_chapter_list(&text, duration)?
+        }
+    };
+    let segments = merge_ranges(ranges);
+    if segments.is_empty() {
+        return Err(ClipFlowError::invalid("The cut list has no segments"));
+    }
+    Ok(segments)
+}
@@ -0,0 +1,100 @@
+//! Editing intermediates: ProRes or DNxHR in MOV for handing clips off to
+//! Premiere, Resolve, or Final Cut
+//!
+//! Unlike a delivery export, an intermediate keeps the source's color space
+//! instead of converting to BT.709 - the editor grades from it - so the
+//! source's matrix, primaries, transfer, and range are copied onto the
+//! output explicitly. Leaving them off is what makes NLEs guess and shift
+//! the picture on import.
+
+use crate::error::Result;
+use crate::output::{self, OverwritePolicy};
+use crate::{color, process, timecode};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum IntermediateFormat {
+    /// ProRes 422 LT, 10-bit
+    Prores422Lt,
+    /// ProRes 422, 10-bit
+    Prores422,
+    /// ProRes 422 HQ, 10-bit
+    Prores422Hq,
+    /// DNxHR SQ, 8-bit 4:2:2
+    DnxhrSq,
+    /// DNxHR HQ, 8-bit 4:2:2
+    DnxhrHq,
+    /// DNxHR HQX, 10-bit 4:2:2
+    DnxhrHqx,
+}
+
+impl IntermediateFormat {
+    fn codec_args(&self) -> Vec<&'static str> {
+        // The apl0 vendor tag makes Apple software treat prores_ks output as
+        // its own ProRes
+        let prores = |profile: &'static str| vec!["-c:v", "prores_ks", "-profile:v", profile, "-vendor", "apl0", "-pix_fmt", "yuv422p10le"];
+        match self {
+            IntermediateFormat::Prores422Lt => prores("1"),
+            IntermediateFormat::Prores422 => prores("2"),
+            IntermediateFormat::Prores422Hq => prores("3"),
+            IntermediateFormat::DnxhrSq => vec!["-c:v", "dnxhd", "-profile:v", "dnxhr_sq", "-pix_fmt", "yuv422p"],
+            IntermediateFormat::DnxhrHq => vec!["-c:v", "dnxhd", "-profile:v", "dnxhr_hq", "-pix_fmt", "yuv422p"],
+            IntermediateFormat::DnxhrHqx => vec!["-c:v", "dnxhd", "-profile:v", "dnxhr_hqx", "-pix_fmt", "yuv422p10le"],
+        }
+    }
+}
+
+/// Output flags restating the source's color tags, with a BT.709 fallback
+/// when the source can't be probed
+fn source_color_tags(input_path: &str) -> Vec<String> {
+    let info = match color::source_color(input_path) {
+        Ok(info) => info,
+        Err(_) => return color::bt709_output_args(),
+    };
+    vec![
+        "-colorspace".to_string(), info.matrix,
+        "-color_primaries".to_string(), info.primaries,
+        "-color_trc".to_string(), info.transfer,
+        "-color_range".to_string(), info.range,
+    ]
+}
+
+/// Render `input_path` to `output_path` as an editing intermediate
+pub async fn render_intermediate(input_path: &str, output_path: &str, format: IntermediateFormat) -> Result<()> {
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-map".to_string(), "0:v:0".to_string(),
+        "-map".to_string(), "0:a?".to_string(),
+    ];
+    args.extend(format.codec_args().into_iter().map(String::from));
+    // Uncompressed audio, as NLEs expect alongside these codecs
+    args.extend(["-c:a".to_string(), "pcm_s24le".to_string()]);
+    args.extend(source_color_tags(input_path));
+    if let Ok(tc) = timecode::source_timecode(input_path) {
+        if tc.embedded {
+            args.extend(["-timecode".to_string(), tc.start_timecode]);
+        }
+    }
+    args.push(output_path.to_string());
+    args.push("-y".to_string());
+    process::run_ffmpeg_async(&args, "intermediate export").await
+}
+
+/// Export an editing intermediate, returning the path written
+/// The output extension is replaced with .mov, the only container both
+/// codecs are reliably read from
+#[tauri::command]
+pub async fn export_intermediate(
+    input_path: &str,
+    output_path: &str,
+    format: IntermediateFormat,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let path = Path::new(output_path).with_extension("mov").to_string_lossy().into_owned();
+    output::write_atomically_async(&path, overwrite.unwrap_or_default(), |partial| async move {
+        render_intermediate(input_path, &partial, format).await
+    })
+    .await
+}
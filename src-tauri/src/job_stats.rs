@@ -0,0 +1,296 @@
+//! Progress, time remaining, and throughput of render jobs
+//!
+//! A running job's ffmpeg stats lines ("frame=... fps=... time=...") are
+//! read as they're written. The ETA divides the output still to be written
+//! by the job's speed - seconds of output per second of rendering, not
+//! counting time spent paused. Early on that speed is mostly noise, so the
+//! estimate leans on the average of earlier jobs with the same preset until
+//! enough of this one has run to trust its own number. Those averages are
+//! kept in the app data dir.
+
+use crate::error::{ClipFlowError, Result};
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// Window the rolling fps is measured over
+const FPS_WINDOW: Duration = Duration::from_secs(10);
+
+/// Minimum gap between "job-progress" events for one job
+const EMIT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Share of a job after which its own speed is trusted over the history
+const TRUST_FRACTION: f64 = 0.25;
+
+/// Completed runs averaged into a preset's historical speed
+const HISTORY_RUNS: u32 = 10;
+
+/// Serializes read-modify-write cycles on the throughput history
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Clone)]
+pub struct JobStats {
+    pub id: String,
+    /// Seconds of output written so far
+    pub processed_seconds: f64,
+    /// Expected length of the output, when it could be worked out
+    pub total_seconds: Option<f64>,
+    /// 0 to 1
+    pub fraction: Option<f64>,
+    /// Time spent rendering, not counting pauses
+    pub elapsed_seconds: f64,
+    /// Frames encoded per second over the last few seconds
+    pub fps: Option<f64>,
+    /// Seconds of output per second of rendering, so far in this job
+    pub speed: Option<f64>,
+    /// Average speed of earlier jobs with the same preset
+    pub historical_speed: Option<f64>,
+    pub eta_seconds: Option<f64>,
+}
+
+/// Average speed of completed jobs sharing a throughput key
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct Throughput {
+    runs: u32,
+    speed: f64,
+}
+
+struct Tracker {
+    key: String,
+    total_seconds: Option<f64>,
+    processed_seconds: f64,
+    /// Output written by earlier ffmpeg runs of this attempt (the first pass
+    /// of a two-pass encode), which the current run's time= starts over from
+    earlier_runs_seconds: f64,
+    /// time= of the current ffmpeg run
+    run_seconds: f64,
+    /// Rendering time before the current stretch
+    active_before: Duration,
+    /// Start of the current stretch; None while paused
+    active_since: Option<Instant>,
+    /// Recent (when, frames encoded) samples
+    frames: VecDeque<(Instant, u64)>,
+    historical_speed: Option<f64>,
+    last_emit: Option<Instant>,
+}
+
+impl Tracker {
+    fn elapsed(&self) -> Duration {
+        self.active_before + self.active_since.map(|since| since.elapsed()).unwrap_or_default()
+    }
+
+    fn fps(&self) -> Option<f64> {
+        let (first, last) = (self.frames.front()?, self.frames.back()?);
+        let seconds = last.0.duration_since(first.0).as_secs_f64();
+        (seconds >= 1.0).then(|| last.1.saturating_sub(first.1) as f64 / seconds)
+    }
+
+    fn speed(&self) -> Option<f64> {
+        let elapsed = self.elapsed().as_secs_f64();
+        (elapsed >= 1.0 && self.processed_seconds > 0.0).then(|| self.processed_seconds / elapsed)
+    }
+
+    fn fraction(&self) -> Option<f64> {
+        self.total_seconds
+            .filter(|total| *total > 0.0)
+            .map(|total| (self.processed_seconds / total).clamp(0.0, 1.0))
+    }
+
+    /// Own speed blended with the history, weighted by how far along the
+    /// job is
+    fn eta(&self) -> Option<f64> {
+        let remaining = (self.total_seconds? - self.processed_seconds).max(0.0);
+        let trust = (self.fraction().unwrap_or(0.0) / TRUST_FRACTION).min(1.0);
+        let speed = match (self.speed(), self.historical_speed) {
+            (Some(own), Some(past)) => own * trust + past * (1.0 - trust),
+            (own, past) => own.or(past)?,
+        };
+        (speed > 0.0).then(|| remaining / speed)
+    }
+
+    fn stats(&self, id: &str) -> JobStats {
+        JobStats {
+            id: id.to_string(),
+            processed_seconds: self.processed_seconds,
+            total_seconds: self.total_seconds,
+            fraction: self.fraction(),
+            elapsed_seconds: self.elapsed().as_secs_f64(),
+            fps: self.fps(),
+            speed: self.speed(),
+            historical_speed: self.historical_speed,
+            eta_seconds: self.eta(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct JobStatsState {
+    trackers: Mutex<HashMap<String, Tracker>>,
+}
+
+fn history_path(app: &AppHandle) -> Result<PathBuf> {
+    settings::data_file(app, "job_throughput.json")
+}
+
+fn read_history(app: &AppHandle) -> HashMap<String, Throughput> {
+    history_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn historical_speed(app: &AppHandle, key: &str) -> Option<f64> {
+    let _guard = HISTORY_LOCK.lock().unwrap();
+    read_history(app).get(key).map(|t| t.speed)
+}
+
+/// Fold a finished job's speed into the running average for its key
+fn record_history(app: &AppHandle, key: &str, speed: f64) -> Result<()> {
+    let _guard = HISTORY_LOCK.lock().unwrap();
+    let mut history = read_history(app);
+    let entry = history.entry(key.to_string()).or_insert(Throughput { runs: 0, speed });
+    entry.runs = (entry.runs + 1).min(HISTORY_RUNS);
+    entry.speed += (speed - entry.speed) / entry.runs as f64;
+
+    let path = history_path(app)?;
+    let json = serde_json::to_string_pretty(&history).map_err(|e| ClipFlowError::io("Failed to serialize job throughput", e))?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json).map_err(|e| ClipFlowError::io("Failed to write job throughput", e))?;
+    fs::rename(&tmp, &path).map_err(|e| ClipFlowError::io("Failed to save job throughput", e))
+}
+
+/// Value of `key=` in an ffmpeg stats line; ffmpeg pads values with spaces
+fn stat<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let start = line.find(&format!("{}=", key))? + key.len() + 1;
+    line[start..].split_whitespace().next()
+}
+
+/// "00:01:02.50" into seconds; "N/A" and the negative times ffmpeg prints
+/// before the first frame give None
+fn parse_time(text: &str) -> Option<f64> {
+    if text.starts_with('-') {
+        return None;
+    }
+    let mut seconds = 0.0;
+    for part in text.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    (seconds >= 0.0).then_some(seconds)
+}
+
+/// Start tracking an attempt at a job, resetting any earlier attempt
+/// `key` groups jobs whose speeds are comparable, e.g. by preset.
+/// `total_seconds` counts the output of every ffmpeg run, so a two-pass
+/// encode is twice the clip's duration.
+pub fn begin(app: &AppHandle, id: &str, key: &str, total_seconds: Option<f64>) {
+    let tracker = Tracker {
+        key: key.to_string(),
+        total_seconds,
+        processed_seconds: 0.0,
+        earlier_runs_seconds: 0.0,
+        run_seconds: 0.0,
+        active_before: Duration::ZERO,
+        active_since: Some(Instant::now()),
+        frames: VecDeque::new(),
+        historical_speed: historical_speed(app, key),
+        last_emit: None,
+    };
+    app.state::<JobStatsState>().trackers.lock().unwrap().insert(id.to_string(), tracker);
+}
+
+/// Take in one line of a job's ffmpeg stderr, emitting "job-progress" now
+/// and then
+pub fn record(app: &AppHandle, id: &str, line: &str) {
+    let time = stat(line, "time").and_then(parse_time);
+    let frame = stat(line, "frame").and_then(|f| f.parse::<u64>().ok());
+    if time.is_none() && frame.is_none() {
+        return;
+    }
+    let state = app.state::<JobStatsState>();
+    let mut trackers = state.trackers.lock().unwrap();
+    let tracker = match trackers.get_mut(id) {
+        Some(tracker) => tracker,
+        None => return,
+    };
+    let now = Instant::now();
+    if let Some(time) = time {
+        // A second ffmpeg run (the next pass) counts time from zero again
+        if time < tracker.run_seconds {
+            tracker.earlier_runs_seconds += tracker.run_seconds;
+        }
+        tracker.run_seconds = time;
+        tracker.processed_seconds = tracker.earlier_runs_seconds + time;
+    }
+    if let Some(frame) = frame {
+        // A second ffmpeg run in the same job starts counting from zero
+        if tracker.frames.back().is_some_and(|(_, last)| frame < *last) {
+            tracker.frames.clear();
+        }
+        tracker.frames.push_back((now, frame));
+        while tracker.frames.front().is_some_and(|(at, _)| now.duration_since(*at) > FPS_WINDOW) {
+            tracker.frames.pop_front();
+        }
+    }
+    if tracker.last_emit.is_some_and(|at| now.duration_since(at) < EMIT_INTERVAL) {
+        return;
+    }
+    tracker.last_emit = Some(now);
+    let _ = app.emit("job-progress", tracker.stats(id));
+}
+
+/// Stop or restart a job's clock while it's paused
+pub fn set_active(app: &AppHandle, id: &str, active: bool) {
+    let state = app.state::<JobStatsState>();
+    let mut trackers = state.trackers.lock().unwrap();
+    if let Some(tracker) = trackers.get_mut(id) {
+        match (active, tracker.active_since) {
+            (true, None) => tracker.active_since = Some(Instant::now()),
+            (false, Some(since)) => {
+                tracker.active_before += since.elapsed();
+                tracker.active_since = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Stop tracking a job, adding its speed to the history if it succeeded
+pub fn finish(app: &AppHandle, id: &str, succeeded: bool) {
+    let tracker = app.state::<JobStatsState>().trackers.lock().unwrap().remove(id);
+    let tracker = match tracker {
+        Some(tracker) if succeeded => tracker,
+        _ => return,
+    };
+    let (total, elapsed) = (tracker.total_seconds.unwrap_or(0.0), tracker.elapsed().as_secs_f64());
+    if total <= 0.0 || elapsed < 1.0 {
+        return;
+    }
+    if let Err(e) = record_history(app, &tracker.key, total / elapsed) {
+        tracing::warn!(job = %id, error = %e, "job throughput not saved");
+    }
+}
+
+/// Current stats of a running job, or just the historical speed for one
+/// that isn't running
+pub fn stats(app: &AppHandle, id: &str, key: &str) -> JobStats {
+    if let Some(tracker) = app.state::<JobStatsState>().trackers.lock().unwrap().get(id) {
+        return tracker.stats(id);
+    }
+    JobStats {
+        id: id.to_string(),
+        processed_seconds: 0.0,
+        total_seconds: None,
+        fraction: None,
+        elapsed_seconds: 0.0,
+        fps: None,
+        speed: None,
+        historical_speed: historical_speed(app, key),
+        eta_seconds: None,
+    }
+}
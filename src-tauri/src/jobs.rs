@@ -0,0 +1,609 @@
+//! Background render jobs, queued so batches can be left unattended
+//!
+//! Up to `max_concurrent_encodes` jobs (one by default) render at once,
+//! highest priority first and oldest first within a priority. Their ffmpeg
+//! processes run below normal CPU priority, and import analysis keeps its
+//! own worker slots outside this limit, so thumbnails, waveforms, and probes
+//! stay responsive while a long export grinds away. A running job can also
+//! be paused, which suspends its ffmpeg processes in place, and resumed
+//! later without losing progress.
+//!
+//! With `auto_fallback_on_failure` enabled in settings, a job that fails
+//! while using a hardware encoder or an expensive filter is retried once
+//! with safe software settings, and the downgrade is recorded on the job.
+//!
+//! The full output of every command a job runs is kept in a per-job log in
+//! the app data dir, and failures carry a diagnosed cause.
+//!
+//! Unfinished jobs are saved to the app data dir whenever the queue
+//! changes. After a crash or an early quit they come back as `Interrupted`
+//! on the next start, in their original order, and wait for the user to
+//! resume or cancel them rather than rendering behind their back.
+
+use crate::error::{ClipFlowError, Result};
+use crate::diagnostics::FfmpegFailure;
+use crate::output::{self, OverwritePolicy};
+use crate::job_stats::{self, JobStats};
+use crate::{now_secs, presets, probe, process, settings, speed};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::Notify;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobSpec {
+    Export {
+        input_path: String,
+        output_path: String,
+        preset: String,
+        #[serde(default)]
+        audio_tracks: Vec<crate::ExportAudioTrack>,
+        #[serde(default)]
+        voice_polish: Option<crate::audio::VoicePolish>,
+        #[serde(default)]
+        overwrite: OverwritePolicy,
+        #[serde(default)]
+        rate_control: presets::RateControl,
+        #[serde(default)]
+        extra_args: Vec<String>,
+    },
+    SlowMotion {
+        input_path: String,
+        output_path: String,
+        target_fps: f64,
+        factor: f64,
+        quality: Option<String>,
+        #[serde(default)]
+        overwrite: OverwritePolicy,
+    },
+}
+
+impl JobSpec {
+    fn output_path(&self) -> &str {
+        match self {
+            JobSpec::Export { output_path, .. } | JobSpec::SlowMotion { output_path, .. } => output_path,
+        }
+    }
+
+    /// Jobs whose render speeds are comparable, for time estimates
+    fn throughput_key(&self) -> String {
+        match self {
+            // Two passes take about twice as long per second of output, so
+            // they're averaged separately
+            JobSpec::Export { preset, rate_control, .. } if rate_control.passes() > 1 => {
+                format!("export:{}:two_pass", preset)
+            }
+            JobSpec::Export { preset, .. } => format!("export:{}", preset),
+            JobSpec::SlowMotion { quality, .. } => format!("slow_motion:{}", quality.as_deref().unwrap_or("balanced")),
+        }
+    }
+
+    /// Seconds of output ffmpeg writes for the result, over every pass
+    fn output_seconds(&self) -> Option<f64> {
+        match self {
+            JobSpec::Export { input_path, rate_control, .. } => {
+                probe::media_duration(input_path).ok().map(|d| d * rate_control.passes() as f64)
+            }
+            JobSpec::SlowMotion { input_path, factor, .. } => probe::media_duration(input_path).ok().map(|d| d * factor),
+        }
+    }
+}
+
+/// Queue order; higher priority jobs start first
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum JobPriority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+    /// Left unfinished when the app last closed; waiting to be resumed
+    Interrupted,
+    /// Running, with its ffmpeg processes suspended
+    Paused,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Job {
+    pub id: String,
+    pub spec: JobSpec,
+    pub priority: JobPriority,
+    pub status: JobStatus,
+    pub error: Option<String>,
+    /// Path the result was written to, once completed
+    pub output: Option<String>,
+    /// Diagnosed cause when ffmpeg failed
+    pub failure: Option<FfmpegFailure>,
+    /// What was given up to get the job through, if it needed a fallback
+    pub downgraded: Option<String>,
+    pub attempts: u32,
+    pub created_at: u64,
+    pub finished_at: Option<u64>,
+    /// Interrupted partway through its render, rather than while queued
+    pub was_running: bool,
+}
+
+/// What's saved of an unfinished job
+#[derive(Serialize, Deserialize)]
+struct SavedJob {
+    id: String,
+    spec: JobSpec,
+    #[serde(default)]
+    priority: JobPriority,
+    created_at: u64,
+    was_running: bool,
+}
+
+/// Settings for one attempt at a job
+#[derive(Clone, Copy)]
+struct Attempt {
+    allow_hardware: bool,
+    safe_filters: bool,
+}
+
+const FULL: Attempt = Attempt { allow_hardware: true, safe_filters: false };
+const SAFE: Attempt = Attempt { allow_hardware: false, safe_filters: true };
+
+/// Most jobs allowed to render at once
+const MAX_CONCURRENT_LIMIT: usize = 8;
+
+#[derive(Default)]
+pub struct JobQueue {
+    jobs: Mutex<Vec<Job>>,
+    /// Jobs rendering right now
+    running: AtomicUsize,
+    wake: Notify,
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn queue_path(app: &AppHandle) -> Result<PathBuf> {
+    settings::data_file(app, "job_queue.json")
+}
+
+/// Save the unfinished jobs so they survive a restart
+fn persist(app: &AppHandle, jobs: &[Job]) {
+    let saved: Vec<SavedJob> = jobs
+        .iter()
+        .filter(|j| matches!(j.status, JobStatus::Queued | JobStatus::Running | JobStatus::Paused | JobStatus::Interrupted))
+        .map(|j| SavedJob {
+            id: j.id.clone(),
+            spec: j.spec.clone(),
+            priority: j.priority,
+            created_at: j.created_at,
+            was_running: matches!(j.status, JobStatus::Running | JobStatus::Paused) || j.was_running,
+        })
+        .collect();
+    let result = queue_path(app).and_then(|path| {
+        let json = serde_json::to_string_pretty(&saved).map_err(|e| ClipFlowError::io("Failed to serialize job queue", e))?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(|e| ClipFlowError::io("Failed to write job queue", e))?;
+        fs::rename(&tmp, &path).map_err(|e| ClipFlowError::io("Failed to save job queue", e))
+    });
+    if let Err(e) = result {
+        tracing::warn!(error = %e, "job queue not saved");
+    }
+}
+
+/// Jobs left unfinished by the previous run
+fn load_interrupted(app: &AppHandle) -> Vec<Job> {
+    let saved: Vec<SavedJob> = queue_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    saved
+        .into_iter()
+        .map(|saved| Job {
+            id: saved.id,
+            spec: saved.spec,
+            priority: saved.priority,
+            status: JobStatus::Interrupted,
+            error: None,
+            output: None,
+            failure: None,
+            downgraded: None,
+            attempts: 0,
+            created_at: saved.created_at,
+            finished_at: None,
+            was_running: saved.was_running,
+        })
+        .collect()
+}
+
+/// Describe what the safe retry gives up for this job, or None if the
+/// first attempt already ran with safe settings
+fn fallback_description(app: &AppHandle, spec: &JobSpec) -> Option<String> {
+    match spec {
+        JobSpec::Export { preset, .. } => {
+            let preset = presets::resolve(app, preset).ok().flatten()?;
+            presets::hardware_encoder(preset.video_codec)
+                .map(|encoder| format!("Hardware encoder {} failed; re-encoded in software", encoder))
+        }
+        JobSpec::SlowMotion { quality, .. } => match quality.as_deref().unwrap_or("balanced") {
+            "fast" => None,
+            other => Some(format!("Motion interpolation ({}) failed; used frame blending instead", other)),
+        },
+    }
+}
+
+fn job_log_path(app: &AppHandle, id: &str) -> Result<PathBuf> {
+    let dir = settings::data_dir(app, "job_logs")?;
+    Ok(dir.join(format!("{}.log", id)))
+}
+
+fn run_attempt(app: &AppHandle, spec: &JobSpec, attempt: Attempt) -> Result<String> {
+    match spec {
+        JobSpec::Export { input_path, output_path, preset, audio_tracks, voice_polish, overwrite, rate_control, extra_args } => {
+            let options = crate::ExportOptions {
+                audio_tracks,
+                voice_polish: *voice_polish,
+                allow_hardware: attempt.allow_hardware,
+                overwrite: *overwrite,
+                rate_control: *rate_control,
+                extra_args,
+                ..Default::default()
+            };
+            crate::render_export(app, input_path, output_path, preset, &options)
+        }
+        JobSpec::SlowMotion { input_path, output_path, target_fps, factor, quality, overwrite } => {
+            let quality = if attempt.safe_filters { "fast" } else { quality.as_deref().unwrap_or("balanced") };
+            output::write_atomically(output_path, *overwrite, |partial| {
+                speed::render_slowmo(input_path, partial, *target_fps, *factor, quality)
+            })
+        }
+    }
+}
+
+fn update(app: &AppHandle, id: &str, change: impl FnOnce(&mut Job)) {
+    let queue = app.state::<JobQueue>();
+    let mut jobs = queue.jobs.lock().unwrap();
+    if let Some(job) = jobs.iter_mut().find(|j| j.id == id) {
+        change(job);
+        let _ = app.emit("job-updated", job.clone());
+    }
+    persist(app, &jobs);
+}
+
+/// Output paths of the jobs rendering right now, paused ones included
+pub fn running_outputs(app: &AppHandle) -> Vec<String> {
+    let queue = app.state::<JobQueue>();
+    let jobs = queue.jobs.lock().unwrap();
+    jobs.iter()
+        .filter(|j| matches!(j.status, JobStatus::Running | JobStatus::Paused))
+        .map(|j| j.spec.output_path().to_string())
+        .collect()
+}
+
+/// Take the highest priority queued job, oldest first, and mark it running
+fn next_job(app: &AppHandle) -> Option<Job> {
+    let queue = app.state::<JobQueue>();
+    let mut jobs = queue.jobs.lock().unwrap();
+    let job = jobs.iter_mut().filter(|j| j.status == JobStatus::Queued).min_by_key(|j| j.priority)?;
+    job.status = JobStatus::Running;
+    let _ = app.emit("job-updated", job.clone());
+    let job = job.clone();
+    persist(app, &jobs);
+    Some(job)
+}
+
+/// Run one attempt, capturing its command output into the job log
+/// Returns the diagnosed ffmpeg failure alongside a failed result
+fn run_logged(
+    app: &AppHandle,
+    spec: &JobSpec,
+    attempt: Attempt,
+    log_path: Option<&Path>,
+) -> (Result<String>, Option<FfmpegFailure>) {
+    // Below normal priority, so an export doesn't starve the UI or analysis
+    let render = || process::background_priority(|| run_attempt(app, spec, attempt));
+    let (result, captured) = match log_path {
+        Some(path) => match process::capture_output(path, render) {
+            Ok(outcome) => outcome,
+            Err(e) => (Err(e), None),
+        },
+        None => (render(), None),
+    };
+    // Prefer the diagnosis attached to the error; commands that only check
+    // the exit status leave it to the captured output
+    let failure = match &result {
+        Ok(_) => None,
+        Err(ClipFlowError::FfmpegFailed { diagnosis, .. }) => Some(diagnosis.clone()),
+        Err(_) => captured,
+    };
+    (result, failure)
+}
+
+/// Failures a software retry can't fix
+fn is_environmental(failure: &Option<FfmpegFailure>) -> bool {
+    matches!(
+        failure,
+        Some(FfmpegFailure::DiskFull | FfmpegFailure::PermissionDenied { .. } | FfmpegFailure::FileNotFound { .. })
+    )
+}
+
+async fn run_job(app: &AppHandle, job: Job) {
+    let log_path = job_log_path(app, &job.id)
+        .map_err(|e| tracing::warn!(job = %job.id, error = %e, "job log unavailable"))
+        .ok();
+    let attempt_app = app.clone();
+    let (id, spec) = (job.id.clone(), job.spec.clone());
+    let run = move |attempt| {
+        let app = attempt_app.clone();
+        let (id, spec) = (id.clone(), spec.clone());
+        let log_path = log_path.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            job_stats::begin(&app, &id, &spec.throughput_key(), spec.output_seconds());
+            let (progress_app, progress_id) = (app.clone(), id.clone());
+            let on_line = move |line: &str| job_stats::record(&progress_app, &progress_id, line);
+            process::watch_stderr(on_line, || {
+                process::in_group(&id, || run_logged(&app, &spec, attempt, log_path.as_deref()))
+            })
+        })
+    };
+    let panicked = |e: tauri::Error| (Err(ClipFlowError::tool("job worker", format!("panicked: {}", e))), None);
+
+    update(app, &job.id, |j| j.attempts = 1);
+    let (mut result, mut failure) = run(FULL).await.unwrap_or_else(panicked);
+
+    if let Err(error) = &result {
+        let fallback = if settings::current().auto_fallback_on_failure && !is_environmental(&failure) {
+            fallback_description(app, &job.spec)
+        } else {
+            None
+        };
+        if let Some(downgrade) = fallback {
+            tracing::warn!(job = %job.id, error = %error, downgrade = %downgrade, "retrying job with safe settings");
+            update(app, &job.id, |j| {
+                j.attempts = 2;
+                j.downgraded = Some(downgrade);
+            });
+            (result, failure) = run(SAFE).await.unwrap_or_else(panicked);
+        }
+    }
+
+    process::release_group(&job.id);
+    job_stats::finish(app, &job.id, result.is_ok());
+    update(app, &job.id, |j| {
+        j.finished_at = Some(now_secs());
+        match result {
+            Ok(path) => {
+                j.status = JobStatus::Completed;
+                j.output = Some(path);
+            }
+            Err(e) => {
+                tracing::warn!(job = %j.id, error = %e, "job failed");
+                j.status = JobStatus::Failed;
+                j.error = Some(match &failure {
+                    Some(failure) => failure.message(),
+                    None => e.to_string(),
+                });
+                j.failure = failure;
+            }
+        }
+    });
+}
+
+/// Restore jobs left over from the last run, then start the background
+/// worker that drains the queue
+pub fn start(app: AppHandle) {
+    let interrupted = load_interrupted(&app);
+    if !interrupted.is_empty() {
+        tracing::info!(count = interrupted.len(), "restored interrupted jobs");
+        *app.state::<JobQueue>().jobs.lock().unwrap() = interrupted;
+    }
+    tauri::async_runtime::spawn(async move {
+        let queue = app.state::<JobQueue>();
+        loop {
+            let limit = settings::current().max_concurrent_encodes.clamp(1, MAX_CONCURRENT_LIMIT);
+            let job = if queue.running.load(Ordering::SeqCst) < limit { next_job(&app) } else { None };
+            match job {
+                Some(job) => {
+                    queue.running.fetch_add(1, Ordering::SeqCst);
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        run_job(&app, job).await;
+                        let queue = app.state::<JobQueue>();
+                        queue.running.fetch_sub(1, Ordering::SeqCst);
+                        queue.wake.notify_one();
+                    });
+                }
+                // Woken by a new job, a finished one, or a raised limit
+                None => queue.wake.notified().await,
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn submit_job(
+    app: AppHandle,
+    state: State<'_, JobQueue>,
+    spec: JobSpec,
+    priority: Option<JobPriority>,
+) -> Result<Job> {
+    let job = Job {
+        id: format!("job-{}-{}", now_secs(), NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+        spec,
+        priority: priority.unwrap_or_default(),
+        status: JobStatus::Queued,
+        error: None,
+        output: None,
+        failure: None,
+        downgraded: None,
+        attempts: 0,
+        created_at: now_secs(),
+        finished_at: None,
+        was_running: false,
+    };
+    let mut jobs = state.jobs.lock().unwrap();
+    jobs.push(job.clone());
+    persist(&app, &jobs);
+    drop(jobs);
+    state.wake.notify_one();
+    Ok(job)
+}
+
+#[tauri::command]
+pub async fn list_jobs(state: State<'_, JobQueue>) -> Result<Vec<Job>> {
+    Ok(state.jobs.lock().unwrap().clone())
+}
+
+#[tauri::command]
+pub async fn get_job(state: State<'_, JobQueue>, id: String) -> Result<Job> {
+    state
+        .jobs
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|j| j.id == id)
+        .cloned()
+        .ok_or_else(|| ClipFlowError::not_found(format!("Job not found: {}", id)))
+}
+
+/// Progress, encode speed, and estimated time remaining of a job
+/// Jobs that aren't running only report the historical speed of their
+/// preset; running ones also emit the same stats as "job-progress".
+#[tauri::command]
+pub async fn get_job_stats(app: AppHandle, state: State<'_, JobQueue>, id: String) -> Result<JobStats> {
+    let key = state
+        .jobs
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|j| j.id == id)
+        .map(|j| j.spec.throughput_key())
+        .ok_or_else(|| ClipFlowError::not_found(format!("Job not found: {}", id)))?;
+    Ok(job_stats::stats(&app, &id, &key))
+}
+
+/// Cancel a job that hasn't started yet, or one left over from the last run
+#[tauri::command]
+pub async fn cancel_job(app: AppHandle, id: String) -> Result<()> {
+    let queue = app.state::<JobQueue>();
+    let mut jobs = queue.jobs.lock().unwrap();
+    let job = jobs
+        .iter_mut()
+        .find(|j| j.id == id)
+        .ok_or_else(|| ClipFlowError::not_found(format!("Job not found: {}", id)))?;
+    if !matches!(job.status, JobStatus::Queued | JobStatus::Interrupted) {
+        return Err(ClipFlowError::invalid("Only queued jobs can be cancelled"));
+    }
+    job.status = JobStatus::Cancelled;
+    job.finished_at = Some(now_secs());
+    let _ = app.emit("job-updated", job.clone());
+    persist(&app, &jobs);
+    Ok(())
+}
+
+/// Suspend a running job's ffmpeg processes, freeing the CPU without
+/// losing its progress
+/// The job keeps its place among the running jobs until it's resumed.
+#[tauri::command]
+pub async fn pause_job(app: AppHandle, id: String) -> Result<Job> {
+    set_paused(&app, &id, true)
+}
+
+/// Continue a paused job where it left off
+#[tauri::command]
+pub async fn resume_job(app: AppHandle, id: String) -> Result<Job> {
+    set_paused(&app, &id, false)
+}
+
+fn set_paused(app: &AppHandle, id: &str, paused: bool) -> Result<Job> {
+    let queue = app.state::<JobQueue>();
+    let mut jobs = queue.jobs.lock().unwrap();
+    let job = jobs
+        .iter_mut()
+        .find(|j| j.id == id)
+        .ok_or_else(|| ClipFlowError::not_found(format!("Job not found: {}", id)))?;
+    let (from, to) = if paused {
+        (JobStatus::Running, JobStatus::Paused)
+    } else {
+        (JobStatus::Paused, JobStatus::Running)
+    };
+    if job.status != from {
+        return Err(ClipFlowError::invalid(if paused {
+            "Only running jobs can be paused"
+        } else {
+            "Only paused jobs can be resumed"
+        }));
+    }
+    if paused {
+        process::pause_group(id)?;
+    } else {
+        process::resume_group(id)?;
+    }
+    job_stats::set_active(app, id, !paused);
+    job.status = to;
+    tracing::info!(job = %id, paused, "job pause state changed");
+    let _ = app.emit("job-updated", job.clone());
+    Ok(job.clone())
+}
+
+/// Queue interrupted jobs again, in their original order; all of them
+/// when `ids` is omitted
+/// A job that was mid-render starts over from the beginning.
+#[tauri::command]
+pub async fn resume_interrupted_jobs(app: AppHandle, ids: Option<Vec<String>>) -> Result<Vec<Job>> {
+    let queue = app.state::<JobQueue>();
+    let mut jobs = queue.jobs.lock().unwrap();
+    let is_interrupted = |id: &String| jobs.iter().any(|j| &j.id == id && j.status == JobStatus::Interrupted);
+    if let Some(missing) = ids.iter().flatten().find(|id| !is_interrupted(id)) {
+        return Err(ClipFlowError::not_found(format!("No interrupted job {}", missing)));
+    }
+    let mut resumed = Vec::new();
+    for job in jobs.iter_mut().filter(|j| j.status == JobStatus::Interrupted) {
+        if ids.as_ref().is_some_and(|ids| !ids.contains(&job.id)) {
+            continue;
+        }
+        job.status = JobStatus::Queued;
+        let _ = app.emit("job-updated", job.clone());
+        resumed.push(job.clone());
+    }
+    persist(&app, &jobs);
+    drop(jobs);
+    queue.wake.notify_one();
+    Ok(resumed)
+}
+
+/// Full command output of a job, for the error details view
+#[tauri::command]
+pub async fn get_job_log(app: AppHandle, id: String) -> Result<String> {
+    if id.contains(['/', '\\', '.']) {
+        return Err(ClipFlowError::invalid(format!("Invalid job id: {}", id)));
+    }
+    let path = job_log_path(&app, &id)?;
+    if !path.exists() {
+        return Ok(String::new());
+    }
+    fs::read_to_string(path).map_err(|e| ClipFlowError::io("Failed to read job log", e))
+}
+
+/// Let up to `n` queued jobs render at once; saved with the settings
+#[tauri::command]
+pub async fn set_max_concurrent_encodes(app: AppHandle, state: State<'_, JobQueue>, n: usize) -> Result<()> {
+    if !(1..=MAX_CONCURRENT_LIMIT).contains(&n) {
+        return Err(ClipFlowError::invalid(format!(
+            "Concurrent encodes must be between 1 and {}, got {}",
+            MAX_CONCURRENT_LIMIT, n
+        )));
+    }
+    settings::save(&app, settings::Settings { max_concurrent_encodes: n, ..settings::current() })?;
+    state.wake.notify_one();
+    Ok(())
+}
@@ -0,0 +1,230 @@
+//! Word-by-word animated captions, the style used on TikTok and Shorts
+//!
+//! Captions are written as an ASS script, with one event per short line and
+//! per-word timing from Whisper's word timestamps, then burned in with
+//! libass. Each style animates the word being spoken:
+//!
+//! - highlight: the whole line shows, and each word changes color as it's said
+//! - pop-in: words appear one at a time, growing into place
+//! - bounce: like highlight, with the spoken word briefly scaled up
+
+use crate::error::{ClipFlowError, Result};
+use crate::output::{self, OverwritePolicy};
+use crate::project::{Transcript, TranscriptWord};
+use crate::temp::TempFile;
+use crate::{color, escape_filter_path, probe, process, transcripts};
+use serde::Deserialize;
+use std::fs;
+use tauri::AppHandle;
+
+/// Short lines keep the eye on the spoken word
+const DEFAULT_CHARS_PER_LINE: usize = 24;
+
+/// ASS colors are &HAABBGGRR; spoken words turn yellow, the rest are white
+const SPOKEN_COLOR: &str = "&H0000FFFF";
+const UNSPOKEN_COLOR: &str = "&H00FFFFFF";
+
+/// Milliseconds a popping-in word takes to reach full size
+const POP_MS: u32 = 120;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum KaraokeStyle {
+    Highlight,
+    PopIn,
+    Bounce,
+}
+
+/// One caption line and the words in it
+struct Line<'a> {
+    start: f64,
+    end: f64,
+    words: Vec<&'a TranscriptWord>,
+}
+
+/// ASS time, H:MM:SS.cc
+fn ass_timestamp(seconds: f64) -> String {
+    let cs = (seconds.max(0.0) * 100.0).round() as u64;
+    format!("{}:{:02}:{:02}.{:02}", cs / 360_000, (cs % 360_000) / 6000, (cs % 6000) / 100, cs % 100)
+}
+
+/// Braces and backslashes would start override tags
+fn escape_ass(text: &str) -> String {
+    text.replace('\\', "/").replace('{', "(").replace('}', ")")
+}
+
+/// Group each segment's words into lines of at most `cpl` characters,
+/// ending a line early after punctuation
+fn build_lines(transcript: &Transcript, cpl: usize) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    for segment in &transcript.segments {
+        let mut current: Vec<&TranscriptWord> = Vec::new();
+        let mut chars = 0;
+        for word in &segment.words {
+            let len = word.word.chars().count();
+            if !current.is_empty() && chars + 1 + len > cpl {
+                lines.push(std::mem::take(&mut current));
+                chars = 0;
+            }
+            chars += if current.is_empty() { len } else { len + 1 };
+            current.push(word);
+            if word.word.ends_with(['.', ',', '?', '!', ';', ':']) {
+                lines.push(std::mem::take(&mut current));
+                chars = 0;
+            }
+        }
+        if !current.is_empty() {
+            lines.push(current);
+        }
+    }
+    lines
+        .into_iter()
+        .map(|words| Line { start: words[0].start, end: words[words.len() - 1].end, words })
+        .collect()
+}
+
+/// Dialogue text for a line, with each word's animation tags
+fn line_text(line: &Line, style: KaraokeStyle) -> String {
+    let ms = |t: f64| ((t - line.start).max(0.0) * 1000.0).round() as u32;
+    let mut text = String::new();
+    // \k counts from the end of the previous word, so gaps between words
+    // are folded into the following one
+    let mut previous_end = line.start;
+    for (i, word) in line.words.iter().enumerate() {
+        if i > 0 {
+            text.push(' ');
+        }
+        let karaoke = ((word.end - previous_end).max(0.0) * 100.0).round() as u32;
+        previous_end = word.end;
+        let (start, end) = (ms(word.start), ms(word.end).max(ms(word.start) + 1));
+        let tags = match style {
+            KaraokeStyle::Highlight => format!("\\k{}", karaoke),
+            KaraokeStyle::PopIn => format!(
+                "\\alpha&HFF&\\fscx60\\fscy60\\t({},{},\\alpha&H00&\\fscx100\\fscy100)",
+                start,
+                start + POP_MS
+            ),
+            KaraokeStyle::Bounce => {
+                let middle = (start + end) / 2;
+                format!(
+                    "\\k{}\\t({},{},\\fscx125\\fscy125)\\t({},{},\\fscx100\\fscy100)",
+                    karaoke, start, middle, middle, end
+                )
+            }
+        };
+        text.push_str(&format!("{{{}}}{}", tags, escape_ass(&word.word)));
+    }
+    text
+}
+
+/// A complete ASS script of karaoke captions for a `width` x `height` video
+/// Errors when the transcript has no word timings.
+pub fn render_karaoke_ass(
+    transcript: &Transcript,
+    style: KaraokeStyle,
+    width: u32,
+    height: u32,
+    max_chars_per_line: Option<usize>,
+) -> Result<String> {
+    let lines = build_lines(transcript, max_chars_per_line.unwrap_or(DEFAULT_CHARS_PER_LINE).max(1));
+    if lines.is_empty() {
+        return Err(ClipFlowError::invalid("The transcript has no word timings; transcribe the file again"));
+    }
+    // Pop-in words are already white when they appear; the others start
+    // white and change to the spoken color
+    let (primary, secondary) = match style {
+        KaraokeStyle::PopIn => (UNSPOKEN_COLOR, UNSPOKEN_COLOR),
+        KaraokeStyle::Highlight | KaraokeStyle::Bounce => (SPOKEN_COLOR, UNSPOKEN_COLOR),
+    };
+    let font_size = (height.min(width) as f64 * 0.075).round() as u32;
+    let mut script = format!(
+        "[Script Info]\nScriptType: v4.00+\nPlayResX: {w}\nPlayResY: {h}\nWrapStyle: 2\nScaledBorderAndShadow: yes\n\n\
+         [V4+ Styles]\n\
+         Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+         Style: Karaoke,Arial,{size},{primary},{secondary},&H00000000,&H80000000,-1,0,0,0,100,100,0,0,1,{outline},0,2,{margin_x},{margin_x},{margin_v},1\n\n\
+         [Events]\n\
+         Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+        w = width,
+        h = height,
+        size = font_size,
+        primary = primary,
+        secondary = secondary,
+        outline = (font_size / 12).max(2),
+        margin_x = width / 20,
+        margin_v = height / 5,
+    );
+    for line in &lines {
+        script.push_str(&format!(
+            "Dialogue: 0,{},{},Karaoke,,0,0,0,,{}\n",
+            ass_timestamp(line.start),
+            ass_timestamp(line.end),
+            line_text(line, style)
+        ));
+    }
+    Ok(script)
+}
+
+/// The stored transcript of `input_path`, unless one is passed
+fn resolve_transcript(app: &AppHandle, transcript: Option<Transcript>, input_path: &str) -> Result<Transcript> {
+    match transcript {
+        Some(transcript) => Ok(transcript),
+        None => Ok(transcripts::require(app, input_path)?.transcript),
+    }
+}
+
+/// Write karaoke captions for a video as an .ass file
+#[tauri::command]
+pub async fn export_karaoke_captions(
+    app: AppHandle,
+    input_path: &str,
+    output_path: &str,
+    style: KaraokeStyle,
+    transcript: Option<Transcript>,
+    max_chars_per_line: Option<usize>,
+) -> Result<()> {
+    let transcript = resolve_transcript(&app, transcript, input_path)?;
+    let geometry = probe::video_geometry(input_path)?;
+    let script = render_karaoke_ass(&transcript, style, geometry.display_width, geometry.display_height, max_chars_per_line)?;
+    fs::write(output_path, script).map_err(|e| ClipFlowError::io("Failed to write karaoke captions", e))
+}
+
+/// Burn animated word-by-word captions into a video
+/// Without an explicit `transcript`, the stored one of `input_path` is
+/// used. Audio is copied. Returns the path written.
+#[tauri::command]
+pub async fn burn_karaoke_captions(
+    app: AppHandle,
+    input_path: &str,
+    output_path: &str,
+    style: KaraokeStyle,
+    transcript: Option<Transcript>,
+    max_chars_per_line: Option<usize>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let transcript = resolve_transcript(&app, transcript, input_path)?;
+    let geometry = probe::video_geometry(input_path)?;
+    let script = render_karaoke_ass(&transcript, style, geometry.display_width, geometry.display_height, max_chars_per_line)?;
+    // Kept alive until the render is done
+    let ass = TempFile::new("karaoke", "ass")?;
+    fs::write(ass.path(), script).map_err(|e| ClipFlowError::io("Failed to write karaoke captions", e))?;
+
+    let (color_filter, color_tags) = color::export_color_args(input_path);
+    let video_filters: Vec<String> = probe::square_pixel_filter(&geometry)
+        .into_iter()
+        .chain(color_filter)
+        .chain([format!("ass='{}'", escape_filter_path(&ass.path_str()))])
+        .collect();
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-vf".to_string(), video_filters.join(","),
+        "-c:a".to_string(), "copy".to_string(),
+    ];
+    args.extend(color_tags);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "karaoke captions").await
+    })
+    .await
+}
@@ -0,0 +1,65 @@
+//! Languages Whisper can transcribe, and turning what the user picked into
+//! the code Whisper expects
+
+use crate::error::{ClipFlowError, Result};
+use serde::Serialize;
+
+/// Whisper's language table: ISO 639-1 code (or Whisper's own where there
+/// is none) and English name
+const WHISPER_LANGUAGES: [(&str, &str); 100] = [
+    ("en", "English"), ("zh", "Chinese"), ("de", "German"), ("es", "Spanish"),
+    ("ru", "Russian"), ("ko", "Korean"), ("fr", "French"), ("ja", "Japanese"),
+    ("pt", "Portuguese"), ("tr", "Turkish"), ("pl", "Polish"), ("ca", "Catalan"),
+    ("nl", "Dutch"), ("ar", "Arabic"), ("sv", "Swedish"), ("it", "Italian"),
+    ("id", "Indonesian"), ("hi", "Hindi"), ("fi", "Finnish"), ("vi", "Vietnamese"),
+    ("he", "Hebrew"), ("uk", "Ukrainian"), ("el", "Greek"), ("ms", "Malay"),
+    ("cs", "Czech"), ("ro", "Romanian"), ("da", "Danish"), ("hu", "Hungarian"),
+    ("ta", "Tamil"), ("no", "Norwegian"), ("th", "Thai"), ("ur", "Urdu"),
+    ("hr", "Croatian"), ("bg", "Bulgarian"), ("lt", "Lithuanian"), ("la", "Latin"),
+    ("mi", "Maori"), ("ml", "Malayalam"), ("cy", "Welsh"), ("sk", "Slovak"),
+    ("te", "Telugu"), ("fa", "Persian"), ("lv", "Latvian"), ("bn", "Bengali"),
+    ("sr", "Serbian"), ("az", "Azerbaijani"), ("sl", "Slovenian"), ("kn", "Kannada"),
+    ("et", "Estonian"), ("mk", "Macedonian"), ("br", "Breton"), ("eu", "Basque"),
+    ("is", "Icelandic"), ("hy", "Armenian"), ("ne", "Nepali"), ("mn", "Mongolian"),
+    ("bs", "Bosnian"), ("kk", "Kazakh"), ("sq", "Albanian"), ("sw", "Swahili"),
+    ("gl", "Galician"), ("mr", "Marathi"), ("pa", "Punjabi"), ("si", "Sinhala"),
+    ("km", "Khmer"), ("sn", "Shona"), ("yo", "Yoruba"), ("so", "Somali"),
+    ("af", "Afrikaans"), ("oc", "Occitan"), ("ka", "Georgian"), ("be", "Belarusian"),
+    ("tg", "Tajik"), ("sd", "Sindhi"), ("gu", "Gujarati"), ("am", "Amharic"),
+    ("yi", "Yiddish"), ("lo", "Lao"), ("uz", "Uzbek"), ("fo", "Faroese"),
+    ("ht", "Haitian Creole"), ("ps", "Pashto"), ("tk", "Turkmen"), ("nn", "Nynorsk"),
+    ("mt", "Maltese"), ("sa", "Sanskrit"), ("lb", "Luxembourgish"), ("my", "Myanmar"),
+    ("bo", "Tibetan"), ("tl", "Tagalog"), ("mg", "Malagasy"), ("as", "Assamese"),
+    ("tt", "Tatar"), ("haw", "Hawaiian"), ("ln", "Lingala"), ("ha", "Hausa"),
+    ("ba", "Bashkir"), ("jw", "Javanese"), ("su", "Sundanese"), ("yue", "Cantonese"),
+];
+
+#[derive(Serialize)]
+pub struct Language {
+    pub code: String,
+    pub name: String,
+}
+
+/// Whisper's code for `language`, given as a code or an English name in any
+/// case; None for "auto" (or nothing), which lets Whisper detect it
+pub fn resolve(language: Option<&str>) -> Result<Option<String>> {
+    let language = match language.map(str::trim) {
+        None | Some("") => return Ok(None),
+        Some(l) if l.eq_ignore_ascii_case("auto") => return Ok(None),
+        Some(l) => l,
+    };
+    WHISPER_LANGUAGES
+        .iter()
+        .find(|(code, name)| code.eq_ignore_ascii_case(language) || name.eq_ignore_ascii_case(language))
+        .map(|(code, _)| Some(code.to_string()))
+        .ok_or_else(|| ClipFlowError::invalid(format!("Whisper can't transcribe \"{}\"", language)))
+}
+
+/// Languages transcription accepts, plus "auto" for detection
+#[tauri::command]
+pub async fn get_supported_languages() -> Result<Vec<Language>> {
+    let auto = Language { code: "auto".to_string(), name: "Detect automatically".to_string() };
+    Ok(std::iter::once(auto)
+        .chain(WHISPER_LANGUAGES.iter().map(|(code, name)| Language { code: code.to_string(), name: name.to_string() }))
+        .collect())
+}
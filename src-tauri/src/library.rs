@@ -0,0 +1,423 @@
+//! Media library - the index of source files known to ClipFlow, persisted
+//! in the app data dir
+
+use crate::error::{ClipFlowError, Result};
+use crate::{analysis, now_secs, probe, settings};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+/// Serializes read-modify-write cycles on the library index
+static LIBRARY_LOCK: Mutex<()> = Mutex::new(());
+
+/// Extensions treated as video when scanning folders
+pub const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mov", "mkv", "webm", "m4v", "avi", "mts", "m2ts", "ts", "flv"];
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LibraryItem {
+    pub id: String,
+    pub path: String,
+    pub name: String,
+    pub duration: f64,
+    /// Recording time from the container's creation_time tag, falling back
+    /// to the file's modification time (RFC 3339)
+    pub recorded_at: Option<String>,
+    /// Camera/device model from the container tags, if any
+    pub camera: Option<String>,
+    pub added_at: u64,
+}
+
+fn library_path(app: &AppHandle) -> Result<PathBuf> {
+    settings::data_file(app, "library.json")
+}
+
+pub fn read_library(app: &AppHandle) -> Result<Vec<LibraryItem>> {
+    let path = library_path(app)?;
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content = fs::read_to_string(&path).map_err(|e| ClipFlowError::io("Failed to read library", e))?;
+    serde_json::from_str(&content).map_err(|e| ClipFlowError::io("Invalid library index", e))
+}
+
+/// Replace the library index on disk in one rename, so readers never see a
+/// half-written file
+pub fn write_library(app: &AppHandle, items: &[LibraryItem]) -> Result<()> {
+    let path = library_path(app)?;
+    let json = serde_json::to_string_pretty(items).map_err(|e| ClipFlowError::io("Failed to serialize library", e))?;
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, json).map_err(|e| ClipFlowError::io("Failed to write library", e))?;
+    fs::rename(&tmp_path, &path).map_err(|e| ClipFlowError::io("Failed to save library", e))
+}
+
+/// Probe a file into a library item
+pub fn describe_file(path: &str) -> Result<LibraryItem> {
+    Ok(item_from_probe(path, &probe::ffprobe_json(path)?))
+}
+
+fn item_from_probe(path: &str, json: &serde_json::Value) -> LibraryItem {
+    let tags = &json["format"]["tags"];
+
+    let recorded_at = tags["creation_time"]
+        .as_str()
+        .or_else(|| tags["com.apple.quicktime.creationdate"].as_str())
+        .map(|t| t.to_string())
+        .or_else(|| {
+            let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+            Some(DateTime::<Local>::from(modified).to_rfc3339())
+        });
+
+    let camera = tags["com.apple.quicktime.model"]
+        .as_str()
+        .or_else(|| tags["model"].as_str())
+        .or_else(|| tags["com.android.manufacturer"].as_str())
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty());
+
+    LibraryItem {
+        id: format!("{:x}", path_id(path)),
+        path: path.to_string(),
+        name: Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string()),
+        duration: json["format"]["duration"].as_str().and_then(|d| d.parse().ok()).unwrap_or(0.0),
+        recorded_at,
+        camera,
+        added_at: now_secs(),
+    }
+}
+
+/// Files under `dir` whose extension is in `extensions` (case-insensitive),
+/// descending into subfolders when `recursive`
+/// Unreadable subfolders are skipped rather than failing the walk.
+pub fn walk_files(dir: &Path, recursive: bool, extensions: &[&str]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let entries = match fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                if recursive {
+                    pending.push(path);
+                }
+            } else if path
+                .extension()
+                .map(|e| extensions.iter().any(|x| e.eq_ignore_ascii_case(x)))
+                .unwrap_or(false)
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Stable id derived from the path at import time (FNV-1a)
+fn path_id(path: &str) -> u64 {
+    path.bytes().fold(0xcbf29ce484222325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+#[tauri::command]
+pub async fn list_library(app: AppHandle) -> Result<Vec<LibraryItem>> {
+    read_library(&app)
+}
+
+#[tauri::command]
+pub async fn add_to_library(app: AppHandle, paths: Vec<String>) -> Result<Vec<LibraryItem>> {
+    add_paths(&app, &paths)
+}
+
+/// Add files to the library, skipping ones already indexed
+/// Returns only the newly added items
+pub fn add_paths(app: &AppHandle, paths: &[String]) -> Result<Vec<LibraryItem>> {
+    let _guard = LIBRARY_LOCK.lock().unwrap();
+    let mut items = read_library(app)?;
+    let known: HashSet<String> = items.iter().map(|i| i.path.clone()).collect();
+
+    let mut added = Vec::new();
+    for path in paths.iter().filter(|p| !known.contains(*p)) {
+        let item = describe_file(path)?;
+        added.push(item.clone());
+        items.push(item);
+    }
+
+    write_library(app, &items)?;
+    analysis::start(app, added.iter().map(|item| item.path.clone()).collect());
+    Ok(added)
+}
+
+#[tauri::command]
+pub async fn remove_from_library(app: AppHandle, ids: Vec<String>) -> Result<()> {
+    let _guard = LIBRARY_LOCK.lock().unwrap();
+    let mut items = read_library(&app)?;
+    items.retain(|i| !ids.contains(&i.id));
+    write_library(&app, &items)
+}
+
+#[derive(Deserialize)]
+pub struct RenameOptions {
+    /// e.g. "{date}_{camera}_S{scene:02}_T{take:02}"
+    template: String,
+    #[serde(default = "default_one")]
+    scene: u32,
+    /// First take number; increments per file
+    #[serde(default = "default_one")]
+    take_start: u32,
+    /// Only compute the new names, don't touch the disk
+    #[serde(default)]
+    dry_run: bool,
+}
+
+fn default_one() -> u32 {
+    1
+}
+
+#[derive(Serialize)]
+pub struct RenamePlan {
+    id: String,
+    old_path: String,
+    new_path: String,
+}
+
+/// Substitute `{token}` / `{token:NN}` placeholders
+fn render_template(template: &str, item: &LibraryItem, scene: u32, take: u32, index: u32) -> Result<String> {
+    let path = Path::new(&item.path);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let recorded = item
+        .recorded_at
+        .as_deref()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Local));
+
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| ClipFlowError::invalid(format!("Unclosed placeholder in template: {}", template)))?;
+        let token = &rest[open + 1..open + close];
+        let (name, width) = match token.split_once(':') {
+            Some((name, width)) => (name, width.parse::<usize>().ok()),
+            None => (token, None),
+        };
+
+        let number = |n: u32| match width {
+            Some(w) => format!("{:0width$}", n, width = w),
+            None => n.to_string(),
+        };
+        let value = match name {
+            "name" => stem.clone(),
+            "date" => recorded.map(|t| t.format("%Y-%m-%d").to_string()).unwrap_or_else(|| "undated".to_string()),
+            "time" => recorded.map(|t| t.format("%H-%M-%S").to_string()).unwrap_or_else(|| "0000".to_string()),
+            "camera" => item.camera.clone().unwrap_or_else(|| "unknown".to_string()),
+            "scene" => number(scene),
+            "take" => number(take),
+            "index" => number(index),
+            other => return Err(ClipFlowError::invalid(format!("Unknown template placeholder: {{{}}}", other))),
+        };
+        out.push_str(&value);
+        rest = &rest[open + close + 1..];
+    }
+    out.push_str(rest);
+
+    // Keep the result a single, portable file name
+    let sanitized: String = out
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') { '_' } else { c })
+        .collect();
+    if sanitized.trim().is_empty() {
+        return Err(ClipFlowError::invalid("Template produced an empty file name"));
+    }
+    Ok(sanitized)
+}
+
+/// Rename library files on disk from a metadata template
+///
+/// Files are processed in recording order so take numbers follow the shoot.
+/// Either every file is renamed and the index updated, or (on any failure)
+/// the renames done so far are rolled back and nothing changes.
+#[tauri::command]
+pub async fn batch_rename_media(app: AppHandle, ids: Vec<String>, options: RenameOptions) -> Result<Vec<RenamePlan>> {
+    let _guard = LIBRARY_LOCK.lock().unwrap();
+    let mut items = read_library(&app)?;
+
+    let mut targets: Vec<usize> = items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| ids.contains(&item.id))
+        .map(|(i, _)| i)
+        .collect();
+    if targets.len() != ids.len() {
+        return Err(ClipFlowError::invalid("Some media ids are not in the library"));
+    }
+    targets.sort_by(|a, b| items[*a].recorded_at.cmp(&items[*b].recorded_at));
+
+    let mut plans = Vec::new();
+    let mut claimed: HashSet<PathBuf> = HashSet::new();
+    for (n, &i) in targets.iter().enumerate() {
+        let item = &items[i];
+        let old = PathBuf::from(&item.path);
+        let ext = old.extension().map(|e| e.to_string_lossy().into_owned()).unwrap_or_default();
+        let name = render_template(&options.template, item, options.scene, options.take_start + n as u32, n as u32 + 1)?;
+        let new = old.with_file_name(if ext.is_empty() { name } else { format!("{}.{}", name, ext) });
+
+        if new != old && (new.exists() || !claimed.insert(new.clone())) {
+            return Err(ClipFlowError::invalid(format!("Rename would overwrite {}", new.display())));
+        }
+        plans.push(RenamePlan {
+            id: item.id.clone(),
+            old_path: item.path.clone(),
+            new_path: new.to_string_lossy().into_owned(),
+        });
+    }
+
+    if options.dry_run {
+        return Ok(plans);
+    }
+
+    let mut done: Vec<&RenamePlan> = Vec::new();
+    for plan in &plans {
+        if plan.old_path == plan.new_path {
+            continue;
+        }
+        if let Err(e) = fs::rename(&plan.old_path, &plan.new_path) {
+            for undo in done.iter().rev() {
+                let _ = fs::rename(&undo.new_path, &undo.old_path);
+            }
+            return Err(ClipFlowError::io(&format!("Failed to rename {}", plan.old_path), e));
+        }
+        done.push(plan);
+    }
+
+    for plan in &plans {
+        if let Some(item) = items.iter_mut().find(|i| i.id == plan.id) {
+            item.path = plan.new_path.clone();
+            item.name = Path::new(&plan.new_path)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+        }
+    }
+
+    if let Err(e) = write_library(&app, &items) {
+        for undo in done.iter().rev() {
+            let _ = fs::rename(&undo.new_path, &undo.old_path);
+        }
+        return Err(e);
+    }
+
+    Ok(plans)
+}
+
+#[derive(Serialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub name: String,
+    pub size_bytes: u64,
+    pub duration: f64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<f64>,
+    pub video_codec: Option<String>,
+    pub audio_tracks: usize,
+    pub recorded_at: Option<String>,
+    /// Already indexed in the library
+    pub in_library: bool,
+}
+
+#[derive(Serialize)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: String,
+}
+
+/// Media found under a folder, ready to pass to `add_to_library`
+#[derive(Serialize)]
+pub struct ImportManifest {
+    pub root: String,
+    pub files: Vec<ManifestEntry>,
+    /// Matching files ffprobe couldn't read
+    pub skipped: Vec<SkippedFile>,
+    pub total_duration: f64,
+    pub total_bytes: u64,
+}
+
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let (num, den) = (num.parse::<f64>().ok()?, den.parse::<f64>().ok()?);
+    (den > 0.0 && num > 0.0).then_some(num / den)
+}
+
+fn manifest_entry(path: &Path, known: &HashSet<String>) -> Result<ManifestEntry> {
+    let path_str = path.to_string_lossy().into_owned();
+    let json = probe::ffprobe_json(&path_str)?;
+    let item = item_from_probe(&path_str, &json);
+    let streams = json["streams"].as_array().cloned().unwrap_or_default();
+    let video = streams
+        .iter()
+        .find(|s| s["codec_type"] == "video" && s["disposition"]["attached_pic"].as_i64() != Some(1));
+
+    Ok(ManifestEntry {
+        size_bytes: fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+        duration: item.duration,
+        width: video.and_then(|v| v["width"].as_u64()).map(|w| w as u32),
+        height: video.and_then(|v| v["height"].as_u64()).map(|h| h as u32),
+        fps: video.and_then(|v| v["avg_frame_rate"].as_str()).and_then(parse_frame_rate),
+        video_codec: video.and_then(|v| v["codec_name"].as_str()).map(|c| c.to_string()),
+        audio_tracks: streams.iter().filter(|s| s["codec_type"] == "audio").count(),
+        recorded_at: item.recorded_at,
+        in_library: known.contains(&path_str),
+        path: path_str,
+        name: item.name,
+    })
+}
+
+/// Walk a folder and probe every media file in it
+/// `extensions` defaults to the common video formats; files are listed in
+/// path order, so a session's clips come out in recording order when the
+/// camera numbers them.
+#[tauri::command]
+pub async fn scan_directory(
+    app: AppHandle,
+    path: String,
+    recursive: Option<bool>,
+    extensions: Option<Vec<String>>,
+) -> Result<ImportManifest> {
+    let root = Path::new(&path);
+    if !root.is_dir() {
+        return Err(ClipFlowError::not_found(format!("Folder not found: {}", path)));
+    }
+    let extensions: Vec<String> = extensions
+        .map(|exts| exts.iter().map(|e| e.trim_start_matches('.').to_string()).collect())
+        .unwrap_or_else(|| VIDEO_EXTENSIONS.iter().map(|e| e.to_string()).collect());
+    let extensions: Vec<&str> = extensions.iter().map(|e| e.as_str()).collect();
+    let known: HashSet<String> = read_library(&app)?.into_iter().map(|i| i.path).collect();
+
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    for file in walk_files(root, recursive.unwrap_or(false), &extensions) {
+        match manifest_entry(&file, &known) {
+            Ok(entry) => files.push(entry),
+            Err(e) => skipped.push(SkippedFile { path: file.to_string_lossy().into_owned(), reason: e.to_string() }),
+        }
+    }
+
+    Ok(ImportManifest {
+        root: path.clone(),
+        total_duration: files.iter().map(|f| f.duration).sum(),
+        total_bytes: files.iter().map(|f| f.size_bytes).sum(),
+        files,
+        skipped,
+    })
+}
@@ -0,0 +1,185 @@
+//! Titles, descriptions, summaries, and chapters written by a local LLM
+//!
+//! Talks to any server with an OpenAI-compatible chat endpoint - Ollama
+//! (the default, at localhost:11434) and llama.cpp's server both have one -
+//! so YouTube metadata can be drafted without the transcript leaving the
+//! machine. The endpoint and model are set in settings.
+//!
+//! Long transcripts are thinned out evenly to fit the prompt budget rather
+//! than cut off, so chapters and summaries still cover the whole video.
+
+use crate::error::{ClipFlowError, Result};
+use crate::metadata::Chapter;
+use crate::project::Transcript;
+use crate::settings;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Transcript characters sent per prompt; fits an 8k-token context with room
+/// for the answer
+const MAX_TRANSCRIPT_CHARS: usize = 20_000;
+
+/// Local models on modest hardware can take minutes to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+const MAX_TITLES: usize = 20;
+
+fn clock(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    match seconds / 3600 {
+        0 => format!("{}:{:02}", seconds / 60, seconds % 60),
+        hours => format!("{}:{:02}:{:02}", hours, seconds / 60 % 60, seconds % 60),
+    }
+}
+
+/// The transcript as prompt text, one segment per line, optionally
+/// prefixed with its start time
+fn transcript_text(transcript: &Transcript, with_times: bool) -> Result<String> {
+    let lines: Vec<String> = transcript
+        .segments
+        .iter()
+        .filter(|s| !s.text.trim().is_empty())
+        .map(|s| if with_times { format!("[{}] {}", clock(s.start), s.text.trim()) } else { s.text.trim().to_string() })
+        .collect();
+    if lines.is_empty() {
+        return Err(ClipFlowError::invalid("The transcript is empty"));
+    }
+    let total: usize = lines.iter().map(|l| l.len() + 1).sum();
+    let step = total.div_ceil(MAX_TRANSCRIPT_CHARS).max(1);
+    Ok(lines.into_iter().step_by(step).collect::<Vec<_>>().join("\n"))
+}
+
+/// One chat completion from the configured model
+async fn complete(system: &str, prompt: &str) -> Result<String> {
+    let settings = settings::current();
+    let url = format!("{}/chat/completions", settings.llm_endpoint.trim_end_matches('/'));
+    let body = json!({
+        "model": settings.llm_model,
+        "messages": [
+            { "role": "system", "content": system },
+            { "role": "user", "content": prompt },
+        ],
+        "temperature": 0.7,
+        "stream": false,
+    });
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| ClipFlowError::Network(format!("Couldn't set up the LLM client: {}", e)))?;
+    // No retries: a server that isn't running won't start by itself
+    let response = client.post(&url).json(&body).send().await.map_err(|e| {
+        ClipFlowError::Network(if e.is_connect() {
+            format!("Couldn't reach a local model at {} - is Ollama or llama.cpp running?", settings.llm_endpoint)
+        } else {
+            format!("Local model request failed: {}", e)
+        })
+    })?;
+    let status = response.status();
+    let json: Value = response
+        .json()
+        .await
+        .map_err(|e| ClipFlowError::Network(format!("Local model sent an unreadable response: {}", e)))?;
+    if !status.is_success() {
+        let message = json["error"]["message"].as_str().or(json["error"].as_str()).unwrap_or("no details");
+        return Err(ClipFlowError::tool("local model", format!("HTTP {}: {}", status.as_u16(), message)));
+    }
+    json["choices"][0]["message"]["content"]
+        .as_str()
+        .map(|content| content.trim().to_string())
+        .ok_or_else(|| ClipFlowError::tool("local model", "response had no content"))
+}
+
+/// The JSON array in a model's answer, ignoring any chatter around it
+fn json_array(answer: &str) -> Option<Vec<Value>> {
+    let start = answer.find('[')?;
+    let end = answer.rfind(']')?;
+    serde_json::from_str(answer.get(start..=end)?).ok()
+}
+
+fn parse_clock(text: &str) -> Option<f64> {
+    text.trim().split(':').try_fold(0.0, |total, part| Some(total * 60.0 + part.trim().parse::<f64>().ok()?))
+}
+
+/// A few paragraphs describing the video, usable as a YouTube description
+#[tauri::command]
+pub async fn generate_summary(transcript: Transcript) -> Result<String> {
+    let text = transcript_text(&transcript, false)?;
+    complete(
+        "You write YouTube video descriptions from transcripts. Answer with the description only.",
+        &format!(
+            "Write a 2-3 paragraph description of this video for its YouTube page, in the transcript's language \
+             ({}). Say what the viewer will get out of it; don't invent anything not in the transcript.\n\nTranscript:\n{}",
+            transcript.language, text
+        ),
+    )
+    .await
+}
+
+/// `n` candidate titles, best first
+#[tauri::command]
+pub async fn generate_titles(transcript: Transcript, n: usize) -> Result<Vec<String>> {
+    if !(1..=MAX_TITLES).contains(&n) {
+        return Err(ClipFlowError::invalid(format!("Ask for between 1 and {} titles, got {}", MAX_TITLES, n)));
+    }
+    let text = transcript_text(&transcript, false)?;
+    let answer = complete(
+        "You write YouTube titles from transcripts. Answer with a JSON array of strings and nothing else.",
+        &format!(
+            "Suggest {} different titles for this video, best first, each under 70 characters, in the transcript's \
+             language ({}).\n\nTranscript:\n{}",
+            n, transcript.language, text
+        ),
+    )
+    .await?;
+    // Fall back to one title per line if the model ignored the format
+    let titles: Vec<String> = match json_array(&answer) {
+        Some(items) => items.iter().filter_map(|t| t.as_str()).map(str::to_string).collect(),
+        None => answer
+            .lines()
+            .map(|l| l.trim_start_matches(|c: char| c.is_ascii_digit() || "-*.) ".contains(c)))
+            .map(|l| l.trim_matches('"').to_string())
+            .collect(),
+    };
+    let titles: Vec<String> = titles.into_iter().filter(|t| !t.trim().is_empty()).take(n).collect();
+    if titles.is_empty() {
+        return Err(ClipFlowError::tool("local model", "didn't suggest any titles"));
+    }
+    Ok(titles)
+}
+
+/// Chapters at the points where the topic changes, starting at 0:00 as
+/// YouTube requires
+#[tauri::command]
+pub async fn generate_chapters(transcript: Transcript) -> Result<Vec<Chapter>> {
+    let text = transcript_text(&transcript, true)?;
+    let answer = complete(
+        "You split videos into chapters from timestamped transcripts. Answer with a JSON array and nothing else.",
+        &format!(
+            "Split this video into chapters where the topic changes. Answer with a JSON array of objects like \
+             {{\"start\": \"12:34\", \"title\": \"...\"}}, using the timestamps shown, with short titles in the \
+             transcript's language ({}).\n\nTranscript:\n{}",
+            transcript.language, text
+        ),
+    )
+    .await?;
+    let items = json_array(&answer).ok_or_else(|| ClipFlowError::tool("local model", "didn't answer with a chapter list"))?;
+    let mut chapters: Vec<Chapter> = items
+        .iter()
+        .filter_map(|item| {
+            let start = match &item["start"] {
+                Value::Number(n) => n.as_f64()?,
+                Value::String(s) => parse_clock(s)?,
+                _ => return None,
+            };
+            let title = item["title"].as_str()?.trim();
+            (!title.is_empty()).then(|| Chapter { title: title.to_string(), start, end: None })
+        })
+        .collect();
+    chapters.sort_by(|a, b| a.start.total_cmp(&b.start));
+    chapters.dedup_by(|a, b| a.start == b.start);
+    match chapters.first_mut() {
+        Some(first) => first.start = 0.0,
+        None => return Err(ClipFlowError::tool("local model", "didn't suggest any chapters")),
+    }
+    Ok(chapters)
+}
@@ -0,0 +1,132 @@
+//! Structured logging to rotating JSON-lines files in the app log dir,
+//! plus retrieval of recent entries for bug reports
+
+use crate::error::{ClipFlowError, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+const LOG_FILE_PREFIX: &str = "clipflow";
+
+/// Daily log files kept before the oldest are deleted
+const MAX_LOG_FILES: usize = 7;
+
+/// Keeps the background log writer alive for the lifetime of the app
+static GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+fn log_dir(app: &AppHandle) -> Result<PathBuf> {
+    app.path()
+        .app_log_dir()
+        .map_err(|e| ClipFlowError::io("Failed to resolve log dir", e))
+}
+
+/// Install the global tracing subscriber
+pub fn init(app: &AppHandle) -> Result<()> {
+    let dir = log_dir(app)?;
+    fs::create_dir_all(&dir).map_err(|e| ClipFlowError::io("Failed to create log dir", e))?;
+
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(&dir)
+        .map_err(|e| ClipFlowError::io("Failed to create log file", e))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    let _ = GUARD.set(guard);
+
+    tracing_subscriber::registry()
+        .with(LevelFilter::DEBUG)
+        .with(tracing_subscriber::fmt::layer().json().with_writer(writer))
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .try_init()
+        .map_err(|e| ClipFlowError::io("Failed to initialize logging", e))
+}
+
+fn level_rank(level: &str) -> u8 {
+    match level.to_ascii_uppercase().as_str() {
+        "TRACE" => 0,
+        "DEBUG" => 1,
+        "INFO" => 2,
+        "WARN" => 3,
+        "ERROR" => 4,
+        _ => 2,
+    }
+}
+
+#[derive(Serialize)]
+pub struct LogEntry {
+    timestamp: String,
+    level: String,
+    target: String,
+    message: String,
+    /// Structured fields other than the message (args, exit_code, ...)
+    fields: Value,
+}
+
+/// Most recent log entries at or above `level`, newest first
+#[tauri::command]
+pub async fn get_recent_logs(app: AppHandle, level: Option<String>, limit: Option<usize>) -> Result<Vec<LogEntry>> {
+    let min_rank = level_rank(level.as_deref().unwrap_or("INFO"));
+    let limit = limit.unwrap_or(200);
+
+    let mut files: Vec<PathBuf> = fs::read_dir(log_dir(&app)?)
+        .map_err(|e| ClipFlowError::io("Failed to read log dir", e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(LOG_FILE_PREFIX))
+        })
+        .collect();
+    // File names carry the date, so newest sorts last; walk newest first
+    files.sort();
+    files.reverse();
+
+    let mut entries = Vec::new();
+    for file in files {
+        let content = match fs::read_to_string(&file) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+
+        for line in content.lines().rev() {
+            let mut record: Value = match serde_json::from_str(line) {
+                Ok(record) => record,
+                Err(_) => continue,
+            };
+            let level = record["level"].as_str().unwrap_or("INFO").to_string();
+            if level_rank(&level) < min_rank {
+                continue;
+            }
+
+            let mut fields = record["fields"].take();
+            let message = fields
+                .as_object_mut()
+                .and_then(|f| f.remove("message"))
+                .and_then(|m| m.as_str().map(|s| s.to_string()))
+                .unwrap_or_default();
+
+            entries.push(LogEntry {
+                timestamp: record["timestamp"].as_str().unwrap_or("").to_string(),
+                level,
+                target: record["target"].as_str().unwrap_or("").to_string(),
+                message,
+                fields,
+            });
+            if entries.len() >= limit {
+                return Ok(entries);
+            }
+        }
+    }
+
+    Ok(entries)
+}
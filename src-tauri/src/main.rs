@@ -1,25 +1,86 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::process::{Command, Stdio};
 use serde::{Serialize, Deserialize};
-use tauri::Manager;
-use std::path::PathBuf;
+use tauri::{Emitter, Manager};
 use std::fs;
+use std::path::Path;
+use tokio::process::Command;
+use temp::TempFile;
+use cache::CacheKind;
+use error::{ClipFlowError, Result};
+use output::OverwritePolicy;
 
-/// Escape a file path for shell commands
-/// Wraps in quotes if it contains spaces or special characters
-fn escape_path(path: &str) -> String {
-    let path_buf = PathBuf::from(path);
-    let path_str = path_buf.to_string_lossy().into_owned();
-    
-    // Check if path needs escaping
-    if path_str.contains(' ') || path_str.contains('\'') || path_str.contains('&') {
-        // Wrap in single quotes for shell
-        format!("'{}'", path_str.replace("'", "'\\''"))
-    } else {
-        path_str
-    }
-}
+mod analysis;
+mod audio;
+mod audio_quality;
+mod autosave;
+mod cache;
+mod captions;
+mod censor;
+mod chapters;
+mod clipboard;
+mod cloud;
+mod color;
+mod compose;
+mod control_server;
+mod credentials;
+mod dead_air;
+mod dedupe;
+mod deinterlace;
+mod diagnostics;
+mod diarize;
+mod download;
+mod error;
+mod fcpxml;
+mod filtergraph;
+mod fingerprint;
+mod frames;
+mod grading;
+mod highlights;
+mod ingest;
+mod interchange;
+mod intermediate;
+mod job_stats;
+mod jobs;
+mod karaoke;
+mod languages;
+mod library;
+mod llm;
+mod logging;
+mod markers;
+mod metadata;
+mod output;
+mod overlay;
+mod plan;
+mod podcast;
+mod preflight;
+mod presets;
+mod probe;
+mod process;
+mod project;
+mod proxy;
+mod publish;
+mod quality;
+mod recent;
+mod remux;
+mod repair;
+mod retry;
+mod session;
+mod settings;
+mod shorts;
+mod slate;
+mod speed;
+mod system;
+mod temp;
+mod timecode;
+mod timeline;
+mod transcripts;
+mod transform;
+mod transitions;
+mod translate;
+mod vad;
+mod watch;
+mod youtube;
 
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -27,163 +88,259 @@ fn greet(name: &str) -> String {
 }
 
 #[tauri::command]
-async fn get_video_duration(file_path: &str) -> Result<f64, String> {
-    let escaped = escape_path(file_path);
-    
-    let output = Command::new("ffprobe")
-        .args(&[
+async fn get_video_duration(file_path: &str) -> Result<f64> {
+    let ffprobe = settings::ffprobe_bin();
+    let output = process::run(Command::new(&ffprobe)
+        .args([
             "-v", "error",
             "-show_entries", "format=duration",
             "-of", "default=noprint_wrappers=1:nokey=1",
-            &escaped,
-        ])
-        .output();
-
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                let duration_str = String::from_utf8_lossy(&output.stdout);
-                if let Ok(duration) = duration_str.trim().parse::<f64>() {
-                    Ok(duration)
-                } else {
-                    Err("Failed to parse duration".to_string())
-                }
-            } else {
-                let error = String::from_utf8_lossy(&output.stderr);
-                Err(format!("ffprobe failed: {}. Path: {}", error, file_path))
-            }
-        }
-        Err(e) => Err(format!("Failed to run ffprobe: {}. Path: {}", e, file_path)),
+            file_path,
+        ]), None, |_| {})
+        .await
+        .map_err(|e| process::spawn_error(&ffprobe, e))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(ClipFlowError::tool("ffprobe", format!("{}. Path: {}", error.trim(), file_path)));
     }
+    let duration_str = String::from_utf8_lossy(&output.stdout);
+    duration_str
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| ClipFlowError::tool("ffprobe", "Failed to parse duration"))
 }
 
+/// Commands that write a file return the path actually written, which
+/// differs from `output_path` under `OverwritePolicy::AutoIncrement`
 #[tauri::command]
-async fn trim_video(input_path: &str, output_path: &str, start_time: f64, end_time: f64) -> Result<bool, String> {
-    let escaped_input = escape_path(input_path);
-    let escaped_output = escape_path(output_path);
-    
-    let status = Command::new("ffmpeg")
-        .args(&[
-            "-i", &escaped_input,
-            "-ss", &format!("{}", start_time),
-            "-to", &format!("{}", end_time),
-            "-c", "copy",
-            &escaped_output,
-            "-y",
-        ])
-        .status();
-
-    match status {
-        Ok(status) => {
-            if status.success() {
-                Ok(true)
-            } else {
-                Err("ffmpeg trim failed".to_string())
-            }
-        }
-        Err(e) => Err(format!("Failed to run ffmpeg: {}", e)),
+async fn trim_video(
+    input_path: &str,
+    output_path: &str,
+    start_time: f64,
+    end_time: f64,
+    overwrite: Option<OverwritePolicy>,
+    extra_args: Option<Vec<String>>,
+) -> Result<String> {
+    let extra_args = extra_args.unwrap_or_default();
+    presets::validate_extra_args(&extra_args)?;
+    // Carry the source timecode forward so the trimmed clip still lines up
+    // with the original in an NLE
+    let start_tc = timecode::source_timecode_async(input_path)
+        .await
+        .ok()
+        .filter(|tc| tc.embedded)
+        .map(|tc| tc.at(start_time));
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-ss".to_string(), format!("{}", start_time),
+        "-to".to_string(), format!("{}", end_time),
+        "-c".to_string(), "copy".to_string(),
+    ];
+    if let Some(tc) = start_tc {
+        args.push("-timecode".to_string());
+        args.push(tc);
     }
+    args.extend(extra_args);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "trim").await
+    })
+    .await
 }
 
-#[derive(Deserialize)]
+/// A range of the source to keep, in seconds
+#[derive(Serialize, Deserialize)]
 struct CutSegment {
     keep_start: f64,
     keep_end: f64,
 }
 
+/// Fill short gaps between kept segments with room tone instead of cutting
+#[derive(Deserialize)]
+struct RoomToneFill {
+    /// Quiet region of the source to sample the room tone from
+    tone_start: f64,
+    tone_end: f64,
+    /// Gaps up to this long (seconds) are filled; longer ones are cut
+    max_gap: f64,
+}
+
+/// Keep only the given segments, joining them in order
+/// With `room_tone`, short gaps keep their picture and get looped room tone
+/// in place of the removed audio, so small edits don't sound chopped
 #[tauri::command]
-async fn cut_video_remove(input_path: &str, output_path: &str, segments: Vec<CutSegment>) -> Result<bool, String> {
-    let escaped_input = escape_path(input_path);
-    let escaped_output = escape_path(output_path);
-    
+async fn cut_video_remove(
+    input_path: &str,
+    output_path: &str,
+    segments: Vec<CutSegment>,
+    room_tone: Option<RoomToneFill>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let overwrite = overwrite.unwrap_or_default();
+
     if segments.is_empty() {
-        let status = Command::new("ffmpeg")
-            .args(&["-i", &escaped_input, "-c", "copy", &escaped_output, "-y"])
-            .status();
-        return match status {
-            Ok(status) => Ok(status.success()),
-            Err(e) => Err(format!("Failed to run ffmpeg: {}", e)),
-        };
+        return output::write_atomically_async(output_path, overwrite, |partial| async move {
+            process::run_ffmpeg_async(&["-i", input_path, "-c", "copy", partial.as_str(), "-y"], "copy").await
+        })
+        .await;
+    }
+
+    let mut segments = segments;
+    segments.sort_by(|a, b| a.keep_start.total_cmp(&b.keep_start));
+    for pair in segments.windows(2) {
+        if pair[1].keep_start < pair[0].keep_end {
+            return Err(ClipFlowError::invalid(format!("Segments overlap at {:.2}s", pair[1].keep_start)));
+        }
+    }
+    if let Some(bad) = segments.iter().find(|s| s.keep_end <= s.keep_start) {
+        return Err(ClipFlowError::invalid(format!("Segment {:.2}-{:.2}s is empty", bad.keep_start, bad.keep_end)));
+    }
+
+    let audio = probe::audio_tracks_async(input_path).await?.into_iter().next();
+    if let Some(tone) = &room_tone {
+        if tone.tone_end <= tone.tone_start {
+            return Err(ClipFlowError::invalid("Room tone region is empty"));
+        }
+    }
+
+    // (start, end, filled with room tone)
+    let mut pieces: Vec<(f64, f64, bool)> = Vec::new();
+    for (i, seg) in segments.iter().enumerate() {
+        if i > 0 {
+            let gap_start = segments[i - 1].keep_end;
+            let gap = seg.keep_start - gap_start;
+            if let Some(tone) = &room_tone {
+                if gap > 0.0 && gap <= tone.max_gap {
+                    pieces.push((gap_start, seg.keep_start, true));
+                }
+            }
+        }
+        pieces.push((seg.keep_start, seg.keep_end, false));
+    }
+
+    let (color_filter, color_tags) = color::export_color_args(input_path);
+    let color_stage = color_filter.map(|f| format!("{},", f)).unwrap_or_default();
+
+    let mut graph = Vec::new();
+    let mut concat_inputs = String::new();
+    for (i, (start, end, filled)) in pieces.iter().enumerate() {
+        graph.push(format!(
+            "[0:v]{}trim=start={}:end={},setpts=PTS-STARTPTS[v{}]",
+            color_stage, start, end, i
+        ));
+        concat_inputs.push_str(&format!("[v{}]", i));
+
+        if let Some(track) = &audio {
+            let audio_filter = match (&room_tone, filled) {
+                (Some(tone), true) => audio::room_tone_filter(tone.tone_start, tone.tone_end, end - start, track.sample_rate),
+                _ => format!("atrim=start={}:end={},asetpts=PTS-STARTPTS", start, end),
+            };
+            graph.push(format!("[0:a]{}[a{}]", audio_filter, i));
+            concat_inputs.push_str(&format!("[a{}]", i));
+        }
     }
 
-    Err("Complex cut not yet implemented".to_string())
+    let has_audio = audio.is_some();
+    graph.push(format!(
+        "{}concat=n={}:v=1:a={}[outv]{}",
+        concat_inputs,
+        pieces.len(),
+        if has_audio { 1 } else { 0 },
+        if has_audio { "[outa]" } else { "" }
+    ));
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-filter_complex".to_string(), graph.join(";"),
+        "-map".to_string(), "[outv]".to_string(),
+    ];
+    if has_audio {
+        args.push("-map".to_string());
+        args.push("[outa]".to_string());
+    }
+    args.extend(color_tags);
+
+    output::write_atomically_async(output_path, overwrite, |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "cut").await
+    })
+    .await
 }
 
 #[tauri::command]
-async fn extract_audio(input_path: &str, output_path: &str, format: &str) -> Result<bool, String> {
-    let escaped_input = escape_path(input_path);
-    let escaped_output = escape_path(output_path);
-    
-    let status = Command::new("ffmpeg")
-        .args(&[
-            "-i", &escaped_input,
+async fn extract_audio(
+    input_path: &str,
+    output_path: &str,
+    format: &str,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        process::run_ffmpeg_async(&[
+            "-i", input_path,
             "-vn",
             "-acodec", "pcm_s16le",
-            &escaped_output,
+            partial.as_str(),
             "-y",
-        ])
-        .status();
-
-    match status {
-        Ok(status) => {
-            if status.success() {
-                Ok(true)
-            } else {
-                Err("ffmpeg audio extraction failed".to_string())
+        ], "audio extraction").await
+    })
+    .await
+}
+
+/// Pair up silencedetect's silence_start/silence_end lines in order
+fn parse_silence_output(stderr: &str) -> Vec<SilenceSegment> {
+    let mut segments = Vec::new();
+    let mut pending_start: Option<f64> = None;
+
+    for line in stderr.lines() {
+        if let Some(rest) = line.split("silence_start: ").nth(1) {
+            pending_start = rest.trim().parse::<f64>().ok();
+        } else if let Some(rest) = line.split("silence_end: ").nth(1) {
+            let end = rest.split_whitespace().next().and_then(|e| e.parse::<f64>().ok());
+            if let (Some(start), Some(end)) = (pending_start.take(), end) {
+                segments.push(SilenceSegment {
+                    start,
+                    end,
+                    duration: end - start,
+                });
             }
         }
-        Err(e) => Err(format!("Failed to run ffmpeg: {}", e)),
     }
+
+    segments
 }
 
+/// Detect silent ranges, optionally on one specific audio stream
+/// `audio_track` is the position among audio streams (0 = first), so an OBS
+/// recording with game audio on track 1 and the mic on track 2 can be cut
+/// following the voice only
 #[tauri::command]
-async fn analyze_silence(file_path: &str, threshold_db: f64) -> Result<Vec<SilenceSegment>, String> {
-    let escaped = escape_path(file_path);
-    
-    let output = Command::new("ffmpeg")
-        .args(&[
-            "-i", &escaped,
+async fn analyze_silence(file_path: &str, threshold_db: f64, audio_track: Option<u32>) -> Result<Vec<SilenceSegment>> {
+    let track = audio_track.unwrap_or(0);
+    if audio_track.is_some() {
+        let count = probe::audio_tracks_async(file_path).await?.len() as u32;
+        if track >= count {
+            return Err(ClipFlowError::invalid(format!("Audio track {} does not exist (file has {} audio tracks)", track, count)));
+        }
+    }
+
+    let ffmpeg = settings::ffmpeg_bin();
+    let output = process::run(Command::new(&ffmpeg)
+        .args([
+            "-i", file_path,
+            "-map", &format!("0:a:{}", track),
             "-af", &format!("silencedetect=noise={}dB:d=0.5", threshold_db),
             "-f", "null",
             "-",
-        ])
-        .output();
-
-    match output {
-        Ok(output) => {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let mut segments = Vec::new();
-
-            // Parse silence_start and silence_end from output
-            for line in stderr.lines() {
-                if line.contains("silence_start:") {
-                    if let Some(start) = line.split("silence_start: ").nth(1) {
-                        if let Ok(s) = start.trim().parse::<f64>() {
-                            for end_line in stderr.lines() {
-                                if end_line.contains("silence_end:") && !end_line.contains(&format!("silence_start: {}", s)) {
-                                    if let Some(end) = end_line.split("silence_end: ").nth(1) {
-                                        if let Ok(e) = end.split_once(' ') {
-                                            if let Ok(end_val) = e.0.trim().parse::<f64>() {
-                                                segments.push(SilenceSegment {
-                                                    start: s,
-                                                    end: end_val,
-                                                    duration: end_val - s,
-                                                });
-                                                break;
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+        ]), None, |_| {})
+        .await
+        .map_err(|e| process::spawn_error(&ffmpeg, e))?;
 
-            Ok(segments)
-        }
-        Err(e) => Err(format!("Failed to analyze silence: {}", e)),
-    }
+    Ok(parse_silence_output(&String::from_utf8_lossy(&output.stderr)))
 }
 
 #[derive(Serialize)]
@@ -193,122 +350,645 @@ struct SilenceSegment {
     duration: f64,
 }
 
-#[tauri::command]
-async fn export_video(input_path: &str, output_path: &str, quality: &str) -> Result<bool, String> {
-    let escaped_input = escape_path(input_path);
-    let escaped_output = escape_path(output_path);
-    
-    let codec_args = match quality {
-        "high" => vec!["-c:v", "libx264", "-crf", "18"],
-        "medium" => vec!["-c:v", "libx264", "-crf", "23"],
-        "low" => vec!["-c:v", "libx264", "-crf", "28"],
-        _ => vec!["-c:v", "libx264", "-crf", "23"],
+/// A reviewer comment pinned to a point on the timeline
+#[derive(Deserialize)]
+struct ReviewComment {
+    timestamp: f64,
+    duration: Option<f64>,
+    author: Option<String>,
+    text: String,
+}
+
+/// How long a comment stays on screen when the reviewer didn't specify
+const DEFAULT_COMMENT_DURATION: f64 = 4.0;
+
+/// Format seconds as an SRT timestamp (HH:MM:SS,mmm)
+fn format_srt_timestamp(seconds: f64) -> String {
+    let total_ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    let hours = total_ms / 3_600_000;
+    let minutes = (total_ms % 3_600_000) / 60_000;
+    let secs = (total_ms % 60_000) / 1000;
+    let ms = total_ms % 1000;
+    format!("{:02}:{:02}:{:02},{:03}", hours, minutes, secs, ms)
+}
+
+/// Render reviewer comments as SRT cues, one cue per comment
+fn comments_to_srt(comments: &[ReviewComment]) -> String {
+    let mut sorted: Vec<&ReviewComment> = comments.iter().collect();
+    sorted.sort_by(|a, b| a.timestamp.partial_cmp(&b.timestamp).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut srt = String::new();
+    for (i, comment) in sorted.iter().enumerate() {
+        let start = comment.timestamp;
+        let end = start + comment.duration.unwrap_or(DEFAULT_COMMENT_DURATION);
+        let text = match &comment.author {
+            Some(author) => format!("{}: {}", author, comment.text.trim()),
+            None => comment.text.trim().to_string(),
+        };
+        srt.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            format_srt_timestamp(start),
+            format_srt_timestamp(end),
+            text
+        ));
+    }
+    srt
+}
+
+/// Escape a path for use inside an ffmpeg filter argument (e.g. subtitles=...)
+fn escape_filter_path(path: &str) -> String {
+    path.replace('\\', "/").replace(':', "\\:").replace('\'', "\\'")
+}
+
+/// Seconds since the Unix epoch, or 0 if the clock is before it
+fn now_secs() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// An audio stream to carry into a multi-track export
+#[derive(Deserialize, Serialize, Clone)]
+struct ExportAudioTrack {
+    /// Position among the source's audio streams
+    audio_index: u32,
+    /// Track label shown by players and editors, e.g. "Voice"
+    title: Option<String>,
+    language: Option<String>,
+}
+
+/// Optional parts of an export beyond the preset
+#[derive(Default)]
+struct ExportOptions<'a> {
+    comments: &'a [ReviewComment],
+    comment_mode: Option<&'a str>,
+    /// Audio streams to keep as separate tracks; empty keeps the default
+    /// single track
+    audio_tracks: &'a [ExportAudioTrack],
+    voice_polish: Option<audio::VoicePolish>,
+    /// Use the configured hardware encoder if there is one
+    allow_hardware: bool,
+    overwrite: OverwritePolicy,
+    rate_control: presets::RateControl,
+    /// Encoder options passed through after the preset's own, such as
+    /// `-tune film`
+    extra_args: &'a [String],
+}
+
+/// Render an export with the named preset, returning the path written
+fn render_export(
+    app: &tauri::AppHandle,
+    input_path: &str,
+    output_path: &str,
+    quality: &str,
+    options: &ExportOptions,
+) -> Result<String> {
+    let comments = options.comments;
+
+    let preset = match presets::resolve(app, quality)? {
+        Some(preset) => preset,
+        None => {
+            tracing::warn!(preset = quality, "unknown export preset, using medium");
+            presets::resolve(app, "medium")?.ok_or_else(|| ClipFlowError::invalid("Missing built-in preset"))?
+        }
     };
+    presets::validate(&preset)?;
+    presets::validate_output_path(&preset, output_path)?;
+    presets::validate_extra_args(options.extra_args)?;
+    let video_kbps = presets::target_video_kbps(options.rate_control, &preset, || probe::media_duration(input_path))?;
+    preflight::preflight_export_preset(input_path, output_path, &preset, video_kbps).into_result()?;
 
-    let args: Vec<&str> = vec!["-i", &escaped_input]
-        .iter()
-        .chain(codec_args.iter())
-        .chain(&["-preset", "medium", &escaped_output, "-y"])
-        .cloned()
-        .collect();
+    let mut args: Vec<String> = vec!["-i".to_string(), input_path.to_string()];
+    let mut video_filters: Vec<String> = Vec::new();
 
-    let status = Command::new("ffmpeg").args(&args).status();
+    // DV and some broadcast sources use non-square pixels; resample so the
+    // export doesn't play back squished
+    if let Ok(geometry) = probe::video_geometry(input_path) {
+        if let Some(filter) = probe::square_pixel_filter(&geometry) {
+            video_filters.push(filter);
+        }
+    }
+
+    let (color_filter, color_tags) = color::export_color_args(input_path);
+    video_filters.extend(color_filter);
+
+    // Reviewer comments travel with the export, either burned into the
+    // picture or as a separate subtitle track the player can toggle
+    let review_srt = TempFile::new("review_comments", "srt")?;
+    if !comments.is_empty() {
+        fs::write(review_srt.path(), comments_to_srt(comments))
+            .map_err(|e| ClipFlowError::io("Failed to write review comments", e))?;
+        let srt_str = review_srt.path_str();
+
+        match options.comment_mode.unwrap_or("burn") {
+            "burn" => {
+                video_filters.push(format!(
+                    "subtitles='{}':force_style='FontSize=18,BorderStyle=3,Outline=1,Shadow=0,MarginV=30'",
+                    escape_filter_path(&srt_str)
+                ));
+            }
+            "subtitle" => {
+                let subtitle_codec = match preset.container {
+                    presets::Container::Mkv => "srt",
+                    presets::Container::Webm => "webvtt",
+                    presets::Container::Mp4 | presets::Container::Mov => "mov_text",
+                };
+                args.extend([
+                    "-i".to_string(), srt_str,
+                    "-map".to_string(), "0:v".to_string(),
+                ]);
+                if options.audio_tracks.is_empty() {
+                    args.extend(["-map".to_string(), "0:a?".to_string()]);
+                }
+                args.extend([
+                    "-map".to_string(), "1:s".to_string(),
+                    "-c:s".to_string(), subtitle_codec.to_string(),
+                    "-metadata:s:s:0".to_string(), "title=Review comments".to_string(),
+                ]);
+            }
+            other => return Err(ClipFlowError::invalid(format!("Unknown comment mode: {}", other))),
+        }
+    }
 
-    match status {
-        Ok(status) => {
-            if status.success() {
-                Ok(true)
-            } else {
-                Err("ffmpeg export failed".to_string())
+    // Keep the chosen audio streams as separate, labelled tracks for
+    // mixing elsewhere instead of letting ffmpeg pick a single one
+    if !options.audio_tracks.is_empty() {
+        let available = probe::audio_tracks(input_path)?.len() as u32;
+        if !args.iter().any(|a| a == "0:v") {
+            args.extend(["-map".to_string(), "0:v".to_string()]);
+        }
+        for (i, track) in options.audio_tracks.iter().enumerate() {
+            if track.audio_index >= available {
+                return Err(ClipFlowError::invalid(format!("Audio track {} doesn't exist (file has {})", track.audio_index, available)));
+            }
+            args.extend(["-map".to_string(), format!("0:a:{}", track.audio_index)]);
+            if let Some(title) = &track.title {
+                args.extend([format!("-metadata:s:a:{}", i), format!("title={}", title)]);
+            }
+            if let Some(language) = &track.language {
+                args.extend([format!("-metadata:s:a:{}", i), format!("language={}", language)]);
             }
         }
-        Err(e) => Err(format!("Failed to run ffmpeg: {}", e)),
     }
+
+    if !video_filters.is_empty() {
+        args.push("-vf".to_string());
+        args.push(video_filters.join(","));
+    }
+
+    if let Some(polish) = options.voice_polish {
+        args.push("-af".to_string());
+        args.push(polish.filter().to_string());
+    }
+
+    let mut output_tags = color_tags;
+    // Re-encoding drops the tmcd track, so write the source start TC back
+    if let Ok(tc) = timecode::source_timecode(input_path) {
+        if tc.embedded {
+            output_tags.push("-timecode".to_string());
+            output_tags.push(tc.start_timecode);
+        }
+    }
+
+    // Pass log files live in a scratch dir that goes away with this render
+    let passlog_dir = video_kbps.map(|_| temp::TempDir::new("passlog")).transpose()?;
+    if let (Some(kbps), Some(dir)) = (video_kbps, &passlog_dir) {
+        let passlog = dir.path().join("ffmpeg2pass").to_string_lossy().into_owned();
+        let mut first_pass = args.clone();
+        first_pass.extend(presets::two_pass_video_args(&preset, kbps, 1, &passlog)?);
+        first_pass.extend(options.extra_args.iter().cloned());
+        first_pass.extend(["-an", "-sn", "-f", "null", "-"].map(String::from));
+        process::run_ffmpeg(&first_pass, "export (first pass)")?;
+
+        args.extend(presets::two_pass_video_args(&preset, kbps, 2, &passlog)?);
+        args.extend(presets::audio_codec_args(preset.audio_codec));
+    } else {
+        args.extend(presets::codec_args(&preset, options.allow_hardware));
+    }
+    // Later options win, so these override the preset's
+    args.extend(options.extra_args.iter().cloned());
+    args.extend(output_tags);
+
+    output::write_atomically(output_path, options.overwrite, |partial| {
+        args.push(partial.to_string());
+        args.push("-y".to_string());
+        process::run_ffmpeg(&args, "export")
+    })
+}
+
+/// Export with an export preset
+/// `quality` names the preset: a built-in ("high", "medium", "low") or a user preset
+/// `rate_control` swaps the preset's CRF for a two-pass bitrate or size target
+/// `extra_args` are raw ffmpeg options for the encoder, checked against a blocklist
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn export_video(
+    app: tauri::AppHandle,
+    input_path: &str,
+    output_path: &str,
+    quality: &str,
+    comments: Option<Vec<ReviewComment>>,
+    comment_mode: Option<&str>,
+    audio_tracks: Option<Vec<ExportAudioTrack>>,
+    voice_polish: Option<audio::VoicePolish>,
+    overwrite: Option<OverwritePolicy>,
+    rate_control: Option<presets::RateControl>,
+    extra_args: Option<Vec<String>>,
+) -> Result<String> {
+    let comments = comments.unwrap_or_default();
+    let audio_tracks = audio_tracks.unwrap_or_default();
+    let extra_args = extra_args.unwrap_or_default();
+    let comment_mode = comment_mode.map(str::to_string);
+    let (input_path, output_path, quality) = (input_path.to_string(), output_path.to_string(), quality.to_string());
+    // Carried over to the blocking thread when this export is being planned
+    let plan = process::PlanRecorder::current();
+    // The render shares the blocking pool with queued jobs rather than
+    // holding an async runtime thread for its whole length
+    tauri::async_runtime::spawn_blocking(move || {
+        let options = ExportOptions {
+            comments: &comments,
+            comment_mode: comment_mode.as_deref(),
+            audio_tracks: &audio_tracks,
+            voice_polish,
+            allow_hardware: true,
+            overwrite: overwrite.unwrap_or_default(),
+            rate_control: rate_control.unwrap_or_default(),
+            extra_args: &extra_args,
+        };
+        match &plan {
+            Some(plan) => plan.record_blocking(|| render_export(&app, &input_path, &output_path, &quality, &options)),
+            None => render_export(&app, &input_path, &output_path, &quality, &options),
+        }
+    })
+    .await
+    .map_err(|e| ClipFlowError::io("Export thread failed", e))?
 }
 
 /// Whisper Transcription - Local AI (no cloud API)
 
+/// Extract 16kHz mono audio for Whisper from all of `input_path`, or just
+/// `range` (start and end in seconds)
+async fn extract_speech_audio(input_path: &str, range: Option<(f64, f64)>) -> Result<TempFile> {
+    let wav_file = TempFile::new("transcribe", "wav")?;
+    let wav = wav_file.path_str();
+    let mut args: Vec<String> = Vec::new();
+    if let Some((start, end)) = range {
+        args.extend(["-ss".to_string(), format!("{}", start), "-t".to_string(), format!("{}", end - start)]);
+    }
+    args.extend(
+        ["-i", input_path, "-vn", "-acodec", "pcm_s16le", "-ar", "16000", "-ac", "1", wav.as_str(), "-y"]
+            .map(str::to_string),
+    );
+    process::run_ffmpeg_async(&args, "transcription audio extraction").await?;
+    Ok(wav_file)
+}
+
+/// Run Whisper over `wav`, emitting segments for `source_path` as they're
+/// finished
+/// `offset` is where the audio starts in the source; every time is shifted
+/// by it.
+async fn run_whisper(
+    app: &tauri::AppHandle,
+    source_path: &str,
+    wav: &TempFile,
+    model: &str,
+    language: Option<&str>,
+    offset: f64,
+) -> Result<TranscriptionResult> {
+    let json_file = TempFile::adopt(wav.path().with_extension("json"));
+    let temp_dir = temp::scratch_dir()?.to_string_lossy().into_owned();
+    let temp_wav = wav.path_str();
+
+    // Without --language, Whisper detects the language from the first 30 seconds
+    let mut whisper = Command::new("whisper");
+    // Word timings cost little extra and drive karaoke captions
+    whisper.args([
+        temp_wav.as_str(),
+        "--model", model,
+        "--output_format", "json",
+        "--output_dir", &temp_dir,
+        "--word_timestamps", "True",
+    ]);
+    if let Some(language) = language {
+        whisper.args(["--language", language]);
+    }
+    // Verbose mode prints each segment as it's finished; Python only
+    // flushes a pipe that often when unbuffered
+    whisper.args(["--verbose", "True"]).env("PYTHONUNBUFFERED", "1");
+    let duration = probe::media_duration_async(source_path).await.unwrap_or(0.0);
+    let mut next_id = 0;
+    let mut on_line = |line: &str| {
+        let (start, end, text) = match parse_whisper_segment(line) {
+            Some(segment) => segment,
+            None => return,
+        };
+        let (start, end) = (start + offset, end + offset);
+        let segment = TranscriptionSegment { id: next_id, start, end, text, speaker: None, words: Vec::new() };
+        next_id += 1;
+        let _ = app.emit("transcription-segment", PartialSegment { path: source_path.to_string(), segment });
+        if duration > 0.0 {
+            let _ = app.emit("transcription-progress", TranscriptionProgress {
+                path: source_path.to_string(),
+                processed_seconds: end,
+                duration,
+                fraction: (end / duration).clamp(0.0, 1.0),
+            });
+        }
+    };
+    let output = process::run_streaming(&mut whisper, None, Some(&mut on_line), |_| {})
+        .await
+        .map_err(|e| process::spawn_error("whisper", e))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(ClipFlowError::tool("whisper", error.trim()));
+    }
+
+    let json_content = fs::read_to_string(json_file.path())
+        .map_err(|e| ClipFlowError::io("Failed to read Whisper output file", e))?;
+    let json: serde_json::Value = serde_json::from_str(&json_content)
+        .map_err(|_| ClipFlowError::tool("whisper", "Failed to parse Whisper output"))?;
+
+    let segments = json["segments"]
+        .as_array()
+        .unwrap_or(&vec![])
+        .iter()
+        .map(|seg| TranscriptionSegment {
+            id: seg["id"].as_i64().unwrap_or(0) as usize,
+            start: seg["start"].as_f64().unwrap_or(0.0) + offset,
+            end: seg["end"].as_f64().unwrap_or(0.0) + offset,
+            text: seg["text"].as_str().unwrap_or("").trim().to_string(),
+            speaker: None,
+            words: seg["words"]
+                .as_array()
+                .unwrap_or(&vec![])
+                .iter()
+                .map(|w| project::TranscriptWord {
+                    start: w["start"].as_f64().unwrap_or(0.0) + offset,
+                    end: w["end"].as_f64().unwrap_or(0.0) + offset,
+                    word: w["word"].as_str().unwrap_or("").trim().to_string(),
+                })
+                .filter(|w| !w.word.is_empty())
+                .collect(),
+        })
+        .collect();
+
+    Ok(TranscriptionResult {
+        text: json["text"].as_str().unwrap_or("").trim().to_string(),
+        segments,
+        language: json["language"].as_str().or(language).unwrap_or("en").to_string(),
+        // Only written by some Whisper builds (faster-whisper based ones)
+        language_probability: language.is_none().then(|| json["language_probability"].as_f64()).flatten(),
+        duration: json["duration"].as_f64().unwrap_or(duration),
+    })
+}
+
+fn transcription_cache_name(source_key: &str, model: &str) -> String {
+    format!("{}-{}.json", source_key, model)
+}
+
+/// Whisper output cached for this content and model, if any
+fn cached_transcription(app: &tauri::AppHandle, source_key: &str, model: &str) -> Result<Option<TranscriptionResult>> {
+    let path = match cache::lookup(app, CacheKind::Transcriptions, &transcription_cache_name(source_key, model))? {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    Ok(fs::read_to_string(path).ok().and_then(|content| serde_json::from_str(&content).ok()))
+}
+
+fn cache_transcription(
+    app: &tauri::AppHandle,
+    source_path: &str,
+    source_key: &str,
+    model: &str,
+    result: &TranscriptionResult,
+) -> Result<()> {
+    let file_name = transcription_cache_name(source_key, model);
+    let json = serde_json::to_string(result).map_err(|e| ClipFlowError::io("Failed to serialize transcription", e))?;
+    fs::write(cache::entry_path(app, CacheKind::Transcriptions, &file_name)?, json)
+        .map_err(|e| ClipFlowError::io("Failed to cache transcription", e))?;
+    cache::insert(app, CacheKind::Transcriptions, &file_name, source_path)?;
+    Ok(())
+}
+
+/// Transcribe a file and keep the result in the transcript store
+/// `language` is a code or name from `get_supported_languages`; without
+/// one (or with "auto") Whisper detects it. With `diarize`, each segment is
+/// also labelled with who's speaking.
+/// Segments are emitted as "transcription-segment" as Whisper finishes
+/// them, along with "transcription-progress", so long files fill in live.
+/// Results are cached by file content and model, so transcribing the same
+/// footage again returns at once.
 #[tauri::command]
-async fn transcribe_audio(input_path: &str, model: &str) -> Result<TranscriptionResult, String> {
-    let escaped_input = escape_path(input_path);
-    let temp_wav = "/tmp/clipflow_audio.wav";
-    
-    // Extract audio using ffmpeg
-    let extract_status = Command::new("ffmpeg")
-        .args(&[
-            "-i", &escaped_input,
-            "-vn",
-            "-acodec", "pcm_s16le",
-            "-ar", "16000",
-            "-ac", "1",
-            temp_wav,
-            "-y",
-        ])
-        .status();
+async fn transcribe_audio(
+    app: tauri::AppHandle,
+    input_path: &str,
+    model: &str,
+    language: Option<String>,
+    diarize: Option<bool>,
+    num_speakers: Option<u32>,
+) -> Result<TranscriptionResult> {
+    let language = languages::resolve(language.as_deref())?;
+    let diarize = diarize.unwrap_or(false);
+    let source_key = cache::source_key(Path::new(input_path))?;
 
-    match extract_status {
-        Ok(status) => {
-            if !status.success() {
-                return Err("Failed to extract audio for transcription".to_string());
-            }
+    if let Some(cached) = cached_transcription(&app, &source_key, model)? {
+        let language_matches = language.as_ref().is_none_or(|l| *l == cached.language);
+        let has_speakers = cached.segments.iter().any(|s| s.speaker.is_some());
+        // Transcriptions cached before word timings were kept are redone
+        let has_words = cached.segments.iter().any(|s| !s.words.is_empty());
+        if language_matches && has_words && (has_speakers || !diarize) {
+            tracing::info!(file = %input_path, model = %model, "using cached transcription");
+            transcripts::save_new(&app, input_path, model, &source_key, cached.to_transcript())?;
+            return Ok(cached);
         }
-        Err(e) => return Err(format!("Failed to run ffmpeg: {}", e)),
     }
 
-    // Run Whisper transcription
-    let output = Command::new("whisper")
-        .args(&[
-            temp_wav,
-            "--model", model,
-            "--output_format", "json",
-            "--output_dir", "/tmp",
-            "--language", "English",
-        ])
-        .output();
-
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                let json_path = temp_wav.replace(".wav", ".json");
-                match std::fs::read_to_string(&json_path) {
-                    Ok(json_content) => {
-                        match serde_json::from_str::<serde_json::Value>(&json_content) {
-                            Ok(json) => {
-                                let segments = json["segments"]
-                                    .as_array()
-                                    .unwrap_or(&vec![])
-                                    .iter()
-                                    .map(|seg| TranscriptionSegment {
-                                        id: seg["id"].as_i64().unwrap_or(0) as usize,
-                                        start: seg["start"].as_f64().unwrap_or(0.0),
-                                        end: seg["end"].as_f64().unwrap_or(0.0),
-                                        text: seg["text"].as_str().unwrap_or("").trim().to_string(),
-                                    })
-                                    .collect();
-
-                                Ok(TranscriptionResult {
-                                    text: json["text"].as_str().unwrap_or("").trim().to_string(),
-                                    segments,
-                                    language: json["language"].as_str().unwrap_or("en").to_string(),
-                                    duration: json["duration"].as_f64().unwrap_or(0.0),
-                                })
-                            }
-                            Err(_) => Err("Failed to parse Whisper output".to_string()),
-                        }
-                    }
-                    Err(_) => Err("Failed to read Whisper output file".to_string()),
-                }
-            } else {
-                let error = String::from_utf8_lossy(&output.stderr);
-                Err(format!("Whisper failed: {}", error))
-            }
+    let wav = extract_speech_audio(input_path, None).await?;
+    let mut result = run_whisper(&app, input_path, &wav, model, language.as_deref(), 0.0).await?;
+    if diarize {
+        let turns = diarize::speaker_turns(&wav.path_str(), num_speakers).await?;
+        for segment in &mut result.segments {
+            segment.speaker = diarize::speaker_for(&turns, segment.start, segment.end);
         }
-        Err(e) => Err(format!("Failed to run Whisper: {}", e)),
     }
+
+    if let Err(e) = cache_transcription(&app, input_path, &source_key, model, &result) {
+        tracing::warn!(file = %input_path, error = %e, "transcription not cached");
+    }
+    transcripts::save_new(&app, input_path, model, &source_key, result.to_transcript())?;
+    Ok(result)
+}
+
+/// Cached Whisper output for a file's current content, without running
+/// Whisper; `model` defaults to the one in settings
+/// None when the file has never been transcribed with that model, or has
+/// changed since.
+#[tauri::command]
+async fn get_cached_transcript(app: tauri::AppHandle, path: &str, model: Option<String>) -> Result<Option<TranscriptionResult>> {
+    let model = model.unwrap_or_else(|| settings::current().whisper_model);
+    cached_transcription(&app, &cache::source_key(Path::new(path))?, &model)
+}
+
+/// Transcribe `start..end` of a file again and splice the result into its
+/// stored transcript, e.g. after re-cutting part of it
+/// The model and language default to the stored transcript's; segments
+/// outside the range, and their edit history, are left as they are.
+#[tauri::command]
+async fn retranscribe_range(
+    app: tauri::AppHandle,
+    source_path: &str,
+    start: f64,
+    end: f64,
+    model: Option<String>,
+    language: Option<String>,
+) -> Result<transcripts::StoredTranscript> {
+    if !(start >= 0.0 && end > start) {
+        return Err(ClipFlowError::invalid(format!("Invalid range {:.2}-{:.2}s", start, end)));
+    }
+    let stored = transcripts::require(&app, source_path)?;
+    let model = model.unwrap_or(stored.model);
+    let language = match language {
+        Some(language) => languages::resolve(Some(&language))?,
+        None => Some(stored.transcript.language),
+    };
+
+    let wav = extract_speech_audio(source_path, Some((start, end))).await?;
+    let partial = run_whisper(&app, source_path, &wav, &model, language.as_deref(), start).await?;
+    let source_key = cache::source_key(Path::new(source_path))?;
+    let stored = transcripts::replace_range(&app, source_path, start, end, partial.to_transcript().segments, &source_key)?;
+
+    let duration = probe::media_duration_async(source_path).await.unwrap_or(0.0);
+    let merged = TranscriptionResult::from_transcript(&stored.transcript, duration);
+    if let Err(e) = cache_transcription(&app, source_path, &source_key, &model, &merged) {
+        tracing::warn!(file = %source_path, error = %e, "transcription not cached");
+    }
+    Ok(stored)
+}
+
+/// Parse a segment line Whisper prints in verbose mode,
+/// "[00:01.000 --> 00:04.500]  text", into (start, end, text)
+fn parse_whisper_segment(line: &str) -> Option<(f64, f64, String)> {
+    let (times, text) = line.trim_start().strip_prefix('[')?.split_once(']')?;
+    let (start, end) = times.split_once("-->")?;
+    let seconds = |clock: &str| {
+        clock.trim().split(':').try_fold(0.0, |total, part| Some(total * 60.0 + part.parse::<f64>().ok()?))
+    };
+    Some((seconds(start)?, seconds(end)?, text.trim().to_string()))
+}
+
+/// Seconds of audio sampled per track for language detection
+const LANGUAGE_SAMPLE_SECONDS: f64 = 30.0;
+
+#[derive(Serialize)]
+struct TrackLanguage {
+    stream_index: u32,
+    audio_index: u32,
+    /// Language tag from the container, if the muxer wrote one
+    tag_language: Option<String>,
+    /// ISO 639-1 code detected by Whisper
+    detected_language: Option<String>,
+    /// Human-readable label, e.g. "English - Commentary" or "Japanese (Track 2)"
+    label: String,
+}
+
+fn language_name(code: &str) -> String {
+    let name = match code {
+        "en" | "eng" => "English",
+        "ja" | "jpn" => "Japanese",
+        "es" | "spa" => "Spanish",
+        "fr" | "fra" | "fre" => "French",
+        "de" | "deu" | "ger" => "German",
+        "it" | "ita" => "Italian",
+        "pt" | "por" => "Portuguese",
+        "ru" | "rus" => "Russian",
+        "zh" | "zho" | "chi" => "Chinese",
+        "ko" | "kor" => "Korean",
+        "nl" | "nld" | "dut" => "Dutch",
+        "pl" | "pol" => "Polish",
+        "tr" | "tur" => "Turkish",
+        "ar" | "ara" => "Arabic",
+        "hi" | "hin" => "Hindi",
+        "sv" | "swe" => "Swedish",
+        other => return other.to_string(),
+    };
+    name.to_string()
+}
+
+/// Run a quick Whisper pass over a short sample of one audio track and
+/// return the language it detected
+async fn detect_sample_language(input_path: &str, audio_index: u32, offset: f64) -> Result<Option<String>> {
+    let wav_file = TempFile::new("langsample", "wav")?;
+    let json_file = TempFile::adopt(wav_file.path().with_extension("json"));
+    let temp_dir = temp::scratch_dir()?;
+    let sample_wav = wav_file.path_str();
+
+    process::run_ffmpeg_async(&[
+        "-ss", &format!("{}", offset),
+        "-i", input_path,
+        "-map", &format!("0:a:{}", audio_index),
+        "-t", &format!("{}", LANGUAGE_SAMPLE_SECONDS),
+        "-ar", "16000",
+        "-ac", "1",
+        &sample_wav,
+        "-y",
+    ], &format!("audio track {} sampling", audio_index)).await?;
+
+    // Without --language, Whisper detects the language from the first 30s
+    let output = process::run(Command::new("whisper")
+        .args([
+            sample_wav.as_str(),
+            "--model", "tiny",
+            "--output_format", "json",
+            "--output_dir", &temp_dir.to_string_lossy(),
+        ]), None, |_| {})
+        .await
+        .map_err(|e| process::spawn_error("whisper", e))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(ClipFlowError::tool("whisper", error.trim()));
+    }
+    Ok(fs::read_to_string(json_file.path())
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|json| json["language"].as_str().map(|l| l.to_string())))
+}
+
+/// Detect the spoken language of every audio track in a file
+#[tauri::command]
+async fn detect_track_languages(file_path: &str) -> Result<Vec<TrackLanguage>> {
+    let tracks = probe::audio_tracks_async(file_path).await?;
+
+    // Sample from a third of the way in to skip intros and silent lead-ins
+    let duration = probe::media_duration_async(file_path).await.unwrap_or(0.0);
+    let offset = if duration > LANGUAGE_SAMPLE_SECONDS * 2.0 { duration / 3.0 } else { 0.0 };
+
+    let mut results = Vec::new();
+    for track in tracks {
+        let detected = detect_sample_language(file_path, track.audio_index, offset).await?;
+        let language = detected.as_deref().or(track.language_tag.as_deref()).map(language_name);
+
+        let label = match (&language, &track.title) {
+            (Some(lang), Some(title)) => format!("{} - {}", lang, title),
+            (Some(lang), None) => format!("{} (Track {})", lang, track.audio_index + 1),
+            (None, Some(title)) => title.clone(),
+            (None, None) => format!("Track {}", track.audio_index + 1),
+        };
+
+        results.push(TrackLanguage {
+            stream_index: track.stream_index,
+            audio_index: track.audio_index,
+            tag_language: track.language_tag,
+            detected_language: detected,
+            label,
+        });
+    }
+
+    Ok(results)
 }
 
 #[tauri::command]
-async fn get_available_whisper_models() -> Result<Vec<WhisperModel>, String> {
+async fn get_available_whisper_models() -> Result<Vec<WhisperModel>> {
     Ok(vec![
         WhisperModel {
             name: "tiny".to_string(),
@@ -343,20 +1023,87 @@ async fn get_available_whisper_models() -> Result<Vec<WhisperModel>, String> {
     ])
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 struct TranscriptionResult {
     text: String,
     segments: Vec<TranscriptionSegment>,
     language: String,
+    /// Confidence in a detected language, when Whisper reports one; None
+    /// when the language was chosen
+    language_probability: Option<f64>,
     duration: f64,
 }
 
-#[derive(Serialize)]
+impl TranscriptionResult {
+    fn from_transcript(transcript: &project::Transcript, duration: f64) -> TranscriptionResult {
+        TranscriptionResult {
+            text: transcript.text.clone(),
+            segments: transcript
+                .segments
+                .iter()
+                .map(|s| TranscriptionSegment {
+                    id: s.id,
+                    start: s.start,
+                    end: s.end,
+                    text: s.text.clone(),
+                    speaker: s.speaker.clone(),
+                    words: s.words.clone(),
+                })
+                .collect(),
+            language: transcript.language.clone(),
+            language_probability: None,
+            duration,
+        }
+    }
+
+    fn to_transcript(&self) -> project::Transcript {
+        project::Transcript {
+            language: self.language.clone(),
+            text: self.text.clone(),
+            segments: self
+                .segments
+                .iter()
+                .map(|s| project::TranscriptSegment {
+                    id: s.id,
+                    start: s.start,
+                    end: s.end,
+                    text: s.text.clone(),
+                    speaker: s.speaker.clone(),
+                    words: s.words.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A segment as Whisper finishes it; ids count up from 0 like the final
+/// result's
+#[derive(Serialize, Clone)]
+struct PartialSegment {
+    path: String,
+    segment: TranscriptionSegment,
+}
+
+#[derive(Serialize, Clone)]
+struct TranscriptionProgress {
+    path: String,
+    /// End of the last finished segment
+    processed_seconds: f64,
+    duration: f64,
+    /// 0 to 1
+    fraction: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 struct TranscriptionSegment {
     id: usize,
     start: f64,
     end: f64,
     text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    speaker: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    words: Vec<project::TranscriptWord>,
 }
 
 #[derive(Serialize)]
@@ -371,7 +1118,7 @@ struct WhisperModel {
 #[tauri::command]
 async fn open_file_dialog(
     multiple: bool,
-) -> Result<Vec<String>, String> {
+) -> Result<Vec<String>> {
     use tauri::api::dialog::OpenDialog;
     
     let result = OpenDialog::new()
@@ -389,6 +1136,31 @@ async fn open_file_dialog(
 fn main() {
     tauri::Builder::default()
         .plugin(tauri_shell::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .manage(autosave::AutosaveState::default())
+        .manage(control_server::ControlServer::default())
+        .manage(download::DownloadState::default())
+        .manage(job_stats::JobStatsState::default())
+        .manage(jobs::JobQueue::default())
+        .manage(publish::PublishQueue::default())
+        .manage(system::SystemMonitor::default())
+        .manage(watch::WatchState::default())
+        .setup(|app| {
+            if let Err(e) = logging::init(app.handle()) {
+                eprintln!("{}", e);
+            }
+            settings::load(app.handle());
+            if let Err(e) = session::start(app.handle()) {
+                tracing::warn!(error = %e, "session tracking unavailable");
+            }
+            autosave::start(app.handle().clone());
+            jobs::start(app.handle().clone());
+            publish::start(app.handle().clone());
+            system::start(app.handle().clone());
+            watch::start(app.handle().clone());
+            control_server::start(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             get_video_duration,
@@ -399,8 +1171,168 @@ fn main() {
             export_video,
             transcribe_audio,
             get_available_whisper_models,
-            open_file_dialog
+            open_file_dialog,
+            project::save_project,
+            project::load_project,
+            autosave::update_autosave_project,
+            autosave::list_recovery_points,
+            autosave::restore_recovery_point,
+            credentials::list_credentials,
+            credentials::add_credential,
+            credentials::remove_credential,
+            credentials::test_credential,
+            settings::get_settings,
+            settings::set_settings,
+            probe::list_audio_tracks,
+            detect_track_languages,
+            recent::add_recent_file,
+            recent::get_recent_files,
+            recent::clear_recent_files,
+            logging::get_recent_logs,
+            timecode::get_source_timecode,
+            timecode::format_timecodes,
+            probe::get_video_geometry,
+            speed::change_speed,
+            speed::change_speed_segments,
+            color::get_color_info,
+            speed::interpolate_slowmo,
+            transform::stabilize_video,
+            transform::transform_video,
+            transform::detect_crop,
+            library::list_library,
+            library::add_to_library,
+            library::remove_from_library,
+            library::batch_rename_media,
+            ingest::verified_ingest,
+            overlay::overlay_image,
+            presets::list_export_presets,
+            presets::validate_export_preset,
+            presets::save_export_preset,
+            presets::delete_export_preset,
+            audio::fade_audio,
+            transitions::crossfade_clips,
+            jobs::submit_job,
+            jobs::list_jobs,
+            jobs::get_job,
+            jobs::cancel_job,
+            clipboard::paste_import,
+            download::download_media,
+            download::cancel_download,
+            audio::replace_audio,
+            audio::add_audio_track,
+            audio::mix_music,
+            session::get_crash_report,
+            session::terminate_orphaned_processes,
+            session::adopt_orphaned_processes,
+            session::cleanup_crash_artifacts,
+            audio::downmix_to_mono,
+            audio::split_stereo,
+            audio::swap_channels,
+            audio::select_audio_track,
+            jobs::get_job_log,
+            captions::export_captions,
+            preflight::preflight_export,
+            proxy::get_proxy_capabilities,
+            proxy::create_proxy,
+            publish::schedule_publish,
+            publish::list_scheduled_publishes,
+            publish::cancel_scheduled_publish,
+            slate::list_slate_presets,
+            slate::save_slate_preset,
+            slate::delete_slate_preset,
+            slate::prepend_slate,
+            transform::rescale_video,
+            fingerprint::find_duplicate_segments,
+            transcripts::get_transcript,
+            transcripts::edit_transcript_segment,
+            transcripts::revert_transcript_segment,
+            transcripts::delete_transcript,
+            intermediate::export_intermediate,
+            podcast::export_podcast,
+            chapters::generate_chapters,
+            chapters::embed_chapters,
+            chapters::export_chapter_list,
+            metadata::get_metadata,
+            metadata::set_metadata,
+            remux::remux,
+            repair::repair_video,
+            watch::add_watch_folder,
+            watch::remove_watch_folder,
+            watch::list_watch_folders,
+            library::scan_directory,
+            dedupe::hash_files,
+            dedupe::find_duplicates,
+            proxy::get_or_create_proxy,
+            cache::get_cache_stats,
+            cache::clear_cache,
+            analysis::analyze_media,
+            jobs::resume_interrupted_jobs,
+            jobs::set_max_concurrent_encodes,
+            jobs::pause_job,
+            jobs::resume_job,
+            jobs::get_job_stats,
+            system::get_system_stats,
+            languages::get_supported_languages,
+            get_cached_transcript,
+            retranscribe_range,
+            translate::translate_transcript,
+            llm::generate_summary,
+            llm::generate_titles,
+            llm::generate_chapters,
+            highlights::detect_highlights,
+            shorts::generate_shorts,
+            censor::detect_profanity,
+            censor::censor_audio,
+            vad::detect_voice_activity,
+            audio_quality::analyze_audio_quality,
+            dead_air::detect_black_frames,
+            dead_air::detect_frozen_frames,
+            repair::verify_file,
+            quality::compare_quality,
+            grading::adjust_color,
+            grading::preview_color_adjustment,
+            grading::list_luts,
+            grading::apply_lut,
+            color::tonemap_video,
+            deinterlace::detect_interlacing,
+            deinterlace::deinterlace,
+            frames::extract_frame,
+            frames::export_image_sequence,
+            frames::images_to_video,
+            frames::image_plus_audio,
+            speed::timelapse,
+            speed::reverse_clip,
+            speed::boomerang,
+            compose::compose_split,
+            transitions::attach_bumpers,
+            overlay::overlay_text,
+            karaoke::export_karaoke_captions,
+            karaoke::burn_karaoke_captions,
+            timeline::render_timeline,
+            filtergraph::run_custom_filtergraph,
+            plan::plan_render,
+            interchange::export_edit,
+            interchange::import_cuts,
+            markers::list_markers,
+            markers::add_marker,
+            markers::update_marker,
+            markers::delete_marker,
+            markers::export_marker_chapters,
+            markers::import_marker_file,
+            youtube::start_youtube_auth,
+            youtube::upload_to_youtube,
+            cloud::list_cloud_destinations,
+            cloud::save_cloud_destination,
+            cloud::remove_cloud_destination,
+            cloud::upload_export,
+            control_server::get_control_server_info,
+            control_server::regenerate_control_server_token
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, event| {
+            if let tauri::RunEvent::Exit = event {
+                session::end();
+            }
+        });
 }
@@ -0,0 +1,220 @@
+//! Markers: named, colored points in a project's clips, stored in the
+//! project file
+//!
+//! Markers can be written out as a YouTube chapter list of the edit, and
+//! imported from the bookmark files OBS scripts and Twitch's stream marker
+//! export produce while streaming.
+
+use crate::chapters;
+use crate::error::{ClipFlowError, Result};
+use crate::interchange;
+use crate::metadata::Chapter;
+use crate::project::{self, Marker, MarkerColor, Project};
+use std::fs;
+use std::path::Path;
+
+fn load(project_path: &str) -> Result<Project> {
+    project::read_project(Path::new(project_path))
+}
+
+fn save(project_path: &str, project: &Project) -> Result<()> {
+    project::write_project(Path::new(project_path), project)
+}
+
+/// Next unused "marker-N" id
+fn next_id(project: &Project) -> String {
+    let last = project
+        .markers
+        .iter()
+        .filter_map(|marker| marker.id.strip_prefix("marker-")?.parse::<u64>().ok())
+        .max()
+        .unwrap_or(0);
+    format!("marker-{}", last + 1)
+}
+
+/// The marker's clip must be in the project, and its time inside the clip
+fn check_marker(project: &Project, clip_id: &str, time: f64) -> Result<()> {
+    let clip = project
+        .clips
+        .iter()
+        .find(|clip| clip.id == clip_id)
+        .ok_or_else(|| ClipFlowError::not_found(format!("No clip {} in the project", clip_id)))?;
+    if !(0.0..=clip.duration).contains(&time) {
+        return Err(ClipFlowError::invalid(format!(
+            "Marker at {:.2}s is outside {} ({:.2}s long)",
+            time, clip.name, clip.duration
+        )));
+    }
+    Ok(())
+}
+
+/// "1h2m3s" / "12m5s" / "45s", as Twitch writes marker times
+fn parse_duration_text(text: &str) -> Option<f64> {
+    if text.is_empty() {
+        return None;
+    }
+    let mut total = 0.0;
+    let mut number = String::new();
+    for c in text.chars() {
+        match c {
+            '0'..='9' | '.' => number.push(c),
+            'h' | 'm' | 's' => {
+                let scale = match c {
+                    'h' => 3600.0,
+                    'm' => 60.0,
+                    _ => 1.0,
+                };
+                total += number.parse::<f64>().ok()? * scale;
+                number.clear();
+            }
+            _ => return None,
+        }
+    }
+    number.is_empty().then_some(total)
+}
+
+fn parse_time(text: &str) -> Option<f64> {
+    let text = text.trim().trim_matches('"');
+    chapters::parse_timestamp(text).or_else(|| parse_duration_text(text))
+}
+
+/// (time, name) of each bookmark line
+/// Lines are either CSV rows with a time column ("00:12:34,Big play") or a
+/// time followed by a name ("00:12:34 - Big play"); lines with no time,
+/// like headers, are skipped.
+fn parse_bookmarks(text: &str) -> Vec<(f64, String)> {
+    let mut bookmarks = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let fields: Vec<&str> = if line.contains(',') {
+            line.split(',').collect()
+        } else {
+            let (time, name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            vec![time, name]
+        };
+        let time_index = match fields.iter().position(|field| parse_time(field).is_some()) {
+            Some(index) => index,
+            None => continue,
+        };
+        let name = fields
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != time_index)
+            .map(|(_, field)| field.trim().trim_matches('"').trim_start_matches(['-', '–', ':', ' ']))
+            .find(|field| !field.is_empty() && parse_time(field).is_none())
+            .unwrap_or_default();
+        bookmarks.push((parse_time(fields[time_index]).unwrap_or(0.0), name.to_string()));
+    }
+    bookmarks
+}
+
+/// Markers in the project, in clip order and then by time
+#[tauri::command]
+pub async fn list_markers(project_path: &str) -> Result<Vec<Marker>> {
+    let project = load(project_path)?;
+    let clip_order = |marker: &Marker| project.clips.iter().position(|clip| clip.id == marker.clip_id);
+    let mut markers = project.markers.clone();
+    markers.sort_by(|a, b| clip_order(a).cmp(&clip_order(b)).then(a.time.total_cmp(&b.time)));
+    Ok(markers)
+}
+
+/// Add a marker at `time` seconds into clip `clip_id`
+#[tauri::command]
+pub async fn add_marker(
+    project_path: &str,
+    clip_id: String,
+    time: f64,
+    name: Option<String>,
+    color: Option<MarkerColor>,
+) -> Result<Marker> {
+    let mut project = load(project_path)?;
+    check_marker(&project, &clip_id, time)?;
+    let marker = Marker {
+        id: next_id(&project),
+        name: name.unwrap_or_else(|| format!("Marker {}", project.markers.len() + 1)),
+        color: color.unwrap_or_default(),
+        clip_id,
+        time,
+    };
+    project.markers.push(marker.clone());
+    save(project_path, &project)?;
+    Ok(marker)
+}
+
+/// Replace the marker with the same id
+#[tauri::command]
+pub async fn update_marker(project_path: &str, marker: Marker) -> Result<Marker> {
+    let mut project = load(project_path)?;
+    check_marker(&project, &marker.clip_id, marker.time)?;
+    let existing = project
+        .markers
+        .iter_mut()
+        .find(|m| m.id == marker.id)
+        .ok_or_else(|| ClipFlowError::not_found(format!("No marker {}", marker.id)))?;
+    *existing = marker.clone();
+    save(project_path, &project)?;
+    Ok(marker)
+}
+
+#[tauri::command]
+pub async fn delete_marker(project_path: &str, id: &str) -> Result<()> {
+    let mut project = load(project_path)?;
+    let before = project.markers.len();
+    project.markers.retain(|marker| marker.id != id);
+    if project.markers.len() == before {
+        return Err(ClipFlowError::not_found(format!("No marker {}", id)));
+    }
+    save(project_path, &project)
+}
+
+/// Write the markers as a YouTube chapter list of the edit
+/// Marker times are moved to where they play after cuts; markers inside
+/// cut ranges are left out. Returns the list.
+#[tauri::command]
+pub async fn export_marker_chapters(project_path: &str, output_path: &str) -> Result<String> {
+    let project = load(project_path)?;
+    let chapters: Vec<Chapter> = project
+        .markers
+        .iter()
+        .filter_map(|marker| {
+            let start = interchange::edit_time(&project, &marker.clip_id, marker.time)?;
+            Some(Chapter { title: marker.name.clone(), start, end: None })
+        })
+        .collect();
+    chapters::export_chapter_list(chapters, output_path).await
+}
+
+/// Add a marker for each bookmark in an OBS or Twitch bookmark file
+/// Times are taken as seconds into clip `clip_id`, the recording the
+/// bookmarks were made on; ones past its end are skipped. Returns the
+/// markers added.
+#[tauri::command]
+pub async fn import_marker_file(project_path: &str, clip_id: String, path: &str) -> Result<Vec<Marker>> {
+    let text = fs::read_to_string(path).map_err(|e| ClipFlowError::io("Failed to read bookmark file", e))?;
+    let bookmarks = parse_bookmarks(&text);
+    if bookmarks.is_empty() {
+        return Err(ClipFlowError::invalid("No bookmarks with a time found in the file"));
+    }
+
+    let mut project = load(project_path)?;
+    check_marker(&project, &clip_id, 0.0)?;
+    let mut added = Vec::new();
+    for (time, name) in bookmarks {
+        if check_marker(&project, &clip_id, time).is_err() {
+            continue;
+        }
+        let marker = Marker {
+            id: next_id(&project),
+            name: if name.is_empty() { format!("Marker {}", project.markers.len() + 1) } else { name },
+            color: MarkerColor::default(),
+            clip_id: clip_id.clone(),
+            time,
+        };
+        project.markers.push(marker.clone());
+        added.push(marker);
+    }
+    if added.is_empty() {
+        return Err(ClipFlowError::invalid("Every bookmark is past the end of the clip"));
+    }
+    save(project_path, &project)?;
+    Ok(added)
+}
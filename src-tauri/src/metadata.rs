@@ -0,0 +1,220 @@
+//! Container metadata: global tags and chapter markers
+//!
+//! Both are handed to ffmpeg as an FFMETADATA file mapped in with
+//! `-map_metadata`, which every muxer we write understands: MP4/M4A store
+//! chapters as a chapter track, MP3 as ID3 CHAP frames, Ogg as Vorbis
+//! comments, and MKV natively.
+//!
+//! Tags on an existing file are edited with `-metadata` over a stream copy.
+
+use crate::error::{ClipFlowError, Result};
+use crate::output::{self, OverwritePolicy};
+use crate::temp::TempFile;
+use crate::{probe, process};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Chapter {
+    pub title: String,
+    /// Seconds
+    pub start: f64,
+    /// Seconds; defaults to the next chapter's start, or the end of the file
+    pub end: Option<f64>,
+}
+
+/// Chapters sorted by start with every end filled in, clamped to `duration`
+pub fn normalize_chapters(chapters: &[Chapter], duration: f64) -> Result<Vec<Chapter>> {
+    let mut sorted = chapters.to_vec();
+    sorted.sort_by(|a, b| a.start.total_cmp(&b.start));
+    let starts: Vec<f64> = sorted.iter().map(|c| c.start).collect();
+
+    let mut normalized = Vec::with_capacity(sorted.len());
+    for (i, chapter) in sorted.into_iter().enumerate() {
+        if chapter.title.trim().is_empty() {
+            return Err(ClipFlowError::invalid(format!("Chapter at {:.2}s has no title", chapter.start)));
+        }
+        if chapter.start < 0.0 || chapter.start >= duration {
+            return Err(ClipFlowError::invalid(format!(
+                "Chapter \"{}\" starts at {:.2}s, outside the {:.2}s file",
+                chapter.title, chapter.start, duration
+            )));
+        }
+        let next = starts.get(i + 1).copied().unwrap_or(duration);
+        let end = chapter.end.unwrap_or(next).min(duration);
+        if end <= chapter.start {
+            return Err(ClipFlowError::invalid(format!("Chapter \"{}\" is empty", chapter.title)));
+        }
+        normalized.push(Chapter { end: Some(end), ..chapter });
+    }
+    Ok(normalized)
+}
+
+/// Escape a value for an FFMETADATA file, where `=`, `;`, `#`, `\` and
+/// newlines are special
+fn escape_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// FFMETADATA text for `tags` and already-normalized `chapters`
+pub fn ffmetadata(tags: &BTreeMap<String, String>, chapters: &[Chapter]) -> String {
+    let mut text = String::from(";FFMETADATA1\n");
+    for (key, value) in tags {
+        text.push_str(&format!("{}={}\n", escape_value(key), escape_value(value)));
+    }
+    for chapter in chapters {
+        // Millisecond timebase keeps the numbers integral
+        let start = (chapter.start * 1000.0).round() as u64;
+        let end = (chapter.end.unwrap_or(chapter.start) * 1000.0).round() as u64;
+        text.push_str(&format!(
+            "[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            start,
+            end,
+            escape_value(&chapter.title)
+        ));
+    }
+    text
+}
+
+/// Write an FFMETADATA file for ffmpeg to read with `-i <file>`
+/// `-map_metadata <index>` then applies it to the output.
+pub fn write_ffmetadata(tags: &BTreeMap<String, String>, chapters: &[Chapter]) -> Result<TempFile> {
+    let file = TempFile::new("ffmetadata", "txt")?;
+    fs::write(file.path(), ffmetadata(tags, chapters)).map_err(|e| ClipFlowError::io("Failed to write metadata file", e))?;
+    Ok(file)
+}
+
+#[derive(Serialize)]
+pub struct MediaMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub comment: Option<String>,
+    /// As stored, usually ISO 8601 UTC
+    pub creation_date: Option<String>,
+    /// Every container-level tag, including the ones above
+    pub tags: BTreeMap<String, String>,
+    pub chapters: Vec<Chapter>,
+}
+
+/// Normalize a date to the ISO 8601 UTC form ffmpeg writes as creation_time
+/// Accepts RFC 3339 timestamps and plain "YYYY-MM-DD" dates (midnight UTC).
+fn creation_time(date: &str) -> Result<String> {
+    let parsed = DateTime::parse_from_rfc3339(date.trim())
+        .map(|d| d.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map(|d| d.and_time(NaiveTime::MIN).and_utc())
+        })
+        .map_err(|_| ClipFlowError::invalid(format!("Unrecognized date \"{}\" - use YYYY-MM-DD or RFC 3339", date)))?;
+    Ok(parsed.format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string())
+}
+
+/// Container tags and chapters of a file
+/// Tag keys are lowercased, since MP4, MKV, and ID3 disagree on case.
+#[tauri::command]
+pub async fn get_metadata(input_path: &str) -> Result<MediaMetadata> {
+    let json = probe::ffprobe_json(input_path)?;
+    let tags: BTreeMap<String, String> = json["format"]["tags"]
+        .as_object()
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|(key, value)| Some((key.to_lowercase(), value.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+    let chapters = json["chapters"]
+        .as_array()
+        .map(|chapters| {
+            chapters
+                .iter()
+                .filter_map(|c| {
+                    Some(Chapter {
+                        title: c["tags"]["title"].as_str().unwrap_or_default().to_string(),
+                        start: c["start_time"].as_str()?.parse().ok()?,
+                        end: c["end_time"].as_str().and_then(|e| e.parse().ok()),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let tag = |key: &str| tags.get(key).cloned();
+    Ok(MediaMetadata {
+        title: tag("title"),
+        artist: tag("artist"),
+        comment: tag("comment"),
+        creation_date: tag("creation_time").or_else(|| tag("date")),
+        chapters,
+        tags,
+    })
+}
+
+/// Copy `input_path` to `output_path` with its container tags changed;
+/// streams are copied untouched
+///
+/// Unset fields keep their current value and an empty string removes the
+/// tag. Returns the path written.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn set_metadata(
+    input_path: &str,
+    output_path: &str,
+    title: Option<String>,
+    artist: Option<String>,
+    comment: Option<String>,
+    creation_date: Option<String>,
+    custom_tags: Option<BTreeMap<String, String>>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let mut tags: BTreeMap<String, String> = BTreeMap::new();
+    for (key, value) in [("title", title), ("artist", artist), ("comment", comment)] {
+        if let Some(value) = value {
+            tags.insert(key.to_string(), value);
+        }
+    }
+    if let Some(date) = creation_date {
+        let value = if date.trim().is_empty() { String::new() } else { creation_time(&date)? };
+        tags.insert("creation_time".to_string(), value);
+    }
+    let custom_tags = custom_tags.unwrap_or_default();
+    if let Some(key) = custom_tags.keys().find(|k| k.trim().is_empty() || k.contains('=')) {
+        return Err(ClipFlowError::invalid(format!("Invalid tag name \"{}\"", key)));
+    }
+    let has_custom = !custom_tags.is_empty();
+    tags.extend(custom_tags);
+    if tags.is_empty() {
+        return Err(ClipFlowError::invalid("No metadata to set"));
+    }
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-map".to_string(), "0".to_string(),
+        "-map_metadata".to_string(), "0".to_string(),
+        "-c".to_string(), "copy".to_string(),
+    ];
+    for (key, value) in &tags {
+        args.extend(["-metadata".to_string(), format!("{}={}", key, value)]);
+    }
+    // The MP4 muxer drops tags it has no atom for unless told otherwise
+    let ext = Path::new(output_path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if has_custom && matches!(ext.as_str(), "mp4" | "m4v" | "m4a" | "mov") {
+        args.extend(["-movflags".to_string(), "use_metadata_tags".to_string()]);
+    }
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.extend([partial, "-y".to_string()]);
+        process::run_ffmpeg_async(&args, "metadata update").await
+    })
+    .await
+}
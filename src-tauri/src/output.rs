@@ -0,0 +1,137 @@
+//! Safe handling of render outputs
+//!
+//! Renders write to a hidden partial file beside the destination and are
+//! renamed into place only when they finish, so a failed or interrupted
+//! render never leaves a truncated file under the real name, and an existing
+//! file is only replaced by a complete one.
+//!
+//! While a render is only being planned, the render is handed the final
+//! path (so the plan shows it) and nothing is moved.
+
+use crate::error::{ClipFlowError, Result};
+use crate::process::PlanRecorder;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Marker in partial file names, so leftovers from a crash are recognizable
+const PARTIAL_MARKER: &str = "clipflow-partial";
+
+/// Numbers partial files, so two renders to one destination don't share one
+static PARTIAL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// What to do when the output file already exists
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum OverwritePolicy {
+    /// Refuse to render
+    Fail,
+    /// Replace the existing file once the new one is complete
+    #[default]
+    Overwrite,
+    /// Write "name (1).mp4", "name (2).mp4", ... instead
+    AutoIncrement,
+}
+
+/// Final output path under `policy`
+pub fn resolve_output_path(output_path: &str, policy: OverwritePolicy) -> Result<PathBuf> {
+    let path = PathBuf::from(output_path);
+    if !path.exists() {
+        return Ok(path);
+    }
+    match policy {
+        OverwritePolicy::Overwrite => Ok(path),
+        OverwritePolicy::Fail => Err(ClipFlowError::invalid(format!("{} already exists", output_path))),
+        OverwritePolicy::AutoIncrement => {
+            let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+            let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+            (1..10_000)
+                .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+                .find(|candidate| !candidate.exists())
+                .ok_or_else(|| ClipFlowError::invalid(format!("No free file name next to {}", output_path)))
+        }
+    }
+}
+
+/// A render target that is moved to its destination on `commit` and
+/// deleted if dropped before then
+pub struct PartialOutput {
+    partial: PathBuf,
+    destination: PathBuf,
+    committed: bool,
+}
+
+impl PartialOutput {
+    /// The partial keeps the destination's extension so ffmpeg still picks
+    /// the right muxer from it
+    pub fn new(destination: &Path) -> PartialOutput {
+        let stem = destination.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let extension = destination.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+        let partial = destination.with_file_name(format!(
+            ".{}.{}-{}-{}{}",
+            stem,
+            PARTIAL_MARKER,
+            std::process::id(),
+            PARTIAL_COUNTER.fetch_add(1, Ordering::Relaxed),
+            extension
+        ));
+        PartialOutput { partial, destination: destination.to_path_buf(), committed: false }
+    }
+
+    pub fn path_str(&self) -> String {
+        self.partial.to_string_lossy().into_owned()
+    }
+
+    /// Move the finished render to its destination, replacing any file there
+    pub fn commit(mut self) -> Result<PathBuf> {
+        fs::rename(&self.partial, &self.destination)
+            .map_err(|e| ClipFlowError::io(&format!("Failed to move render into place at {}", self.destination.display()), e))?;
+        self.committed = true;
+        Ok(self.destination.clone())
+    }
+}
+
+impl Drop for PartialOutput {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.partial);
+        }
+    }
+}
+
+/// Run `render` against a partial file and move it to `output_path` (as
+/// adjusted by `policy`) only if it succeeds
+/// Returns the path actually written
+pub fn write_atomically(
+    output_path: &str,
+    policy: OverwritePolicy,
+    render: impl FnOnce(&str) -> Result<()>,
+) -> Result<String> {
+    let destination = resolve_output_path(output_path, policy)?;
+    if PlanRecorder::current().is_some() {
+        render(&destination.to_string_lossy())?;
+        return Ok(destination.to_string_lossy().into_owned());
+    }
+    let partial = PartialOutput::new(&destination);
+    render(&partial.path_str())?;
+    Ok(partial.commit()?.to_string_lossy().into_owned())
+}
+
+/// `write_atomically` for renders run on the async runtime
+/// Dropping the returned future mid-render deletes the partial file.
+pub async fn write_atomically_async<F, Fut>(output_path: &str, policy: OverwritePolicy, render: F) -> Result<String>
+where
+    F: FnOnce(String) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let destination = resolve_output_path(output_path, policy)?;
+    if PlanRecorder::current().is_some() {
+        render(destination.to_string_lossy().into_owned()).await?;
+        return Ok(destination.to_string_lossy().into_owned());
+    }
+    let partial = PartialOutput::new(&destination);
+    render(partial.path_str()).await?;
+    Ok(partial.commit()?.to_string_lossy().into_owned())
+}
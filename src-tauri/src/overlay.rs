@@ -0,0 +1,244 @@
+//! Image, logo, and text overlays composited onto video
+
+use crate::error::{ClipFlowError, Result};
+use crate::output::{self, OverwritePolicy};
+use crate::temp::TempFile;
+use crate::{color, escape_filter_path, probe, process};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Gap between an overlay and the frame edge, in pixels
+const OVERLAY_MARGIN: u32 = 24;
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverlayPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+impl OverlayPosition {
+    /// overlay filter x:y expressions for this corner
+    pub fn expressions(&self) -> (String, String) {
+        let m = OVERLAY_MARGIN;
+        match self {
+            OverlayPosition::TopLeft => (format!("{}", m), format!("{}", m)),
+            OverlayPosition::TopRight => (format!("W-w-{}", m), format!("{}", m)),
+            OverlayPosition::BottomLeft => (format!("{}", m), format!("H-h-{}", m)),
+            OverlayPosition::BottomRight => (format!("W-w-{}", m), format!("H-h-{}", m)),
+            OverlayPosition::Center => ("(W-w)/2".to_string(), "(H-h)/2".to_string()),
+        }
+    }
+
+    /// drawtext x:y expressions for this corner
+    pub fn text_expressions(&self) -> (String, String) {
+        let (x, y) = self.expressions();
+        let text = |e: String| e.replace("W-w", "w-text_w").replace("H-h", "h-text_h");
+        (text(x), text(y))
+    }
+}
+
+/// A line or block of text shown over the video
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TextOverlay {
+    /// Line breaks are kept
+    pub text: String,
+    /// Path to a font file, or a font family name looked up by fontconfig;
+    /// ffmpeg's default font if unset
+    pub font: Option<String>,
+    /// Font size as a fraction of the frame height, default 0.05
+    pub size: Option<f64>,
+    /// Any color ffmpeg accepts, e.g. "white" or "#ffcc00"; white if unset
+    pub color: Option<String>,
+    /// Background box color, with optional opacity (e.g. "black@0.6"); no
+    /// box if unset
+    #[serde(rename = "box")]
+    pub box_color: Option<String>,
+    pub position: OverlayPosition,
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+}
+
+/// Color names, hex values, and an @opacity suffix; anything else could
+/// break out of the filter options
+fn check_color(color: &str) -> Result<()> {
+    let valid = !color.is_empty() && color.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '#' | '@' | '.'));
+    if !valid {
+        return Err(ClipFlowError::invalid(format!("Invalid color: {}", color)));
+    }
+    Ok(())
+}
+
+/// enable= expression limiting a filter to [start, end], or None for always
+pub fn enable_between(start: Option<f64>, end: Option<f64>) -> Option<String> {
+    match (start, end) {
+        (None, None) => None,
+        (Some(s), None) => Some(format!("gte(t,{})", s)),
+        (None, Some(e)) => Some(format!("lte(t,{})", e)),
+        (Some(s), Some(e)) => Some(format!("between(t,{},{})", s, e)),
+    }
+}
+
+/// Composite a watermark/logo image over the video
+///
+/// `scale` is the logo width as a fraction of the video width, `opacity`
+/// runs 0-1, and `start`/`end` (seconds) limit when it's shown.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn overlay_image(
+    input_path: &str,
+    output_path: &str,
+    image_path: &str,
+    position: OverlayPosition,
+    opacity: f64,
+    scale: f64,
+    start: Option<f64>,
+    end: Option<f64>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    if !(0.0..=1.0).contains(&opacity) {
+        return Err(ClipFlowError::invalid(format!("Opacity must be between 0 and 1, got {}", opacity)));
+    }
+    if !(0.01..=1.0).contains(&scale) {
+        return Err(ClipFlowError::invalid(format!("Scale must be between 0.01 and 1, got {}", scale)));
+    }
+    if let (Some(s), Some(e)) = (start, end) {
+        if e <= s {
+            return Err(ClipFlowError::invalid(format!("Overlay end ({}) must be after start ({})", e, s)));
+        }
+    }
+
+    let geometry = probe::video_geometry(input_path)?;
+    // Even width keeps yuv420 happy; height follows the logo's own aspect
+    let logo_width = ((geometry.display_width as f64 * scale / 2.0).round() * 2.0).max(2.0) as u32;
+
+    let (color_filter, color_tags) = color::export_color_args(input_path);
+    let base_filters: Vec<String> = probe::square_pixel_filter(&geometry)
+        .into_iter()
+        .chain(color_filter)
+        .collect();
+    let base = if base_filters.is_empty() {
+        "[0:v]null[base]".to_string()
+    } else {
+        format!("[0:v]{}[base]", base_filters.join(","))
+    };
+
+    let (x, y) = position.expressions();
+    let enable = enable_between(start, end)
+        .map(|e| format!(":enable='{}'", e))
+        .unwrap_or_default();
+
+    let graph = format!(
+        "{};[1:v]scale={}:-1,format=rgba,colorchannelmixer=aa={}[logo];[base][logo]overlay={}:{}{}[outv]",
+        base, logo_width, opacity, x, y, enable
+    );
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-i".to_string(), image_path.to_string(),
+        "-filter_complex".to_string(), graph,
+        "-map".to_string(), "[outv]".to_string(),
+        "-map".to_string(), "0:a?".to_string(),
+        "-c:a".to_string(), "copy".to_string(),
+    ];
+    args.extend(color_tags);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "overlay").await
+    })
+    .await
+}
+
+/// drawtext filter for one text item
+/// The text goes through `text_file`, which sidesteps escaping quotes,
+/// colons, and line breaks, and `%` is left unexpanded.
+pub fn drawtext_filter(item: &TextOverlay, text_file: &TempFile, frame_height: u32) -> Result<String> {
+    let size = item.size.unwrap_or(0.05);
+    if size.is_nan() || !(0.01..=0.5).contains(&size) {
+        return Err(ClipFlowError::invalid(format!("Text size must be between 0.01 and 0.5, got {}", size)));
+    }
+    let font_color = item.color.as_deref().unwrap_or("white");
+    check_color(font_color)?;
+    let font_size = (frame_height as f64 * size).round().max(8.0) as u32;
+    let (x, y) = item.position.text_expressions();
+
+    let mut options = vec![
+        format!("textfile='{}'", escape_filter_path(&text_file.path_str())),
+        "expansion=none".to_string(),
+        format!("fontcolor={}", font_color),
+        format!("fontsize={}", font_size),
+        format!("line_spacing={}", font_size / 4),
+    ];
+    match item.font.as_deref() {
+        Some(font) if Path::new(font).is_file() => options.push(format!("fontfile='{}'", escape_filter_path(font))),
+        Some(font) => options.push(format!("font='{}'", escape_filter_path(font))),
+        None => {}
+    }
+    if let Some(box_color) = &item.box_color {
+        check_color(box_color)?;
+        options.push(format!("box=1:boxcolor={}:boxborderw={}", box_color, font_size / 3));
+    }
+    options.push(format!("x={}:y={}", x, y));
+    if let Some(enable) = enable_between(item.start, item.end) {
+        options.push(format!("enable='{}'", enable));
+    }
+    Ok(format!("drawtext={}", options.join(":")))
+}
+
+/// Draw titles, captions, or lower thirds over the video
+/// Each item has its own style, position, and time range. Audio is copied.
+#[tauri::command]
+pub async fn overlay_text(
+    input_path: &str,
+    output_path: &str,
+    items: Vec<TextOverlay>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    if items.is_empty() {
+        return Err(ClipFlowError::invalid("No text to overlay"));
+    }
+    for item in &items {
+        if item.text.trim().is_empty() {
+            return Err(ClipFlowError::invalid("Overlay text must not be empty"));
+        }
+        if let (Some(s), Some(e)) = (item.start, item.end) {
+            if e <= s {
+                return Err(ClipFlowError::invalid(format!("Overlay end ({}) must be after start ({})", e, s)));
+            }
+        }
+    }
+
+    let geometry = probe::video_geometry(input_path)?;
+    let (color_filter, color_tags) = color::export_color_args(input_path);
+    let mut filters: Vec<String> = probe::square_pixel_filter(&geometry).into_iter().chain(color_filter).collect();
+    // Kept alive until the render is done
+    let mut text_files = Vec::new();
+    for item in &items {
+        let text_file = TempFile::new("overlay-text", "txt")?;
+        fs::write(text_file.path(), &item.text).map_err(|e| ClipFlowError::io("Failed to write overlay text", e))?;
+        filters.push(drawtext_filter(item, &text_file, geometry.display_height)?);
+        text_files.push(text_file);
+    }
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-vf".to_string(), filters.join(","),
+        "-map".to_string(), "0:v:0".to_string(),
+        "-map".to_string(), "0:a?".to_string(),
+        "-c:a".to_string(), "copy".to_string(),
+    ];
+    args.extend(color_tags);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "text overlay").await
+    })
+    .await
+}
@@ -0,0 +1,256 @@
+//! Dry runs: the exact ffmpeg commands a render would run, without running
+//! them
+//!
+//! `plan_render` takes the name and arguments of a render command, exactly
+//! as they'd be passed to invoke, and runs the command with ffmpeg renders
+//! recorded instead of executed (see `process::PlanRecorder`). Validation,
+//! probing, and analysis passes still happen, so a plan fails for the same
+//! reasons the render would. Nothing is written to the output path (or the
+//! output folder of an image sequence or a set of Shorts).
+
+use crate::censor::{CensorMode, CensorRange};
+use crate::color::ToneMapping;
+use crate::compose::{SplitAudio, SplitLayout};
+use crate::deinterlace::DeinterlaceMode;
+use crate::error::{ClipFlowError, Result};
+use crate::filtergraph::ChainSpec;
+use crate::frames::{ImageFormat, ImageSource};
+use crate::intermediate::IntermediateFormat;
+use crate::karaoke::KaraokeStyle;
+use crate::metadata::Chapter;
+use crate::output::OverwritePolicy;
+use crate::overlay::{OverlayPosition, TextOverlay};
+use crate::podcast::{PodcastFormat, PodcastMetadata};
+use crate::presets::Container;
+use crate::process::PlanRecorder;
+use crate::project::Transcript;
+use crate::proxy::ProxyFormat;
+use crate::speed::SpeedSegment;
+use crate::timeline::Timeline;
+use crate::transform::{CropRect, FitMode, ScalingAlgorithm};
+use crate::{audio, censor, chapters, color, compose, deinterlace, filtergraph, frames, grading, intermediate, karaoke};
+use crate::{metadata, overlay, podcast, presets, probe, proxy, remux, repair, shorts, slate, speed, timeline};
+use crate::{transform, transitions};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tauri::AppHandle;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PlannedCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Quoted for a POSIX shell, ready to paste
+    pub command_line: String,
+}
+
+/// What the last command will produce, read from its arguments, falling back
+/// to the first input where they don't say (filters that resize or retime
+/// aren't taken into account)
+#[derive(Serialize, Clone, Debug)]
+pub struct OutputEstimate {
+    pub output_path: String,
+    pub container: Option<String>,
+    /// None when ffmpeg picks the codec from the container
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub duration: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<f64>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RenderPlan {
+    /// In the order the render runs them
+    pub commands: Vec<PlannedCommand>,
+    pub estimate: Option<OutputEstimate>,
+}
+
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c));
+    match plain {
+        true => arg.to_string(),
+        false => format!("'{}'", arg.replace('\'', "'\\''")),
+    }
+}
+
+/// Value following the last occurrence of any of `flags`
+fn flag_value<'a>(args: &'a [String], flags: &[&str]) -> Option<&'a str> {
+    args.windows(2).rev().find(|pair| flags.contains(&pair[0].as_str())).map(|pair| pair[1].as_str())
+}
+
+fn estimate(args: &[String]) -> Option<OutputEstimate> {
+    let output_path = args.iter().rev().find(|a| a.as_str() != "-y")?.clone();
+    let input_at = args.iter().position(|a| a == "-i");
+    let first_input = input_at.and_then(|i| args.get(i + 1)).map(String::as_str);
+    let seek: f64 = input_at.and_then(|i| flag_value(&args[..i], &["-ss"])).and_then(|v| v.parse().ok()).unwrap_or(0.0);
+    let duration = flag_value(args, &["-t"])
+        .and_then(|v| v.parse().ok())
+        .or_else(|| first_input.and_then(|i| probe::media_duration(i).ok()).map(|d| (d - seek).max(0.0)));
+    let geometry = first_input.and_then(|i| probe::video_geometry(i).ok());
+    let size = flag_value(args, &["-s"]).and_then(|s| {
+        let (w, h) = s.split_once('x')?;
+        Some((w.parse().ok()?, h.parse().ok()?))
+    });
+    let fps = flag_value(args, &["-r"])
+        .and_then(|v| v.parse().ok())
+        .or_else(|| first_input.and_then(|i| probe::frame_rate(i).ok()));
+    Some(OutputEstimate {
+        container: Path::new(&output_path).extension().map(|e| e.to_string_lossy().to_ascii_lowercase()),
+        video_codec: flag_value(args, &["-c:v", "-vcodec"]).map(str::to_string),
+        audio_codec: flag_value(args, &["-c:a", "-acodec"]).map(str::to_string),
+        duration,
+        width: size.map(|(w, _)| w).or(geometry.as_ref().map(|g| g.display_width)),
+        height: size.map(|(_, h)| h).or(geometry.as_ref().map(|g| g.display_height)),
+        fps,
+        output_path,
+    })
+}
+
+fn parse<T: DeserializeOwned>(args: serde_json::Value) -> Result<T> {
+    serde_json::from_value(args).map_err(|e| ClipFlowError::invalid(format!("Invalid arguments: {}", e)))
+}
+
+/// One match arm per plannable command: its arguments as invoke passes
+/// them (camelCase keys), and how to call it
+macro_rules! plannable {
+    ($command:expr, $args:expr, { $($name:literal ($($param:ident: $ty:ty),*) => $call:expr,)* }) => {
+        match $command {
+            $($name => {
+                #[derive(Deserialize)]
+                #[serde(rename_all = "camelCase")]
+                struct Args {
+                    $($param: $ty,)*
+                }
+                let Args { $($param,)* } = parse($args)?;
+                $call.await.map(|_| ())
+            })*
+            other => Err(ClipFlowError::invalid(format!("{} can't be planned", other))),
+        }
+    };
+}
+
+/// The ffmpeg commands `command` would run with `args`, without running them
+/// `args` are the command's arguments as passed to invoke.
+#[tauri::command]
+pub async fn plan_render(app: AppHandle, command: String, args: serde_json::Value) -> Result<RenderPlan> {
+    let recorder = PlanRecorder::default();
+    recorder
+        .record(async {
+            plannable!(command.as_str(), args, {
+                "trim_video" (input_path: String, output_path: String, start_time: f64, end_time: f64, overwrite: Option<OverwritePolicy>, extra_args: Option<Vec<String>>)
+                    => crate::trim_video(&input_path, &output_path, start_time, end_time, overwrite, extra_args),
+                "cut_video_remove" (input_path: String, output_path: String, segments: Vec<crate::CutSegment>, room_tone: Option<crate::RoomToneFill>, overwrite: Option<OverwritePolicy>)
+                    => crate::cut_video_remove(&input_path, &output_path, segments, room_tone, overwrite),
+                "extract_audio" (input_path: String, output_path: String, format: String, overwrite: Option<OverwritePolicy>)
+                    => crate::extract_audio(&input_path, &output_path, &format, overwrite),
+                "export_video" (input_path: String, output_path: String, quality: String, comments: Option<Vec<crate::ReviewComment>>, comment_mode: Option<String>, audio_tracks: Option<Vec<crate::ExportAudioTrack>>, voice_polish: Option<audio::VoicePolish>, overwrite: Option<OverwritePolicy>, rate_control: Option<presets::RateControl>, extra_args: Option<Vec<String>>)
+                    => crate::export_video(app.clone(), &input_path, &output_path, &quality, comments, comment_mode.as_deref(), audio_tracks, voice_polish, overwrite, rate_control, extra_args),
+                "fade_audio" (input_path: String, output_path: String, fade_in_s: f64, fade_out_s: f64, overwrite: Option<OverwritePolicy>)
+                    => audio::fade_audio(&input_path, &output_path, fade_in_s, fade_out_s, overwrite),
+                "replace_audio" (video_input: String, audio_input: String, output_path: String, offset: f64, overwrite: Option<OverwritePolicy>)
+                    => audio::replace_audio(&video_input, &audio_input, &output_path, offset, overwrite),
+                "add_audio_track" (video_input: String, audio_input: String, output_path: String, offset: f64, title: Option<String>, language: Option<String>, overwrite: Option<OverwritePolicy>)
+                    => audio::add_audio_track(&video_input, &audio_input, &output_path, offset, title, language, overwrite),
+                "mix_music" (input_path: String, music_path: String, output_path: String, music_gain_db: f64, duck: bool, overwrite: Option<OverwritePolicy>)
+                    => audio::mix_music(&input_path, &music_path, &output_path, music_gain_db, duck, overwrite),
+                "downmix_to_mono" (input_path: String, output_path: String, audio_track: Option<u32>, overwrite: Option<OverwritePolicy>)
+                    => audio::downmix_to_mono(&input_path, &output_path, audio_track, overwrite),
+                "split_stereo" (input_path: String, left_output: String, right_output: String, audio_track: Option<u32>, overwrite: Option<OverwritePolicy>)
+                    => audio::split_stereo(&input_path, &left_output, &right_output, audio_track, overwrite),
+                "swap_channels" (input_path: String, output_path: String, audio_track: Option<u32>, overwrite: Option<OverwritePolicy>)
+                    => audio::swap_channels(&input_path, &output_path, audio_track, overwrite),
+                "select_audio_track" (input_path: String, output_path: String, audio_track: u32, overwrite: Option<OverwritePolicy>)
+                    => audio::select_audio_track(&input_path, &output_path, audio_track, overwrite),
+                "censor_audio" (input_path: String, output_path: String, ranges: Vec<CensorRange>, mode: CensorMode, overwrite: Option<OverwritePolicy>)
+                    => censor::censor_audio(&input_path, &output_path, ranges, mode, overwrite),
+                "embed_chapters" (input_path: String, output_path: String, chapters: Vec<Chapter>, overwrite: Option<OverwritePolicy>)
+                    => chapters::embed_chapters(&input_path, &output_path, chapters, overwrite),
+                "tonemap_video" (input_path: String, output_path: String, mapping: Option<ToneMapping>, overwrite: Option<OverwritePolicy>)
+                    => color::tonemap_video(&input_path, &output_path, mapping, overwrite),
+                "compose_split" (inputs: Vec<String>, output_path: String, layout: SplitLayout, audio: Option<SplitAudio>, width: Option<u32>, height: Option<u32>, overwrite: Option<OverwritePolicy>)
+                    => compose::compose_split(inputs, &output_path, layout, audio, width, height, overwrite),
+                "deinterlace" (input_path: String, output_path: String, mode: DeinterlaceMode, double_rate: Option<bool>, overwrite: Option<OverwritePolicy>)
+                    => deinterlace::deinterlace(&input_path, &output_path, mode, double_rate, overwrite),
+                "run_custom_filtergraph" (inputs: Vec<String>, chains: Vec<ChainSpec>, maps: Vec<String>, output_path: String, preset: Option<String>, overwrite: Option<OverwritePolicy>)
+                    => filtergraph::run_custom_filtergraph(app.clone(), inputs, chains, maps, &output_path, preset, overwrite),
+                "extract_frame" (input_path: String, timestamp: f64, output_path: String, format: ImageFormat, overwrite: Option<OverwritePolicy>)
+                    => frames::extract_frame(&input_path, timestamp, &output_path, format, overwrite),
+                "export_image_sequence" (input_path: String, output_dir: String, fps: Option<f64>, format: ImageFormat)
+                    => frames::export_image_sequence(&input_path, &output_dir, fps, format),
+                "images_to_video" (images: ImageSource, output_path: String, fps: f64, overwrite: Option<OverwritePolicy>)
+                    => frames::images_to_video(images, &output_path, fps, overwrite),
+                "image_plus_audio" (image_path: String, audio_path: String, output_path: String, overwrite: Option<OverwritePolicy>)
+                    => frames::image_plus_audio(&image_path, &audio_path, &output_path, overwrite),
+                "adjust_color" (input_path: String, output_path: String, brightness: Option<f64>, contrast: Option<f64>, saturation: Option<f64>, gamma: Option<f64>, temperature: Option<f64>, overwrite: Option<OverwritePolicy>)
+                    => grading::adjust_color(&input_path, &output_path, brightness, contrast, saturation, gamma, temperature, overwrite),
+                "apply_lut" (input_path: String, output_path: String, cube_path: String, intensity: Option<f64>, overwrite: Option<OverwritePolicy>)
+                    => grading::apply_lut(&input_path, &output_path, &cube_path, intensity, overwrite),
+                "export_intermediate" (input_path: String, output_path: String, format: IntermediateFormat, overwrite: Option<OverwritePolicy>)
+                    => intermediate::export_intermediate(&input_path, &output_path, format, overwrite),
+                "burn_karaoke_captions" (input_path: String, output_path: String, style: KaraokeStyle, transcript: Option<Transcript>, max_chars_per_line: Option<usize>, overwrite: Option<OverwritePolicy>)
+                    => karaoke::burn_karaoke_captions(app.clone(), &input_path, &output_path, style, transcript, max_chars_per_line, overwrite),
+                "set_metadata" (input_path: String, output_path: String, title: Option<String>, artist: Option<String>, comment: Option<String>, creation_date: Option<String>, custom_tags: Option<BTreeMap<String, String>>, overwrite: Option<OverwritePolicy>)
+                    => metadata::set_metadata(&input_path, &output_path, title, artist, comment, creation_date, custom_tags, overwrite),
+                "overlay_image" (input_path: String, output_path: String, image_path: String, position: OverlayPosition, opacity: f64, scale: f64, start: Option<f64>, end: Option<f64>, overwrite: Option<OverwritePolicy>)
+                    => overlay::overlay_image(&input_path, &output_path, &image_path, position, opacity, scale, start, end, overwrite),
+                "overlay_text" (input_path: String, output_path: String, items: Vec<TextOverlay>, overwrite: Option<OverwritePolicy>)
+                    => overlay::overlay_text(&input_path, &output_path, items, overwrite),
+                "export_podcast" (input_path: String, output_path: String, format: PodcastFormat, chapters: Option<Vec<Chapter>>, metadata: Option<PodcastMetadata>, overwrite: Option<OverwritePolicy>)
+                    => podcast::export_podcast(&input_path, &output_path, format, chapters, metadata, overwrite),
+                "create_proxy" (input_path: String, output_path: String, format: Option<ProxyFormat>, overwrite: Option<OverwritePolicy>)
+                    => proxy::create_proxy(&input_path, &output_path, format, overwrite),
+                "remux" (input_path: String, output_container: Container, output_path: Option<String>, overwrite: Option<OverwritePolicy>)
+                    => remux::remux(&input_path, output_container, output_path, overwrite),
+                "repair_video" (input_path: String, output_path: String, overwrite: Option<OverwritePolicy>)
+                    => repair::repair_video(&input_path, &output_path, overwrite),
+                "generate_shorts" (input: String, output_dir: String, count: Option<usize>, max_duration: Option<f64>)
+                    => shorts::generate_shorts(app.clone(), &input, &output_dir, count, max_duration),
+                "prepend_slate" (input_path: String, output_path: String, slate_name: String, variables: Option<HashMap<String, String>>, overwrite: Option<OverwritePolicy>)
+                    => slate::prepend_slate(app.clone(), &input_path, &output_path, &slate_name, variables, overwrite),
+                "change_speed" (input_path: String, output_path: String, factor: f64, keep_pitch: bool, overwrite: Option<OverwritePolicy>)
+                    => speed::change_speed(&input_path, &output_path, factor, keep_pitch, overwrite),
+                "timelapse" (input_path: String, output_path: String, speed_factor: f64, smooth_blend: Option<bool>, overwrite: Option<OverwritePolicy>)
+                    => speed::timelapse(&input_path, &output_path, speed_factor, smooth_blend, overwrite),
+                "change_speed_segments" (input_path: String, output_path: String, segments: Vec<SpeedSegment>, keep_pitch: bool, overwrite: Option<OverwritePolicy>)
+                    => speed::change_speed_segments(&input_path, &output_path, segments, keep_pitch, overwrite),
+                "reverse_clip" (input_path: String, output_path: String, overwrite: Option<OverwritePolicy>)
+                    => speed::reverse_clip(&input_path, &output_path, overwrite),
+                "boomerang" (input_path: String, output_path: String, loops: Option<u32>, overwrite: Option<OverwritePolicy>)
+                    => speed::boomerang(&input_path, &output_path, loops, overwrite),
+                "interpolate_slowmo" (input_path: String, output_path: String, target_fps: f64, factor: f64, quality: Option<String>, overwrite: Option<OverwritePolicy>)
+                    => speed::interpolate_slowmo(&input_path, &output_path, target_fps, factor, quality.as_deref(), overwrite),
+                "render_timeline" (timeline: Timeline, output_path: String, preset: Option<String>, overwrite: Option<OverwritePolicy>)
+                    => timeline::render_timeline(app.clone(), timeline, &output_path, preset, overwrite),
+                "stabilize_video" (input_path: String, output_path: String, smoothness: Option<u32>, overwrite: Option<OverwritePolicy>)
+                    => transform::stabilize_video(&input_path, &output_path, smoothness, overwrite),
+                "transform_video" (input_path: String, output_path: String, crop: Option<CropRect>, rotate: Option<u32>, flip_h: bool, flip_v: bool, overwrite: Option<OverwritePolicy>)
+                    => transform::transform_video(&input_path, &output_path, crop, rotate, flip_h, flip_v, overwrite),
+                "rescale_video" (input_path: String, output_path: String, width: Option<u32>, height: Option<u32>, fps: Option<f64>, scaling_algo: Option<ScalingAlgorithm>, mode: Option<FitMode>, overwrite: Option<OverwritePolicy>)
+                    => transform::rescale_video(&input_path, &output_path, width, height, fps, scaling_algo, mode, overwrite),
+                "crossfade_clips" (clip_a: String, clip_b: String, output_path: String, duration: f64, overwrite: Option<OverwritePolicy>)
+                    => transitions::crossfade_clips(&clip_a, &clip_b, &output_path, duration, overwrite),
+                "attach_bumpers" (intro: Option<String>, main: String, outro: Option<String>, output_path: String, transition: Option<String>, duration: f64, overwrite: Option<OverwritePolicy>)
+                    => transitions::attach_bumpers(intro, &main, outro, &output_path, transition, duration, overwrite),
+            })
+        })
+        .await?;
+
+    let commands: Vec<PlannedCommand> = recorder
+        .commands()
+        .into_iter()
+        .filter_map(|mut command| {
+            if command.is_empty() {
+                return None;
+            }
+            let program = command.remove(0);
+            let command_line = std::iter::once(&program).chain(&command).map(|a| shell_quote(a)).collect::<Vec<_>>().join(" ");
+            Some(PlannedCommand { program, args: command, command_line })
+        })
+        .collect();
+    let estimate = commands.last().and_then(|last| estimate(&last.args));
+    Ok(RenderPlan { commands, estimate })
+}
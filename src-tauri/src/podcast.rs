@@ -0,0 +1,170 @@
+//! Audio-only podcast export
+//!
+//! One pass produces a publishable episode: the audio is pulled from the
+//! source, normalized to podcast loudness with a measured two-pass
+//! loudnorm, and written with tags, chapters, and cover art embedded.
+
+use crate::audio::{self, LoudnessTarget};
+use crate::error::{ClipFlowError, Result};
+use crate::metadata::{self, Chapter};
+use crate::output::{self, OverwritePolicy};
+use crate::{probe, process};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum PodcastFormat {
+    /// MP3 with ID3v2.3 tags and CHAP chapters
+    Mp3,
+    /// AAC in M4A with chapter track
+    M4a,
+    /// Opus in Ogg with Vorbis comment chapters; no cover art
+    Opus,
+}
+
+impl PodcastFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PodcastFormat::Mp3 => "mp3",
+            PodcastFormat::M4a => "m4a",
+            PodcastFormat::Opus => "opus",
+        }
+    }
+
+    fn codec_args(&self) -> Vec<&'static str> {
+        match self {
+            // ID3v2.3 is the newest version Apple Podcasts reads chapters from
+            PodcastFormat::Mp3 => vec![
+                "-c:a", "libmp3lame", "-b:a", "128k", "-ar", "44100",
+                "-id3v2_version", "3", "-write_id3v1", "1",
+            ],
+            PodcastFormat::M4a => vec!["-c:a", "aac", "-b:a", "128k", "-ar", "48000", "-movflags", "+faststart"],
+            PodcastFormat::Opus => vec!["-c:a", "libopus", "-b:a", "96k", "-ar", "48000"],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct PodcastMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    /// Show name
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    /// Release date, e.g. "2024-05-01"
+    pub date: Option<String>,
+    pub comment: Option<String>,
+    /// JPEG or PNG cover, ideally square and at least 1400px
+    pub artwork_path: Option<String>,
+}
+
+impl PodcastMetadata {
+    fn tags(&self) -> BTreeMap<String, String> {
+        [
+            ("title", &self.title),
+            ("artist", &self.artist),
+            ("album", &self.album),
+            ("genre", &self.genre),
+            ("date", &self.date),
+            ("comment", &self.comment),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.as_ref().filter(|v| !v.trim().is_empty()).map(|v| (key.to_string(), v.clone())))
+        .collect()
+    }
+}
+
+fn validate_artwork(artwork: &str, format: PodcastFormat) -> Result<()> {
+    if format == PodcastFormat::Opus {
+        return Err(ClipFlowError::invalid("Cover art can't be embedded in .opus files - use MP3 or M4A"));
+    }
+    if !Path::new(artwork).is_file() {
+        return Err(ClipFlowError::not_found(format!("Artwork not found: {}", artwork)));
+    }
+    let ext = Path::new(artwork)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if !matches!(ext.as_str(), "jpg" | "jpeg" | "png") {
+        return Err(ClipFlowError::invalid("Artwork must be a JPEG or PNG image"));
+    }
+    Ok(())
+}
+
+/// Render the episode from `input_path` into `output_path`
+pub async fn render_podcast(
+    input_path: &str,
+    output_path: &str,
+    format: PodcastFormat,
+    chapters: &[Chapter],
+    episode: &PodcastMetadata,
+) -> Result<()> {
+    if probe::audio_tracks(input_path)?.is_empty() {
+        return Err(ClipFlowError::invalid(format!("{} has no audio", input_path)));
+    }
+    let duration = probe::media_duration(input_path)?;
+    let chapters = metadata::normalize_chapters(chapters, duration)?;
+    let metadata_file = metadata::write_ffmetadata(&episode.tags(), &chapters)?;
+
+    let target = LoudnessTarget::PODCAST;
+    let measured = audio::measure_loudness(input_path, target).await?;
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-i".to_string(), metadata_file.path_str(),
+    ];
+    if let Some(artwork) = &episode.artwork_path {
+        args.extend(["-i".to_string(), artwork.clone()]);
+    }
+    args.extend([
+        "-map".to_string(), "0:a:0".to_string(),
+        "-map_metadata".to_string(), "1".to_string(),
+        "-map_chapters".to_string(), "1".to_string(),
+        // loudnorm resamples to 192 kHz internally; the codec args set the
+        // real output rate
+        "-af".to_string(), audio::loudnorm_filter(target, &measured),
+    ]);
+    args.extend(format.codec_args().into_iter().map(String::from));
+    if episode.artwork_path.is_some() {
+        args.extend([
+            "-map".to_string(), "2:v".to_string(),
+            "-c:v".to_string(), "copy".to_string(),
+            "-disposition:v".to_string(), "attached_pic".to_string(),
+        ]);
+        if format == PodcastFormat::Mp3 {
+            args.extend([
+                "-metadata:s:v".to_string(), "title=Album cover".to_string(),
+                "-metadata:s:v".to_string(), "comment=Cover (front)".to_string(),
+            ]);
+        }
+    }
+    args.push(output_path.to_string());
+    args.push("-y".to_string());
+    process::run_ffmpeg_async(&args, "podcast export").await
+}
+
+/// Export a loudness-normalized, tagged podcast episode, returning the
+/// path written
+/// The output extension is replaced to match `format`.
+#[tauri::command]
+pub async fn export_podcast(
+    input_path: &str,
+    output_path: &str,
+    format: PodcastFormat,
+    chapters: Option<Vec<Chapter>>,
+    metadata: Option<PodcastMetadata>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let metadata = metadata.unwrap_or_default();
+    if let Some(artwork) = &metadata.artwork_path {
+        validate_artwork(artwork, format)?;
+    }
+    let chapters = chapters.unwrap_or_default();
+    let path = Path::new(output_path).with_extension(format.extension()).to_string_lossy().into_owned();
+    output::write_atomically_async(&path, overwrite.unwrap_or_default(), |partial| async move {
+        render_podcast(input_path, &partial, format, &chapters, &metadata).await
+    })
+    .await
+}
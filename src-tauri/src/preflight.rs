@@ -0,0 +1,203 @@
+//! Checks run before a render starts: estimated output size against free
+//! disk space, write permission on the target, and existing-file overwrites
+//!
+//! A render that fails at 90% because the disk filled up wastes the whole
+//! encode, so these are caught up front and reported together.
+
+use crate::error::{ClipFlowError, Result};
+use crate::presets::{self, ExportPreset, VideoCodec};
+use crate::{probe, timecode};
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// Extra space required beyond the estimate, since CRF sizes vary with content
+const SIZE_MARGIN: f64 = 1.25;
+
+/// Bits per pixel per frame for H.264 at CRF 23; each 6 CRF steps halves it
+const H264_BPP_AT_CRF23: f64 = 0.1;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The render can go ahead, but the user should know
+    Warning,
+    /// The render would fail; don't start it
+    Error,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PreflightIssue {
+    pub severity: Severity,
+    /// Stable identifier for the frontend, e.g. "insufficient_space"
+    pub code: &'static str,
+    pub message: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PreflightReport {
+    pub output_path: String,
+    pub estimated_bytes: Option<u64>,
+    /// Free space on the output volume, if it could be determined
+    pub available_bytes: Option<u64>,
+    pub output_exists: bool,
+    pub writable: bool,
+    pub issues: Vec<PreflightIssue>,
+}
+
+impl PreflightReport {
+    fn push(&mut self, severity: Severity, code: &'static str, message: String) {
+        self.issues.push(PreflightIssue { severity, code, message });
+    }
+
+    /// Turn blocking issues into an error, for renders that run unattended
+    pub fn into_result(self) -> Result<()> {
+        let blocking: Vec<String> = self
+            .issues
+            .into_iter()
+            .filter(|i| i.severity == Severity::Error)
+            .map(|i| i.message)
+            .collect();
+        if blocking.is_empty() {
+            Ok(())
+        } else {
+            Err(ClipFlowError::PreflightFailed(blocking.join("; ")))
+        }
+    }
+}
+
+/// Relative size of each codec's output next to H.264 at the same CRF
+fn codec_efficiency(codec: VideoCodec) -> f64 {
+    match codec {
+        VideoCodec::H264 => 1.0,
+        VideoCodec::Hevc => 0.6,
+        VideoCodec::Vp9 => 0.65,
+        VideoCodec::Av1 => 0.5,
+    }
+}
+
+/// Rough output size for encoding `input_path` with `preset`, at
+/// `video_kbps` when the bitrate is fixed rather than CRF
+pub fn estimate_output_size(input_path: &str, preset: &ExportPreset, video_kbps: Option<u32>) -> Result<u64> {
+    let json = probe::ffprobe_json(input_path)?;
+    let duration = json["format"]["duration"]
+        .as_str()
+        .and_then(|d| d.parse::<f64>().ok())
+        .ok_or_else(|| ClipFlowError::tool("ffprobe", format!("No duration for {}", input_path)))?;
+    let streams = json["streams"].as_array().cloned().unwrap_or_default();
+
+    let video_bits_per_sec = streams
+        .iter()
+        .find(|s| s["codec_type"] == "video")
+        .map(|video| {
+            if let Some(kbps) = video_kbps {
+                return kbps as f64 * 1000.0;
+            }
+            let pixels = video["width"].as_f64().unwrap_or(0.0) * video["height"].as_f64().unwrap_or(0.0);
+            let fps = video["r_frame_rate"].as_str().and_then(timecode::parse_rate).unwrap_or(30.0);
+            let bpp = H264_BPP_AT_CRF23 * 2f64.powf((23.0 - preset.crf as f64) / 6.0);
+            pixels * fps * bpp * codec_efficiency(preset.video_codec)
+        })
+        .unwrap_or(0.0);
+    let audio_bits_per_sec = if streams.iter().any(|s| s["codec_type"] == "audio") {
+        presets::audio_kbps(preset.audio_codec) as f64 * 1000.0
+    } else {
+        0.0
+    };
+
+    Ok(((video_bits_per_sec + audio_bits_per_sec) * duration / 8.0) as u64)
+}
+
+fn output_dir(output: &Path) -> PathBuf {
+    output
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_path_buf()
+}
+
+/// Whether a file can be created in `dir`, found by actually creating one
+fn dir_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".clipflow_write_test_{}", std::process::id()));
+    let created = OpenOptions::new().write(true).create_new(true).open(&probe).is_ok();
+    if created {
+        let _ = fs::remove_file(&probe);
+    }
+    created
+}
+
+/// Check that `output_path` can take a render of about `estimated_bytes`
+pub fn check_output(output_path: &str, estimated_bytes: Option<u64>) -> PreflightReport {
+    let output = Path::new(output_path);
+    let mut report = PreflightReport {
+        output_path: output_path.to_string(),
+        estimated_bytes,
+        available_bytes: None,
+        output_exists: output.exists(),
+        writable: false,
+        issues: Vec::new(),
+    };
+
+    let dir = output_dir(output);
+    if !dir.is_dir() {
+        report.push(Severity::Error, "missing_directory", format!("Folder {} doesn't exist", dir.display()));
+        return report;
+    }
+
+    report.writable = if report.output_exists {
+        OpenOptions::new().append(true).open(output).is_ok()
+    } else {
+        dir_writable(&dir)
+    };
+    if !report.writable {
+        report.push(Severity::Error, "not_writable", format!("No permission to write to {}", output_path));
+    }
+
+    if report.output_exists {
+        report.push(Severity::Warning, "overwrite", format!("{} already exists and will be replaced", output_path));
+    }
+
+    report.available_bytes = fs2::available_space(&dir).ok();
+    if let (Some(estimated), Some(available)) = (estimated_bytes, report.available_bytes) {
+        // An overwritten file's space comes back once it's replaced
+        let reclaimed = if report.output_exists { fs::metadata(output).map(|m| m.len()).unwrap_or(0) } else { 0 };
+        let needed = (estimated as f64 * SIZE_MARGIN) as u64;
+        if needed > available + reclaimed {
+            report.push(
+                Severity::Error,
+                "insufficient_space",
+                format!(
+                    "Output needs about {} MB but only {} MB is free on the target drive",
+                    needed / 1_000_000,
+                    (available + reclaimed) / 1_000_000
+                ),
+            );
+        }
+    }
+
+    report
+}
+
+/// Full preflight for an export with a preset
+pub fn preflight_export_preset(
+    input_path: &str,
+    output_path: &str,
+    preset: &ExportPreset,
+    video_kbps: Option<u32>,
+) -> PreflightReport {
+    let estimate = estimate_output_size(input_path, preset, video_kbps);
+    let mut report = check_output(output_path, estimate.as_ref().ok().copied());
+    if let Err(e) = estimate {
+        report.push(Severity::Warning, "size_unknown", format!("Couldn't estimate output size: {}", e));
+    }
+    report
+}
+
+/// Report on whether an export would succeed, before starting it
+#[tauri::command]
+pub async fn preflight_export(app: AppHandle, input_path: &str, output_path: &str, quality: &str) -> Result<PreflightReport> {
+    let preset = presets::resolve(&app, quality)?
+        .ok_or_else(|| ClipFlowError::not_found(format!("Unknown export preset: {}", quality)))?;
+    Ok(preflight_export_preset(input_path, output_path, &preset, None))
+}
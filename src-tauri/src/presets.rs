@@ -0,0 +1,563 @@
+//! Export presets: container + codec choices, validated up front so bad
+//! combinations fail with a clear message instead of an ffmpeg mux error
+
+use crate::error::{ClipFlowError, Result};
+use crate::settings::{self, HardwareAcceleration};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Container {
+    Mp4,
+    Mkv,
+    Mov,
+    Webm,
+}
+
+impl Container {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Container::Mp4 => "mp4",
+            Container::Mkv => "mkv",
+            Container::Mov => "mov",
+            Container::Webm => "webm",
+        }
+    }
+
+    pub fn from_extension(ext: &str) -> Option<Container> {
+        match ext.to_ascii_lowercase().as_str() {
+            "mp4" | "m4v" => Some(Container::Mp4),
+            "mkv" => Some(Container::Mkv),
+            "mov" => Some(Container::Mov),
+            "webm" => Some(Container::Webm),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    H264,
+    Hevc,
+    Vp9,
+    Av1,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioCodec {
+    Aac,
+    Opus,
+    Mp3,
+    Pcm,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ExportPreset {
+    pub name: String,
+    pub container: Container,
+    pub video_codec: VideoCodec,
+    pub audio_codec: AudioCodec,
+    pub crf: u32,
+    /// Encoder speed/efficiency tradeoff, e.g. x264's "medium"
+    pub speed: String,
+    #[serde(default, skip_deserializing)]
+    pub builtin: bool,
+}
+
+/// How the video bitrate is decided
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum RateControl {
+    /// Constant quality at the preset's CRF
+    #[default]
+    Crf,
+    /// Average video bitrate in kbit/s, encoded in two passes
+    TargetBitrate { video_kbps: u32 },
+    /// Bitrate derived from the duration so the file lands near this size,
+    /// for platforms with upload limits; also two-pass
+    TargetSize { megabytes: f64 },
+}
+
+impl RateControl {
+    /// ffmpeg runs the video goes through
+    pub fn passes(&self) -> u32 {
+        match self {
+            RateControl::Crf => 1,
+            RateControl::TargetBitrate { .. } | RateControl::TargetSize { .. } => 2,
+        }
+    }
+}
+
+/// Lowest video bitrate a size target may work out to before it's refused
+const MIN_VIDEO_KBPS: f64 = 100.0;
+
+/// Share of a size target left for container overhead
+const MUX_OVERHEAD: f64 = 0.02;
+
+impl VideoCodec {
+    /// CRF giving roughly the picture quality of x264 at `h264_crf`
+    ///
+    /// The encoders' scales don't line up: x265 looks like x264 about 5
+    /// points lower, and libvpx and SVT-AV1 use a 0-63 range.
+    pub fn equivalent_crf(&self, h264_crf: u32) -> u32 {
+        match self {
+            VideoCodec::H264 => h264_crf,
+            VideoCodec::Hevc => (h264_crf + 5).min(51),
+            VideoCodec::Vp9 => ((h264_crf as f64 * 1.35).round() as u32).min(63),
+            VideoCodec::Av1 => (h264_crf + 12).min(63),
+        }
+    }
+
+    /// Container used when none is asked for: MP4 where players handle the
+    /// codec in it everywhere, WebM for VP9
+    pub fn default_container(&self) -> Container {
+        match self {
+            VideoCodec::H264 | VideoCodec::Hevc | VideoCodec::Av1 => Container::Mp4,
+            VideoCodec::Vp9 => Container::Webm,
+        }
+    }
+
+    fn default_audio(&self) -> AudioCodec {
+        match self.default_container() {
+            Container::Webm => AudioCodec::Opus,
+            _ => AudioCodec::Aac,
+        }
+    }
+}
+
+/// x264 speed names from fastest to slowest, the scale presets use for
+/// every encoder
+const SPEEDS: [&str; 10] = [
+    "ultrafast", "superfast", "veryfast", "faster", "fast", "medium", "slow", "slower", "veryslow", "placebo",
+];
+
+/// Position of a speed name on the x264 scale, "medium" if unknown
+fn speed_index(speed: &str) -> usize {
+    SPEEDS.iter().position(|s| *s == speed).unwrap_or(5)
+}
+
+/// libvpx `-cpu-used` for a speed name: 0 (slowest) to 5, "medium" is 2
+fn vp9_cpu_used(speed: &str) -> String {
+    7usize.saturating_sub(speed_index(speed)).min(5).to_string()
+}
+
+/// SVT-AV1 `-preset` for a speed name: 13 (fastest) down to 4, "medium" is 8
+fn svt_av1_preset(speed: &str) -> String {
+    (13 - speed_index(speed)).to_string()
+}
+
+/// Built-in preset `generate_shorts` exports with
+pub const SHORTS_PRESET: &str = "shorts";
+
+fn builtin(name: &str, codec: VideoCodec, h264_crf: u32) -> ExportPreset {
+    ExportPreset {
+        name: name.to_string(),
+        container: codec.default_container(),
+        video_codec: codec,
+        audio_codec: codec.default_audio(),
+        crf: codec.equivalent_crf(h264_crf),
+        speed: "medium".to_string(),
+        builtin: true,
+    }
+}
+
+/// "high"/"medium"/"low" in H.264, plus the same tiers for the newer
+/// codecs, e.g. "medium-av1", and "shorts" for vertical clips
+pub fn builtin_presets() -> Vec<ExportPreset> {
+    let tiers = [("high", 18), ("medium", 23), ("low", 28)];
+    let mut presets: Vec<ExportPreset> = tiers.iter().map(|(name, crf)| builtin(name, VideoCodec::H264, *crf)).collect();
+    for (codec, suffix) in [(VideoCodec::Hevc, "hevc"), (VideoCodec::Vp9, "vp9"), (VideoCodec::Av1, "av1")] {
+        presets.extend(tiers.iter().map(|(name, crf)| builtin(&format!("{}-{}", name, suffix), codec, *crf)));
+    }
+    // Shorts are re-encoded on upload anyway; a bit of headroom survives it
+    presets.push(builtin(SHORTS_PRESET, VideoCodec::H264, 20));
+    presets
+}
+
+fn codec_name(codec: VideoCodec) -> &'static str {
+    match codec {
+        VideoCodec::H264 => "H.264",
+        VideoCodec::Hevc => "HEVC",
+        VideoCodec::Vp9 => "VP9",
+        VideoCodec::Av1 => "AV1",
+    }
+}
+
+/// Check that the container can carry the preset's codecs
+pub fn validate(preset: &ExportPreset) -> Result<()> {
+    let video_ok = match preset.container {
+        Container::Mp4 => matches!(preset.video_codec, VideoCodec::H264 | VideoCodec::Hevc | VideoCodec::Av1),
+        Container::Mov => matches!(preset.video_codec, VideoCodec::H264 | VideoCodec::Hevc),
+        Container::Mkv => true,
+        Container::Webm => matches!(preset.video_codec, VideoCodec::Vp9 | VideoCodec::Av1),
+    };
+    if !video_ok {
+        return Err(ClipFlowError::invalid(format!(
+            "{} video can't be stored in .{} - use {}",
+            codec_name(preset.video_codec),
+            preset.container.extension(),
+            match preset.video_codec {
+                VideoCodec::Vp9 => ".webm or .mkv",
+                VideoCodec::Av1 => ".mp4, .webm or .mkv",
+                _ => ".mp4, .mov or .mkv",
+            }
+        )));
+    }
+
+    let audio_ok = match preset.container {
+        Container::Mp4 => matches!(preset.audio_codec, AudioCodec::Aac | AudioCodec::Mp3),
+        Container::Mov => matches!(preset.audio_codec, AudioCodec::Aac | AudioCodec::Pcm),
+        Container::Mkv => true,
+        Container::Webm => preset.audio_codec == AudioCodec::Opus,
+    };
+    if !audio_ok {
+        return Err(ClipFlowError::invalid(format!(
+            "{:?} audio can't be stored in .{}",
+            preset.audio_codec,
+            preset.container.extension()
+        )));
+    }
+
+    let max_crf = match preset.video_codec {
+        VideoCodec::H264 | VideoCodec::Hevc => 51,
+        VideoCodec::Vp9 | VideoCodec::Av1 => 63,
+    };
+    if preset.crf > max_crf {
+        return Err(ClipFlowError::invalid(format!("CRF {} is out of range for {} (0-{})", preset.crf, codec_name(preset.video_codec), max_crf)));
+    }
+
+    if preset.name.trim().is_empty() {
+        return Err(ClipFlowError::invalid("Preset name must not be empty"));
+    }
+
+    Ok(())
+}
+
+/// Check an output path's extension against the preset's container
+pub fn validate_output_path(preset: &ExportPreset, output_path: &str) -> Result<()> {
+    let ext = Path::new(output_path)
+        .extension()
+        .map(|e| e.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match Container::from_extension(&ext) {
+        Some(container) if container == preset.container => Ok(()),
+        _ => Err(ClipFlowError::invalid(format!(
+            "Preset \"{}\" produces .{} files, but the output path ends in \"{}\"",
+            preset.name,
+            preset.container.extension(),
+            if ext.is_empty() { "(no extension)".to_string() } else { format!(".{}", ext) }
+        ))),
+    }
+}
+
+/// Hardware encoder for a codec under the configured acceleration, if any
+///
+/// "Auto" only picks VideoToolbox, which every supported Mac has; NVENC,
+/// QSV, and AMF depend on the GPU and driver, so they must be chosen
+/// explicitly in settings.
+pub fn hardware_encoder(codec: VideoCodec) -> Option<&'static str> {
+    let accel = match settings::current().hardware_acceleration {
+        HardwareAcceleration::Auto if cfg!(target_os = "macos") => HardwareAcceleration::VideoToolbox,
+        other => other,
+    };
+    match (accel, codec) {
+        (HardwareAcceleration::Nvenc, VideoCodec::H264) => Some("h264_nvenc"),
+        (HardwareAcceleration::Nvenc, VideoCodec::Hevc) => Some("hevc_nvenc"),
+        (HardwareAcceleration::Nvenc, VideoCodec::Av1) => Some("av1_nvenc"),
+        (HardwareAcceleration::Qsv, VideoCodec::H264) => Some("h264_qsv"),
+        (HardwareAcceleration::Qsv, VideoCodec::Hevc) => Some("hevc_qsv"),
+        (HardwareAcceleration::Qsv, VideoCodec::Vp9) => Some("vp9_qsv"),
+        (HardwareAcceleration::Qsv, VideoCodec::Av1) => Some("av1_qsv"),
+        (HardwareAcceleration::VideoToolbox, VideoCodec::H264) => Some("h264_videotoolbox"),
+        (HardwareAcceleration::VideoToolbox, VideoCodec::Hevc) => Some("hevc_videotoolbox"),
+        (HardwareAcceleration::Amf, VideoCodec::H264) => Some("h264_amf"),
+        (HardwareAcceleration::Amf, VideoCodec::Hevc) => Some("hevc_amf"),
+        _ => None,
+    }
+}
+
+/// Rate-control arguments approximating the preset's CRF on a hardware encoder
+fn hardware_quality_args(encoder: &str, crf: u32) -> Vec<String> {
+    if encoder.ends_with("_nvenc") {
+        vec!["-rc".into(), "vbr".into(), "-cq".into(), crf.to_string(), "-b:v".into(), "0".into()]
+    } else if encoder.ends_with("_qsv") {
+        vec!["-global_quality".into(), crf.to_string()]
+    } else if encoder.ends_with("_videotoolbox") {
+        // VideoToolbox takes 1-100, higher is better
+        let q = 100u32.saturating_sub(crf * 2).clamp(1, 100);
+        vec!["-q:v".into(), q.to_string()]
+    } else {
+        vec!["-rc".into(), "cqp".into(), "-qp_i".into(), crf.to_string(), "-qp_p".into(), crf.to_string()]
+    }
+}
+
+/// Audio bitrate of the preset's audio codec in kbit/s
+pub fn audio_kbps(codec: AudioCodec) -> u32 {
+    match codec {
+        AudioCodec::Aac | AudioCodec::Mp3 => 192,
+        AudioCodec::Opus => 160,
+        // 16-bit stereo at 48 kHz
+        AudioCodec::Pcm => 1536,
+    }
+}
+
+pub fn audio_codec_args(codec: AudioCodec) -> Vec<String> {
+    let bitrate = format!("{}k", audio_kbps(codec));
+    let args = match codec {
+        AudioCodec::Aac => vec!["-c:a", "aac", "-b:a", &bitrate],
+        AudioCodec::Opus => vec!["-c:a", "libopus", "-b:a", &bitrate],
+        AudioCodec::Mp3 => vec!["-c:a", "libmp3lame", "-b:a", &bitrate],
+        AudioCodec::Pcm => vec!["-c:a", "pcm_s16le"],
+    };
+    args.into_iter().map(|s| s.to_string()).collect()
+}
+
+/// Video bitrate (kbit/s) for a rate control mode, or None for CRF
+/// `duration` is only read for size targets
+pub fn target_video_kbps(rate_control: RateControl, preset: &ExportPreset, duration: impl FnOnce() -> Result<f64>) -> Result<Option<u32>> {
+    match rate_control {
+        RateControl::Crf => Ok(None),
+        RateControl::TargetBitrate { video_kbps } if video_kbps as f64 >= MIN_VIDEO_KBPS => Ok(Some(video_kbps)),
+        RateControl::TargetBitrate { video_kbps } => Err(ClipFlowError::invalid(format!(
+            "Target bitrate must be at least {} kbit/s, got {}",
+            MIN_VIDEO_KBPS, video_kbps
+        ))),
+        RateControl::TargetSize { megabytes } => {
+            let duration = duration()?;
+            if duration <= 0.0 {
+                return Err(ClipFlowError::invalid("Can't size an export with no duration"));
+            }
+            let total_kbits = megabytes * 8_000.0 * (1.0 - MUX_OVERHEAD);
+            let video_kbps = total_kbits / duration - audio_kbps(preset.audio_codec) as f64;
+            if video_kbps < MIN_VIDEO_KBPS {
+                return Err(ClipFlowError::invalid(format!(
+                    "{} MB is too small for {:.0}s of video at acceptable quality",
+                    megabytes, duration
+                )));
+            }
+            Ok(Some(video_kbps as u32))
+        }
+    }
+}
+
+/// Video encoder arguments for one pass of a two-pass encode at `video_kbps`
+///
+/// Two-pass always runs in software, since the hardware encoders have no
+/// equivalent. `passlog` is the stats file prefix shared by both passes.
+pub fn two_pass_video_args(preset: &ExportPreset, video_kbps: u32, pass: u32, passlog: &str) -> Result<Vec<String>> {
+    let bitrate = format!("{}k", video_kbps);
+    let pass = pass.to_string();
+    let args = match preset.video_codec {
+        VideoCodec::H264 => vec![
+            "-c:v".to_string(), "libx264".to_string(),
+            "-b:v".to_string(), bitrate,
+            "-preset".to_string(), preset.speed.clone(),
+            "-pass".to_string(), pass,
+            "-passlogfile".to_string(), passlog.to_string(),
+        ],
+        // x265 keeps its own stats file instead of honoring -passlogfile
+        VideoCodec::Hevc => vec![
+            "-c:v".to_string(), "libx265".to_string(),
+            "-b:v".to_string(), bitrate,
+            "-preset".to_string(), preset.speed.clone(),
+            "-x265-params".to_string(), format!("pass={}:stats='{}'", pass, passlog.replace('\\', "/")),
+            "-tag:v".to_string(), "hvc1".to_string(),
+        ],
+        VideoCodec::Vp9 => vec![
+            "-c:v".to_string(), "libvpx-vp9".to_string(),
+            "-b:v".to_string(), bitrate,
+            "-row-mt".to_string(), "1".to_string(),
+            "-deadline".to_string(), "good".to_string(),
+            "-cpu-used".to_string(), vp9_cpu_used(&preset.speed),
+            "-pass".to_string(), pass,
+            "-passlogfile".to_string(), passlog.to_string(),
+        ],
+        VideoCodec::Av1 => {
+            return Err(ClipFlowError::invalid("Two-pass encoding supports H.264, HEVC, and VP9, not AV1"));
+        }
+    };
+    Ok(args)
+}
+
+/// Encoder arguments for the preset's video and audio codecs
+/// With `allow_hardware`, uses the configured hardware encoder when there is one
+pub fn codec_args(preset: &ExportPreset, allow_hardware: bool) -> Vec<String> {
+    let crf = preset.crf.to_string();
+    let hardware = if allow_hardware { hardware_encoder(preset.video_codec) } else { None };
+    let cpu_used = vp9_cpu_used(&preset.speed);
+    let svt_preset = svt_av1_preset(&preset.speed);
+
+    let args: Vec<&str> = match (hardware, preset.video_codec) {
+        (Some(encoder), _) => vec!["-c:v", encoder],
+        (None, VideoCodec::H264) => vec!["-c:v", "libx264", "-crf", &crf, "-preset", &preset.speed],
+        (None, VideoCodec::Hevc) => vec!["-c:v", "libx265", "-crf", &crf, "-preset", &preset.speed, "-tag:v", "hvc1"],
+        // Constant-quality mode in libvpx needs an explicit zero bitrate
+        (None, VideoCodec::Vp9) => vec![
+            "-c:v", "libvpx-vp9", "-crf", &crf, "-b:v", "0", "-row-mt", "1",
+            "-deadline", "good", "-cpu-used", &cpu_used,
+        ],
+        (None, VideoCodec::Av1) => vec!["-c:v", "libsvtav1", "-crf", &crf, "-preset", &svt_preset],
+    };
+    let mut args: Vec<String> = args.into_iter().map(|s| s.to_string()).collect();
+    args.extend(audio_codec_args(preset.audio_codec));
+    if let Some(encoder) = hardware {
+        args.extend(hardware_quality_args(encoder, preset.crf));
+    }
+    args
+}
+
+/// Flags user-supplied arguments may not set: they add inputs or outputs,
+/// replace the filter graph or stream mapping, or write other files
+const BLOCKED_FLAGS: [&str; 22] = [
+    "-i", "-y", "-n", "-f", "-map", "-filter_complex", "-lavfi", "-filter_complex_script",
+    "-filter_script", "-vf", "-af", "-filter", "-attach", "-dump_attachment", "-progress",
+    "-report", "-vstats", "-vstats_file", "-passlogfile", "-sdp_file", "-protocol_whitelist",
+    "-protocol_blacklist",
+];
+
+/// ffmpeg options that take no value, each also accepted with a "no"
+/// prefix (-noautorotate); anything else is assumed to take one
+const BOOLEAN_FLAGS: [&str; 30] = [
+    "-an", "-vn", "-sn", "-dn", "-shortest", "-bitexact", "-copyts", "-start_at_zero", "-copyinkf",
+    "-accurate_seek", "-autorotate", "-autoscale", "-re", "-stdin", "-stats", "-hide_banner",
+    "-benchmark", "-benchmark_all", "-dump", "-hex", "-xerror", "-ignore_unknown", "-copy_unknown",
+    "-recast_media", "-fix_sub_duration", "-fix_sub_duration_heartbeat", "-qphist", "-psnr",
+    "-debug_ts", "-print_graphs",
+];
+
+fn is_boolean_flag(name: &str) -> bool {
+    BOOLEAN_FLAGS.contains(&name)
+        || name.strip_prefix("-no").is_some_and(|rest| BOOLEAN_FLAGS.contains(&format!("-{}", rest).as_str()))
+}
+
+/// Check extra ffmpeg arguments passed through to an encode
+/// Every value must follow a flag that takes one; a stray one would be
+/// read as another output file.
+pub fn validate_extra_args(args: &[String]) -> Result<()> {
+    let mut expects_value = false;
+    for arg in args {
+        let is_flag = arg.starts_with('-') && arg.len() > 1 && arg.parse::<f64>().is_err();
+        if !is_flag {
+            if !expects_value {
+                return Err(ClipFlowError::invalid(format!("Extra argument '{}' doesn't follow a flag", arg)));
+            }
+            expects_value = false;
+            continue;
+        }
+        if expects_value {
+            return Err(ClipFlowError::invalid(format!("Extra argument '{}' is missing a value", arg)));
+        }
+        // Stream specifiers don't change what a flag does: -filter:v is -filter
+        let name = arg.split(':').next().unwrap_or(arg);
+        // A leading "-/" reads the option's value from a file
+        if BLOCKED_FLAGS.contains(&name) || name.starts_with("-/") || name.starts_with("-stats_") {
+            return Err(ClipFlowError::invalid(format!("Extra argument '{}' is not allowed", arg)));
+        }
+        expects_value = !is_boolean_flag(name);
+    }
+    if expects_value {
+        return Err(ClipFlowError::invalid("The last extra argument is missing a value"));
+    }
+    Ok(())
+}
+
+fn presets_path(app: &AppHandle) -> Result<PathBuf> {
+    settings::config_file(app, "presets.json")
+}
+
+fn read_user_presets(app: &AppHandle) -> Result<Vec<ExportPreset>> {
+    let path = presets_path(app)?;
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content = fs::read_to_string(&path).map_err(|e| ClipFlowError::io("Failed to read presets", e))?;
+    serde_json::from_str(&content).map_err(|e| ClipFlowError::io("Invalid presets file", e))
+}
+
+fn write_user_presets(app: &AppHandle, presets: &[ExportPreset]) -> Result<()> {
+    let json = serde_json::to_string_pretty(presets).map_err(|e| ClipFlowError::io("Failed to serialize presets", e))?;
+    fs::write(presets_path(app)?, json).map_err(|e| ClipFlowError::io("Failed to write presets", e))
+}
+
+/// Look up a preset by name; user presets shadow built-ins
+pub fn resolve(app: &AppHandle, name: &str) -> Result<Option<ExportPreset>> {
+    Ok(read_user_presets(app)?
+        .into_iter()
+        .chain(builtin_presets())
+        .find(|p| p.name == name))
+}
+
+#[tauri::command]
+pub async fn list_export_presets(app: AppHandle) -> Result<Vec<ExportPreset>> {
+    let mut presets = builtin_presets();
+    presets.extend(read_user_presets(&app)?);
+    Ok(presets)
+}
+
+#[tauri::command]
+pub async fn validate_export_preset(preset: ExportPreset) -> Result<()> {
+    validate(&preset)
+}
+
+#[tauri::command]
+pub async fn save_export_preset(app: AppHandle, preset: ExportPreset) -> Result<()> {
+    validate(&preset)?;
+    if builtin_presets().iter().any(|p| p.name == preset.name) {
+        return Err(ClipFlowError::invalid(format!("\"{}\" is a built-in preset name", preset.name)));
+    }
+
+    let mut presets = read_user_presets(&app)?;
+    presets.retain(|p| p.name != preset.name);
+    presets.push(preset);
+    write_user_presets(&app, &presets)
+}
+
+#[tauri::command]
+pub async fn delete_export_preset(app: AppHandle, name: String) -> Result<()> {
+    let mut presets = read_user_presets(&app)?;
+    presets.retain(|p| p.name != name);
+    write_user_presets(&app, &presets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(args: &[&str]) -> Result<()> {
+        validate_extra_args(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn accepts_flags_with_values() {
+        assert!(check(&["-movflags", "+faststart", "-tune", "film", "-g", "-1"]).is_ok());
+        assert!(check(&["-an", "-metadata:s:v", "title=Main", "-noautorotate", "-shortest"]).is_ok());
+    }
+
+    #[test]
+    fn rejects_a_path_after_a_boolean_flag() {
+        assert!(check(&["-an", "/home/u/.bashrc"]).is_err());
+        assert!(check(&["-vn", "out.mp4"]).is_err());
+        assert!(check(&["-nostdin", "x.mkv"]).is_err());
+        assert!(check(&["-shortest", "-movflags", "+faststart", "extra.mp4"]).is_err());
+    }
+
+    #[test]
+    fn rejects_stray_and_missing_values() {
+        assert!(check(&["out.mp4"]).is_err());
+        assert!(check(&["-crf"]).is_err());
+        assert!(check(&["-crf", "-an"]).is_err());
+    }
+
+    #[test]
+    fn rejects_blocked_flags() {
+        assert!(check(&["-y"]).is_err());
+        assert!(check(&["-filter:v", "null"]).is_err());
+        assert!(check(&["-/vf", "graph.txt"]).is_err());
+        assert!(check(&["-stats_enc_post", "x"]).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! Stream-level probing via ffprobe's JSON output
+
+use crate::error::{ClipFlowError, Result};
+use crate::{process, settings};
+use serde::Serialize;
+use serde_json::Value;
+use std::process::{Command, Output};
+use std::time::Duration;
+
+/// Longest an ffprobe run may take; it only reads headers, so a hang means
+/// a stalled network share or a broken file
+const PROBE_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn probe_args(file_path: &str) -> [&str; 9] {
+    [
+        "-v", "error",
+        "-show_format",
+        "-show_streams",
+        "-show_chapters",
+        "-of", "json",
+        file_path,
+    ]
+}
+
+/// Run ffprobe over a file and return its JSON description of format,
+/// streams, and chapters
+pub fn ffprobe_json(file_path: &str) -> Result<Value> {
+    let ffprobe = settings::ffprobe_bin();
+    let output = process::output(Command::new(&ffprobe).args(probe_args(file_path)))
+        .map_err(|e| process::spawn_error(&ffprobe, e))?;
+    parse_probe(file_path, &output)
+}
+
+/// `ffprobe_json` for async commands, waiting without holding a runtime
+/// thread
+pub async fn ffprobe_json_async(file_path: &str) -> Result<Value> {
+    let ffprobe = settings::ffprobe_bin();
+    let output = process::run(tokio::process::Command::new(&ffprobe).args(probe_args(file_path)), Some(PROBE_TIMEOUT), |_| {})
+        .await
+        .map_err(|e| process::spawn_error(&ffprobe, e))?;
+    parse_probe(file_path, &output)
+}
+
+fn parse_probe(file_path: &str, output: &Output) -> Result<Value> {
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(ClipFlowError::invalid(format!("Can't read media file {}: {}", file_path, error.trim())));
+    }
+
+    serde_json::from_slice(&output.stdout).map_err(|e| ClipFlowError::tool("ffprobe", format!("unreadable output: {}", e)))
+}
+
+/// Container duration in seconds
+pub fn media_duration(file_path: &str) -> Result<f64> {
+    duration_of(&ffprobe_json(file_path)?, file_path)
+}
+
+pub async fn media_duration_async(file_path: &str) -> Result<f64> {
+    duration_of(&ffprobe_json_async(file_path).await?, file_path)
+}
+
+fn duration_of(json: &Value, file_path: &str) -> Result<f64> {
+    json["format"]["duration"]
+        .as_str()
+        .and_then(|d| d.parse::<f64>().ok())
+        .ok_or_else(|| ClipFlowError::invalid(format!("No duration in media file {}", file_path)))
+}
+
+/// Frame rate of the first video stream, 30 if it can't be read
+pub fn frame_rate(file_path: &str) -> Result<f64> {
+    let json = ffprobe_json(file_path)?;
+    Ok(json["streams"]
+        .as_array()
+        .and_then(|streams| streams.iter().find(|s| s["codec_type"] == "video"))
+        .and_then(|v| v["r_frame_rate"].as_str())
+        .and_then(crate::timecode::parse_rate)
+        .unwrap_or(30.0))
+}
+
+#[derive(Serialize, Clone)]
+pub struct AudioTrack {
+    /// Absolute stream index in the container (for `-map 0:N`)
+    pub stream_index: u32,
+    /// Position among audio streams (for `-map 0:a:N`)
+    pub audio_index: u32,
+    pub codec: String,
+    pub channels: u32,
+    pub sample_rate: u32,
+    pub language_tag: Option<String>,
+    pub title: Option<String>,
+}
+
+/// List the audio streams of a file in container order
+pub fn audio_tracks(file_path: &str) -> Result<Vec<AudioTrack>> {
+    Ok(audio_tracks_of(&ffprobe_json(file_path)?))
+}
+
+pub async fn audio_tracks_async(file_path: &str) -> Result<Vec<AudioTrack>> {
+    Ok(audio_tracks_of(&ffprobe_json_async(file_path).await?))
+}
+
+fn audio_tracks_of(json: &Value) -> Vec<AudioTrack> {
+    let streams = json["streams"].as_array().cloned().unwrap_or_default();
+
+    streams
+        .iter()
+        .filter(|s| s["codec_type"] == "audio")
+        .enumerate()
+        .map(|(i, s)| AudioTrack {
+            stream_index: s["index"].as_u64().unwrap_or(0) as u32,
+            audio_index: i as u32,
+            codec: s["codec_name"].as_str().unwrap_or("unknown").to_string(),
+            channels: s["channels"].as_u64().unwrap_or(0) as u32,
+            sample_rate: s["sample_rate"].as_str().and_then(|r| r.parse().ok()).unwrap_or(48000),
+            language_tag: s["tags"]["language"]
+                .as_str()
+                .filter(|l| *l != "und")
+                .map(|l| l.to_string()),
+            title: s["tags"]["title"].as_str().map(|t| t.to_string()),
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub async fn list_audio_tracks(file_path: &str) -> Result<Vec<AudioTrack>> {
+    audio_tracks_async(file_path).await
+}
+
+/// Pixel geometry of the first video stream
+#[derive(Serialize, Clone)]
+pub struct VideoGeometry {
+    /// Stored (coded) frame size
+    pub width: u32,
+    pub height: u32,
+    /// Sample (pixel) aspect ratio, e.g. (10, 11) for NTSC DV
+    pub sample_aspect_ratio: (u32, u32),
+    /// Frame size as it should be displayed
+    pub display_width: u32,
+    pub display_height: u32,
+    /// True when pixels aren't square and the frame needs stretching
+    pub anamorphic: bool,
+}
+
+fn parse_ratio(ratio: Option<&str>) -> Option<(u32, u32)> {
+    let (num, den) = ratio?.split_once(':')?;
+    let num = num.parse::<u32>().ok()?;
+    let den = den.parse::<u32>().ok()?;
+    // ffprobe reports 0:1 when the container doesn't say
+    if num == 0 || den == 0 {
+        None
+    } else {
+        Some((num, den))
+    }
+}
+
+pub fn video_geometry(file_path: &str) -> Result<VideoGeometry> {
+    geometry_of(&ffprobe_json(file_path)?, file_path)
+}
+
+pub async fn video_geometry_async(file_path: &str) -> Result<VideoGeometry> {
+    geometry_of(&ffprobe_json_async(file_path).await?, file_path)
+}
+
+fn geometry_of(json: &Value, file_path: &str) -> Result<VideoGeometry> {
+    let video = json["streams"]
+        .as_array()
+        .and_then(|streams| streams.iter().find(|s| s["codec_type"] == "video"))
+        .ok_or_else(|| ClipFlowError::invalid(format!("No video stream found. Path: {}", file_path)))?;
+
+    let width = video["width"].as_u64().unwrap_or(0) as u32;
+    let height = video["height"].as_u64().unwrap_or(0) as u32;
+    let sar = parse_ratio(video["sample_aspect_ratio"].as_str()).unwrap_or((1, 1));
+    let anamorphic = sar.0 != sar.1;
+
+    // Stretch horizontally, keeping the height, and round to an even width
+    // so the result stays encodable as 4:2:0
+    let display_width = if anamorphic {
+        ((width as f64 * sar.0 as f64 / sar.1 as f64 / 2.0).round() * 2.0) as u32
+    } else {
+        width
+    };
+
+    Ok(VideoGeometry {
+        width,
+        height,
+        sample_aspect_ratio: sar,
+        display_width,
+        display_height: height,
+        anamorphic,
+    })
+}
+
+/// Filter that resamples non-square pixels to square ones, or None if the
+/// source already has square pixels
+pub fn square_pixel_filter(geometry: &VideoGeometry) -> Option<String> {
+    if geometry.anamorphic {
+        Some(format!("scale={}:{},setsar=1", geometry.display_width, geometry.display_height))
+    } else {
+        None
+    }
+}
+
+#[tauri::command]
+pub async fn get_video_geometry(file_path: &str) -> Result<VideoGeometry> {
+    video_geometry(file_path)
+}
@@ -0,0 +1,547 @@
+//! Logged wrappers around external command execution
+//!
+//! Every ffmpeg/ffprobe/whisper invocation goes through here so its full
+//! argument list, duration, exit code, and stderr tail end up in the logs.
+//!
+//! `output` and `run_ffmpeg` block the calling thread, which is right for
+//! renders on the blocking pool (jobs, background analysis) where job logs
+//! capture their output per thread. Commands awaited directly on the async
+//! runtime use `run` and `run_ffmpeg_async` instead, which wait without
+//! holding a runtime thread and kill the child if the command is dropped.
+//! When a sync helper does end up blocking on a runtime thread, `output`
+//! moves that thread's other tasks elsewhere for the wait.
+//!
+//! Blocking commands can also be started inside a named group (a job), so
+//! everything the job is running can be suspended and resumed together:
+//! SIGSTOP/SIGCONT on Unix, NtSuspendProcess/NtResumeProcess on Windows.
+//!
+//! While a render is being planned (a dry run), `run_ffmpeg` and
+//! `run_ffmpeg_async` record their argument lists instead of running ffmpeg.
+//! Analysis commands (probes, loudness measurement) still run, since the
+//! render's arguments can depend on them.
+
+use crate::diagnostics::{self, FfmpegFailure};
+use crate::error::{ClipFlowError, Result};
+use crate::{session, settings};
+use std::cell::{Cell, RefCell};
+use std::ffi::OsStr;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::future::Future;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+
+/// Keep only this many trailing bytes of stderr in log records
+const STDERR_LOG_LIMIT: usize = 4000;
+
+fn stderr_tail(stderr: &[u8]) -> String {
+    let text = String::from_utf8_lossy(stderr);
+    if text.len() <= STDERR_LOG_LIMIT {
+        return text.into_owned();
+    }
+    let mut start = text.len() - STDERR_LOG_LIMIT;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("...{}", &text[start..])
+}
+
+/// Per-thread sink collecting the full output of every command a job runs
+struct Capture {
+    log: File,
+    last_failure: Option<FfmpegFailure>,
+}
+
+thread_local! {
+    static CAPTURE: RefCell<Option<Capture>> = const { RefCell::new(None) };
+    static BACKGROUND: Cell<bool> = const { Cell::new(false) };
+    static GROUP: RefCell<Option<String>> = const { RefCell::new(None) };
+    static STDERR_WATCH: RefCell<Option<Box<dyn FnMut(&str)>>> = const { RefCell::new(None) };
+}
+
+/// Running children of each group, and which groups are paused
+struct Groups {
+    children: Vec<(String, u32)>,
+    paused: Vec<String>,
+}
+
+static GROUPS: Mutex<Groups> = Mutex::new(Groups { children: Vec::new(), paused: Vec::new() });
+
+/// ffmpeg invocations recorded in place of running them, program first
+#[derive(Clone, Default)]
+pub struct PlanRecorder(Arc<Mutex<Vec<Vec<String>>>>);
+
+tokio::task_local! {
+    static PLAN: PlanRecorder;
+}
+
+thread_local! {
+    static PLAN_BLOCKING: RefCell<Option<PlanRecorder>> = const { RefCell::new(None) };
+}
+
+impl PlanRecorder {
+    /// The recorder of the render being planned on this task or thread
+    pub fn current() -> Option<PlanRecorder> {
+        PLAN.try_with(|plan| plan.clone()).ok().or_else(|| PLAN_BLOCKING.with(|plan| plan.borrow().clone()))
+    }
+
+    /// Await `render` with its ffmpeg runs recorded instead of run
+    pub async fn record<F: Future>(&self, render: F) -> F::Output {
+        PLAN.scope(self.clone(), render).await
+    }
+
+    /// `record` for work handed to a blocking thread
+    pub fn record_blocking<T>(&self, f: impl FnOnce() -> T) -> T {
+        PLAN_BLOCKING.with(|plan| *plan.borrow_mut() = Some(self.clone()));
+        let value = f();
+        PLAN_BLOCKING.with(|plan| *plan.borrow_mut() = None);
+        value
+    }
+
+    pub fn commands(&self) -> Vec<Vec<String>> {
+        self.0.lock().map(|commands| commands.clone()).unwrap_or_default()
+    }
+
+    /// Record a command in place of running it, for renders that don't go
+    /// through `run_ffmpeg`
+    pub fn push<S: AsRef<OsStr>>(&self, program: &str, args: &[S]) {
+        let command = std::iter::once(program.to_string())
+            .chain(args.iter().map(|a| a.as_ref().to_string_lossy().into_owned()))
+            .collect();
+        if let Ok(mut commands) = self.0.lock() {
+            commands.push(command);
+        }
+    }
+}
+
+/// Run `f`, appending the complete stderr of every command it runs on this
+/// thread to `log_path`
+/// Also returns the classification of the last failed ffmpeg run, if any
+pub fn capture_output<T>(log_path: &Path, f: impl FnOnce() -> T) -> Result<(T, Option<FfmpegFailure>)> {
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)
+        .map_err(|e| ClipFlowError::io("Failed to open job log", e))?;
+    CAPTURE.with(|c| *c.borrow_mut() = Some(Capture { log, last_failure: None }));
+    let value = f();
+    let capture = CAPTURE.with(|c| c.borrow_mut().take());
+    Ok((value, capture.and_then(|c| c.last_failure)))
+}
+
+/// Run `f` with every command it starts on this thread at below-normal CPU
+/// priority, so a long encode leaves room for previews and analysis
+pub fn background_priority<T>(f: impl FnOnce() -> T) -> T {
+    BACKGROUND.with(|b| b.set(true));
+    let value = f();
+    BACKGROUND.with(|b| b.set(false));
+    value
+}
+
+/// Run `f`, passing each stderr line of every command it runs on this
+/// thread to `on_line` as it's written, e.g. to follow ffmpeg's progress
+pub fn watch_stderr<T>(on_line: impl FnMut(&str) + 'static, f: impl FnOnce() -> T) -> T {
+    STDERR_WATCH.with(|w| *w.borrow_mut() = Some(Box::new(on_line)));
+    let value = f();
+    STDERR_WATCH.with(|w| *w.borrow_mut() = None);
+    value
+}
+
+/// Wait for `child`, handing its stderr to `on_line` line by line
+/// Stdout is drained on a helper thread so a chatty child can't stall on it.
+fn wait_watched(mut child: Child, on_line: &mut dyn FnMut(&str)) -> io::Result<Output> {
+    let mut stdout_pipe = child.stdout.take().expect("stdout is piped");
+    let mut stderr_pipe = child.stderr.take().expect("stderr is piped");
+    let stdout_reader = std::thread::spawn(move || {
+        let mut stdout = Vec::new();
+        stdout_pipe.read_to_end(&mut stdout).map(|_| stdout)
+    });
+    let (mut stderr, mut pending, mut chunk) = (Vec::new(), Vec::new(), [0u8; 8192]);
+    loop {
+        let n = stderr_pipe.read(&mut chunk)?;
+        if n == 0 {
+            break;
+        }
+        stderr.extend_from_slice(&chunk[..n]);
+        pending.extend_from_slice(&chunk[..n]);
+        drain_lines(&mut pending, on_line);
+    }
+    pending.push(b'\n');
+    drain_lines(&mut pending, on_line);
+    let stdout = stdout_reader.join().map_err(|_| io::Error::other("stdout reader panicked"))??;
+    Ok(Output { status: child.wait()?, stdout, stderr })
+}
+
+/// Run `f` with every command it starts on this thread in `group`, so
+/// `pause_group` and `resume_group` reach them
+pub fn in_group<T>(group: &str, f: impl FnOnce() -> T) -> T {
+    GROUP.with(|g| *g.borrow_mut() = Some(group.to_string()));
+    let value = f();
+    GROUP.with(|g| *g.borrow_mut() = None);
+    value
+}
+
+#[cfg(windows)]
+fn set_suspended(pid: u32, suspend: bool) -> io::Result<()> {
+    use std::ffi::c_void;
+    const PROCESS_SUSPEND_RESUME: u32 = 0x0800;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn OpenProcess(access: u32, inherit: i32, pid: u32) -> *mut c_void;
+        fn CloseHandle(handle: *mut c_void) -> i32;
+    }
+    #[link(name = "ntdll")]
+    extern "system" {
+        fn NtSuspendProcess(handle: *mut c_void) -> i32;
+        fn NtResumeProcess(handle: *mut c_void) -> i32;
+    }
+    // SAFETY: the handle is checked before use and closed right after
+    unsafe {
+        let handle = OpenProcess(PROCESS_SUSPEND_RESUME, 0, pid);
+        if handle.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let status = if suspend { NtSuspendProcess(handle) } else { NtResumeProcess(handle) };
+        CloseHandle(handle);
+        if status < 0 {
+            return Err(io::Error::other(format!("NTSTATUS {:#x}", status)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn set_suspended(pid: u32, suspend: bool) -> io::Result<()> {
+    let status = Command::new("kill")
+        .args([if suspend { "-STOP" } else { "-CONT" }, &pid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("kill exited with {}", status)));
+    }
+    Ok(())
+}
+
+/// Suspend every running command in `group`; commands the group starts
+/// while paused are suspended as soon as they spawn
+pub fn pause_group(group: &str) -> Result<()> {
+    let mut groups = GROUPS.lock().unwrap();
+    if !groups.paused.iter().any(|g| g == group) {
+        groups.paused.push(group.to_string());
+    }
+    for (_, pid) in groups.children.iter().filter(|(g, _)| g == group) {
+        set_suspended(*pid, true).map_err(|e| ClipFlowError::io("Failed to pause process", e))?;
+    }
+    Ok(())
+}
+
+/// Let a paused group's commands carry on
+pub fn resume_group(group: &str) -> Result<()> {
+    let mut groups = GROUPS.lock().unwrap();
+    groups.paused.retain(|g| g != group);
+    for (_, pid) in groups.children.iter().filter(|(g, _)| g == group) {
+        set_suspended(*pid, false).map_err(|e| ClipFlowError::io("Failed to resume process", e))?;
+    }
+    Ok(())
+}
+
+/// Forget a finished group's paused state
+pub fn release_group(group: &str) {
+    GROUPS.lock().unwrap().paused.retain(|g| g != group);
+}
+
+/// Membership of a child in a group, until dropped
+struct GroupGuard {
+    pid: u32,
+}
+
+fn join_group(group: String, pid: u32) -> GroupGuard {
+    let mut groups = GROUPS.lock().unwrap();
+    if groups.paused.contains(&group) {
+        if let Err(e) = set_suspended(pid, true) {
+            tracing::warn!(group = %group, pid, error = %e, "couldn't suspend command started while paused");
+        }
+    }
+    groups.children.push((group, pid));
+    GroupGuard { pid }
+}
+
+impl Drop for GroupGuard {
+    fn drop(&mut self) {
+        GROUPS.lock().unwrap().children.retain(|(_, pid)| *pid != self.pid);
+    }
+}
+
+/// Windows takes the priority class as a spawn flag
+#[cfg(windows)]
+fn start_in_background(cmd: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const BELOW_NORMAL_PRIORITY_CLASS: u32 = 0x0000_4000;
+    cmd.creation_flags(BELOW_NORMAL_PRIORITY_CLASS);
+}
+
+#[cfg(not(windows))]
+fn start_in_background(_cmd: &mut Command) {}
+
+/// Elsewhere the child is reniced once it's running
+fn renice(pid: u32) {
+    if !cfg!(windows) {
+        let _ = Command::new("renice")
+            .args(["-n", "10", "-p", &pid.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+fn record_capture(program: &str, args: &[String], result: &io::Result<Output>) {
+    CAPTURE.with(|c| {
+        let mut capture = c.borrow_mut();
+        let Some(capture) = capture.as_mut() else {
+            return;
+        };
+        let _ = writeln!(capture.log, "$ {} {}", program, args.join(" "));
+        match result {
+            Ok(out) => {
+                let _ = capture.log.write_all(&out.stderr);
+                let _ = writeln!(capture.log, "[exit code {:?}]\n", out.status.code());
+                if !out.status.success() && program.contains("ffmpeg") {
+                    capture.last_failure = Some(diagnostics::classify(&String::from_utf8_lossy(&out.stderr)));
+                }
+            }
+            Err(e) => {
+                let _ = writeln!(capture.log, "[failed to start: {}]\n", e);
+            }
+        }
+    });
+}
+
+/// Run a command to completion, capturing its output, and log the invocation
+/// The child is tracked in the session file while it runs
+pub fn output(cmd: &mut Command) -> io::Result<Output> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let args: Vec<String> = cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect();
+
+    let background = BACKGROUND.with(|b| b.get());
+    let group = GROUP.with(|g| g.borrow().clone());
+    if background {
+        start_in_background(cmd);
+    }
+    let started = Instant::now();
+    // Sync helpers (probes, render planning) are still reached from async
+    // commands; hand this runtime thread's other tasks off while we wait
+    let result = tokio::task::block_in_place(|| {
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .and_then(|child| {
+                if background {
+                    renice(child.id());
+                }
+                let _tracked = session::track(child.id(), &program, &args);
+                let _grouped = group.map(|group| join_group(group, child.id()));
+                // Taken out while the child runs so the callback can't be reentered
+                match STDERR_WATCH.with(|w| w.borrow_mut().take()) {
+                    Some(mut on_line) => {
+                        let result = wait_watched(child, &mut *on_line);
+                        STDERR_WATCH.with(|w| *w.borrow_mut() = Some(on_line));
+                        result
+                    }
+                    None => child.wait_with_output(),
+                }
+            })
+    });
+    record_capture(&program, &args, &result);
+    log_finished(&program, &args, started, &result);
+    result
+}
+
+fn log_finished(program: &str, args: &[String], started: Instant, result: &io::Result<Output>) {
+    let duration_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(out) if out.status.success() => {
+            tracing::info!(
+                program = %program,
+                args = ?args,
+                duration_ms,
+                exit_code = out.status.code(),
+                "external command finished"
+            );
+        }
+        Ok(out) => {
+            tracing::warn!(
+                program = %program,
+                args = ?args,
+                duration_ms,
+                exit_code = out.status.code(),
+                stderr = %stderr_tail(&out.stderr),
+                "external command failed"
+            );
+        }
+        Err(e) => {
+            tracing::error!(
+                program = %program,
+                args = ?args,
+                error = %e,
+                "external command could not be started"
+            );
+        }
+    }
+}
+
+/// Feed each complete line of `buffer` to `on_line`, keeping the unfinished
+/// tail; ffmpeg ends its progress lines with `\r`, so those count too
+fn drain_lines(buffer: &mut Vec<u8>, on_line: &mut dyn FnMut(&str)) {
+    while let Some(end) = buffer.iter().position(|b| *b == b'\n' || *b == b'\r') {
+        let line: Vec<u8> = buffer.drain(..=end).collect();
+        let line = String::from_utf8_lossy(&line[..end]);
+        if !line.trim().is_empty() {
+            on_line(&line);
+        }
+    }
+}
+
+/// Async counterpart of `output` for code running on the async runtime
+///
+/// Nothing blocks a runtime thread while the command runs, and the child is
+/// killed if the returned future is dropped (a cancelled command) or runs
+/// past `timeout`, which fails with `ErrorKind::TimedOut`. Stderr is passed
+/// to `on_stderr_line` as it's written, as well as collected in the output.
+pub async fn run(
+    cmd: &mut tokio::process::Command,
+    timeout: Option<Duration>,
+    on_stderr_line: impl FnMut(&str),
+) -> io::Result<Output> {
+    run_streaming(cmd, timeout, None, on_stderr_line).await
+}
+
+/// `run`, also passing stdout to `on_stdout_line` as it's written, for
+/// tools that report progress there
+/// Without a callback stdout is only collected, since it may be binary.
+pub async fn run_streaming(
+    cmd: &mut tokio::process::Command,
+    timeout: Option<Duration>,
+    mut on_stdout_line: Option<&mut (dyn FnMut(&str) + Send)>,
+    mut on_stderr_line: impl FnMut(&str),
+) -> io::Result<Output> {
+    let program = cmd.as_std().get_program().to_string_lossy().into_owned();
+    let args: Vec<String> = cmd.as_std().get_args().map(|a| a.to_string_lossy().into_owned()).collect();
+
+    let started = Instant::now();
+    let result: io::Result<Output> = async {
+        let mut child = cmd
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let _tracked = child.id().map(|pid| session::track(pid, &program, &args));
+        let mut stdout_pipe = child.stdout.take().expect("stdout is piped");
+        let mut stderr_pipe = child.stderr.take().expect("stderr is piped");
+
+        let read_stdout = async {
+            let (mut stdout, mut pending, mut chunk) = (Vec::new(), Vec::new(), [0u8; 8192]);
+            loop {
+                let n = stdout_pipe.read(&mut chunk).await?;
+                if n == 0 {
+                    break;
+                }
+                stdout.extend_from_slice(&chunk[..n]);
+                if let Some(on_line) = on_stdout_line.as_deref_mut() {
+                    pending.extend_from_slice(&chunk[..n]);
+                    drain_lines(&mut pending, on_line);
+                }
+            }
+            if let Some(on_line) = on_stdout_line.as_deref_mut() {
+                pending.push(b'\n');
+                drain_lines(&mut pending, on_line);
+            }
+            Ok::<_, io::Error>(stdout)
+        };
+        let read_stderr = async {
+            let (mut stderr, mut pending, mut chunk) = (Vec::new(), Vec::new(), [0u8; 8192]);
+            loop {
+                let n = stderr_pipe.read(&mut chunk).await?;
+                if n == 0 {
+                    break;
+                }
+                stderr.extend_from_slice(&chunk[..n]);
+                pending.extend_from_slice(&chunk[..n]);
+                drain_lines(&mut pending, &mut on_stderr_line);
+            }
+            pending.push(b'\n');
+            drain_lines(&mut pending, &mut on_stderr_line);
+            Ok::<_, io::Error>(stderr)
+        };
+        let finish = async {
+            let (stdout, stderr, status) = tokio::try_join!(read_stdout, read_stderr, child.wait())?;
+            Ok::<_, io::Error>(Output { status, stdout, stderr })
+        };
+        match timeout {
+            Some(limit) => tokio::time::timeout(limit, finish).await.unwrap_or_else(|_| {
+                Err(io::Error::new(io::ErrorKind::TimedOut, format!("timed out after {}s", limit.as_secs())))
+            }),
+            None => finish.await,
+        }
+    }
+    .await;
+    log_finished(&program, &args, started, &result);
+    result
+}
+
+/// Error for a command that couldn't be started
+pub fn spawn_error(program: &str, error: io::Error) -> ClipFlowError {
+    let is_ffmpeg = program.contains("ffmpeg") || program.contains("ffprobe");
+    if is_ffmpeg && error.kind() == io::ErrorKind::NotFound {
+        ClipFlowError::FfmpegNotFound { program: program.to_string() }
+    } else if error.kind() == io::ErrorKind::TimedOut {
+        ClipFlowError::tool(program, error.to_string())
+    } else {
+        ClipFlowError::tool(program, format!("could not be started: {}", error))
+    }
+}
+
+/// Run ffmpeg with the given arguments, mapping failure to an error naming
+/// the operation and the diagnosed cause (e.g. "ffmpeg speed change failed:
+/// The disk is full...")
+pub fn run_ffmpeg<S: AsRef<OsStr>>(args: &[S], operation: &str) -> Result<()> {
+    let ffmpeg = settings::ffmpeg_bin();
+    if let Some(plan) = PlanRecorder::current() {
+        plan.push(&ffmpeg, args);
+        return Ok(());
+    }
+    let out = output(Command::new(&ffmpeg).args(args)).map_err(|e| spawn_error(&ffmpeg, e))?;
+    check_ffmpeg(&out, operation)
+}
+
+/// Async counterpart of `run_ffmpeg`; dropping the future kills ffmpeg
+pub async fn run_ffmpeg_async<S: AsRef<OsStr>>(args: &[S], operation: &str) -> Result<()> {
+    let ffmpeg = settings::ffmpeg_bin();
+    if let Some(plan) = PlanRecorder::current() {
+        plan.push(&ffmpeg, args);
+        return Ok(());
+    }
+    let out = run(tokio::process::Command::new(&ffmpeg).args(args), None, |_| {})
+        .await
+        .map_err(|e| spawn_error(&ffmpeg, e))?;
+    check_ffmpeg(&out, operation)
+}
+
+/// Turn a finished ffmpeg run into `FfmpegFailed` if it exited with an error
+pub fn check_ffmpeg(out: &Output, operation: &str) -> Result<()> {
+    if out.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    Err(ClipFlowError::FfmpegFailed {
+        operation: operation.to_string(),
+        code: out.status.code(),
+        stderr: stderr_tail(&out.stderr),
+        diagnosis: diagnostics::classify(&stderr),
+    })
+}
@@ -0,0 +1,213 @@
+//! Project files - the full editing state (clips, cuts, transcript, export
+//! settings) serialized to a versioned JSON document on disk
+
+use crate::error::{ClipFlowError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+/// Current on-disk schema version
+/// Bump this and add a step to `migrate` whenever the layout changes
+pub const PROJECT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Project {
+    pub version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub clips: Vec<Clip>,
+    #[serde(default)]
+    pub cuts: Vec<Cut>,
+    #[serde(default)]
+    pub transcript: Option<Transcript>,
+    #[serde(default)]
+    pub export_settings: ExportSettings,
+    #[serde(default)]
+    pub markers: Vec<Marker>,
+}
+
+/// A source file placed in the project, trimmed to its in/out points
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Clip {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub duration: f64,
+    pub in_point: f64,
+    pub out_point: f64,
+}
+
+/// A range removed from a clip (silence, filler words, manual cuts)
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Cut {
+    pub clip_id: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkerColor {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Cyan,
+    #[default]
+    Blue,
+    Purple,
+    Pink,
+}
+
+/// A named point in a clip, e.g. a highlight bookmarked while streaming
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Marker {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub color: MarkerColor,
+    pub clip_id: String,
+    /// Seconds from the start of the clip's source file
+    pub time: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Transcript {
+    pub language: String,
+    pub text: String,
+    pub segments: Vec<TranscriptSegment>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TranscriptSegment {
+    pub id: usize,
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    /// e.g. "Speaker 1", when the transcription was diarized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+    /// Per-word timing, when Whisper reported it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<TranscriptWord>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TranscriptWord {
+    pub start: f64,
+    pub end: f64,
+    pub word: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ExportSettings {
+    pub quality: String,
+    pub format: String,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        ExportSettings {
+            quality: "medium".to_string(),
+            format: "mp4".to_string(),
+        }
+    }
+}
+
+/// Upgrade a raw project document to the current schema version
+///
+/// Version 0 is the unversioned dump of the frontend's state
+/// (`videos` + `exportSettings`, camelCase) from before projects
+/// were persisted by the backend.
+fn migrate(mut doc: Value) -> Result<Value> {
+    let mut version = doc.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+
+    if version > PROJECT_VERSION {
+        return Err(ClipFlowError::invalid(format!(
+            "Project was saved by a newer version of ClipFlow (schema v{}, supported up to v{})",
+            version, PROJECT_VERSION
+        )));
+    }
+
+    while version < PROJECT_VERSION {
+        doc = match version {
+            0 => migrate_v0_to_v1(doc),
+            _ => unreachable!(),
+        };
+        version += 1;
+    }
+
+    Ok(doc)
+}
+
+fn migrate_v0_to_v1(doc: Value) -> Value {
+    let clips: Vec<Value> = doc["videos"]
+        .as_array()
+        .map(|videos| {
+            videos
+                .iter()
+                .map(|video| {
+                    let duration = video["duration"].as_f64().unwrap_or(0.0);
+                    json!({
+                        "id": video["id"].as_str().unwrap_or(""),
+                        "name": video["name"].as_str().unwrap_or(""),
+                        "path": video["path"].as_str().unwrap_or(""),
+                        "duration": duration,
+                        "in_point": 0.0,
+                        "out_point": duration,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let settings = &doc["exportSettings"];
+
+    json!({
+        "version": 1,
+        "name": doc["name"].as_str().unwrap_or("Untitled"),
+        "clips": clips,
+        "cuts": [],
+        "transcript": null,
+        "markers": [],
+        "export_settings": {
+            "quality": settings["quality"].as_str().unwrap_or("medium"),
+            "format": settings["format"].as_str().unwrap_or("mp4"),
+        },
+    })
+}
+
+/// Serialize a project and write it to disk
+/// Writes to a sibling temp file first so a crash mid-save never truncates the project
+pub fn write_project(path: &Path, project: &Project) -> Result<()> {
+    let mut project = project.clone();
+    project.version = PROJECT_VERSION;
+
+    let json = serde_json::to_string_pretty(&project)
+        .map_err(|e| ClipFlowError::io("Failed to serialize project", e))?;
+
+    let tmp_path = path.with_extension("clipflow.tmp");
+    fs::write(&tmp_path, json).map_err(|e| ClipFlowError::io("Failed to write project", e))?;
+    fs::rename(&tmp_path, path).map_err(|e| ClipFlowError::io("Failed to save project", e))
+}
+
+/// Read a project from disk, migrating older schema versions
+pub fn read_project(path: &Path) -> Result<Project> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| ClipFlowError::io(&format!("Failed to read project {}", path.display()), e))?;
+    let doc: Value = serde_json::from_str(&content)
+        .map_err(|e| ClipFlowError::invalid(format!("Project file is not valid JSON: {}", e)))?;
+
+    serde_json::from_value(migrate(doc)?).map_err(|e| ClipFlowError::invalid(format!("Invalid project file: {}", e)))
+}
+
+#[tauri::command]
+pub async fn save_project(path: &str, project: Project) -> Result<()> {
+    write_project(Path::new(path), &project)
+}
+
+#[tauri::command]
+pub async fn load_project(path: &str) -> Result<Project> {
+    read_project(Path::new(path))
+}
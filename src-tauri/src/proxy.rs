@@ -0,0 +1,190 @@
+//! Edit-friendly proxy media for smooth scrubbing
+//!
+//! Long-GOP camera files decode slowly when seeking, so previews use an
+//! intra-only proxy instead. The proxy codec is picked per machine: ProRes
+//! Proxy where VideoToolbox decodes it in hardware, All-I H.264 where a GPU
+//! decodes H.264, and DNxHR LB (cheap to decode in software) otherwise.
+//!
+//! The player's proxies live in the shared cache, named by a hash of the
+//! source's contents, so a renamed or moved source still finds its proxy
+//! and an edited one gets a fresh proxy.
+
+use crate::cache::{self, CacheKind};
+use crate::error::Result;
+use crate::output::{self, OverwritePolicy, PartialOutput};
+use crate::{probe, process, settings};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+use tauri::AppHandle;
+use tokio::sync::Mutex;
+
+/// Proxy frame height; width follows the source aspect ratio
+const PROXY_HEIGHT: u32 = 540;
+
+/// Sources taller than this get a cached proxy; smaller ones play fine as-is
+const PROXY_MIN_SOURCE_HEIGHT: u32 = 1080;
+
+/// Serializes proxy generation, so two requests for the same source don't
+/// both render it
+static PROXY_LOCK: Mutex<()> = Mutex::const_new(());
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyFormat {
+    /// H.264 with every frame a keyframe, in MP4
+    AllIntraH264,
+    /// ProRes 422 Proxy in MOV
+    ProresProxy,
+    /// DNxHR LB in MOV
+    DnxhrLb,
+}
+
+impl ProxyFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ProxyFormat::AllIntraH264 => "mp4",
+            ProxyFormat::ProresProxy | ProxyFormat::DnxhrLb => "mov",
+        }
+    }
+
+    fn codec_args(&self) -> Vec<&'static str> {
+        match self {
+            ProxyFormat::AllIntraH264 => vec![
+                "-c:v", "libx264", "-preset", "veryfast", "-crf", "23", "-g", "1",
+                "-pix_fmt", "yuv420p", "-c:a", "aac", "-b:a", "128k",
+            ],
+            ProxyFormat::ProresProxy => vec![
+                "-c:v", "prores_ks", "-profile:v", "0", "-pix_fmt", "yuv422p10le",
+                "-c:a", "pcm_s16le",
+            ],
+            ProxyFormat::DnxhrLb => vec![
+                "-c:v", "dnxhd", "-profile:v", "dnxhr_lb", "-pix_fmt", "yuv422p",
+                "-c:a", "pcm_s16le",
+            ],
+        }
+    }
+}
+
+static HWACCELS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Hardware decode methods this ffmpeg build can use ("cuda", "videotoolbox", ...)
+pub fn hardware_decoders() -> &'static [String] {
+    HWACCELS.get_or_init(|| {
+        let ffmpeg = settings::ffmpeg_bin();
+        let output = match process::output(Command::new(&ffmpeg).args(["-hide_banner", "-hwaccels"])) {
+            Ok(output) if output.status.success() => output,
+            _ => return Vec::new(),
+        };
+        // First line is the "Hardware acceleration methods:" header
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .skip(1)
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect()
+    })
+}
+
+/// Best proxy format for this machine's decode hardware
+pub fn select_format(hwaccels: &[String]) -> ProxyFormat {
+    let has = |name: &str| hwaccels.iter().any(|h| h == name);
+    if cfg!(target_os = "macos") && has("videotoolbox") {
+        ProxyFormat::ProresProxy
+    } else if ["cuda", "qsv", "d3d11va", "dxva2", "vaapi"].iter().any(|h| has(h)) {
+        ProxyFormat::AllIntraH264
+    } else {
+        ProxyFormat::DnxhrLb
+    }
+}
+
+#[derive(Serialize)]
+pub struct ProxyCapabilities {
+    pub hardware_decoders: Vec<String>,
+    pub recommended: ProxyFormat,
+}
+
+/// Render a proxy of `input_path` in `format`
+pub async fn render_proxy(input_path: &str, output_path: &str, format: ProxyFormat) -> Result<()> {
+    // Scale from display width, not storage width, for non-square pixels
+    let mut video_filters: Vec<String> = match probe::video_geometry_async(input_path).await {
+        Ok(geometry) => probe::square_pixel_filter(&geometry).into_iter().collect(),
+        Err(_) => Vec::new(),
+    };
+    video_filters.push(format!("scale=-2:{}", PROXY_HEIGHT));
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-vf".to_string(), video_filters.join(","),
+        "-map".to_string(), "0:v:0".to_string(),
+        "-map".to_string(), "0:a?".to_string(),
+    ];
+    args.extend(format.codec_args().into_iter().map(String::from));
+    args.push(output_path.to_string());
+    args.push("-y".to_string());
+    process::run_ffmpeg_async(&args, "proxy").await
+}
+
+/// Decode hardware found and the proxy format it favors
+#[tauri::command]
+pub async fn get_proxy_capabilities() -> Result<ProxyCapabilities> {
+    let hardware_decoders = hardware_decoders().to_vec();
+    let recommended = select_format(&hardware_decoders);
+    Ok(ProxyCapabilities { hardware_decoders, recommended })
+}
+
+#[derive(Serialize)]
+pub struct CreatedProxy {
+    pub path: String,
+    pub format: ProxyFormat,
+}
+
+/// Create a proxy; `format` defaults to the one recommended for this machine
+/// The output extension is replaced to match the format's container
+#[tauri::command]
+pub async fn create_proxy(
+    input_path: &str,
+    output_path: &str,
+    format: Option<ProxyFormat>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<CreatedProxy> {
+    let format = format.unwrap_or_else(|| select_format(hardware_decoders()));
+    let path = Path::new(output_path).with_extension(format.extension()).to_string_lossy().into_owned();
+    let path = output::write_atomically_async(&path, overwrite.unwrap_or_default(), |partial| async move {
+        render_proxy(input_path, &partial, format).await
+    })
+    .await?;
+    Ok(CreatedProxy { path, format })
+}
+
+#[derive(Serialize)]
+pub struct PlaybackMedia {
+    /// What the player should open: the proxy, or the source itself
+    pub path: String,
+    pub is_proxy: bool,
+}
+
+/// What the preview player should open for `input_path`, rendering and
+/// caching a proxy first if the source is large and has none yet
+/// `force` makes a proxy even for sources small enough to play directly.
+#[tauri::command]
+pub async fn get_or_create_proxy(app: AppHandle, input_path: &str, force: Option<bool>) -> Result<PlaybackMedia> {
+    let geometry = probe::video_geometry(input_path)?;
+    if geometry.display_height <= PROXY_MIN_SOURCE_HEIGHT && !force.unwrap_or(false) {
+        return Ok(PlaybackMedia { path: input_path.to_string(), is_proxy: false });
+    }
+
+    let format = ProxyFormat::AllIntraH264;
+    let file_name = format!("{}.{}", cache::source_key(Path::new(input_path))?, format.extension());
+    let _guard = PROXY_LOCK.lock().await;
+    if let Some(path) = cache::lookup(&app, CacheKind::Proxies, &file_name)? {
+        return Ok(PlaybackMedia { path: path.to_string_lossy().into_owned(), is_proxy: true });
+    }
+
+    let partial = PartialOutput::new(&cache::entry_path(&app, CacheKind::Proxies, &file_name)?);
+    render_proxy(input_path, &partial.path_str(), format).await?;
+    partial.commit()?;
+    let path = cache::insert(&app, CacheKind::Proxies, &file_name, input_path)?;
+    tracing::info!(source = %input_path, proxy = %path.display(), "cached proxy created");
+    Ok(PlaybackMedia { path: path.to_string_lossy().into_owned(), is_proxy: true })
+}
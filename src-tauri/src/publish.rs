@@ -0,0 +1,247 @@
+//! Scheduled publishing: finished files are held with a delivery target
+//! until their publish time, then delivered unattended
+//!
+//! The queue is persisted to the app data dir, so a batch scheduled on
+//! Friday still goes out on Monday even if the app was restarted in between.
+//! Deliveries whose time passed while the app was closed run at next start.
+
+use crate::error::{ClipFlowError, Result};
+use crate::retry::{self, NetworkError};
+use crate::{cloud, now_secs, settings, youtube};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+
+/// How often the queue is checked for deliveries that are due
+const PUBLISH_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Where a scheduled file goes when its time comes
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Delivery {
+    /// POST a JSON notice describing the file, plus any extra payload
+    Webhook {
+        url: String,
+        #[serde(default)]
+        payload: serde_json::Value,
+    },
+    /// Upload to a connected YouTube account, "default" unless named
+    #[serde(rename = "youtube")]
+    YouTube {
+        account: Option<String>,
+        #[serde(flatten)]
+        details: youtube::VideoDetails,
+    },
+    /// Upload to a saved S3-compatible storage destination, by id
+    Cloud { destination: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PublishStatus {
+    Scheduled,
+    Running,
+    Published,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScheduledPublish {
+    pub id: String,
+    pub file_path: String,
+    pub delivery: Delivery,
+    /// Unix seconds; the delivery runs at the first check after this
+    pub publish_at: u64,
+    pub status: PublishStatus,
+    pub error: Option<String>,
+    /// What the destination reported back, e.g. an HTTP status or video URL
+    pub result: Option<String>,
+    pub created_at: u64,
+    pub finished_at: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct PublishQueue {
+    items: Mutex<Vec<ScheduledPublish>>,
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn queue_path(app: &AppHandle) -> Result<PathBuf> {
+    settings::data_file(app, "publish_queue.json")
+}
+
+fn persist(app: &AppHandle, items: &[ScheduledPublish]) {
+    let write = || -> Result<()> {
+        let json = serde_json::to_string_pretty(items).map_err(|e| ClipFlowError::io("Failed to serialize publish queue", e))?;
+        fs::write(queue_path(app)?, json).map_err(|e| ClipFlowError::io("Failed to write publish queue", e))
+    };
+    if let Err(e) = write() {
+        tracing::warn!(error = %e, "failed to save publish queue");
+    }
+}
+
+fn load(app: &AppHandle) -> Vec<ScheduledPublish> {
+    let mut items: Vec<ScheduledPublish> = queue_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    // Deliveries cut off by a quit or crash are tried again
+    for item in items.iter_mut().filter(|i| i.status == PublishStatus::Running) {
+        item.status = PublishStatus::Scheduled;
+    }
+    items
+}
+
+fn update(app: &AppHandle, id: &str, change: impl FnOnce(&mut ScheduledPublish)) {
+    let queue = app.state::<PublishQueue>();
+    let mut items = queue.items.lock().unwrap();
+    if let Some(item) = items.iter_mut().find(|i| i.id == id) {
+        change(item);
+        let _ = app.emit("publish-updated", item.clone());
+    }
+    persist(app, &items);
+}
+
+async fn send_webhook(
+    client: &reqwest::Client,
+    url: &str,
+    body: &serde_json::Value,
+) -> std::result::Result<String, NetworkError> {
+    let response = client.post(url).json(body).send().await?;
+    let response = retry::check_response(response).await?;
+    Ok(format!("HTTP {}", response.status().as_u16()))
+}
+
+/// Carry out one delivery, returning what the destination reported
+async fn deliver(app: &AppHandle, item: &ScheduledPublish) -> Result<String> {
+    let path = Path::new(&item.file_path);
+    let size = fs::metadata(path)
+        .map_err(|_| ClipFlowError::not_found(format!("File to publish is gone: {}", item.file_path)))?
+        .len();
+    let policy = settings::current().network_retry;
+
+    match &item.delivery {
+        Delivery::Webhook { url, payload } => {
+            let body = serde_json::json!({
+                "file_path": item.file_path,
+                "file_name": path.file_name().map(|n| n.to_string_lossy().into_owned()),
+                "size_bytes": size,
+                "scheduled_for": item.publish_at,
+                "payload": payload,
+            });
+            let client = reqwest::Client::new();
+            retry::with_retry(&policy, "webhook delivery", |_| send_webhook(&client, url, &body)).await
+        }
+        Delivery::YouTube { account, details } => {
+            youtube::upload(app, &item.file_path, details, account.as_deref().unwrap_or("default")).await
+        }
+        Delivery::Cloud { destination } => {
+            let destination = cloud::find_destination(app, destination)?;
+            cloud::upload_file(app, &item.file_path, &destination).await
+        }
+    }
+}
+
+async fn run_due(app: &AppHandle) {
+    let now = now_secs();
+    let due: Vec<ScheduledPublish> = {
+        let queue = app.state::<PublishQueue>();
+        let items = queue.items.lock().unwrap();
+        items
+            .iter()
+            .filter(|i| i.status == PublishStatus::Scheduled && i.publish_at <= now)
+            .cloned()
+            .collect()
+    };
+
+    for item in due {
+        update(app, &item.id, |i| i.status = PublishStatus::Running);
+        let outcome = deliver(app, &item).await;
+        update(app, &item.id, |i| {
+            i.finished_at = Some(now_secs());
+            match outcome {
+                Ok(result) => {
+                    tracing::info!(publish = %i.id, file = %i.file_path, "scheduled publish delivered");
+                    i.status = PublishStatus::Published;
+                    i.result = Some(result);
+                }
+                Err(e) => {
+                    tracing::warn!(publish = %i.id, error = %e, "scheduled publish failed");
+                    i.status = PublishStatus::Failed;
+                    i.error = Some(e.to_string());
+                }
+            }
+        });
+    }
+}
+
+/// Load the saved queue and start delivering items as they come due
+pub fn start(app: AppHandle) {
+    *app.state::<PublishQueue>().items.lock().unwrap() = load(&app);
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(PUBLISH_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            run_due(&app).await;
+        }
+    });
+}
+
+/// Hold `file_path` until `publish_at` (unix seconds), then deliver it
+#[tauri::command]
+pub async fn schedule_publish(
+    app: AppHandle,
+    state: State<'_, PublishQueue>,
+    file_path: String,
+    delivery: Delivery,
+    publish_at: u64,
+) -> Result<ScheduledPublish> {
+    if !Path::new(&file_path).is_file() {
+        return Err(ClipFlowError::not_found(format!("File not found: {}", file_path)));
+    }
+    let item = ScheduledPublish {
+        id: format!("publish-{}-{}", now_secs(), NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+        file_path,
+        delivery,
+        publish_at,
+        status: PublishStatus::Scheduled,
+        error: None,
+        result: None,
+        created_at: now_secs(),
+        finished_at: None,
+    };
+    let mut items = state.items.lock().unwrap();
+    items.push(item.clone());
+    persist(&app, &items);
+    Ok(item)
+}
+
+#[tauri::command]
+pub async fn list_scheduled_publishes(state: State<'_, PublishQueue>) -> Result<Vec<ScheduledPublish>> {
+    Ok(state.items.lock().unwrap().clone())
+}
+
+/// Cancel a delivery that hasn't started yet
+#[tauri::command]
+pub async fn cancel_scheduled_publish(app: AppHandle, state: State<'_, PublishQueue>, id: String) -> Result<()> {
+    let mut items = state.items.lock().unwrap();
+    let item = items
+        .iter_mut()
+        .find(|i| i.id == id)
+        .ok_or_else(|| ClipFlowError::not_found(format!("Scheduled publish not found: {}", id)))?;
+    if item.status != PublishStatus::Scheduled {
+        return Err(ClipFlowError::invalid("Only scheduled deliveries can be cancelled"));
+    }
+    item.status = PublishStatus::Cancelled;
+    item.finished_at = Some(now_secs());
+    let _ = app.emit("publish-updated", item.clone());
+    persist(&app, &items);
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//! Measured picture quality of an encode against its source
+//!
+//! VMAF (Netflix's perceptual metric, 0-100), PSNR, and SSIM are computed
+//! in one decode of both files, with the encode scaled back to the source's
+//! frame size first, so a preset's CRF can be picked from numbers instead of
+//! squinting at stills. VMAF needs an ffmpeg built with libvmaf; without
+//! it only PSNR and SSIM are reported.
+
+use crate::error::{ClipFlowError, Result};
+use crate::{probe, process, settings};
+use serde::Serialize;
+use tokio::process::Command;
+
+/// Largest length difference, in seconds, still compared frame by frame
+const MAX_DURATION_MISMATCH: f64 = 1.0;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct QualityComparison {
+    /// 0-100; 93+ is generally indistinguishable from the source. None when
+    /// ffmpeg has no libvmaf
+    pub vmaf: Option<f64>,
+    /// Average over all frames, dB; None for identical pictures
+    pub psnr: Option<f64>,
+    /// 0-1
+    pub ssim: Option<f64>,
+}
+
+async fn has_libvmaf(ffmpeg: &str) -> bool {
+    match process::run(Command::new(ffmpeg).args(["-hide_banner", "-filters"]), None, |_| {}).await {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).lines().any(|l| l.split_whitespace().nth(1) == Some("libvmaf")),
+        Err(_) => false,
+    }
+}
+
+/// Number following `key` on the last line containing it
+fn last_value(stderr: &str, key: &str) -> Option<f64> {
+    let line = stderr.lines().rev().find(|l| l.contains(key))?;
+    line.split(key).nth(1)?.split_whitespace().next()?.parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+/// Compare `encoded` against `original`, frame by frame
+#[tauri::command]
+pub async fn compare_quality(original: &str, encoded: &str) -> Result<QualityComparison> {
+    let source = probe::video_geometry(original)?;
+    probe::video_geometry(encoded)?;
+    let (original_duration, encoded_duration) = (probe::media_duration(original)?, probe::media_duration(encoded)?);
+    if (original_duration - encoded_duration).abs() > MAX_DURATION_MISMATCH {
+        return Err(ClipFlowError::invalid(format!(
+            "The files have different lengths ({:.1}s and {:.1}s), so their frames don't line up",
+            original_duration, encoded_duration
+        )));
+    }
+
+    let ffmpeg = settings::ffmpeg_bin();
+    let vmaf = has_libvmaf(&ffmpeg).await;
+    let metrics = if vmaf { 3 } else { 2 };
+    let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+    // Each metric filter takes the encode first and the reference second
+    let mut graph = format!(
+        "[0:v]setpts=PTS-STARTPTS,scale={w}:{h}:flags=bicubic,format=yuv420p,split={n}[d0][d1]{d2};\
+         [1:v]setpts=PTS-STARTPTS,format=yuv420p,split={n}[r0][r1]{r2};\
+         [d0][r0]psnr;[d1][r1]ssim",
+        w = source.width,
+        h = source.height,
+        n = metrics,
+        d2 = if vmaf { "[d2]" } else { "" },
+        r2 = if vmaf { "[r2]" } else { "" },
+    );
+    if vmaf {
+        graph.push_str(&format!(";[d2][r2]libvmaf=n_threads={}", threads));
+    }
+
+    let output = process::run(Command::new(&ffmpeg).args([
+        "-hide_banner",
+        "-i", encoded,
+        "-i", original,
+        "-filter_complex", graph.as_str(),
+        "-f", "null",
+        "-",
+    ]), None, |_| {})
+    .await
+    .map_err(|e| process::spawn_error(&ffmpeg, e))?;
+    process::check_ffmpeg(&output, "quality comparison")?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let comparison = QualityComparison {
+        vmaf: vmaf.then(|| last_value(&stderr, "VMAF score:")).flatten(),
+        psnr: last_value(&stderr, "average:"),
+        ssim: last_value(&stderr, "All:"),
+    };
+    tracing::info!(original = %original, encoded = %encoded, vmaf = ?comparison.vmaf, psnr = ?comparison.psnr, ssim = ?comparison.ssim, "compared quality");
+    Ok(comparison)
+}
@@ -0,0 +1,86 @@
+//! Recently opened files, persisted in the app data dir for the
+//! "Continue editing" screen
+
+use crate::error::{ClipFlowError, Result};
+use crate::{now_secs, settings};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// Entries beyond this are dropped, oldest first
+const MAX_RECENT_FILES: usize = 20;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RecentFile {
+    path: String,
+    name: String,
+    duration: f64,
+    /// Playhead position when the file was last closed, in seconds
+    last_position: f64,
+    last_opened: u64,
+}
+
+fn recent_path(app: &AppHandle) -> Result<PathBuf> {
+    settings::data_file(app, "recent_files.json")
+}
+
+fn read_recent(app: &AppHandle) -> Result<Vec<RecentFile>> {
+    let path = recent_path(app)?;
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content = fs::read_to_string(&path).map_err(|e| ClipFlowError::io("Failed to read recent files", e))?;
+    // A corrupt list isn't worth failing over; start fresh
+    Ok(serde_json::from_str(&content).unwrap_or_default())
+}
+
+fn write_recent(app: &AppHandle, files: &[RecentFile]) -> Result<()> {
+    let json = serde_json::to_string_pretty(files)
+        .map_err(|e| ClipFlowError::io("Failed to serialize recent files", e))?;
+    fs::write(recent_path(app)?, json).map_err(|e| ClipFlowError::io("Failed to write recent files", e))
+}
+
+/// Record a file as opened, or update its position if already listed
+#[tauri::command]
+pub async fn add_recent_file(app: AppHandle, path: String, duration: f64, last_position: f64) -> Result<()> {
+    let name = Path::new(&path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.clone());
+
+    let mut files = read_recent(&app)?;
+    files.retain(|f| f.path != path);
+    files.insert(
+        0,
+        RecentFile {
+            path,
+            name,
+            duration,
+            last_position,
+            last_opened: now_secs(),
+        },
+    );
+    files.truncate(MAX_RECENT_FILES);
+
+    write_recent(&app, &files)
+}
+
+/// Recent files, most recent first, with files that no longer exist pruned
+#[tauri::command]
+pub async fn get_recent_files(app: AppHandle) -> Result<Vec<RecentFile>> {
+    let files = read_recent(&app)?;
+    let count = files.len();
+    let existing: Vec<RecentFile> = files.into_iter().filter(|f| Path::new(&f.path).exists()).collect();
+
+    if existing.len() != count {
+        write_recent(&app, &existing)?;
+    }
+
+    Ok(existing)
+}
+
+#[tauri::command]
+pub async fn clear_recent_files(app: AppHandle) -> Result<()> {
+    write_recent(&app, &[])
+}
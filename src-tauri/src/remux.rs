@@ -0,0 +1,172 @@
+//! Lossless container changes
+//!
+//! Rewrapping copies the encoded streams as-is into a new container, so
+//! it's fast and loses nothing - the usual fix for an OBS .mkv that an
+//! editor or phone refuses to open. Each stream is checked against what
+//! the target container can carry first: text subtitles are converted to
+//! the container's own format, streams it can't hold at all (image
+//! subtitles in MP4, fonts, data tracks) are dropped and reported, and
+//! video or audio it can't hold fails the whole remux rather than leaving
+//! a file with missing sound.
+
+use crate::error::{ClipFlowError, Result};
+use crate::output::{self, OverwritePolicy};
+use crate::presets::Container;
+use crate::{probe, process};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamAction {
+    Copied,
+    /// Re-encoded to the target container's subtitle format
+    Converted,
+    Dropped,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct StreamOutcome {
+    /// Stream index in the input
+    pub index: u64,
+    /// "video", "audio", "subtitle", "data", or "attachment"
+    pub kind: String,
+    pub codec: String,
+    pub action: StreamAction,
+}
+
+#[derive(Serialize)]
+pub struct RemuxResult {
+    pub path: String,
+    pub streams: Vec<StreamOutcome>,
+}
+
+fn video_supported(container: Container, codec: &str) -> bool {
+    match container {
+        Container::Mkv => true,
+        Container::Mp4 => matches!(codec, "h264" | "hevc" | "av1" | "vp9" | "mpeg4" | "mpeg2video"),
+        Container::Mov => matches!(codec, "h264" | "hevc" | "prores" | "dnxhd" | "mpeg4" | "mpeg2video" | "mjpeg"),
+        Container::Webm => matches!(codec, "vp8" | "vp9" | "av1"),
+    }
+}
+
+fn audio_supported(container: Container, codec: &str) -> bool {
+    match container {
+        Container::Mkv => true,
+        Container::Mp4 => matches!(codec, "aac" | "mp3" | "ac3" | "eac3" | "alac" | "opus" | "flac"),
+        Container::Mov => matches!(codec, "aac" | "mp3" | "ac3" | "eac3" | "alac") || codec.starts_with("pcm_"),
+        Container::Webm => matches!(codec, "opus" | "vorbis"),
+    }
+}
+
+/// Subtitle encoder a text track needs in `container`, or "copy"
+fn text_subtitle_codec(container: Container) -> &'static str {
+    match container {
+        Container::Mkv => "copy",
+        Container::Mp4 | Container::Mov => "mov_text",
+        Container::Webm => "webvtt",
+    }
+}
+
+const TEXT_SUBTITLES: [&str; 6] = ["subrip", "ass", "ssa", "mov_text", "webvtt", "text"];
+
+/// What happens to each input stream, plus the encoder for the ones kept
+/// Errors if a video or audio stream can't be carried by `container`.
+fn plan_streams(streams: &[Value], container: Container) -> Result<Vec<(StreamOutcome, &'static str)>> {
+    let mut plan = Vec::new();
+    let mut unsupported = Vec::new();
+    for stream in streams {
+        let kind = stream["codec_type"].as_str().unwrap_or("data").to_string();
+        let codec = stream["codec_name"].as_str().unwrap_or("unknown").to_string();
+        let cover_art = stream["disposition"]["attached_pic"].as_i64() == Some(1);
+        let (action, encoder) = match kind.as_str() {
+            "video" if cover_art => {
+                if container == Container::Mkv { (StreamAction::Copied, "copy") } else { (StreamAction::Dropped, "") }
+            }
+            "video" if video_supported(container, &codec) => (StreamAction::Copied, "copy"),
+            "audio" if audio_supported(container, &codec) => (StreamAction::Copied, "copy"),
+            "video" | "audio" => {
+                unsupported.push(format!("{} ({})", codec, kind));
+                continue;
+            }
+            "subtitle" if TEXT_SUBTITLES.contains(&codec.as_str()) => match text_subtitle_codec(container) {
+                "copy" => (StreamAction::Copied, "copy"),
+                encoder if codec == encoder => (StreamAction::Copied, "copy"),
+                encoder => (StreamAction::Converted, encoder),
+            },
+            _ if container == Container::Mkv => (StreamAction::Copied, "copy"),
+            _ => (StreamAction::Dropped, ""),
+        };
+        let index = stream["index"].as_u64().unwrap_or(0);
+        plan.push((StreamOutcome { index, kind, codec, action }, encoder));
+    }
+    if !unsupported.is_empty() {
+        return Err(ClipFlowError::invalid(format!(
+            ".{} can't hold {} without re-encoding - try .mkv, or export instead",
+            container.extension(),
+            unsupported.join(", ")
+        )));
+    }
+    if !plan.iter().any(|(s, _)| s.action != StreamAction::Dropped && (s.kind == "video" || s.kind == "audio")) {
+        return Err(ClipFlowError::invalid("No video or audio streams to remux"));
+    }
+    Ok(plan)
+}
+
+/// Rewrap the planned streams of `input_path` into `output_path`
+async fn render_remux(input_path: &str, output_path: &str, container: Container, plan: &[(StreamOutcome, &str)]) -> Result<()> {
+    let mut args: Vec<String> = vec!["-i".to_string(), input_path.to_string()];
+    let kept = plan.iter().filter(|(s, _)| s.action != StreamAction::Dropped);
+    for (out_index, (stream, encoder)) in kept.enumerate() {
+        args.extend([
+            "-map".to_string(), format!("0:{}", stream.index),
+            format!("-c:{}", out_index), encoder.to_string(),
+        ]);
+        // Apple players only accept HEVC tagged hvc1, not ffmpeg's default hev1
+        if stream.codec == "hevc" && matches!(container, Container::Mp4 | Container::Mov) {
+            args.extend([format!("-tag:{}", out_index), "hvc1".to_string()]);
+        }
+    }
+    args.extend([
+        "-map_metadata".to_string(), "0".to_string(),
+        "-map_chapters".to_string(), "0".to_string(),
+    ]);
+    if matches!(container, Container::Mp4 | Container::Mov) {
+        args.extend(["-movflags".to_string(), "+faststart".to_string()]);
+    }
+    args.push(output_path.to_string());
+    args.push("-y".to_string());
+    process::run_ffmpeg_async(&args, "remux").await
+}
+
+/// Rewrap `input_path` into `output_container` without re-encoding
+/// `output_path` defaults to the input path with the container's extension.
+#[tauri::command]
+pub async fn remux(
+    input_path: &str,
+    output_container: Container,
+    output_path: Option<String>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<RemuxResult> {
+    let output_path = output_path.unwrap_or_else(|| {
+        Path::new(input_path).with_extension(output_container.extension()).to_string_lossy().into_owned()
+    });
+    let ext = Path::new(&output_path).extension().map(|e| e.to_string_lossy().into_owned()).unwrap_or_default();
+    if Container::from_extension(&ext) != Some(output_container) {
+        return Err(ClipFlowError::invalid(format!("Output path must end in .{}", output_container.extension())));
+    }
+    if Path::new(&output_path) == Path::new(input_path) {
+        return Err(ClipFlowError::invalid("Remux output would overwrite the input"));
+    }
+    let json = probe::ffprobe_json(input_path)?;
+    let streams = json["streams"].as_array().cloned().unwrap_or_default();
+    let plan = plan_streams(&streams, output_container)?;
+
+    let streams = plan.iter().map(|(s, _)| s.clone()).collect();
+    let path = output::write_atomically_async(&output_path, overwrite.unwrap_or_default(), |partial| async move {
+        render_remux(input_path, &partial, output_container, &plan).await
+    })
+    .await?;
+    Ok(RemuxResult { path, streams })
+}
@@ -0,0 +1,207 @@
+//! Salvaging broken recordings
+//!
+//! A recording cut off by a crash or power loss is usually intact up to the
+//! point it stopped, but has no index or a damaged one, and players give up
+//! on it. Rewrapping it with ffmpeg's error tolerance on - skip corrupt
+//! packets, regenerate missing timestamps - writes a fresh, indexed file
+//! from whatever can be read.
+//!
+//! MKV, TS, and FLV recover well this way. An MP4/MOV whose moov atom was
+//! never written can't be opened at all, since that atom is the only
+//! description of its streams; those fail with a diagnosis saying so.
+//!
+//! `verify_file` finds damage without changing anything, by decoding the
+//! whole file and collecting every error ffmpeg reports on the way.
+
+use crate::error::{ClipFlowError, Result};
+use crate::output::{self, OverwritePolicy};
+use crate::{probe, process, settings};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::process::Command;
+
+/// Problem lines kept in the report
+const MAX_WARNINGS: usize = 20;
+
+/// Decode errors kept in a verification report; a badly damaged file can
+/// produce one per frame
+const MAX_DECODE_ERRORS: usize = 500;
+
+#[derive(Serialize)]
+pub struct RepairReport {
+    pub path: String,
+    /// Duration the damaged file claims, when it claims one
+    pub source_duration: Option<f64>,
+    pub recovered_duration: f64,
+    /// Streams in the repaired file, e.g. "h264 video"
+    pub streams: Vec<String>,
+    /// Damaged spots ffmpeg reported and skipped
+    pub errors_skipped: usize,
+    /// The first few of those messages
+    pub warnings: Vec<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct DecodeError {
+    /// Roughly where in the file it happened, in seconds
+    pub time: Option<f64>,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct VerifyReport {
+    /// No decode errors, and the whole file could be read
+    pub ok: bool,
+    pub duration: Option<f64>,
+    /// How far decoding got
+    pub decoded_duration: f64,
+    pub error_count: usize,
+    /// The first `MAX_DECODE_ERRORS` errors
+    pub errors: Vec<DecodeError>,
+}
+
+fn is_problem_line(line: &str) -> bool {
+    let line = line.to_ascii_lowercase();
+    ["error", "corrupt", "invalid", "truncat", "non monoton", "missing"].iter().any(|w| line.contains(w))
+}
+
+/// Rewrap `input_path` into `output_path` with error tolerance on,
+/// returning ffmpeg's complaints along the way
+async fn render_repair(input_path: &str, output_path: &str) -> Result<Vec<String>> {
+    let ffmpeg = settings::ffmpeg_bin();
+    let args = [
+        "-hide_banner",
+        "-err_detect", "ignore_err",
+        "-fflags", "+genpts+discardcorrupt",
+        "-i", input_path,
+        "-map", "0:v?",
+        "-map", "0:a?",
+        "-c", "copy",
+        "-avoid_negative_ts", "make_zero",
+        output_path,
+        "-y",
+    ];
+    if let Some(plan) = process::PlanRecorder::current() {
+        plan.push(&ffmpeg, &args);
+        return Ok(Vec::new());
+    }
+    let output = process::run(Command::new(&ffmpeg).args(args), None, |_| {})
+        .await
+        .map_err(|e| process::spawn_error(&ffmpeg, e))?;
+    process::check_ffmpeg(&output, "recording repair")?;
+
+    Ok(String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .filter(|l| is_problem_line(l))
+        .map(|l| l.trim().to_string())
+        .collect())
+}
+
+/// Duration and stream list of a repaired file
+fn describe(path: &str) -> Result<(f64, Vec<String>)> {
+    let json = probe::ffprobe_json(path)?;
+    let duration = json["format"]["duration"]
+        .as_str()
+        .and_then(|d| d.parse::<f64>().ok())
+        .unwrap_or(0.0);
+    let streams = json["streams"]
+        .as_array()
+        .map(|streams| {
+            streams
+                .iter()
+                .map(|s| format!("{} {}", s["codec_name"].as_str().unwrap_or("unknown"), s["codec_type"].as_str().unwrap_or("data")))
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok((duration, streams))
+}
+
+/// Recover what can be read from a truncated or unfinalized recording
+#[tauri::command]
+pub async fn repair_video(input_path: &str, output_path: &str, overwrite: Option<OverwritePolicy>) -> Result<RepairReport> {
+    let source_duration = probe::media_duration(input_path).ok();
+    let mut problems = Vec::new();
+    let mut recovered = (0.0, Vec::new());
+    // Checked before the partial is moved into place, so an empty result
+    // leaves nothing behind
+    let path = output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| {
+        let (problems, recovered) = (&mut problems, &mut recovered);
+        async move {
+            *problems = render_repair(input_path, &partial).await?;
+            // A planned repair has no file to check
+            if process::PlanRecorder::current().is_some() {
+                return Ok(());
+            }
+            *recovered = describe(&partial)?;
+            if recovered.1.is_empty() || recovered.0 <= 0.0 {
+                return Err(ClipFlowError::invalid(format!("Nothing playable could be recovered from {}", input_path)));
+            }
+            Ok(())
+        }
+    })
+    .await?;
+    let (recovered_duration, streams) = recovered;
+    tracing::info!(input = %input_path, recovered = recovered_duration, errors = problems.len(), "repaired recording");
+
+    Ok(RepairReport {
+        path,
+        source_duration,
+        recovered_duration,
+        streams,
+        errors_skipped: problems.len(),
+        warnings: problems.into_iter().take(MAX_WARNINGS).collect(),
+    })
+}
+
+/// Decode all of a file and list the errors found, with roughly where
+/// they happened, to check a recording is intact before editing it
+/// Nothing is written. Decoding takes about as long as a fast export.
+#[tauri::command]
+pub async fn verify_file(file_path: &str) -> Result<VerifyReport> {
+    let duration = probe::media_duration(file_path).ok();
+    let ffmpeg = settings::ffmpeg_bin();
+    // Errors carry no timestamp, so -progress reports the decode position
+    // on stdout and each error is stamped with the latest one
+    let position_us = AtomicU64::new(0);
+    let mut on_progress = |line: &str| {
+        if let Some(us) = line.strip_prefix("out_time_us=").and_then(|v| v.trim().parse::<u64>().ok()) {
+            position_us.store(us, Ordering::Relaxed);
+        }
+    };
+    let mut errors: Vec<DecodeError> = Vec::new();
+    let mut error_count = 0;
+    let output = process::run_streaming(
+        Command::new(&ffmpeg).args([
+            "-v", "error",
+            "-nostats",
+            "-progress", "pipe:1",
+            "-i", file_path,
+            "-map", "0:v?",
+            "-map", "0:a?",
+            "-f", "null",
+            "-",
+        ]),
+        None,
+        Some(&mut on_progress),
+        |line| {
+            error_count += 1;
+            if errors.len() < MAX_DECODE_ERRORS {
+                let us = position_us.load(Ordering::Relaxed);
+                let time = (us > 0).then_some(us as f64 / 1_000_000.0);
+                errors.push(DecodeError { time, message: line.trim().to_string() });
+            }
+        },
+    )
+    .await
+    .map_err(|e| process::spawn_error(&ffmpeg, e))?;
+    if !output.status.success() && error_count == 0 {
+        process::check_ffmpeg(&output, "file verification")?;
+    }
+
+    let decoded_duration = position_us.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+    // Decoding stopping well short of the container's duration means the
+    // end is missing, even without an error to say so
+    let complete = output.status.success() && duration.is_none_or(|d| decoded_duration >= d - 1.0);
+    tracing::info!(file = %file_path, errors = error_count, decoded = decoded_duration, "verified file");
+    Ok(VerifyReport { ok: error_count == 0 && complete, duration, decoded_duration, error_count, errors })
+}
@@ -0,0 +1,195 @@
+//! Shared retry/backoff layer for network-facing features (model downloads,
+//! uploads, webhooks)
+
+use crate::error::{ClipFlowError, Result};
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one
+    pub max_attempts: u32,
+    pub initial_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub backoff_multiplier: f64,
+    /// Give up once this much time has passed since the first attempt,
+    /// even if attempts remain
+    pub max_elapsed_secs: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 6,
+            initial_delay_ms: 1_000,
+            max_delay_ms: 60_000,
+            backoff_multiplier: 2.0,
+            max_elapsed_secs: 15 * 60,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Backoff before the given retry (1-based), with up to 25% jitter so
+    /// parallel transfers don't hammer the server in lockstep
+    fn delay_for(&self, retry: u32) -> Duration {
+        let base = self.initial_delay_ms as f64 * self.backoff_multiplier.powi(retry.saturating_sub(1) as i32);
+        let capped = base.min(self.max_delay_ms as f64);
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+        let jitter = capped * 0.25 * (nanos % 1000) as f64 / 1000.0;
+        Duration::from_millis((capped + jitter) as u64)
+    }
+}
+
+/// Outcome of a single failed attempt, deciding whether to try again
+#[derive(Debug)]
+pub enum NetworkError {
+    /// Connection reset, timeout, 5xx - worth retrying
+    Transient(String),
+    /// 429 or similar; wait at least `retry_after` if the server said so
+    RateLimited { retry_after: Option<Duration>, message: String },
+    /// Bad credentials, 4xx, invalid input - retrying won't help
+    Fatal(String),
+}
+
+impl NetworkError {
+    pub fn message(&self) -> &str {
+        match self {
+            NetworkError::Transient(m) | NetworkError::Fatal(m) => m,
+            NetworkError::RateLimited { message, .. } => message,
+        }
+    }
+}
+
+impl From<reqwest::Error> for NetworkError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() || e.is_connect() || e.is_request() || e.is_body() {
+            NetworkError::Transient(e.to_string())
+        } else if let Some(status) = e.status() {
+            classify_status(status.as_u16(), None, e.to_string())
+        } else {
+            NetworkError::Fatal(e.to_string())
+        }
+    }
+}
+
+/// Map an HTTP status to a retry decision
+pub fn classify_status(status: u16, retry_after: Option<Duration>, message: String) -> NetworkError {
+    match status {
+        429 => NetworkError::RateLimited { retry_after, message },
+        408 | 500..=599 => NetworkError::Transient(message),
+        _ => NetworkError::Fatal(message),
+    }
+}
+
+/// Turn a non-success response into a `NetworkError`, honoring Retry-After
+pub async fn check_response(response: reqwest::Response) -> std::result::Result<reqwest::Response, NetworkError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+    let body = response.text().await.unwrap_or_default();
+    let message = format!("HTTP {}: {}", status.as_u16(), body.chars().take(500).collect::<String>());
+
+    Err(classify_status(status.as_u16(), retry_after, message))
+}
+
+/// Run `op` until it succeeds, fails fatally, or the policy is exhausted
+/// `op` receives the 1-based attempt number
+pub async fn with_retry<T, F, Fut>(policy: &RetryPolicy, label: &str, mut op: F) -> Result<T>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = std::result::Result<T, NetworkError>>,
+{
+    let started = std::time::Instant::now();
+    let max_elapsed = Duration::from_secs(policy.max_elapsed_secs);
+    let mut attempt = 1;
+
+    loop {
+        let error = match op(attempt).await {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+
+        if let NetworkError::Fatal(message) = &error {
+            return Err(ClipFlowError::Network(format!("{} failed: {}", label, message)));
+        }
+        if attempt >= policy.max_attempts || started.elapsed() >= max_elapsed {
+            return Err(ClipFlowError::Network(format!(
+                "{} failed after {} attempts: {}",
+                label,
+                attempt,
+                error.message()
+            )));
+        }
+
+        let mut delay = policy.delay_for(attempt);
+        if let NetworkError::RateLimited { retry_after: Some(wait), .. } = &error {
+            delay = delay.max(*wait);
+        }
+        tracing::warn!(
+            operation = label,
+            attempt,
+            error = error.message(),
+            delay_ms = delay.as_millis() as u64,
+            "network operation failed, retrying"
+        );
+
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Persisted progress of a long transfer, so an interrupted upload or
+/// download resumes from the last confirmed byte instead of starting over
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct TransferCheckpoint {
+    pub id: String,
+    /// Upload session URL, multipart upload id, etc. - whatever the remote
+    /// side needs to continue the transfer
+    pub session: Option<String>,
+    pub bytes_done: u64,
+    pub total_bytes: u64,
+    pub attempts: u32,
+    /// Provider-specific resume data (e.g. completed part ETags)
+    #[serde(default)]
+    pub extra: serde_json::Value,
+}
+
+fn checkpoint_path(app: &AppHandle, id: &str) -> Result<PathBuf> {
+    let dir = settings::data_dir(app, "transfers")?;
+    let safe_id: String = id.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect();
+    Ok(dir.join(format!("{}.json", safe_id)))
+}
+
+impl TransferCheckpoint {
+    pub fn load(app: &AppHandle, id: &str) -> Option<TransferCheckpoint> {
+        let path = checkpoint_path(app, id).ok()?;
+        let content = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save(&self, app: &AppHandle) -> Result<()> {
+        let json = serde_json::to_string(self).map_err(|e| ClipFlowError::io("Failed to serialize checkpoint", e))?;
+        fs::write(checkpoint_path(app, &self.id)?, json).map_err(|e| ClipFlowError::io("Failed to write checkpoint", e))
+    }
+
+    pub fn clear(app: &AppHandle, id: &str) {
+        if let Ok(path) = checkpoint_path(app, id) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! Crash detection and cleanup of work orphaned by a previous session
+//!
+//! Each running instance keeps a session file in the app data dir listing
+//! the external processes (ffmpeg, whisper) it has in flight. The file is
+//! removed on a clean exit, so a session file whose owner is no longer
+//! running means that instance crashed. Its processes may still be running
+//! and its scratch files and half-written outputs are left behind.
+
+use crate::error::{ClipFlowError, Result};
+use crate::{now_secs, settings, temp};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// How often adopted orphans are checked for exit
+const ADOPT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TrackedProcess {
+    pub pid: u32,
+    pub program: String,
+    pub args: Vec<String>,
+    /// File the process writes, for commands that produce one
+    pub output_path: Option<String>,
+    pub started_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct SessionFile {
+    pid: u32,
+    started_at: u64,
+    processes: Vec<TrackedProcess>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OrphanedProcess {
+    #[serde(flatten)]
+    process: TrackedProcess,
+    /// Still running after its parent session died
+    running: bool,
+}
+
+#[derive(Serialize, Clone, Default)]
+pub struct CrashReport {
+    crashed_sessions: Vec<u32>,
+    processes: Vec<OrphanedProcess>,
+    /// Scratch files left by the crashed sessions
+    temp_artifacts: Vec<String>,
+    /// Outputs that were being written when the session died
+    partial_outputs: Vec<String>,
+}
+
+struct Session {
+    path: PathBuf,
+    file: SessionFile,
+}
+
+static SESSION: OnceLock<Mutex<Session>> = OnceLock::new();
+static CRASH_REPORT: Mutex<Option<CrashReport>> = Mutex::new(None);
+
+fn sessions_dir(app: &AppHandle) -> Result<PathBuf> {
+    settings::data_dir(app, "sessions")
+}
+
+/// Name of the running process with this pid, if any
+fn process_name(pid: u32) -> Option<String> {
+    let out = if cfg!(windows) {
+        Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+            .stderr(Stdio::null())
+            .output()
+            .ok()?
+    } else {
+        Command::new("ps")
+            .args(["-p", &pid.to_string(), "-o", "comm="])
+            .stderr(Stdio::null())
+            .output()
+            .ok()?
+    };
+    let text = String::from_utf8_lossy(&out.stdout);
+    let name = if cfg!(windows) {
+        // "ffmpeg.exe","1234",... - or an INFO line when nothing matched
+        text.lines().next()?.split(',').next()?.trim_matches('"').to_string()
+    } else {
+        text.trim().to_string()
+    };
+    (out.status.success() && !name.is_empty() && !name.starts_with("INFO:")).then_some(name)
+}
+
+/// Whether `pid` is still running `program`; a name check guards against
+/// the pid having been reused by something unrelated
+fn is_running(pid: u32, program: &str) -> bool {
+    let stem = Path::new(program)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    process_name(pid).is_some_and(|name| name.to_lowercase().contains(&stem))
+}
+
+fn terminate(pid: u32) -> bool {
+    let status = if cfg!(windows) {
+        Command::new("taskkill").args(["/PID", &pid.to_string(), "/T", "/F"]).status()
+    } else {
+        Command::new("kill").args(["-TERM", &pid.to_string()]).status()
+    };
+    status.map(|s| s.success()).unwrap_or(false)
+}
+
+fn write_session(session: &Session) {
+    if let Ok(json) = serde_json::to_string_pretty(&session.file) {
+        let tmp = session.path.with_extension("json.tmp");
+        if fs::write(&tmp, json).is_ok() {
+            let _ = fs::rename(&tmp, &session.path);
+        }
+    }
+}
+
+/// Scratch files created by a given process (temp names embed the pid)
+fn temp_artifacts_of(pid: u32) -> Vec<String> {
+    let marker = format!("_{}_", pid);
+    temp::scratch_dir()
+        .ok()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_name().to_string_lossy().contains(&marker))
+                .map(|e| e.path().to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Look for sessions that died without a clean exit, then register this one
+pub fn start(app: &AppHandle) -> Result<()> {
+    let dir = sessions_dir(app)?;
+    let own_pid = std::process::id();
+    let exe = std::env::current_exe()
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut report = CrashReport::default();
+    for entry in fs::read_dir(&dir).map_err(|e| ClipFlowError::io("Failed to read sessions dir", e))? {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(_) => continue,
+        };
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let previous: SessionFile = match fs::read_to_string(&path).ok().and_then(|c| serde_json::from_str(&c).ok()) {
+            Some(previous) => previous,
+            None => {
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+        };
+        if previous.pid == own_pid || is_running(previous.pid, &exe) {
+            // Another instance is alive and still owns its processes
+            continue;
+        }
+
+        tracing::warn!(pid = previous.pid, processes = previous.processes.len(), "found crashed session");
+        report.crashed_sessions.push(previous.pid);
+        report.temp_artifacts.extend(temp_artifacts_of(previous.pid));
+        for process in previous.processes {
+            let running = is_running(process.pid, &process.program);
+            if let Some(output) = &process.output_path {
+                if Path::new(output).exists() {
+                    report.partial_outputs.push(output.clone());
+                }
+            }
+            report.processes.push(OrphanedProcess { process, running });
+        }
+        let _ = fs::remove_file(&path);
+    }
+
+    if !report.crashed_sessions.is_empty() {
+        let _ = app.emit("crash-recovery-available", report.clone());
+        *CRASH_REPORT.lock().unwrap() = Some(report);
+    }
+
+    let session = Session {
+        path: dir.join(format!("{}.json", own_pid)),
+        file: SessionFile { pid: own_pid, started_at: now_secs(), processes: vec![] },
+    };
+    write_session(&session);
+    let _ = SESSION.set(Mutex::new(session));
+    Ok(())
+}
+
+/// Remove this session's file on a clean exit
+pub fn end() {
+    if let Some(session) = SESSION.get() {
+        let _ = fs::remove_file(&session.lock().unwrap().path);
+    }
+}
+
+/// Record a running child process until the returned guard is dropped
+pub fn track(pid: u32, program: &str, args: &[String]) -> ProcessGuard {
+    // ffmpeg's last argument is its output; "-y" follows it in some commands
+    let output_path = if Path::new(program).file_stem().is_some_and(|s| s.to_string_lossy().contains("ffmpeg")) {
+        args.iter().rev().find(|a| a.as_str() != "-y").cloned()
+    } else {
+        None
+    };
+    if let Some(session) = SESSION.get() {
+        let mut session = session.lock().unwrap();
+        session.file.processes.push(TrackedProcess {
+            pid,
+            program: program.to_string(),
+            args: args.to_vec(),
+            output_path,
+            started_at: now_secs(),
+        });
+        write_session(&session);
+    }
+    ProcessGuard { pid }
+}
+
+pub struct ProcessGuard {
+    pid: u32,
+}
+
+impl Drop for ProcessGuard {
+    fn drop(&mut self) {
+        if let Some(session) = SESSION.get() {
+            let mut session = session.lock().unwrap();
+            session.file.processes.retain(|p| p.pid != self.pid);
+            write_session(&session);
+        }
+    }
+}
+
+/// What a previous crashed session left behind, if anything
+#[tauri::command]
+pub async fn get_crash_report() -> Result<Option<CrashReport>> {
+    Ok(CRASH_REPORT.lock().unwrap().clone())
+}
+
+/// Kill orphaned processes that are still running
+#[tauri::command]
+pub async fn terminate_orphaned_processes() -> Result<Vec<u32>> {
+    let mut report = CRASH_REPORT.lock().unwrap();
+    let mut killed = Vec::new();
+    if let Some(report) = report.as_mut() {
+        for orphan in report.processes.iter_mut().filter(|o| o.running) {
+            if is_running(orphan.process.pid, &orphan.process.program) && terminate(orphan.process.pid) {
+                tracing::info!(pid = orphan.process.pid, "terminated orphaned process");
+                killed.push(orphan.process.pid);
+            }
+            orphan.running = false;
+        }
+    }
+    Ok(killed)
+}
+
+/// Let still-running orphans finish, emitting "orphan-finished" with each
+/// one's output path as it exits
+#[tauri::command]
+pub async fn adopt_orphaned_processes(app: AppHandle) -> Result<usize> {
+    let orphans: Vec<TrackedProcess> = CRASH_REPORT
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|r| r.processes.iter().filter(|o| o.running).map(|o| o.process.clone()).collect())
+        .unwrap_or_default();
+    let count = orphans.len();
+
+    tauri::async_runtime::spawn(async move {
+        let mut pending = orphans;
+        while !pending.is_empty() {
+            tokio::time::sleep(ADOPT_POLL_INTERVAL).await;
+            pending.retain(|p| {
+                if is_running(p.pid, &p.program) {
+                    return true;
+                }
+                let _ = app.emit("orphan-finished", p.clone());
+                false
+            });
+        }
+    });
+    Ok(count)
+}
+
+/// Delete the crashed sessions' scratch files, and optionally their
+/// partial outputs, then dismiss the report
+///
+/// Outputs of processes that are still running are never deleted.
+#[tauri::command]
+pub async fn cleanup_crash_artifacts(delete_partial_outputs: bool) -> Result<usize> {
+    let report = match CRASH_REPORT.lock().unwrap().take() {
+        Some(report) => report,
+        None => return Ok(0),
+    };
+
+    let mut removed = 0;
+    for path in &report.temp_artifacts {
+        let path = Path::new(path);
+        let result = if path.is_dir() { fs::remove_dir_all(path) } else { fs::remove_file(path) };
+        if result.is_ok() {
+            removed += 1;
+        }
+    }
+
+    if delete_partial_outputs {
+        let busy: Vec<&str> = report
+            .processes
+            .iter()
+            .filter(|o| is_running(o.process.pid, &o.process.program))
+            .filter_map(|o| o.process.output_path.as_deref())
+            .collect();
+        for output in report.partial_outputs.iter().filter(|o| !busy.contains(&o.as_str())) {
+            if fs::remove_file(output).is_ok() {
+                removed += 1;
+            }
+        }
+    }
+
+    Ok(removed)
+}
@@ -0,0 +1,183 @@
+//! Persistent application settings, stored as JSON in the config dir
+
+use crate::error::{ClipFlowError, Result};
+use crate::retry::RetryPolicy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{OnceLock, RwLock};
+use tauri::{AppHandle, Emitter, Manager};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum HardwareAcceleration {
+    /// Use a hardware encoder if one is detected, software otherwise
+    Auto,
+    /// Always encode in software (libx264 and friends)
+    None,
+    Nvenc,
+    Qsv,
+    VideoToolbox,
+    Amf,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    pub default_export_preset: String,
+    /// Explicit ffmpeg binary, or None to use the one on PATH
+    pub ffmpeg_path: Option<String>,
+    /// Explicit ffprobe binary, or None to use the one on PATH
+    pub ffprobe_path: Option<String>,
+    pub whisper_model: String,
+    /// Speaker diarization tool, or None to use `diarize` on PATH
+    pub diarize_path: Option<String>,
+    /// Argos Translate binary, or None to use `argos-translate` on PATH
+    pub translate_path: Option<String>,
+    /// yt-dlp binary for downloading online videos, or None to use `yt-dlp`
+    /// on PATH
+    pub ytdlp_path: Option<String>,
+    /// OpenAI-compatible API base of the local LLM server (Ollama, llama.cpp)
+    pub llm_endpoint: String,
+    /// Model the LLM server should use
+    pub llm_model: String,
+    /// Words flagged by profanity detection on top of the built-in list;
+    /// a trailing `*` matches any ending
+    pub profanity_words: Vec<String>,
+    /// Scratch directory for intermediate files, or None for the system temp dir
+    pub temp_dir: Option<String>,
+    /// Folder of .cube LUTs, or None for "luts" in the app data dir
+    pub lut_dir: Option<String>,
+    pub hardware_acceleration: HardwareAcceleration,
+    /// Backoff limits for downloads, uploads, and webhooks
+    pub network_retry: RetryPolicy,
+    /// Retry failed jobs once with software encoding and cheaper filters
+    pub auto_fallback_on_failure: bool,
+    /// Size cap for cached proxies, thumbnails, and other derived files
+    pub cache_limit_mb: u64,
+    /// Queued jobs rendered at the same time
+    pub max_concurrent_encodes: usize,
+    /// Run the local HTTP/WebSocket API for scripts and external tools
+    pub control_server_enabled: bool,
+    /// Port the control server listens on, on 127.0.0.1
+    pub control_server_port: u16,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            default_export_preset: "medium".to_string(),
+            ffmpeg_path: None,
+            ffprobe_path: None,
+            whisper_model: "base".to_string(),
+            diarize_path: None,
+            translate_path: None,
+            ytdlp_path: None,
+            llm_endpoint: "http://localhost:11434/v1".to_string(),
+            llm_model: "llama3.1".to_string(),
+            profanity_words: Vec::new(),
+            temp_dir: None,
+            lut_dir: None,
+            hardware_acceleration: HardwareAcceleration::Auto,
+            network_retry: RetryPolicy::default(),
+            auto_fallback_on_failure: false,
+            cache_limit_mb: 10 * 1024,
+            max_concurrent_encodes: 1,
+            control_server_enabled: false,
+            control_server_port: 47810,
+        }
+    }
+}
+
+static CURRENT: OnceLock<RwLock<Settings>> = OnceLock::new();
+
+fn store() -> &'static RwLock<Settings> {
+    CURRENT.get_or_init(|| RwLock::new(Settings::default()))
+}
+
+/// Snapshot of the current settings for use outside of commands
+pub fn current() -> Settings {
+    store().read().unwrap().clone()
+}
+
+/// ffmpeg binary to invoke, honoring the configured path
+pub fn ffmpeg_bin() -> String {
+    current().ffmpeg_path.unwrap_or_else(|| "ffmpeg".to_string())
+}
+
+/// ffprobe binary to invoke, honoring the configured path
+pub fn ffprobe_bin() -> String {
+    current().ffprobe_path.unwrap_or_else(|| "ffprobe".to_string())
+}
+
+/// Scratch directory for intermediate files
+pub fn temp_dir() -> PathBuf {
+    current().temp_dir.map(PathBuf::from).unwrap_or_else(std::env::temp_dir)
+}
+
+fn created(dir: PathBuf) -> Result<PathBuf> {
+    fs::create_dir_all(&dir).map_err(|e| ClipFlowError::io(&format!("Failed to create {}", dir.display()), e))?;
+    Ok(dir)
+}
+
+fn app_data_dir(app: &AppHandle) -> Result<PathBuf> {
+    app.path().app_data_dir().map_err(|e| ClipFlowError::io("Failed to resolve app data dir", e))
+}
+
+/// `name` in the app's config dir, which is created if needed
+pub fn config_file(app: &AppHandle, name: &str) -> Result<PathBuf> {
+    let dir = app.path().app_config_dir().map_err(|e| ClipFlowError::io("Failed to resolve config dir", e))?;
+    Ok(created(dir)?.join(name))
+}
+
+/// `name` in the app's data dir, which is created if needed
+pub fn data_file(app: &AppHandle, name: &str) -> Result<PathBuf> {
+    Ok(created(app_data_dir(app)?)?.join(name))
+}
+
+/// Folder `name` under the app's data dir, created if needed
+pub fn data_dir(app: &AppHandle, name: &str) -> Result<PathBuf> {
+    created(app_data_dir(app)?.join(name))
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf> {
+    config_file(app, "settings.json")
+}
+
+/// Load settings from disk at startup
+/// A missing or unreadable file falls back to defaults rather than blocking launch
+pub fn load(app: &AppHandle) {
+    let settings = settings_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<Settings>(&content).ok())
+        .unwrap_or_default();
+    *store().write().unwrap() = settings;
+}
+
+#[tauri::command]
+pub async fn get_settings() -> Result<Settings> {
+    Ok(current())
+}
+
+/// Write `settings` to disk, make them current, and announce the change
+pub fn save(app: &AppHandle, settings: Settings) -> Result<()> {
+    let json = serde_json::to_string_pretty(&settings)
+        .map_err(|e| ClipFlowError::io("Failed to serialize settings", e))?;
+    fs::write(settings_path(app)?, json).map_err(|e| ClipFlowError::io("Failed to write settings", e))?;
+
+    *store().write().unwrap() = settings.clone();
+
+    app.emit("settings-changed", settings)
+        .map_err(|e| ClipFlowError::io("Failed to emit settings change", e))
+}
+
+#[tauri::command]
+pub async fn set_settings(app: AppHandle, settings: Settings) -> Result<()> {
+    if let Some(dir) = &settings.temp_dir {
+        if !PathBuf::from(dir).is_dir() {
+            return Err(ClipFlowError::invalid(format!("Temp directory does not exist: {}", dir)));
+        }
+    }
+    save(&app, settings)
+}
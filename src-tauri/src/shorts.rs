@@ -0,0 +1,171 @@
+//! One-step vertical clips: highlights cut, reframed to 9:16, captioned,
+//! and exported ready to upload as Shorts
+//!
+//! The file is transcribed first if it has no up-to-date transcript, since
+//! both the highlight picker and the captions use it. Reframing is a center
+//! crop; vertical sources are only scaled.
+
+use crate::captions::{self, CaptionOptions};
+use crate::error::{ClipFlowError, Result};
+use crate::highlights::{self, Highlight};
+use crate::output::{self, OverwritePolicy};
+use crate::project::Transcript;
+use crate::temp::TempFile;
+use crate::{escape_filter_path, presets, process, settings, transcripts};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, Emitter};
+
+/// YouTube's limit for a Short
+const MAX_SHORT_SECONDS: f64 = 180.0;
+const DEFAULT_MAX_DURATION: f64 = 60.0;
+const DEFAULT_COUNT: usize = 3;
+const MAX_COUNT: usize = 20;
+
+/// Narrow lines so captions stay clear of the edges on a phone
+const CAPTION_CHARS_PER_LINE: usize = 24;
+
+/// Large, bold captions sitting above the Shorts UI at the bottom of the
+/// screen (sizes are in libass's 288-line script space)
+const CAPTION_STYLE: &str = "FontSize=16,Bold=1,Outline=2,Shadow=0,Alignment=2,MarginV=70";
+
+#[derive(Serialize, Clone)]
+pub struct Short {
+    pub path: String,
+    pub start: f64,
+    pub end: f64,
+    pub confidence: f64,
+    pub reasons: Vec<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct ShortsProgress {
+    input: String,
+    done: usize,
+    total: usize,
+}
+
+/// The part of `transcript` inside `start..end`, retimed to start at zero
+fn clip_transcript(transcript: &Transcript, start: f64, end: f64) -> Transcript {
+    let segments: Vec<_> = transcript
+        .segments
+        .iter()
+        .filter(|s| s.end > start && s.start < end)
+        .map(|s| {
+            let mut segment = s.clone();
+            segment.start = (s.start - start).max(0.0);
+            segment.end = s.end.min(end) - start;
+            segment.words.retain(|w| w.end > start && w.start < end);
+            for word in &mut segment.words {
+                word.start = (word.start - start).max(0.0);
+                word.end = word.end.min(end) - start;
+            }
+            segment
+        })
+        .collect();
+    Transcript {
+        language: transcript.language.clone(),
+        text: segments.iter().map(|s| s.text.as_str()).collect::<Vec<_>>().join(" "),
+        segments,
+    }
+}
+
+/// Cut, reframe, caption, and encode one highlight to `partial`
+async fn render_short(
+    input: &str,
+    partial: &str,
+    highlight: &Highlight,
+    transcript: &Transcript,
+    preset: &presets::ExportPreset,
+) -> Result<()> {
+    let options = CaptionOptions { max_chars_per_line: Some(CAPTION_CHARS_PER_LINE), ..CaptionOptions::default() };
+    let srt = TempFile::new("short_captions", "srt")?;
+    let content = captions::render_captions(&clip_transcript(transcript, highlight.start, highlight.end), "srt", &options)?;
+    fs::write(srt.path(), content).map_err(|e| ClipFlowError::io("Failed to write Short captions", e))?;
+
+    let filters = [
+        "crop=w='min(iw,ih*9/16)':h='min(ih,iw*16/9)'".to_string(),
+        "scale=1080:1920:flags=lanczos".to_string(),
+        "setsar=1".to_string(),
+        format!("subtitles='{}':force_style='{}'", escape_filter_path(&srt.path_str()), CAPTION_STYLE),
+    ];
+    let mut args: Vec<String> = vec![
+        "-ss".to_string(), highlight.start.to_string(),
+        "-t".to_string(), (highlight.end - highlight.start).to_string(),
+        "-i".to_string(), input.to_string(),
+        "-map".to_string(), "0:v:0".to_string(),
+        "-map".to_string(), "0:a:0?".to_string(),
+        "-vf".to_string(), filters.join(","),
+    ];
+    args.extend(presets::codec_args(preset, true));
+    args.extend([
+        "-pix_fmt", "yuv420p",
+        "-ar", "48000",
+        "-movflags", "+faststart",
+    ].map(str::to_string));
+    args.extend([partial.to_string(), "-y".to_string()]);
+    process::run_ffmpeg_async(&args, "Short export").await
+}
+
+/// Make up to `count` vertical, captioned clips of at most `max_duration`
+/// seconds from the best moments of `input`, best first
+/// Clips are named after the input ("talk_short_1.mp4", ...) and never
+/// replace existing files. Emits "shorts-progress" as each one finishes.
+#[tauri::command]
+pub async fn generate_shorts(
+    app: AppHandle,
+    input: &str,
+    output_dir: &str,
+    count: Option<usize>,
+    max_duration: Option<f64>,
+) -> Result<Vec<Short>> {
+    let count = count.unwrap_or(DEFAULT_COUNT);
+    if !(1..=MAX_COUNT).contains(&count) {
+        return Err(ClipFlowError::invalid(format!("Ask for between 1 and {} Shorts, got {}", MAX_COUNT, count)));
+    }
+    let max_duration = max_duration.unwrap_or(DEFAULT_MAX_DURATION);
+    if max_duration.is_nan() || !(1.0..=MAX_SHORT_SECONDS).contains(&max_duration) {
+        return Err(ClipFlowError::invalid(format!("Shorts can be up to {} seconds long", MAX_SHORT_SECONDS)));
+    }
+    let preset = presets::resolve(&app, presets::SHORTS_PRESET)?
+        .ok_or_else(|| ClipFlowError::invalid("Missing built-in Shorts preset"))?;
+    presets::validate(&preset)?;
+    if process::PlanRecorder::current().is_none() {
+        fs::create_dir_all(output_dir).map_err(|e| ClipFlowError::io("Failed to create Shorts folder", e))?;
+    }
+
+    let transcript = match transcripts::load(&app, input)? {
+        Some(stored) if !stored.stale => stored.transcript,
+        _ => {
+            let model = settings::current().whisper_model;
+            crate::transcribe_audio(app.clone(), input, &model, None, None, None).await?.to_transcript()
+        }
+    };
+    let highlights = highlights::find_highlights(&app, input, count, max_duration).await?;
+    if highlights.is_empty() {
+        return Err(ClipFlowError::invalid(format!("Found nothing in {} worth making a Short of", input)));
+    }
+
+    let stem = Path::new(input).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let extension = preset.container.extension();
+    let (transcript, preset) = (&transcript, &preset);
+    let mut shorts = Vec::with_capacity(highlights.len());
+    for (i, highlight) in highlights.iter().enumerate() {
+        let output_path = Path::new(output_dir).join(format!("{}_short_{}.{}", stem, i + 1, extension));
+        let path = output::write_atomically_async(&output_path.to_string_lossy(), OverwritePolicy::AutoIncrement, |partial| async move {
+            render_short(input, &partial, highlight, transcript, preset).await
+        })
+        .await?;
+        tracing::info!(input = %input, output = %path, start = highlight.start, "Short exported");
+        shorts.push(Short {
+            path,
+            start: highlight.start,
+            end: highlight.end,
+            confidence: highlight.confidence,
+            reasons: highlight.reasons.clone(),
+        });
+        let _ = app.emit("shorts-progress", ShortsProgress { input: input.to_string(), done: i + 1, total: highlights.len() });
+    }
+    Ok(shorts)
+}
@@ -0,0 +1,229 @@
+//! Disclaimer/consent slates prepended to a video
+//!
+//! A slate is a text template rendered onto a plain background for a fixed
+//! number of seconds, optionally with a voiceover, then joined in front of
+//! the main video. Slates are saved as named presets, since clients that
+//! require one usually want the same wording on every delivery.
+
+use crate::error::{ClipFlowError, Result};
+use crate::output::{self, OverwritePolicy};
+use crate::temp::TempFile;
+use crate::transitions::{normalize_audio_filter, normalize_filter};
+use crate::{color, escape_filter_path, probe, process, settings};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SlatePreset {
+    pub name: String,
+    /// Slate text; `{placeholders}` are filled from the variables passed at
+    /// render time, plus `{date}` and `{file_name}`
+    pub template: String,
+    /// Seconds the slate stays on screen
+    pub duration: f64,
+    /// Background color in any form ffmpeg accepts, e.g. "black" or "#1a1a1a"
+    #[serde(default = "default_background")]
+    pub background: String,
+    #[serde(default = "default_text_color")]
+    pub text_color: String,
+    /// Font size as a fraction of the frame height
+    #[serde(default = "default_font_scale")]
+    pub font_scale: f64,
+    /// Audio played under the slate; silence if unset
+    pub voiceover_path: Option<String>,
+}
+
+fn default_background() -> String {
+    "black".to_string()
+}
+
+fn default_text_color() -> String {
+    "white".to_string()
+}
+
+fn default_font_scale() -> f64 {
+    0.045
+}
+
+fn validate(preset: &SlatePreset) -> Result<()> {
+    if preset.name.trim().is_empty() {
+        return Err(ClipFlowError::invalid("Slate name must not be empty"));
+    }
+    if preset.template.trim().is_empty() {
+        return Err(ClipFlowError::invalid("Slate text must not be empty"));
+    }
+    if !(0.5..=60.0).contains(&preset.duration) {
+        return Err(ClipFlowError::invalid(format!("Slate duration must be 0.5-60s, got {}", preset.duration)));
+    }
+    if !(0.01..=0.2).contains(&preset.font_scale) {
+        return Err(ClipFlowError::invalid(format!("Font scale must be 0.01-0.2, got {}", preset.font_scale)));
+    }
+    if let Some(voiceover) = &preset.voiceover_path {
+        if !Path::new(voiceover).is_file() {
+            return Err(ClipFlowError::not_found(format!("Voiceover not found: {}", voiceover)));
+        }
+    }
+    Ok(())
+}
+
+/// Fill `{placeholders}` in the template; unknown ones are left as written
+pub fn fill_template(template: &str, variables: &HashMap<String, String>) -> String {
+    variables
+        .iter()
+        .fold(template.to_string(), |text, (key, value)| text.replace(&format!("{{{}}}", key), value))
+}
+
+fn slates_path(app: &AppHandle) -> Result<PathBuf> {
+    settings::config_file(app, "slates.json")
+}
+
+fn read_slates(app: &AppHandle) -> Result<Vec<SlatePreset>> {
+    let path = slates_path(app)?;
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let content = fs::read_to_string(&path).map_err(|e| ClipFlowError::io("Failed to read slates", e))?;
+    serde_json::from_str(&content).map_err(|e| ClipFlowError::io("Invalid slates file", e))
+}
+
+fn write_slates(app: &AppHandle, slates: &[SlatePreset]) -> Result<()> {
+    let json = serde_json::to_string_pretty(slates).map_err(|e| ClipFlowError::io("Failed to serialize slates", e))?;
+    fs::write(slates_path(app)?, json).map_err(|e| ClipFlowError::io("Failed to write slates", e))
+}
+
+/// Render `slate` in front of `input_path` into `output_path`
+pub async fn render_with_slate(
+    input_path: &str,
+    output_path: &str,
+    slate: &SlatePreset,
+    variables: &HashMap<String, String>,
+) -> Result<()> {
+    let mut variables = variables.clone();
+    variables
+        .entry("date".to_string())
+        .or_insert_with(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+    if let Some(name) = Path::new(input_path).file_name() {
+        variables.entry("file_name".to_string()).or_insert_with(|| name.to_string_lossy().into_owned());
+    }
+
+    // drawtext reads the text from a file, which sidesteps escaping quotes,
+    // colons, and line breaks in the wording
+    let text_file = TempFile::new("slate", "txt")?;
+    fs::write(text_file.path(), fill_template(&slate.template, &variables))
+        .map_err(|e| ClipFlowError::io("Failed to write slate text", e))?;
+
+    let geometry = probe::video_geometry(input_path)?;
+    let fps = probe::frame_rate(input_path)?;
+    let (width, height) = (geometry.display_width, geometry.height);
+    let normalize = normalize_filter(width, height, fps);
+    let has_audio = !probe::audio_tracks(input_path)?.is_empty();
+    let font_size = (height as f64 * slate.font_scale).round().max(8.0) as u32;
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-f".to_string(), "lavfi".to_string(),
+        "-i".to_string(), format!("color=c={}:s={}x{}:r={}:d={}", slate.background, width, height, fps, slate.duration),
+    ];
+    match &slate.voiceover_path {
+        Some(voiceover) => args.extend(["-i".to_string(), voiceover.to_string()]),
+        None => args.extend([
+            "-f".to_string(), "lavfi".to_string(),
+            "-t".to_string(), format!("{}", slate.duration),
+            "-i".to_string(), "anullsrc=r=48000:cl=stereo".to_string(),
+        ]),
+    }
+
+    let (color_filter, color_tags) = color::export_color_args(input_path);
+    let color_stage = color_filter.map(|f| format!("{},", f)).unwrap_or_default();
+
+    let mut graph = vec![
+        format!(
+            "[1:v]drawtext=textfile='{}':fontcolor={}:fontsize={}:line_spacing={}:x=(w-text_w)/2:y=(h-text_h)/2,{}[sv]",
+            escape_filter_path(&text_file.path_str()),
+            slate.text_color,
+            font_size,
+            font_size / 2,
+            normalize
+        ),
+        format!("[0:v]{}{}[mv]", color_stage, normalize),
+    ];
+    if has_audio {
+        graph.push(format!(
+            "[2:a]atrim=0:{d},apad=whole_dur={d},{}[sa]",
+            normalize_audio_filter(),
+            d = slate.duration
+        ));
+        graph.push(format!("[0:a]{}[ma]", normalize_audio_filter()));
+        graph.push("[sv][sa][mv][ma]concat=n=2:v=1:a=1[outv][outa]".to_string());
+    } else {
+        graph.push("[sv][mv]concat=n=2:v=1:a=0[outv]".to_string());
+    }
+
+    args.extend([
+        "-filter_complex".to_string(), graph.join(";"),
+        "-map".to_string(), "[outv]".to_string(),
+    ]);
+    if has_audio {
+        args.extend(["-map".to_string(), "[outa]".to_string(), "-c:a".to_string(), "aac".to_string()]);
+    }
+    args.extend([
+        "-c:v".to_string(), "libx264".to_string(),
+        "-crf".to_string(), "18".to_string(),
+        "-preset".to_string(), "medium".to_string(),
+    ]);
+    args.extend(color_tags);
+    args.push(output_path.to_string());
+    args.push("-y".to_string());
+
+    process::run_ffmpeg_async(&args, "slate").await
+}
+
+#[tauri::command]
+pub async fn list_slate_presets(app: AppHandle) -> Result<Vec<SlatePreset>> {
+    read_slates(&app)
+}
+
+#[tauri::command]
+pub async fn save_slate_preset(app: AppHandle, slate: SlatePreset) -> Result<()> {
+    validate(&slate)?;
+    let mut slates = read_slates(&app)?;
+    slates.retain(|s| s.name != slate.name);
+    slates.push(slate);
+    write_slates(&app, &slates)
+}
+
+#[tauri::command]
+pub async fn delete_slate_preset(app: AppHandle, name: String) -> Result<()> {
+    let mut slates = read_slates(&app)?;
+    let before = slates.len();
+    slates.retain(|s| s.name != name);
+    if slates.len() == before {
+        return Err(ClipFlowError::not_found(format!("No slate named \"{}\"", name)));
+    }
+    write_slates(&app, &slates)
+}
+
+/// Prepend the named slate preset to a video, returning the path written
+#[tauri::command]
+pub async fn prepend_slate(
+    app: AppHandle,
+    input_path: &str,
+    output_path: &str,
+    slate_name: &str,
+    variables: Option<HashMap<String, String>>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let slate = read_slates(&app)?
+        .into_iter()
+        .find(|s| s.name == slate_name)
+        .ok_or_else(|| ClipFlowError::not_found(format!("No slate named \"{}\"", slate_name)))?;
+    validate(&slate)?;
+    let variables = variables.unwrap_or_default();
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        render_with_slate(input_path, &partial, &slate, &variables).await
+    })
+    .await
+}
@@ -0,0 +1,448 @@
+//! Speed changes and time remapping
+
+use crate::error::{ClipFlowError, Result};
+use crate::output::{self, OverwritePolicy};
+use crate::temp::TempDir;
+use crate::{color, probe, process};
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+
+/// Slowest and fastest factors we accept; beyond this the output is either
+/// a slideshow or a handful of frames
+const MIN_SPEED: f64 = 0.05;
+const MAX_SPEED: f64 = 100.0;
+
+/// Fastest timelapse: a day condensed to under 10 seconds
+const MAX_TIMELAPSE_SPEED: f64 = 10_000.0;
+
+/// Longest stretch reversed in one go; the reverse filter holds every frame
+/// of its input in memory, about 6GB a minute of 1080p
+const REVERSE_CHUNK_SECONDS: f64 = 10.0;
+
+/// Boomerangs are for short moments; longer ones are refused
+const MAX_BOOMERANG_SECONDS: f64 = 30.0;
+const MAX_BOOMERANG_LOOPS: u32 = 10;
+
+/// A portion of the clip played at its own speed
+#[derive(Deserialize, Clone)]
+pub struct SpeedSegment {
+    start: f64,
+    end: f64,
+    factor: f64,
+}
+
+fn validate_factor(factor: f64) -> Result<()> {
+    if !factor.is_finite() || factor < MIN_SPEED || factor > MAX_SPEED {
+        return Err(ClipFlowError::invalid(format!("Speed factor must be between {} and {}, got {}", MIN_SPEED, MAX_SPEED, factor)));
+    }
+    Ok(())
+}
+
+/// Audio filter chain for a speed factor
+///
+/// With `keep_pitch`, uses atempo, which only accepts 0.5-2.0 per instance,
+/// so larger changes are split into a chain (e.g. 4x = atempo=2,atempo=2).
+/// Without it, resamples so pitch shifts along with speed like tape.
+pub fn audio_speed_filter(factor: f64, keep_pitch: bool, sample_rate: u32) -> String {
+    if !keep_pitch {
+        return format!(
+            "asetrate={}*{},aresample={}",
+            sample_rate, factor, sample_rate
+        );
+    }
+
+    let mut remaining = factor;
+    let mut stages = Vec::new();
+    while remaining > 2.0 {
+        stages.push("atempo=2.0".to_string());
+        remaining /= 2.0;
+    }
+    while remaining < 0.5 {
+        stages.push("atempo=0.5".to_string());
+        remaining /= 0.5;
+    }
+    stages.push(format!("atempo={}", remaining));
+    stages.join(",")
+}
+
+#[tauri::command]
+pub async fn change_speed(
+    input_path: &str,
+    output_path: &str,
+    factor: f64,
+    keep_pitch: bool,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    validate_factor(factor)?;
+
+    let audio = probe::audio_tracks(input_path)?.into_iter().next();
+
+    let (color_filter, color_tags) = color::export_color_args(input_path);
+    let video_filter = match color_filter {
+        Some(color) => format!("{},setpts=PTS/{}", color, factor),
+        None => format!("setpts=PTS/{}", factor),
+    };
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-filter:v".to_string(), video_filter,
+    ];
+    match audio {
+        Some(track) => {
+            args.push("-filter:a".to_string());
+            args.push(audio_speed_filter(factor, keep_pitch, track.sample_rate));
+        }
+        None => args.push("-an".to_string()),
+    }
+    args.extend(color_tags);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "speed change").await
+    })
+    .await
+}
+
+/// Condense long footage by keeping every `speed_factor`th frame, e.g. 60
+/// for an hour-long recording into a minute
+///
+/// Frames are dropped with select before anything else runs, so the rest of
+/// the chain only sees the frames that are kept, unlike a setpts speed
+/// change which pushes every frame through. `smooth_blend` keeps twice as
+/// many and averages each pair, for a touch of motion blur instead of
+/// jumps. Audio is dropped; at these speeds it's noise.
+#[tauri::command]
+pub async fn timelapse(
+    input_path: &str,
+    output_path: &str,
+    speed_factor: f64,
+    smooth_blend: Option<bool>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    if !speed_factor.is_finite() || !(2.0..=MAX_TIMELAPSE_SPEED).contains(&speed_factor) {
+        return Err(ClipFlowError::invalid(format!(
+            "Timelapse speed must be between 2 and {}, got {}",
+            MAX_TIMELAPSE_SPEED, speed_factor
+        )));
+    }
+    let step = speed_factor.round() as u64;
+
+    let mut video_filters = if smooth_blend.unwrap_or(false) && step >= 4 {
+        vec![
+            format!("select='not(mod(n,{}))'", step / 2),
+            "tblend=all_mode=average".to_string(),
+            "framestep=2".to_string(),
+        ]
+    } else {
+        vec![format!("select='not(mod(n,{}))'", step)]
+    };
+    // Renumber the kept frames back-to-back at the source frame rate
+    video_filters.push("setpts=N/FRAME_RATE/TB".to_string());
+    let (color_filter, color_tags) = color::export_color_args(input_path);
+    video_filters.extend(color_filter);
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-filter:v".to_string(), video_filters.join(","),
+        "-an".to_string(),
+    ];
+    args.extend(color_tags);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "timelapse").await
+    })
+    .await
+}
+
+/// Re-encode `input_path` into near-lossless pieces in `dir`, in playback
+/// order, reversing each piece (and their order) when `reverse` is set
+async fn render_pieces(input_path: &str, dir: &TempDir, reverse: bool, with_audio: bool) -> Result<Vec<PathBuf>> {
+    let duration = probe::media_duration(input_path)?;
+    let (color_filter, _) = color::export_color_args(input_path);
+    let mut video_filters: Vec<String> = color_filter.into_iter().collect();
+    if reverse {
+        video_filters.push("reverse".to_string());
+    }
+    let chunk = if reverse { REVERSE_CHUNK_SECONDS } else { duration };
+
+    let mut pieces = Vec::new();
+    let mut start = 0.0;
+    while start < duration {
+        let piece = dir.path().join(format!("{}_{:05}.mkv", if reverse { "reversed" } else { "forward" }, pieces.len()));
+        let mut args: Vec<String> = vec![
+            "-ss".to_string(), start.to_string(),
+            "-t".to_string(), chunk.to_string(),
+            "-i".to_string(), input_path.to_string(),
+            "-c:v".to_string(), "libx264".to_string(),
+            "-crf".to_string(), "10".to_string(),
+            "-preset".to_string(), "veryfast".to_string(),
+        ];
+        if !video_filters.is_empty() {
+            args.extend(["-filter:v".to_string(), video_filters.join(",")]);
+        }
+        if with_audio {
+            if reverse {
+                args.extend(["-filter:a".to_string(), "areverse".to_string()]);
+            }
+            args.extend(["-c:a".to_string(), "pcm_s16le".to_string()]);
+        } else {
+            args.push("-an".to_string());
+        }
+        args.extend([piece.to_string_lossy().into_owned(), "-y".to_string()]);
+        process::run_ffmpeg_async(&args, if reverse { "reverse" } else { "boomerang" }).await?;
+        pieces.push(piece);
+        start += chunk;
+    }
+    if reverse {
+        pieces.reverse();
+    }
+    Ok(pieces)
+}
+
+/// Join pieces rendered by `render_pieces` into the final output
+async fn join_pieces(pieces: &[PathBuf], dir: &TempDir, output_path: &str, with_audio: bool) -> Result<()> {
+    let list: String = pieces
+        .iter()
+        .map(|p| format!("file '{}'\n", p.to_string_lossy().replace('\'', "'\\''")))
+        .collect();
+    let list_path = dir.path().join("pieces.txt");
+    fs::write(&list_path, list).map_err(|e| ClipFlowError::io("Failed to write piece list", e))?;
+
+    let mut args: Vec<String> = vec![
+        "-f".to_string(), "concat".to_string(),
+        "-safe".to_string(), "0".to_string(),
+        "-i".to_string(), list_path.to_string_lossy().into_owned(),
+    ];
+    if !with_audio {
+        args.push("-an".to_string());
+    }
+    args.extend(color::bt709_output_args());
+    args.push(output_path.to_string());
+    args.push("-y".to_string());
+    process::run_ffmpeg_async(&args, "joining pieces").await
+}
+
+/// Play a clip backwards, audio included
+/// Long clips are reversed in short pieces, so memory use stays flat.
+#[tauri::command]
+pub async fn reverse_clip(input_path: &str, output_path: &str, overwrite: Option<OverwritePolicy>) -> Result<String> {
+    let with_audio = !probe::audio_tracks(input_path)?.is_empty();
+    let dir = TempDir::new("reverse")?;
+    let pieces = render_pieces(input_path, &dir, true, with_audio).await?;
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        join_pieces(&pieces, &dir, &partial, with_audio).await
+    })
+    .await
+}
+
+/// Play a short clip forwards then backwards, `loops` times over, for
+/// social posts
+/// The result is silent; reversed audio rarely survives a boomerang.
+#[tauri::command]
+pub async fn boomerang(
+    input_path: &str,
+    output_path: &str,
+    loops: Option<u32>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let loops = loops.unwrap_or(1);
+    if !(1..=MAX_BOOMERANG_LOOPS).contains(&loops) {
+        return Err(ClipFlowError::invalid(format!("Loops must be between 1 and {}, got {}", MAX_BOOMERANG_LOOPS, loops)));
+    }
+    let duration = probe::media_duration(input_path)?;
+    if duration > MAX_BOOMERANG_SECONDS {
+        return Err(ClipFlowError::invalid(format!(
+            "Boomerangs work on clips up to {}s; trim this one ({:.1}s) first",
+            MAX_BOOMERANG_SECONDS, duration
+        )));
+    }
+
+    let dir = TempDir::new("boomerang")?;
+    // Both directions share one encoding, so they join without re-decoding
+    // differently formatted sources
+    let forward = render_pieces(input_path, &dir, false, false).await?;
+    let backward = render_pieces(input_path, &dir, true, false).await?;
+    let once: Vec<PathBuf> = forward.iter().chain(&backward).cloned().collect();
+    let pieces: Vec<PathBuf> = (0..loops).flat_map(|_| once.iter().cloned()).collect();
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        join_pieces(&pieces, &dir, &partial, false).await
+    })
+    .await
+}
+
+/// Fill the gaps between requested segments with 1x segments so the whole
+/// clip is covered, in order
+fn cover_timeline(mut segments: Vec<SpeedSegment>, duration: f64) -> Result<Vec<SpeedSegment>> {
+    segments.sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut covered = Vec::new();
+    let mut cursor = 0.0;
+    for seg in segments {
+        validate_factor(seg.factor)?;
+        if seg.end <= seg.start {
+            return Err(ClipFlowError::invalid(format!("Segment end ({}) must be after start ({})", seg.end, seg.start)));
+        }
+        if seg.start >= duration {
+            return Err(ClipFlowError::invalid(format!("Segment start ({}) is past the end of the clip ({}s)", seg.start, duration)));
+        }
+        if seg.start < cursor {
+            return Err(ClipFlowError::invalid(format!("Speed segments overlap at {}s", seg.start)));
+        }
+        if seg.start > cursor {
+            covered.push(SpeedSegment { start: cursor, end: seg.start, factor: 1.0 });
+        }
+        cursor = seg.end.min(duration);
+        covered.push(SpeedSegment { start: seg.start, end: cursor, factor: seg.factor });
+    }
+    if cursor < duration {
+        covered.push(SpeedSegment { start: cursor, end: duration, factor: 1.0 });
+    }
+
+    Ok(covered)
+}
+
+/// Apply different speeds to different portions of a clip in one render
+#[tauri::command]
+pub async fn change_speed_segments(
+    input_path: &str,
+    output_path: &str,
+    segments: Vec<SpeedSegment>,
+    keep_pitch: bool,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let duration = probe::media_duration(input_path)?;
+    let audio = probe::audio_tracks(input_path)?.into_iter().next();
+    let segments = cover_timeline(segments, duration)?;
+    let (color_filter, color_tags) = color::export_color_args(input_path);
+    let color_stage = color_filter.map(|f| format!("{},", f)).unwrap_or_default();
+
+    let mut graph = Vec::new();
+    let mut concat_inputs = String::new();
+    for (i, seg) in segments.iter().enumerate() {
+        graph.push(format!(
+            "[0:v]{}trim=start={}:end={},setpts=(PTS-STARTPTS)/{}[v{}]",
+            color_stage, seg.start, seg.end, seg.factor, i
+        ));
+        concat_inputs.push_str(&format!("[v{}]", i));
+
+        if let Some(track) = &audio {
+            graph.push(format!(
+                "[0:a]atrim=start={}:end={},asetpts=PTS-STARTPTS,{}[a{}]",
+                seg.start,
+                seg.end,
+                audio_speed_filter(seg.factor, keep_pitch, track.sample_rate),
+                i
+            ));
+            concat_inputs.push_str(&format!("[a{}]", i));
+        }
+    }
+
+    let has_audio = audio.is_some();
+    graph.push(format!(
+        "{}concat=n={}:v=1:a={}[outv]{}",
+        concat_inputs,
+        segments.len(),
+        if has_audio { 1 } else { 0 },
+        if has_audio { "[outa]" } else { "" }
+    ));
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-filter_complex".to_string(), graph.join(";"),
+        "-map".to_string(), "[outv]".to_string(),
+    ];
+    if has_audio {
+        args.push("-map".to_string());
+        args.push("[outa]".to_string());
+    }
+    args.extend(color_tags);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "speed change").await
+    })
+    .await
+}
+
+/// minterpolate settings for a quality/speed tradeoff
+/// "fast" blends neighbouring frames; the others do motion-compensated
+/// interpolation, which looks much smoother but can take 10x+ realtime
+fn interpolation_filter(fps: f64, quality: &str) -> Result<String> {
+    let mode = match quality {
+        "fast" => "mi_mode=blend",
+        "balanced" => "mi_mode=mci:mc_mode=obmc:me_mode=bilat",
+        "high" => "mi_mode=mci:mc_mode=aobmc:me_mode=bidir:vsbmc=1",
+        other => return Err(ClipFlowError::invalid(format!("Unknown interpolation quality: {}", other))),
+    };
+    Ok(format!("minterpolate=fps={}:{}", fps, mode))
+}
+
+/// Smooth slow motion: synthesize in-between frames with minterpolate so
+/// `factor`x slowed footage still plays at `target_fps`
+pub fn render_slowmo(input_path: &str, output_path: &str, target_fps: f64, factor: f64, quality: &str) -> Result<()> {
+    if !(1.0..=16.0).contains(&factor) {
+        return Err(ClipFlowError::invalid(format!("Slow motion factor must be between 1 and 16, got {}", factor)));
+    }
+    if !(1.0..=240.0).contains(&target_fps) {
+        return Err(ClipFlowError::invalid(format!("Target fps must be between 1 and 240, got {}", target_fps)));
+    }
+
+    let audio = probe::audio_tracks(input_path)?.into_iter().next();
+
+    // Interpolate up to target_fps * factor, then stretch timestamps so
+    // playback at target_fps is `factor` times slower
+    let (color_filter, color_tags) = color::export_color_args(input_path);
+    let mut video_filters: Vec<String> = color_filter.into_iter().collect();
+    video_filters.push(interpolation_filter(target_fps * factor, quality)?);
+    video_filters.push(format!("setpts={}*PTS", factor));
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-filter:v".to_string(), video_filters.join(","),
+        "-r".to_string(), format!("{}", target_fps),
+    ];
+    match audio {
+        Some(track) => {
+            args.push("-filter:a".to_string());
+            args.push(audio_speed_filter(1.0 / factor, true, track.sample_rate));
+        }
+        None => args.push("-an".to_string()),
+    }
+    args.extend(color_tags);
+    args.push(output_path.to_string());
+    args.push("-y".to_string());
+
+    process::run_ffmpeg(&args, "slow motion interpolation")
+}
+
+#[tauri::command]
+pub async fn interpolate_slowmo(
+    input_path: &str,
+    output_path: &str,
+    target_fps: f64,
+    factor: f64,
+    quality: Option<&str>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    // Shares its render with the job queue, which runs on the blocking pool
+    let (input_path, output_path) = (input_path.to_string(), output_path.to_string());
+    let quality = quality.unwrap_or("balanced").to_string();
+    let render = move || {
+        output::write_atomically(&output_path, overwrite.unwrap_or_default(), |partial| {
+            render_slowmo(&input_path, partial, target_fps, factor, &quality)
+        })
+    };
+    // Carried over to the blocking thread when this render is being planned
+    let plan = process::PlanRecorder::current();
+    tauri::async_runtime::spawn_blocking(move || match &plan {
+        Some(plan) => plan.record_blocking(render),
+        None => render(),
+    })
+    .await
+    .map_err(|e| ClipFlowError::io("Slow motion thread failed", e))?
+}
@@ -0,0 +1,223 @@
+//! Machine load: CPU, memory, GPU, and free space where renders write
+//!
+//! CPU usage only means something measured between two samples, so a
+//! background loop keeps sampling and `get_system_stats` reports the usage
+//! since the last sample. While any job is rendering the loop also emits
+//! "system-stats", letting the UI warn before an export starves the machine.
+//!
+//! GPU figures come from `nvidia-smi`, so they're only reported for NVIDIA
+//! cards with the driver tools installed.
+
+use crate::error::Result;
+use crate::{jobs, settings};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use sysinfo::System;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::process::Command;
+
+/// How often the machine is sampled
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Longest `nvidia-smi` may take before the GPU is left out
+const GPU_QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// CPU usage above which the machine counts as saturated
+const CPU_WARNING_PERCENT: f32 = 95.0;
+
+/// Share of memory in use above which swapping is near
+const MEMORY_WARNING_FRACTION: f64 = 0.9;
+
+/// Free space below which a volume is about to run out
+const LOW_SPACE_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Set once `nvidia-smi` turns out not to exist, so it isn't spawned again
+static NO_GPU_TOOL: AtomicBool = AtomicBool::new(false);
+
+#[derive(Serialize, Clone)]
+pub struct GpuStats {
+    pub name: String,
+    pub utilization_percent: f32,
+    pub memory_used_bytes: u64,
+    pub memory_total_bytes: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct VolumeStats {
+    pub path: String,
+    /// What's written there: "temp", "cache", or "output"
+    pub purpose: String,
+    pub available_bytes: u64,
+    pub total_bytes: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct SystemStats {
+    /// Average over all cores since the previous sample
+    pub cpu_percent: f32,
+    pub cpu_count: usize,
+    pub memory_used_bytes: u64,
+    pub memory_total_bytes: u64,
+    /// Empty when GPU stats aren't available
+    pub gpus: Vec<GpuStats>,
+    pub volumes: Vec<VolumeStats>,
+    /// Human-readable reasons the machine is under strain
+    pub warnings: Vec<String>,
+}
+
+pub struct SystemMonitor {
+    system: Mutex<System>,
+}
+
+impl Default for SystemMonitor {
+    fn default() -> Self {
+        SystemMonitor { system: Mutex::new(System::new()) }
+    }
+}
+
+/// Parse one line of `nvidia-smi --query-gpu=name,utilization.gpu,memory.used,memory.total`
+/// in csv/noheader/nounits form; memory is in MiB
+fn parse_gpu_line(line: &str) -> Option<GpuStats> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    if fields.len() != 4 {
+        return None;
+    }
+    let mib = |field: &str| field.parse::<u64>().ok().map(|m| m * 1024 * 1024);
+    Some(GpuStats {
+        name: fields[0].to_string(),
+        utilization_percent: fields[1].parse().ok()?,
+        memory_used_bytes: mib(fields[2])?,
+        memory_total_bytes: mib(fields[3])?,
+    })
+}
+
+async fn gpu_stats() -> Vec<GpuStats> {
+    if NO_GPU_TOOL.load(Ordering::Relaxed) {
+        return Vec::new();
+    }
+    let query = Command::new("nvidia-smi")
+        .args([
+            "--query-gpu=name,utilization.gpu,memory.used,memory.total",
+            "--format=csv,noheader,nounits",
+        ])
+        .kill_on_drop(true)
+        .output();
+    match tokio::time::timeout(GPU_QUERY_TIMEOUT, query).await {
+        Ok(Ok(output)) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).lines().filter_map(parse_gpu_line).collect()
+        }
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            NO_GPU_TOOL.store(true, Ordering::Relaxed);
+            Vec::new()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Where renders write right now: scratch space, the cache, and the output
+/// folders of running jobs
+fn volumes(app: &AppHandle) -> Vec<VolumeStats> {
+    let mut places: Vec<(PathBuf, &str)> = vec![(settings::temp_dir(), "temp")];
+    if let Ok(cache) = app.path().app_cache_dir() {
+        places.push((cache, "cache"));
+    }
+    for output in jobs::running_outputs(app) {
+        let dir = Path::new(&output).parent().map(Path::to_path_buf).unwrap_or_default();
+        places.push((dir, "output"));
+    }
+
+    let mut volumes: Vec<VolumeStats> = Vec::new();
+    for (path, purpose) in places {
+        // Folders that don't exist yet are measured at their nearest ancestor
+        let existing = match path.ancestors().find(|p| p.is_dir()) {
+            Some(existing) => existing.to_path_buf(),
+            None => continue,
+        };
+        let path = path.to_string_lossy().into_owned();
+        if volumes.iter().any(|v| v.path == path) {
+            continue;
+        }
+        if let (Ok(available_bytes), Ok(total_bytes)) = (fs2::available_space(&existing), fs2::total_space(&existing)) {
+            volumes.push(VolumeStats { path, purpose: purpose.to_string(), available_bytes, total_bytes });
+        }
+    }
+    volumes
+}
+
+fn warnings(stats: &SystemStats) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if stats.cpu_percent >= CPU_WARNING_PERCENT {
+        warnings.push(format!("CPU is at {:.0}%", stats.cpu_percent));
+    }
+    if stats.memory_total_bytes > 0
+        && stats.memory_used_bytes as f64 / stats.memory_total_bytes as f64 >= MEMORY_WARNING_FRACTION
+    {
+        warnings.push(format!(
+            "Memory is nearly full ({:.1} of {:.1} GB in use)",
+            stats.memory_used_bytes as f64 / 1e9,
+            stats.memory_total_bytes as f64 / 1e9
+        ));
+    }
+    for volume in stats.volumes.iter().filter(|v| v.available_bytes < LOW_SPACE_BYTES) {
+        warnings.push(format!(
+            "Only {:.1} GB free for {} files at {}",
+            volume.available_bytes as f64 / 1e9,
+            volume.purpose,
+            volume.path
+        ));
+    }
+    warnings
+}
+
+async fn sample(app: &AppHandle, monitor: &SystemMonitor) -> SystemStats {
+    let (cpu_percent, cpu_count, memory_used_bytes, memory_total_bytes) = {
+        let mut system = monitor.system.lock().unwrap();
+        system.refresh_cpu();
+        system.refresh_memory();
+        (
+            system.global_cpu_info().cpu_usage(),
+            system.cpus().len(),
+            system.total_memory().saturating_sub(system.available_memory()),
+            system.total_memory(),
+        )
+    };
+    let mut stats = SystemStats {
+        cpu_percent,
+        cpu_count,
+        memory_used_bytes,
+        memory_total_bytes,
+        gpus: gpu_stats().await,
+        volumes: volumes(app),
+        warnings: Vec::new(),
+    };
+    stats.warnings = warnings(&stats);
+    stats
+}
+
+/// Start sampling, emitting "system-stats" while jobs are rendering
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let monitor = app.state::<SystemMonitor>();
+        let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if jobs::running_outputs(&app).is_empty() {
+                // Keeps the CPU baseline fresh for the next request
+                monitor.system.lock().unwrap().refresh_cpu();
+                continue;
+            }
+            let stats = sample(&app, &monitor).await;
+            let _ = app.emit("system-stats", stats);
+        }
+    });
+}
+
+/// Current CPU, memory, GPU, and disk load, with warnings when any of them
+/// is close to its limit
+#[tauri::command]
+pub async fn get_system_stats(app: AppHandle, monitor: State<'_, SystemMonitor>) -> Result<SystemStats> {
+    Ok(sample(&app, &monitor).await)
+}
@@ -0,0 +1,85 @@
+//! Temp file manager - scratch files for intermediate renders, analysis
+//! samples, and pass logs, removed automatically when dropped
+
+use crate::error::{ClipFlowError, Result};
+use crate::settings;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Directory all ClipFlow scratch files live under
+pub fn scratch_dir() -> Result<PathBuf> {
+    let dir = settings::temp_dir().join("clipflow");
+    fs::create_dir_all(&dir).map_err(|e| ClipFlowError::io("Failed to create temp dir", e))?;
+    Ok(dir)
+}
+
+/// Unique name so concurrent jobs never share a scratch file
+fn unique_name(prefix: &str) -> String {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{}_{}_{}_{}", prefix, std::process::id(), millis, n)
+}
+
+/// A scratch file path that is deleted when this guard goes out of scope
+/// The file itself isn't created; hand the path to the tool that writes it
+pub struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    pub fn new(prefix: &str, extension: &str) -> Result<TempFile> {
+        let path = scratch_dir()?.join(format!("{}.{}", unique_name(prefix), extension));
+        Ok(TempFile { path })
+    }
+
+    /// Take ownership of a file some tool wrote next to one of ours
+    /// (e.g. Whisper's .json beside the .wav we gave it)
+    pub fn adopt(path: PathBuf) -> TempFile {
+        TempFile { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn path_str(&self) -> String {
+        self.path.to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// A scratch directory removed with its contents when dropped
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(prefix: &str) -> Result<TempDir> {
+        let path = scratch_dir()?.join(unique_name(prefix));
+        fs::create_dir_all(&path).map_err(|e| ClipFlowError::io("Failed to create temp dir", e))?;
+        Ok(TempDir { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn path_str(&self) -> String {
+        self.path.to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,148 @@
+//! SMPTE timecode handling for sources that don't start at 00:00:00:00
+
+use crate::error::{ClipFlowError, Result};
+use crate::probe;
+use serde::Serialize;
+
+#[derive(Serialize, Clone)]
+pub struct SourceTimecode {
+    /// Start timecode as written by the camera/recorder, e.g. "01:00:00:00"
+    pub start_timecode: String,
+    pub fps: f64,
+    pub drop_frame: bool,
+    /// False when the file carried no timecode and we assumed 00:00:00:00
+    pub embedded: bool,
+}
+
+/// Parse an ffprobe rational like "30000/1001" or "25/1"
+pub fn parse_rate(rate: &str) -> Option<f64> {
+    match rate.split_once('/') {
+        Some((num, den)) => {
+            let num = num.parse::<f64>().ok()?;
+            let den = den.parse::<f64>().ok()?;
+            if den == 0.0 || num == 0.0 {
+                None
+            } else {
+                Some(num / den)
+            }
+        }
+        None => rate.parse::<f64>().ok().filter(|r| *r > 0.0),
+    }
+}
+
+/// Frames dropped per minute for drop-frame rates (2 at 29.97, 4 at 59.94)
+fn drop_count(fps: f64) -> u64 {
+    (fps * 0.066666).round() as u64
+}
+
+/// Convert a frame count to a timecode string
+pub fn frames_to_timecode(frames: u64, fps: f64, drop_frame: bool) -> String {
+    let nominal = fps.round().max(1.0) as u64;
+    let mut frames = frames;
+
+    if drop_frame {
+        let drop = drop_count(fps);
+        let frames_per_10_min = (fps * 600.0).round() as u64;
+        let frames_per_min = nominal * 60 - drop;
+        let tens = frames / frames_per_10_min;
+        let rem = frames % frames_per_10_min;
+        frames += drop * 9 * tens;
+        if rem > drop {
+            frames += drop * ((rem - drop) / frames_per_min);
+        }
+    }
+
+    let ff = frames % nominal;
+    let ss = (frames / nominal) % 60;
+    let mm = (frames / (nominal * 60)) % 60;
+    let hh = (frames / (nominal * 3600)) % 24;
+    let sep = if drop_frame { ';' } else { ':' };
+
+    format!("{:02}:{:02}:{:02}{}{:02}", hh, mm, ss, sep, ff)
+}
+
+/// Convert a timecode string ("HH:MM:SS:FF" or "HH:MM:SS;FF") to a frame count
+pub fn timecode_to_frames(tc: &str, fps: f64, drop_frame: bool) -> Result<u64> {
+    let parts: Vec<u64> = tc
+        .split(|c| c == ':' || c == ';' || c == '.')
+        .map(|p| p.parse::<u64>())
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|_| ClipFlowError::invalid(format!("Invalid timecode: {}", tc)))?;
+    if parts.len() != 4 {
+        return Err(ClipFlowError::invalid(format!("Invalid timecode: {}", tc)));
+    }
+
+    let nominal = fps.round().max(1.0) as u64;
+    let (hh, mm, ss, ff) = (parts[0], parts[1], parts[2], parts[3]);
+    let mut frames = (hh * 3600 + mm * 60 + ss) * nominal + ff;
+
+    if drop_frame {
+        let total_minutes = hh * 60 + mm;
+        frames -= drop_count(fps) * (total_minutes - total_minutes / 10);
+    }
+
+    Ok(frames)
+}
+
+impl SourceTimecode {
+    /// Timecode of a point `seconds` into the file
+    pub fn at(&self, seconds: f64) -> String {
+        let start = timecode_to_frames(&self.start_timecode, self.fps, self.drop_frame).unwrap_or(0);
+        let offset = (seconds.max(0.0) * self.fps).round() as u64;
+        frames_to_timecode(start + offset, self.fps, self.drop_frame)
+    }
+}
+
+/// Read the start timecode of a file from its container or tmcd track
+pub fn source_timecode(file_path: &str) -> Result<SourceTimecode> {
+    Ok(timecode_of(&probe::ffprobe_json(file_path)?))
+}
+
+pub async fn source_timecode_async(file_path: &str) -> Result<SourceTimecode> {
+    Ok(timecode_of(&probe::ffprobe_json_async(file_path).await?))
+}
+
+fn timecode_of(json: &serde_json::Value) -> SourceTimecode {
+    let streams = json["streams"].as_array().cloned().unwrap_or_default();
+
+    let video = streams.iter().find(|s| s["codec_type"] == "video");
+    let fps = video
+        .and_then(|v| v["r_frame_rate"].as_str())
+        .and_then(parse_rate)
+        .unwrap_or(30.0);
+
+    // Timecode can live on the format, the video stream, or a data (tmcd) stream
+    let embedded = json["format"]["tags"]["timecode"]
+        .as_str()
+        .or_else(|| video.and_then(|v| v["tags"]["timecode"].as_str()))
+        .or_else(|| streams.iter().find_map(|s| s["tags"]["timecode"].as_str()))
+        .map(|tc| tc.to_string());
+
+    match embedded {
+        Some(tc) => SourceTimecode {
+            drop_frame: tc.contains(';'),
+            start_timecode: tc,
+            fps,
+            embedded: true,
+        },
+        None => SourceTimecode {
+            start_timecode: "00:00:00:00".to_string(),
+            fps,
+            drop_frame: false,
+            embedded: false,
+        },
+    }
+}
+
+#[tauri::command]
+pub async fn get_source_timecode(file_path: &str) -> Result<SourceTimecode> {
+    source_timecode_async(file_path).await
+}
+
+/// Convert edit points (seconds from file start) to SMPTE timecode in the
+/// source's own timecode space
+#[tauri::command]
+pub async fn format_timecodes(file_path: &str, times: Vec<f64>) -> Result<Vec<String>> {
+    let tc = source_timecode_async(file_path).await?;
+    Ok(times.into_iter().map(|t| tc.at(t)).collect())
+}
@@ -0,0 +1,364 @@
+//! Multi-track timelines rendered in one pass
+//!
+//! A timeline is a main video track whose clips play back to back (cut or
+//! joined with transitions), upper video tracks composited over it (picture
+//! in picture, B-roll), audio tracks mixed under it, and text drawn on top.
+//! The whole edit compiles into a single ffmpeg filter_complex, so an
+//! export with trims, transitions, overlays, and gain changes is one encode
+//! instead of a chain of intermediate files.
+//!
+//! Every clip is conformed to the timeline's size, frame rate, and color
+//! space. Sound comes from the main track and the audio tracks; clips on
+//! upper video tracks are picture only.
+
+use crate::error::{ClipFlowError, Result};
+use crate::filtergraph::{Filter, FilterGraph};
+use crate::output::{self, OverwritePolicy};
+use crate::overlay::{self, OverlayPosition, TextOverlay};
+use crate::temp::TempFile;
+use crate::transitions::{normalize_audio_filter, normalize_filter, XFADE_TRANSITIONS};
+use crate::{color, presets, probe, process};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tauri::AppHandle;
+
+/// Slack allowed past a file's probed end, which containers round
+const DURATION_TOLERANCE: f64 = 0.05;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Timeline {
+    /// Frame size and rate; the first main-track clip's unless set
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<f64>,
+    /// Lowest first; the first video track is the main one
+    pub tracks: Vec<Track>,
+    /// Text drawn over everything, timed on the timeline
+    #[serde(default)]
+    pub texts: Vec<TextOverlay>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackKind {
+    Video,
+    Audio,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Track {
+    pub kind: TrackKind,
+    pub clips: Vec<TimelineClip>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TimelineClip {
+    pub path: String,
+    /// Range of the source used, in seconds
+    pub in_point: f64,
+    pub out_point: f64,
+    /// Where the clip starts on the timeline; main-track clips follow one
+    /// another and ignore it
+    #[serde(default)]
+    pub start: f64,
+    /// Transition from the previous clip, main track only
+    pub transition: Option<ClipTransition>,
+    /// Audio gain over the clip, interpolated linearly between keyframes
+    #[serde(default)]
+    pub gain: Vec<GainKeyframe>,
+    /// Upper video tracks: corner to place the clip in, centered if unset
+    pub position: Option<OverlayPosition>,
+    /// Upper video tracks: width as a fraction of the frame, full frame if
+    /// unset
+    pub scale: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ClipTransition {
+    /// An xfade transition name, e.g. "fade" or "wipeleft"
+    pub kind: String,
+    pub duration: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct GainKeyframe {
+    /// Seconds from the start of the clip
+    pub time: f64,
+    pub db: f64,
+}
+
+impl TimelineClip {
+    fn length(&self) -> f64 {
+        self.out_point - self.in_point
+    }
+}
+
+/// volume filter for a clip's gain keyframes, None when there are none
+fn gain_filter(keyframes: &[GainKeyframe]) -> Option<Filter> {
+    let mut keyframes = keyframes.to_vec();
+    keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+    let last = keyframes.last()?;
+    // Built inside out: hold the last level, then ramp between each pair
+    let mut db = last.db.to_string();
+    for pair in keyframes.windows(2).rev() {
+        let (a, b) = (pair[0], pair[1]);
+        if b.time <= a.time {
+            continue;
+        }
+        db = format!(
+            "if(lt(t,{}),{}+({})*(t-{})/{},{})",
+            b.time,
+            a.db,
+            b.db - a.db,
+            a.time,
+            b.time - a.time,
+            db
+        );
+    }
+    db = format!("if(lt(t,{}),{},{})", keyframes[0].time, keyframes[0].db, db);
+    Some(Filter::new("volume").arg(format!("pow(10,({})/20)", db)).opt("eval", "frame"))
+}
+
+fn validate_clip(clip: &TimelineClip) -> Result<()> {
+    if clip.in_point.is_nan() || clip.out_point.is_nan() || clip.in_point < 0.0 || clip.out_point <= clip.in_point {
+        return Err(ClipFlowError::invalid(format!(
+            "{}: out point ({}) must be after in point ({})",
+            clip.path, clip.out_point, clip.in_point
+        )));
+    }
+    let duration = probe::media_duration(&clip.path)?;
+    if clip.out_point > duration + DURATION_TOLERANCE {
+        return Err(ClipFlowError::invalid(format!(
+            "{}: out point {:.2}s is past the end ({:.2}s)",
+            clip.path, clip.out_point, duration
+        )));
+    }
+    if clip.start.is_nan() || clip.start < 0.0 {
+        return Err(ClipFlowError::invalid(format!("{}: timeline start must not be negative", clip.path)));
+    }
+    if let Some(scale) = clip.scale {
+        if scale.is_nan() || !(0.05..=1.0).contains(&scale) {
+            return Err(ClipFlowError::invalid(format!("{}: scale must be between 0.05 and 1, got {}", clip.path, scale)));
+        }
+    }
+    if clip.gain.iter().any(|k| !k.time.is_finite() || !k.db.is_finite() || k.db > 40.0) {
+        return Err(ClipFlowError::invalid(format!("{}: gain keyframes must be finite and at most +40 dB", clip.path)));
+    }
+    Ok(())
+}
+
+/// ffmpeg arguments up to (not including) the output file, plus the temp
+/// files they refer to, which must outlive the render
+fn compile(timeline: &Timeline, preset: &presets::ExportPreset) -> Result<(Vec<String>, Vec<TempFile>)> {
+    let main = timeline
+        .tracks
+        .iter()
+        .find(|t| t.kind == TrackKind::Video)
+        .filter(|t| !t.clips.is_empty())
+        .ok_or_else(|| ClipFlowError::invalid("The timeline needs a video track with at least one clip"))?;
+    for clip in timeline.tracks.iter().flat_map(|t| &t.clips) {
+        validate_clip(clip)?;
+    }
+
+    let first = &main.clips[0];
+    let geometry = probe::video_geometry(&first.path)?;
+    let width = timeline.width.unwrap_or(geometry.display_width);
+    let height = timeline.height.unwrap_or(geometry.display_height);
+    if width < 16 || height < 16 || width % 2 != 0 || height % 2 != 0 {
+        return Err(ClipFlowError::invalid("Width and height must be even numbers of at least 16"));
+    }
+    let fps = match timeline.fps {
+        Some(fps) if fps.is_nan() || !(1.0..=240.0).contains(&fps) => {
+            return Err(ClipFlowError::invalid(format!("Frame rate must be between 1 and 240, got {}", fps)))
+        }
+        Some(fps) => fps,
+        None => probe::frame_rate(&first.path)?,
+    };
+    let normalize = normalize_filter(width, height, fps);
+
+    // One input per clip, trimmed on the way in so nothing outside the
+    // used range is decoded
+    let mut args: Vec<String> = Vec::new();
+    let mut next_input = 0;
+    let mut add_input = |args: &mut Vec<String>, clip: &TimelineClip| {
+        args.extend([
+            "-ss".to_string(), clip.in_point.to_string(),
+            "-t".to_string(), clip.length().to_string(),
+            "-i".to_string(), clip.path.clone(),
+        ]);
+        next_input += 1;
+        next_input - 1
+    };
+    let convert = |path: &str| -> Vec<Filter> { color::export_color_args(path).0.into_iter().map(Filter::raw).collect() };
+    // A clip's sound, ending with `extra` if given; returns its pad
+    let clip_audio = |graph: &mut FilterGraph, input: usize, clip: &TimelineClip, extra: Option<Filter>| -> Result<String> {
+        let pad = graph.label("a");
+        match probe::audio_tracks(&clip.path)?.is_empty() {
+            false => {
+                let filters = std::iter::once(Filter::raw(normalize_audio_filter()))
+                    .chain(gain_filter(&clip.gain))
+                    .chain(extra)
+                    .collect();
+                graph.chain(&[format!("{}:a", input).as_str()], filters, &[pad.as_str()]);
+            }
+            // Silence keeps the audio chain in step with the picture
+            true => {
+                let silence = Filter::new("anullsrc").opt("r", 48000).opt("cl", "stereo");
+                let trim = Filter::new("atrim").opt("duration", clip.length());
+                let filters = [silence, trim, Filter::raw(normalize_audio_filter())]
+                    .into_iter()
+                    .chain(extra)
+                    .collect();
+                graph.chain(&[], filters, &[pad.as_str()]);
+            }
+        }
+        Ok(pad)
+    };
+    let mut graph = FilterGraph::new();
+
+    // Main track: cuts concatenate, transitions overlap the clips
+    let (mut video, mut sound) = (String::new(), String::new());
+    let mut length = 0.0;
+    for (i, clip) in main.clips.iter().enumerate() {
+        let input = add_input(&mut args, clip);
+        let clip_video = graph.label("mv");
+        let mut filters = convert(&clip.path);
+        filters.push(Filter::raw(normalize.clone()));
+        graph.chain(&[format!("{}:v", input).as_str()], filters, &[clip_video.as_str()]);
+        let clip_sound = clip_audio(&mut graph, input, clip, None)?;
+        if i == 0 {
+            (video, sound, length) = (clip_video, clip_sound, clip.length());
+            continue;
+        }
+        let (joined_video, joined_sound) = (graph.label("sv"), graph.label("sa"));
+        match &clip.transition {
+            Some(transition) => {
+                if !XFADE_TRANSITIONS.contains(&transition.kind.as_str()) {
+                    return Err(ClipFlowError::invalid(format!("Unknown transition: {}", transition.kind)));
+                }
+                let d = transition.duration;
+                if d.is_nan() || d <= 0.0 || d >= clip.length() || d >= length {
+                    return Err(ClipFlowError::invalid(format!(
+                        "{}: transition must be longer than 0s and shorter than the clips it joins",
+                        clip.path
+                    )));
+                }
+                let xfade = Filter::new("xfade")
+                    .opt("transition", &transition.kind)
+                    .opt("duration", d)
+                    .opt("offset", length - d);
+                graph.chain(&[video.as_str(), clip_video.as_str()], vec![xfade], &[joined_video.as_str()]);
+                let acrossfade = Filter::new("acrossfade").opt("d", d);
+                graph.chain(&[sound.as_str(), clip_sound.as_str()], vec![acrossfade], &[joined_sound.as_str()]);
+                length += clip.length() - d;
+            }
+            None => {
+                let concat = Filter::new("concat").opt("n", 2).opt("v", 1).opt("a", 1);
+                graph.chain(
+                    &[video.as_str(), sound.as_str(), clip_video.as_str(), clip_sound.as_str()],
+                    vec![concat],
+                    &[joined_video.as_str(), joined_sound.as_str()],
+                );
+                length += clip.length();
+            }
+        }
+        (video, sound) = (joined_video, joined_sound);
+    }
+
+    // Upper video tracks, each clip composited for its span and gone after
+    for track in timeline.tracks.iter().filter(|t| t.kind == TrackKind::Video).skip(1) {
+        for clip in &track.clips {
+            let input = add_input(&mut args, clip);
+            let mut filters = convert(&clip.path);
+            match clip.scale {
+                Some(scale) => {
+                    let clip_width = ((width as f64 * scale / 2.0).round() * 2.0).max(2.0) as u32;
+                    filters.extend([
+                        Filter::new("scale").arg(clip_width).arg(-2),
+                        Filter::new("setsar").arg(1),
+                        Filter::new("fps").arg(fps),
+                        Filter::new("format").arg("yuv420p"),
+                    ]);
+                }
+                None => filters.push(Filter::raw(normalize.clone())),
+            }
+            filters.push(Filter::new("setpts").arg(format!("PTS-STARTPTS+{}/TB", clip.start)));
+            let (x, y) = clip.position.unwrap_or(OverlayPosition::Center).expressions();
+            let (picture, layer) = (graph.label("ov"), graph.label("layer"));
+            graph.chain(&[format!("{}:v", input).as_str()], filters, &[picture.as_str()]);
+            let overlay = Filter::new("overlay").arg(x).arg(y).opt("eof_action", "pass");
+            graph.chain(&[video.as_str(), picture.as_str()], vec![overlay], &[layer.as_str()]);
+            video = layer;
+        }
+    }
+
+    // Audio tracks, placed on the timeline and mixed under the main track
+    let mut mix = vec![sound];
+    for track in timeline.tracks.iter().filter(|t| t.kind == TrackKind::Audio) {
+        for clip in &track.clips {
+            let input = add_input(&mut args, clip);
+            let delay = (clip.start * 1000.0).round() as u64;
+            let adelay = Filter::new("adelay").opt("delays", delay).opt("all", 1);
+            mix.push(clip_audio(&mut graph, input, clip, Some(adelay))?);
+        }
+    }
+    let audio_out = match mix.as_slice() {
+        [only] => only.clone(),
+        several => {
+            let pads: Vec<&str> = several.iter().map(String::as_str).collect();
+            let amix = Filter::new("amix").opt("inputs", several.len()).opt("duration", "first").opt("normalize", 0);
+            graph.chain(&pads, vec![amix], &["outa"]);
+            "outa".to_string()
+        }
+    };
+
+    // Text last, over everything
+    let mut text_files = Vec::new();
+    let mut finish = Vec::new();
+    for item in &timeline.texts {
+        let text_file = TempFile::new("timeline-text", "txt")?;
+        fs::write(text_file.path(), &item.text).map_err(|e| ClipFlowError::io("Failed to write timeline text", e))?;
+        finish.push(Filter::raw(overlay::drawtext_filter(item, &text_file, height)?));
+        text_files.push(text_file);
+    }
+    finish.push(Filter::new("format").arg("yuv420p"));
+    graph.chain(&[video.as_str()], finish, &["outv"]);
+
+    let audio_map = format!("[{}]", audio_out);
+    let filter_complex = graph.build(&["[outv]", audio_map.as_str()])?;
+    args.extend([
+        "-filter_complex".to_string(), filter_complex,
+        "-map".to_string(), "[outv]".to_string(),
+        "-map".to_string(), audio_map,
+        "-t".to_string(), length.to_string(),
+    ]);
+    args.extend(presets::codec_args(preset, true));
+    args.extend(color::bt709_output_args());
+    Ok((args, text_files))
+}
+
+/// Render a whole timeline to one file with an export preset (default
+/// "medium")
+/// Returns the path written.
+#[tauri::command]
+pub async fn render_timeline(
+    app: AppHandle,
+    timeline: Timeline,
+    output_path: &str,
+    preset: Option<String>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let name = preset.unwrap_or_else(|| "medium".to_string());
+    let preset = presets::resolve(&app, &name)?
+        .ok_or_else(|| ClipFlowError::not_found(format!("No export preset named \"{}\"", name)))?;
+    presets::validate(&preset)?;
+    presets::validate_output_path(&preset, output_path)?;
+    let (mut args, _text_files) = compile(&timeline, &preset)?;
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "timeline render").await
+    })
+    .await
+}
@@ -0,0 +1,237 @@
+//! Transcript store: one transcript per source file, kept in the app data
+//! dir with a history of manual corrections
+//!
+//! Whisper output is saved here as soon as a transcription finishes, and
+//! every edit bumps the transcript's version and records what it replaced,
+//! so corrections survive restarts and can be undone segment by segment.
+//! Captioning and cutting read the stored transcript instead of asking the
+//! frontend to pass one around.
+//!
+//! Each transcript remembers the content key of the file it was made from.
+//! Once the file is edited the key no longer matches and the transcript is
+//! reported stale; re-transcribing just the changed range brings it up to
+//! date without running Whisper over the whole file.
+
+use crate::{cache, now_secs, settings};
+use crate::error::{ClipFlowError, Result};
+use crate::project::{Transcript, TranscriptSegment};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SegmentEdit {
+    pub segment_id: usize,
+    /// Transcript version this edit produced
+    pub version: u32,
+    pub edited_at: u64,
+    pub previous: TranscriptSegment,
+    pub current: TranscriptSegment,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StoredTranscript {
+    pub source_path: String,
+    pub model: String,
+    pub created_at: u64,
+    /// 1 for fresh Whisper output, +1 for every edit since
+    pub version: u32,
+    pub transcript: Transcript,
+    /// Oldest first
+    #[serde(default)]
+    pub history: Vec<SegmentEdit>,
+    /// Content key of the file when it was transcribed
+    #[serde(default)]
+    pub source_key: Option<String>,
+    /// The file has changed since; worked out on every load
+    #[serde(skip_deserializing)]
+    pub stale: bool,
+}
+
+fn store_path(app: &AppHandle, source_path: &str) -> Result<PathBuf> {
+    let dir = settings::data_dir(app, "transcripts")?;
+    // Key on the absolute path so "./a.mp4" and "/full/a.mp4" share one transcript
+    let absolute = fs::canonicalize(source_path).unwrap_or_else(|_| PathBuf::from(source_path));
+    let key = blake3::hash(absolute.to_string_lossy().as_bytes()).to_hex();
+    Ok(dir.join(format!("{}.json", &key[..32])))
+}
+
+fn write(app: &AppHandle, stored: &StoredTranscript) -> Result<()> {
+    let path = store_path(app, &stored.source_path)?;
+    let json = serde_json::to_string_pretty(stored).map_err(|e| ClipFlowError::io("Failed to serialize transcript", e))?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json).map_err(|e| ClipFlowError::io("Failed to write transcript", e))?;
+    fs::rename(&tmp, &path).map_err(|e| ClipFlowError::io("Failed to save transcript", e))
+}
+
+/// The stored transcript for a source file, if it has been transcribed
+pub fn load(app: &AppHandle, source_path: &str) -> Result<Option<StoredTranscript>> {
+    let path = store_path(app, source_path)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).map_err(|e| ClipFlowError::io("Failed to read transcript", e))?;
+    let mut stored: StoredTranscript = serde_json::from_str(&content)
+        .map_err(|e| ClipFlowError::io(&format!("Invalid transcript store for {}", source_path), e))?;
+    // A file that can't be read now isn't called stale; it may be offline
+    stored.stale = match (&stored.source_key, cache::source_key(Path::new(source_path))) {
+        (Some(saved), Ok(current)) => *saved != current,
+        _ => false,
+    };
+    Ok(Some(stored))
+}
+
+/// Like `load`, but a missing transcript is an error
+pub fn require(app: &AppHandle, source_path: &str) -> Result<StoredTranscript> {
+    load(app, source_path)?.ok_or_else(|| ClipFlowError::not_found(format!("{} hasn't been transcribed", source_path)))
+}
+
+/// Store fresh Whisper output, replacing any earlier transcript of the file
+/// Segment ids change between runs, so the old edit history goes with it.
+pub fn save_new(
+    app: &AppHandle,
+    source_path: &str,
+    model: &str,
+    source_key: &str,
+    transcript: Transcript,
+) -> Result<StoredTranscript> {
+    let stored = StoredTranscript {
+        source_path: source_path.to_string(),
+        model: model.to_string(),
+        created_at: now_secs(),
+        version: 1,
+        transcript,
+        history: Vec::new(),
+        source_key: Some(source_key.to_string()),
+        stale: false,
+    };
+    write(app, &stored)?;
+    Ok(stored)
+}
+
+fn rebuild_text(transcript: &mut Transcript) {
+    transcript.text = transcript
+        .segments
+        .iter()
+        .map(|s| s.text.trim())
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+}
+
+fn apply_edit(stored: &mut StoredTranscript, current: TranscriptSegment) -> Result<()> {
+    let segment = stored
+        .transcript
+        .segments
+        .iter_mut()
+        .find(|s| s.id == current.id)
+        .ok_or_else(|| ClipFlowError::not_found(format!("No transcript segment {}", current.id)))?;
+    if current.end <= current.start {
+        return Err(ClipFlowError::invalid(format!("Segment {:.2}-{:.2}s is empty", current.start, current.end)));
+    }
+    let previous = std::mem::replace(segment, current.clone());
+    stored.version += 1;
+    stored.history.push(SegmentEdit {
+        segment_id: current.id,
+        version: stored.version,
+        edited_at: now_secs(),
+        previous,
+        current,
+    });
+    rebuild_text(&mut stored.transcript);
+    Ok(())
+}
+
+/// Swap the segments overlapping `start..end` for freshly transcribed ones
+/// and record the file's new content key
+/// New segments get ids after the existing ones, so the edit history of the
+/// segments that stay still points at the right ones.
+pub fn replace_range(
+    app: &AppHandle,
+    source_path: &str,
+    start: f64,
+    end: f64,
+    segments: Vec<TranscriptSegment>,
+    source_key: &str,
+) -> Result<StoredTranscript> {
+    let mut stored = require(app, source_path)?;
+    let transcript = &mut stored.transcript;
+    transcript.segments.retain(|s| s.end <= start || s.start >= end);
+    let first_id = transcript.segments.iter().map(|s| s.id + 1).max().unwrap_or(0);
+    for (n, mut segment) in segments.into_iter().enumerate() {
+        segment.id = first_id + n;
+        transcript.segments.push(segment);
+    }
+    transcript.segments.sort_by(|a, b| a.start.total_cmp(&b.start));
+    rebuild_text(transcript);
+    stored.version += 1;
+    stored.source_key = Some(source_key.to_string());
+    stored.stale = false;
+    write(app, &stored)?;
+    Ok(stored)
+}
+
+#[tauri::command]
+pub async fn get_transcript(app: AppHandle, source_path: &str) -> Result<Option<StoredTranscript>> {
+    load(&app, source_path)
+}
+
+/// Correct one segment's text and/or timing
+#[tauri::command]
+pub async fn edit_transcript_segment(
+    app: AppHandle,
+    source_path: &str,
+    segment_id: usize,
+    text: Option<String>,
+    start: Option<f64>,
+    end: Option<f64>,
+) -> Result<StoredTranscript> {
+    let mut stored = require(&app, source_path)?;
+    let mut segment = stored
+        .transcript
+        .segments
+        .iter()
+        .find(|s| s.id == segment_id)
+        .cloned()
+        .ok_or_else(|| ClipFlowError::not_found(format!("No transcript segment {}", segment_id)))?;
+    if let Some(text) = text {
+        segment.text = text.trim().to_string();
+        // Word timings describe the old wording
+        segment.words.clear();
+    }
+    segment.start = start.unwrap_or(segment.start);
+    segment.end = end.unwrap_or(segment.end);
+
+    apply_edit(&mut stored, segment)?;
+    write(&app, &stored)?;
+    Ok(stored)
+}
+
+/// Undo the latest edit to a segment
+/// The undo is itself recorded as an edit, so it can be undone in turn
+#[tauri::command]
+pub async fn revert_transcript_segment(app: AppHandle, source_path: &str, segment_id: usize) -> Result<StoredTranscript> {
+    let mut stored = require(&app, source_path)?;
+    let previous = stored
+        .history
+        .iter()
+        .rev()
+        .find(|e| e.segment_id == segment_id)
+        .map(|e| e.previous.clone())
+        .ok_or_else(|| ClipFlowError::not_found(format!("Segment {} has no edits to revert", segment_id)))?;
+
+    apply_edit(&mut stored, previous)?;
+    write(&app, &stored)?;
+    Ok(stored)
+}
+
+/// Drop the stored transcript of a file
+#[tauri::command]
+pub async fn delete_transcript(app: AppHandle, source_path: &str) -> Result<()> {
+    let path = store_path(&app, source_path)?;
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| ClipFlowError::io("Failed to delete transcript", e))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,312 @@
+//! Geometric video transforms: stabilization, crop, rotate, flip, rescale
+
+use crate::error::{ClipFlowError, Result};
+use crate::output::{self, OverwritePolicy};
+use crate::temp::TempFile;
+use crate::{color, escape_filter_path, probe, process, settings};
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+/// Two-pass stabilization with vid.stab
+///
+/// `smoothness` is the number of frames on each side averaged for the
+/// camera path (vidstabtransform's `smoothing`); higher is steadier but
+/// crops more and lags behind intentional pans.
+#[tauri::command]
+pub async fn stabilize_video(
+    input_path: &str,
+    output_path: &str,
+    smoothness: Option<u32>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let smoothness = smoothness.unwrap_or(10);
+    if smoothness > 100 {
+        return Err(ClipFlowError::invalid(format!("Smoothness must be between 0 and 100, got {}", smoothness)));
+    }
+
+    let transforms = TempFile::new("stabilize", "trf")?;
+    let transforms_arg = escape_filter_path(&transforms.path_str());
+
+    // Pass 1: analyze camera motion into the transforms file
+    process::run_ffmpeg_async(
+        &[
+            "-i".to_string(), input_path.to_string(),
+            "-vf".to_string(), format!("vidstabdetect=shakiness=5:accuracy=15:result='{}'", transforms_arg),
+            "-f".to_string(), "null".to_string(),
+            "-".to_string(),
+        ],
+        "stabilization analysis",
+    )
+    .await?;
+
+    // Pass 2: apply the smoothed path; optzoom hides the moving borders and
+    // a light unsharp recovers detail lost to the resampling
+    let (color_filter, color_tags) = color::export_color_args(input_path);
+    let mut video_filters: Vec<String> = color_filter.into_iter().collect();
+    video_filters.push(format!(
+        "vidstabtransform=input='{}':smoothing={}:optzoom=1:interpol=bicubic",
+        transforms_arg, smoothness
+    ));
+    video_filters.push("unsharp=5:5:0.8:3:3:0.4".to_string());
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-vf".to_string(), video_filters.join(","),
+        "-c:a".to_string(), "copy".to_string(),
+    ];
+    args.extend(color_tags);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "stabilization").await
+    })
+    .await
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct CropRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Crop, rotate (in 90 degree steps, clockwise), and flip in one re-encode
+#[tauri::command]
+pub async fn transform_video(
+    input_path: &str,
+    output_path: &str,
+    crop: Option<CropRect>,
+    rotate: Option<u32>,
+    flip_h: bool,
+    flip_v: bool,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let (color_filter, color_tags) = color::export_color_args(input_path);
+    let mut video_filters: Vec<String> = color_filter.into_iter().collect();
+
+    if let Some(rect) = crop {
+        let geometry = probe::video_geometry(input_path)?;
+        if rect.width == 0 || rect.height == 0 {
+            return Err(ClipFlowError::invalid("Crop width and height must be greater than zero"));
+        }
+        if rect.x + rect.width > geometry.width || rect.y + rect.height > geometry.height {
+            return Err(ClipFlowError::invalid(format!(
+                "Crop {}x{}+{}+{} exceeds the {}x{} frame",
+                rect.width, rect.height, rect.x, rect.y, geometry.width, geometry.height
+            )));
+        }
+        video_filters.push(format!("crop={}:{}:{}:{}", rect.width, rect.height, rect.x, rect.y));
+    }
+
+    match rotate.unwrap_or(0) % 360 {
+        0 => {}
+        90 => video_filters.push("transpose=clock".to_string()),
+        180 => video_filters.push("hflip,vflip".to_string()),
+        270 => video_filters.push("transpose=cclock".to_string()),
+        other => return Err(ClipFlowError::invalid(format!("Rotation must be a multiple of 90 degrees, got {}", other))),
+    }
+
+    if flip_h {
+        video_filters.push("hflip".to_string());
+    }
+    if flip_v {
+        video_filters.push("vflip".to_string());
+    }
+
+    if video_filters.is_empty() {
+        return Err(ClipFlowError::invalid("No transform requested"));
+    }
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-vf".to_string(), video_filters.join(","),
+        "-c:a".to_string(), "copy".to_string(),
+        // Our filters already applied any display rotation; don't let
+        // players rotate a second time
+        "-metadata:s:v:0".to_string(), "rotate=0".to_string(),
+    ];
+    args.extend(color_tags);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "transform").await
+    })
+    .await
+}
+
+/// How a frame with a different aspect ratio goes into the target size
+#[derive(Deserialize, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum FitMode {
+    /// Scale to fit inside the target; the output keeps the source aspect
+    /// and may come out smaller than requested on one side
+    #[default]
+    Fit,
+    /// Scale to cover the target and crop the overflow
+    Fill,
+    /// Scale to fit inside the target and letterbox/pillarbox the rest
+    Pad,
+}
+
+/// Resampling filter for swscale
+#[derive(Deserialize, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ScalingAlgorithm {
+    Bilinear,
+    #[default]
+    Bicubic,
+    Lanczos,
+    Spline,
+    /// Area averaging, sharpest for large downscales
+    Area,
+    /// Nearest neighbor, for pixel art and screen captures
+    Neighbor,
+}
+
+impl ScalingAlgorithm {
+    fn flag(&self) -> &'static str {
+        match self {
+            ScalingAlgorithm::Bilinear => "bilinear",
+            ScalingAlgorithm::Bicubic => "bicubic",
+            ScalingAlgorithm::Lanczos => "lanczos",
+            ScalingAlgorithm::Spline => "spline",
+            ScalingAlgorithm::Area => "area",
+            ScalingAlgorithm::Neighbor => "neighbor",
+        }
+    }
+}
+
+/// Filters scaling a frame to `width` x `height` under `mode`
+pub fn rescale_filters(width: u32, height: u32, mode: FitMode, algorithm: ScalingAlgorithm) -> Vec<String> {
+    let flags = algorithm.flag();
+    match mode {
+        FitMode::Fit => vec![format!(
+            "scale={}:{}:force_original_aspect_ratio=decrease:force_divisible_by=2:flags={}",
+            width, height, flags
+        )],
+        FitMode::Fill => vec![
+            format!("scale={}:{}:force_original_aspect_ratio=increase:flags={}", width, height, flags),
+            format!("crop={}:{}", width, height),
+        ],
+        FitMode::Pad => vec![
+            format!("scale={}:{}:force_original_aspect_ratio=decrease:flags={}", width, height, flags),
+            format!("pad={w}:{h}:(ow-iw)/2:(oh-ih)/2", w = width, h = height),
+        ],
+    }
+}
+
+/// Change resolution and/or frame rate, e.g. 4K captures down to 1080p
+///
+/// With only one of `width`/`height`, the other follows the source aspect
+/// and `mode` doesn't matter. `fps` resamples the frame rate by dropping or
+/// duplicating frames. Returns the path written.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn rescale_video(
+    input_path: &str,
+    output_path: &str,
+    width: Option<u32>,
+    height: Option<u32>,
+    fps: Option<f64>,
+    scaling_algo: Option<ScalingAlgorithm>,
+    mode: Option<FitMode>,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    if width.is_some_and(|w| w < 2 || w % 2 != 0) || height.is_some_and(|h| h < 2 || h % 2 != 0) {
+        return Err(ClipFlowError::invalid("Width and height must be even numbers of at least 2"));
+    }
+    if let Some(fps) = fps {
+        if !(1.0..=240.0).contains(&fps) {
+            return Err(ClipFlowError::invalid(format!("Frame rate must be between 1 and 240, got {}", fps)));
+        }
+    }
+    if width.is_none() && height.is_none() && fps.is_none() {
+        return Err(ClipFlowError::invalid("No size or frame rate requested"));
+    }
+
+    let algorithm = scaling_algo.unwrap_or_default();
+
+    let (color_filter, color_tags) = color::export_color_args(input_path);
+    let mut video_filters: Vec<String> = color_filter.into_iter().collect();
+
+    // Work in square pixels so fit/fill/pad see the displayed aspect
+    let geometry = probe::video_geometry(input_path)?;
+    video_filters.extend(probe::square_pixel_filter(&geometry));
+
+    match (width, height) {
+        (Some(w), Some(h)) => video_filters.extend(rescale_filters(w, h, mode.unwrap_or_default(), algorithm)),
+        (Some(w), None) => video_filters.push(format!("scale={}:-2:flags={}", w, algorithm.flag())),
+        (None, Some(h)) => video_filters.push(format!("scale=-2:{}:flags={}", h, algorithm.flag())),
+        (None, None) => {}
+    }
+    if width.is_some() || height.is_some() {
+        video_filters.push("setsar=1".to_string());
+    }
+    if let Some(fps) = fps {
+        video_filters.push(format!("fps={}", fps));
+    }
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), input_path.to_string(),
+        "-vf".to_string(), video_filters.join(","),
+        "-c:a".to_string(), "copy".to_string(),
+    ];
+    args.extend(color_tags);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "rescale").await
+    })
+    .await
+}
+
+/// Last crop=W:H:X:Y suggestion in cropdetect's output
+fn parse_cropdetect(stderr: &str) -> Option<CropRect> {
+    let line = stderr.lines().rev().find(|l| l.contains("crop="))?;
+    let spec = line.split("crop=").nth(1)?.split_whitespace().next()?;
+    let parts: Vec<u32> = spec.split(':').filter_map(|p| p.parse().ok()).collect();
+    if parts.len() != 4 {
+        return None;
+    }
+    Some(CropRect { width: parts[0], height: parts[1], x: parts[2], y: parts[3] })
+}
+
+/// Find black letterbox/pillarbox bars and suggest a crop that removes them
+///
+/// Samples a few points across the file and keeps the largest detected
+/// area, so one dark scene can't crop into real picture.
+#[tauri::command]
+pub async fn detect_crop(input_path: &str) -> Result<Option<CropRect>> {
+    let duration = probe::media_duration(input_path)?;
+    let geometry = probe::video_geometry(input_path)?;
+
+    let mut best: Option<CropRect> = None;
+    let ffmpeg = settings::ffmpeg_bin();
+    for fraction in [0.25, 0.5, 0.75] {
+        let output = process::run(Command::new(&ffmpeg).args([
+            "-ss", &format!("{}", duration * fraction),
+            "-i", input_path,
+            "-t", "2",
+            "-vf", "cropdetect=limit=24:round=2:reset=0",
+            "-an",
+            "-f", "null",
+            "-",
+        ]), None, |_| {})
+        .await
+        .map_err(|e| process::spawn_error(&ffmpeg, e))?;
+
+        if let Some(rect) = parse_cropdetect(&String::from_utf8_lossy(&output.stderr)) {
+            let area = |r: &CropRect| r.width as u64 * r.height as u64;
+            if best.is_none_or(|b| area(&rect) > area(&b)) {
+                best = Some(rect);
+            }
+        }
+    }
+
+    // Nothing worth cropping if the bars are negligible
+    Ok(best.filter(|rect| rect.width + 8 < geometry.width || rect.height + 8 < geometry.height))
+}
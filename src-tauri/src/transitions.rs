@@ -0,0 +1,193 @@
+//! Joining clips with transitions (xfade/acrossfade)
+
+use crate::error::{ClipFlowError, Result};
+use crate::output::{self, OverwritePolicy};
+use crate::{color, probe, process};
+
+/// Filters bringing a clip to a common size/rate/format so xfade accepts it
+/// (xfade refuses inputs whose size, frame rate, or pixel format differ)
+pub fn normalize_filter(width: u32, height: u32, fps: f64) -> String {
+    format!(
+        "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={fps},format=yuv420p,settb=AVTB",
+        w = width,
+        h = height,
+        fps = fps
+    )
+}
+
+/// Audio filters bringing a clip to a common sample rate and layout
+pub fn normalize_audio_filter() -> &'static str {
+    "aresample=48000,aformat=sample_fmts=fltp:channel_layouts=stereo"
+}
+
+/// Join two clips, crossfading picture and sound over `duration` seconds
+///
+/// Clip B is conformed to clip A's size and frame rate, so clips from
+/// different sources can be joined directly.
+#[tauri::command]
+pub async fn crossfade_clips(
+    clip_a: &str,
+    clip_b: &str,
+    output_path: &str,
+    duration: f64,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    let duration_a = probe::media_duration(clip_a)?;
+    let duration_b = probe::media_duration(clip_b)?;
+    if duration <= 0.0 || duration >= duration_a.min(duration_b) {
+        return Err(ClipFlowError::invalid(format!(
+            "Crossfade must be longer than 0s and shorter than both clips ({:.2}s, {:.2}s)",
+            duration_a, duration_b
+        )));
+    }
+
+    let geometry = probe::video_geometry(clip_a)?;
+    let fps = probe::frame_rate(clip_a)?;
+    let normalize = normalize_filter(geometry.display_width, geometry.height, fps);
+    let has_audio = !probe::audio_tracks(clip_a)?.is_empty() && !probe::audio_tracks(clip_b)?.is_empty();
+
+    let (color_a, _) = color::export_color_args(clip_a);
+    let (color_b, color_tags) = color::export_color_args(clip_b);
+    let prefix = |c: Option<String>| c.map(|f| format!("{},", f)).unwrap_or_default();
+
+    let mut graph = vec![
+        format!("[0:v]{}{}[va]", prefix(color_a), normalize),
+        format!("[1:v]{}{}[vb]", prefix(color_b), normalize),
+        format!(
+            "[va][vb]xfade=transition=fade:duration={}:offset={}[outv]",
+            duration,
+            duration_a - duration
+        ),
+    ];
+    if has_audio {
+        graph.push(format!("[0:a]{}[aa]", normalize_audio_filter()));
+        graph.push(format!("[1:a]{}[ab]", normalize_audio_filter()));
+        graph.push(format!("[aa][ab]acrossfade=d={}[outa]", duration));
+    }
+
+    let mut args: Vec<String> = vec![
+        "-i".to_string(), clip_a.to_string(),
+        "-i".to_string(), clip_b.to_string(),
+        "-filter_complex".to_string(), graph.join(";"),
+        "-map".to_string(), "[outv]".to_string(),
+    ];
+    if has_audio {
+        args.push("-map".to_string());
+        args.push("[outa]".to_string());
+    }
+    args.extend(color_tags);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "crossfade").await
+    })
+    .await
+}
+
+/// xfade transitions offered for bumpers and timeline cuts; the rest of
+/// xfade's list is rarely wanted
+pub const XFADE_TRANSITIONS: &[&str] = &[
+    "fade", "fadeblack", "fadewhite", "dissolve", "wipeleft", "wiperight", "wipeup", "wipedown", "slideleft",
+    "slideright", "slideup", "slidedown", "smoothleft", "smoothright", "circleopen", "circleclose", "radial",
+    "pixelize", "zoomin",
+];
+
+/// Put an intro and/or outro around a clip, joined with `transition` (an
+/// xfade transition name, default "fade") over `duration` seconds
+///
+/// The bumpers are conformed to the main clip's size and frame rate, and a
+/// clip without sound gets silence so the audio still crossfades.
+#[tauri::command]
+pub async fn attach_bumpers(
+    intro: Option<String>,
+    main: &str,
+    outro: Option<String>,
+    output_path: &str,
+    transition: Option<String>,
+    duration: f64,
+    overwrite: Option<OverwritePolicy>,
+) -> Result<String> {
+    if intro.is_none() && outro.is_none() {
+        return Err(ClipFlowError::invalid("Give an intro, an outro, or both"));
+    }
+    let transition = transition.unwrap_or_else(|| "fade".to_string());
+    if !XFADE_TRANSITIONS.contains(&transition.as_str()) {
+        return Err(ClipFlowError::invalid(format!("Unknown transition: {}", transition)));
+    }
+    let clips: Vec<&str> = intro.as_deref().into_iter().chain([main]).chain(outro.as_deref()).collect();
+    let durations = clips.iter().map(|c| probe::media_duration(c)).collect::<Result<Vec<f64>>>()?;
+    let main_duration = durations[intro.is_some() as usize];
+    let fades = clips.len() - 1;
+    let shortest = durations.iter().cloned().fold(f64::INFINITY, f64::min);
+    if duration.is_nan() || duration <= 0.0 || duration >= shortest || duration * fades as f64 >= main_duration {
+        return Err(ClipFlowError::invalid(format!(
+            "Transition must be longer than 0s, shorter than every clip ({:.2}s the shortest), and fit {} times in the main clip",
+            shortest, fades
+        )));
+    }
+
+    let geometry = probe::video_geometry(main)?;
+    let fps = probe::frame_rate(main)?;
+    let normalize = normalize_filter(geometry.display_width, geometry.height, fps);
+    let audio = clips
+        .iter()
+        .map(|c| probe::audio_tracks(c).map(|tracks| !tracks.is_empty()))
+        .collect::<Result<Vec<bool>>>()?;
+    let has_audio = audio.iter().any(|a| *a);
+    let (_, color_tags) = color::export_color_args(main);
+
+    let mut graph = Vec::new();
+    for (i, clip) in clips.iter().enumerate() {
+        let convert = color::export_color_args(clip).0.map(|f| format!("{},", f)).unwrap_or_default();
+        graph.push(format!("[{}:v]{}{}[v{}]", i, convert, normalize, i));
+        if !has_audio {
+            continue;
+        }
+        if audio[i] {
+            graph.push(format!("[{}:a]{}[a{}]", i, normalize_audio_filter(), i));
+        } else {
+            graph.push(format!("anullsrc=r=48000:cl=stereo,atrim=duration={},{}[a{}]", durations[i], normalize_audio_filter(), i));
+        }
+    }
+    // Each fade starts `duration` before the end of everything joined so far
+    let mut joined = durations[0];
+    let (mut video, mut sound) = ("v0".to_string(), "a0".to_string());
+    for (i, clip_duration) in durations.iter().enumerate().skip(1) {
+        let last = i == clips.len() - 1;
+        let (next_video, next_sound) = match last {
+            true => ("outv".to_string(), "outa".to_string()),
+            false => (format!("x{}", i), format!("y{}", i)),
+        };
+        graph.push(format!(
+            "[{}][v{}]xfade=transition={}:duration={}:offset={}[{}]",
+            video, i, transition, duration, joined - duration, next_video
+        ));
+        if has_audio {
+            graph.push(format!("[{}][a{}]acrossfade=d={}[{}]", sound, i, duration, next_sound));
+        }
+        joined += clip_duration - duration;
+        (video, sound) = (next_video, next_sound);
+    }
+
+    let mut args: Vec<String> = Vec::new();
+    for clip in &clips {
+        args.extend(["-i".to_string(), clip.to_string()]);
+    }
+    args.extend([
+        "-filter_complex".to_string(), graph.join(";"),
+        "-map".to_string(), "[outv]".to_string(),
+    ]);
+    if has_audio {
+        args.push("-map".to_string());
+        args.push("[outa]".to_string());
+    }
+    args.extend(color_tags);
+
+    output::write_atomically_async(output_path, overwrite.unwrap_or_default(), |partial| async move {
+        args.push(partial);
+        args.push("-y".to_string());
+        process::run_ffmpeg_async(&args, "bumpers").await
+    })
+    .await
+}
@@ -0,0 +1,100 @@
+//! Offline translation of transcript segments into a second caption track
+//!
+//! Translation runs through Argos Translate (`translate_path` in settings,
+//! or `argos-translate` on PATH), which works locally once its language
+//! packages are installed. Segments are sent one per line, a batch at a
+//! time; if a batch comes back with a different number of lines the
+//! segments in it are translated one by one, so timings always stay paired
+//! with the right text.
+
+use crate::captions::{self, CaptionOptions};
+use crate::error::{ClipFlowError, Result};
+use crate::project::{Transcript, TranscriptSegment};
+use crate::{languages, process, settings};
+use std::fs;
+use std::path::Path;
+use tokio::process::Command;
+
+/// Characters sent per call; keeps the command line within Windows' limit
+const BATCH_CHARS: usize = 6000;
+
+async fn translate_text(tool: &str, from: &str, to: &str, text: &str) -> Result<String> {
+    let output = process::run(Command::new(tool).args(["--from", from, "--to", to, text]), None, |_| {})
+        .await
+        .map_err(|e| process::spawn_error(tool, e))?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(ClipFlowError::tool("translation", error.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+}
+
+/// Batches of segment indices whose joined text fits in `BATCH_CHARS`
+fn batches(segments: &[TranscriptSegment]) -> Vec<Vec<usize>> {
+    let mut batches: Vec<Vec<usize>> = Vec::new();
+    let mut chars = 0;
+    for (i, segment) in segments.iter().enumerate() {
+        let len = segment.text.len() + 1;
+        match batches.last_mut() {
+            Some(batch) if chars + len <= BATCH_CHARS => batch.push(i),
+            _ => {
+                batches.push(vec![i]);
+                chars = 0;
+            }
+        }
+        chars += len;
+    }
+    batches
+}
+
+/// Translate transcript segments into `target_language`, keeping their
+/// timings, and optionally write them out as captions (SRT or WebVTT,
+/// following the extension of `output_path`)
+/// `source_language` defaults to English.
+#[tauri::command]
+pub async fn translate_transcript(
+    segments: Vec<TranscriptSegment>,
+    source_language: Option<String>,
+    target_language: String,
+    output_path: Option<String>,
+) -> Result<Transcript> {
+    let from = languages::resolve(source_language.as_deref())?.unwrap_or_else(|| "en".to_string());
+    let to = languages::resolve(Some(&target_language))?
+        .ok_or_else(|| ClipFlowError::invalid("Pick a language to translate into"))?;
+    let tool = settings::current().translate_path.unwrap_or_else(|| "argos-translate".to_string());
+
+    let mut translated = segments.clone();
+    if from != to {
+        for batch in batches(&segments) {
+            // Line breaks inside a segment would throw off the pairing
+            let lines: Vec<String> = batch.iter().map(|i| segments[*i].text.replace('\n', " ")).collect();
+            let result = translate_text(&tool, &from, &to, &lines.join("\n")).await?;
+            let result: Vec<&str> = result.lines().collect();
+            if result.len() == batch.len() {
+                for (i, text) in batch.iter().zip(result) {
+                    translated[*i].text = text.trim().to_string();
+                }
+                continue;
+            }
+            tracing::debug!(expected = batch.len(), got = result.len(), "translation merged lines; retrying one by one");
+            for (i, line) in batch.iter().zip(&lines) {
+                translated[*i].text = translate_text(&tool, &from, &to, line).await?.trim().to_string();
+            }
+        }
+    }
+
+    let transcript = Transcript {
+        language: to,
+        text: translated.iter().map(|s| s.text.as_str()).collect::<Vec<_>>().join(" "),
+        segments: translated,
+    };
+    if let Some(output_path) = output_path {
+        let format = Path::new(&output_path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        let content = captions::render_captions(&transcript, &format, &CaptionOptions::default())?;
+        fs::write(&output_path, content).map_err(|e| ClipFlowError::io("Failed to write translated captions", e))?;
+    }
+    Ok(transcript)
+}
@@ -0,0 +1,146 @@
+//! Voice activity detection, for finding speech without transcribing
+//!
+//! Uses the WebRTC voice detector on 8kHz mono audio in 30ms frames, which
+//! runs hundreds of times faster than real time, so silence cutting on a
+//! multi-hour recording doesn't have to wait for Whisper. Audio is decoded
+//! a few minutes at a time to keep memory flat however long the file is.
+//!
+//! Frame decisions are smoothed: short pauses inside speech are kept as
+//! speech, and blips of "speech" too short to be a word are dropped.
+
+use crate::error::{ClipFlowError, Result};
+use crate::{probe, process, settings};
+use serde::Serialize;
+use tokio::process::Command;
+use webrtc_vad::{SampleRate, Vad, VadMode};
+
+const VAD_SAMPLE_RATE: usize = 8000;
+
+/// 30ms, the longest frame the detector takes
+const FRAME_SAMPLES: usize = VAD_SAMPLE_RATE * 30 / 1000;
+
+/// Audio decoded per ffmpeg run
+const CHUNK_SECONDS: f64 = 600.0;
+
+const DEFAULT_AGGRESSIVENESS: u8 = 2;
+const DEFAULT_MIN_SILENCE: f64 = 0.3;
+
+/// Speech shorter than this is treated as noise
+const MIN_SPEECH: f64 = 0.25;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct VadSegment {
+    pub start: f64,
+    pub end: f64,
+    pub speech: bool,
+}
+
+fn vad_mode(aggressiveness: u8) -> VadMode {
+    match aggressiveness {
+        0 => VadMode::Quality,
+        1 => VadMode::LowBitrate,
+        2 => VadMode::Aggressive,
+        _ => VadMode::VeryAggressive,
+    }
+}
+
+/// Mono 8kHz samples of `start..start + CHUNK_SECONDS` of the first audio track
+async fn decode_chunk(file_path: &str, start: f64) -> Result<Vec<i16>> {
+    let ffmpeg = settings::ffmpeg_bin();
+    let (start, length, rate) = (start.to_string(), CHUNK_SECONDS.to_string(), VAD_SAMPLE_RATE.to_string());
+    let output = process::run(Command::new(&ffmpeg).args([
+        "-v", "error",
+        "-ss", start.as_str(),
+        "-t", length.as_str(),
+        "-i", file_path,
+        "-map", "0:a:0",
+        "-ac", "1",
+        "-ar", rate.as_str(),
+        "-f", "s16le",
+        "-",
+    ]), None, |_| {})
+    .await
+    .map_err(|e| process::spawn_error(&ffmpeg, e))?;
+    process::check_ffmpeg(&output, "voice activity audio")?;
+    Ok(output.stdout.chunks_exact(2).map(|s| i16::from_le_bytes([s[0], s[1]])).collect())
+}
+
+/// Speech/non-speech decision for each full frame of `samples`
+/// The detector isn't Send, so one lives only as long as a chunk.
+fn classify(samples: &[i16], mode: VadMode) -> Vec<bool> {
+    let mut vad = Vad::new_with_rate_and_mode(SampleRate::Rate8kHz, mode);
+    samples
+        .chunks_exact(FRAME_SAMPLES)
+        .map(|frame| vad.is_voice_segment(frame).unwrap_or(false))
+        .collect()
+}
+
+/// Alternating speech and non-speech segments covering `duration`
+fn segments(frames: &[bool], duration: f64, min_silence: f64) -> Vec<VadSegment> {
+    let frame_seconds = FRAME_SAMPLES as f64 / VAD_SAMPLE_RATE as f64;
+    let mut speech: Vec<(f64, f64)> = Vec::new();
+    for (i, is_speech) in frames.iter().enumerate() {
+        if !is_speech {
+            continue;
+        }
+        let (start, end) = (i as f64 * frame_seconds, (i + 1) as f64 * frame_seconds);
+        match speech.last_mut() {
+            Some(last) if start - last.1 < min_silence => last.1 = end,
+            _ => speech.push((start, end)),
+        }
+    }
+    speech.retain(|(start, end)| end - start >= MIN_SPEECH);
+
+    let mut result = Vec::new();
+    let mut cursor = 0.0;
+    for (start, end) in speech {
+        let end = end.min(duration);
+        if start > cursor {
+            result.push(VadSegment { start: cursor, end: start, speech: false });
+        }
+        result.push(VadSegment { start, end, speech: true });
+        cursor = end;
+    }
+    if duration > cursor {
+        result.push(VadSegment { start: cursor, end: duration, speech: false });
+    }
+    result
+}
+
+/// Split a file into speech and non-speech segments without transcribing it
+/// `aggressiveness` is 0 (keeps anything that might be speech) to 3 (only
+/// clear speech), 2 unless set; pauses shorter than `min_silence` seconds
+/// stay part of the speech around them.
+#[tauri::command]
+pub async fn detect_voice_activity(
+    file_path: &str,
+    aggressiveness: Option<u8>,
+    min_silence: Option<f64>,
+) -> Result<Vec<VadSegment>> {
+    let aggressiveness = aggressiveness.unwrap_or(DEFAULT_AGGRESSIVENESS);
+    if aggressiveness > 3 {
+        return Err(ClipFlowError::invalid(format!("Aggressiveness must be 0 to 3, got {}", aggressiveness)));
+    }
+    let min_silence = min_silence.unwrap_or(DEFAULT_MIN_SILENCE);
+    if min_silence.is_nan() || min_silence < 0.0 {
+        return Err(ClipFlowError::invalid("Minimum silence must not be negative"));
+    }
+    if probe::audio_tracks(file_path)?.is_empty() {
+        return Err(ClipFlowError::invalid("Input has no audio"));
+    }
+    let duration = probe::media_duration(file_path)?;
+
+    // Chunks are a whole number of frames long, so frame times line up
+    // across chunk boundaries
+    let mut frames = Vec::new();
+    let mut start = 0.0;
+    while start < duration {
+        let samples = decode_chunk(file_path, start).await?;
+        frames.extend(classify(&samples, vad_mode(aggressiveness)));
+        if samples.len() < (CHUNK_SECONDS as usize) * VAD_SAMPLE_RATE {
+            break;
+        }
+        start += CHUNK_SECONDS;
+    }
+    Ok(segments(&frames, duration, min_silence))
+}
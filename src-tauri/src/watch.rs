@@ -0,0 +1,239 @@
+//! Watch folders: directories (e.g. the OBS recording folder) checked for
+//! new video files, which are announced - and optionally run through an
+//! import pipeline - once they've finished writing
+//!
+//! Folders are polled rather than watched through OS notifications. A
+//! recorder appends to its file for hours, so the file appearing isn't the
+//! useful event; the only portable sign that it's done is a size that has
+//! stopped changing (plus, on Windows, the recorder's write lock going away).
+//! Files already in a folder when it's added, or when the app starts, are
+//! left alone.
+
+use crate::error::{ClipFlowError, Result};
+use crate::library::{self, VIDEO_EXTENSIONS};
+use crate::{now_secs, settings};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Emitter, State};
+
+/// How often watched folders are listed
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a file's size and modification time must stay put before it
+/// counts as finished
+const SETTLE_TIME: Duration = Duration::from_secs(5);
+
+/// Steps run on each finished file, in order
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum WatchStep {
+    /// Add the file to the media library
+    Import,
+    /// Transcribe it into the transcript store
+    Transcribe { model: String },
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WatchFolder {
+    pub id: String,
+    pub path: String,
+    #[serde(default)]
+    pub recursive: bool,
+    #[serde(default)]
+    pub pipeline: Vec<WatchStep>,
+    pub added_at: u64,
+}
+
+/// A file that's still being written
+struct Pending {
+    size: u64,
+    modified: Option<SystemTime>,
+    last_change: Instant,
+}
+
+#[derive(Default)]
+pub struct WatchState {
+    folders: Mutex<Vec<WatchFolder>>,
+    pending: Mutex<HashMap<PathBuf, Pending>>,
+    /// Files announced already, or there before watching began
+    known: Mutex<HashSet<PathBuf>>,
+}
+
+#[derive(Serialize, Clone)]
+struct WatchEvent {
+    folder_id: String,
+    path: String,
+    size_bytes: u64,
+}
+
+#[derive(Serialize, Clone)]
+struct PipelineEvent {
+    folder_id: String,
+    path: String,
+    /// None when every step succeeded
+    error: Option<String>,
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn folders_path(app: &AppHandle) -> Result<PathBuf> {
+    settings::config_file(app, "watch_folders.json")
+}
+
+fn persist(app: &AppHandle, folders: &[WatchFolder]) -> Result<()> {
+    let json = serde_json::to_string_pretty(folders).map_err(|e| ClipFlowError::io("Failed to serialize watch folders", e))?;
+    fs::write(folders_path(app)?, json).map_err(|e| ClipFlowError::io("Failed to write watch folders", e))
+}
+
+fn load(app: &AppHandle) -> Vec<WatchFolder> {
+    folders_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn video_files(folder: &WatchFolder) -> Vec<PathBuf> {
+    library::walk_files(Path::new(&folder.path), folder.recursive, VIDEO_EXTENSIONS)
+}
+
+/// Whether the writer has let go of the file
+/// Recorders on Windows hold it open without sharing, so opening it for
+/// writing fails until they're done; elsewhere this always succeeds.
+fn writable(path: &Path) -> bool {
+    OpenOptions::new().append(true).open(path).is_ok()
+}
+
+async fn run_pipeline(app: AppHandle, folder: WatchFolder, path: String) {
+    let mut error = None;
+    for step in &folder.pipeline {
+        let result = match step {
+            WatchStep::Import => library::add_paths(&app, &[path.clone()]).map(|_| ()),
+            WatchStep::Transcribe { model } => crate::transcribe_audio(app.clone(), &path, model, None, None, None).await.map(|_| ()),
+        };
+        if let Err(e) = result {
+            tracing::warn!(file = %path, step = ?step, error = %e, "watch folder pipeline step failed");
+            error = Some(e.to_string());
+            break;
+        }
+    }
+    let _ = app.emit("watch-pipeline-finished", PipelineEvent { folder_id: folder.id, path, error });
+}
+
+/// One pass over every watched folder
+fn poll(app: &AppHandle) {
+    let state = app.state::<WatchState>();
+    let folders = state.folders.lock().unwrap().clone();
+    let mut finished = Vec::new();
+    {
+        let mut pending = state.pending.lock().unwrap();
+        let mut known = state.known.lock().unwrap();
+        for folder in &folders {
+            let unseen: Vec<PathBuf> = video_files(folder).into_iter().filter(|p| !known.contains(p)).collect();
+            for path in unseen {
+                let metadata = match fs::metadata(&path) {
+                    Ok(metadata) => metadata,
+                    Err(_) => continue,
+                };
+                let (size, modified) = (metadata.len(), metadata.modified().ok());
+                let event = WatchEvent { folder_id: folder.id.clone(), path: path.to_string_lossy().into_owned(), size_bytes: size };
+
+                match pending.get_mut(&path) {
+                    None => {
+                        let _ = app.emit("watch-file-detected", event);
+                        pending.insert(path, Pending { size, modified, last_change: Instant::now() });
+                    }
+                    Some(entry) if entry.size != size || entry.modified != modified => {
+                        entry.size = size;
+                        entry.modified = modified;
+                        entry.last_change = Instant::now();
+                    }
+                    Some(entry) if entry.last_change.elapsed() >= SETTLE_TIME && size > 0 && writable(&path) => {
+                        pending.remove(&path);
+                        known.insert(path);
+                        tracing::info!(file = %event.path, folder = %folder.path, "watched file finished writing");
+                        let _ = app.emit("watch-file-ready", event.clone());
+                        finished.push((folder.clone(), event.path));
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        // Forget files deleted before they settled
+        pending.retain(|path, _| path.exists());
+    }
+
+    for (folder, path) in finished.into_iter().filter(|(f, _)| !f.pipeline.is_empty()) {
+        tauri::async_runtime::spawn(run_pipeline(app.clone(), folder, path));
+    }
+}
+
+/// Load the saved folders and start polling them
+pub fn start(app: AppHandle) {
+    let folders = load(&app);
+    {
+        let state = app.state::<WatchState>();
+        state.known.lock().unwrap().extend(folders.iter().flat_map(video_files));
+        *state.folders.lock().unwrap() = folders;
+    }
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            poll(&app);
+        }
+    });
+}
+
+/// Start watching `path` for new recordings
+/// Emits "watch-file-detected" when a file appears, "watch-file-ready" once
+/// it has finished writing, and "watch-pipeline-finished" after `pipeline`
+/// has run on it.
+#[tauri::command]
+pub async fn add_watch_folder(
+    app: AppHandle,
+    state: State<'_, WatchState>,
+    path: String,
+    recursive: Option<bool>,
+    pipeline: Option<Vec<WatchStep>>,
+) -> Result<WatchFolder> {
+    if !Path::new(&path).is_dir() {
+        return Err(ClipFlowError::not_found(format!("Folder not found: {}", path)));
+    }
+    let mut folders = state.folders.lock().unwrap();
+    if folders.iter().any(|f| Path::new(&f.path) == Path::new(&path)) {
+        return Err(ClipFlowError::invalid(format!("{} is already being watched", path)));
+    }
+    let folder = WatchFolder {
+        id: format!("watch-{}-{}", now_secs(), NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+        path,
+        recursive: recursive.unwrap_or(false),
+        pipeline: pipeline.unwrap_or_default(),
+        added_at: now_secs(),
+    };
+    state.known.lock().unwrap().extend(video_files(&folder));
+    folders.push(folder.clone());
+    persist(&app, &folders)?;
+    Ok(folder)
+}
+
+#[tauri::command]
+pub async fn remove_watch_folder(app: AppHandle, state: State<'_, WatchState>, id: String) -> Result<()> {
+    let mut folders = state.folders.lock().unwrap();
+    let before = folders.len();
+    folders.retain(|f| f.id != id);
+    if folders.len() == before {
+        return Err(ClipFlowError::not_found(format!("Watch folder not found: {}", id)));
+    }
+    persist(&app, &folders)
+}
+
+#[tauri::command]
+pub async fn list_watch_folders(state: State<'_, WatchState>) -> Result<Vec<WatchFolder>> {
+    Ok(state.folders.lock().unwrap().clone())
+}
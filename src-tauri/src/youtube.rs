@@ -0,0 +1,498 @@
+//! Uploading finished videos to YouTube through the Data API
+//!
+//! Accounts are connected with Google's OAuth device flow: the user enters
+//! a short code on google.com/device while we poll for the token, so no
+//! browser redirect back into the app is needed. The token (with its
+//! refresh token and the OAuth client it belongs to) is kept in the OS
+//! keychain as a YouTube credential.
+//!
+//! Uploads use the resumable protocol in chunks. The session URL is saved
+//! as a transfer checkpoint, so a dropped connection or an app restart
+//! continues from the last byte YouTube confirmed.
+
+use crate::credentials::{self, CredentialProvider};
+use crate::error::{ClipFlowError, Result};
+use crate::retry::{self, NetworkError, TransferCheckpoint};
+use crate::{now_secs, settings};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+
+const DEVICE_CODE_URL: &str = "https://oauth2.googleapis.com/device/code";
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const UPLOAD_URL: &str = "https://www.googleapis.com/upload/youtube/v3/videos?uploadType=resumable&part=snippet,status";
+const UPLOAD_SCOPE: &str = "https://www.googleapis.com/auth/youtube.upload";
+
+/// Keychain account used when none is named
+const DEFAULT_ACCOUNT: &str = "default";
+
+/// Bytes sent per request; the API wants a multiple of 256 KiB
+const CHUNK_SIZE: u64 = 32 * 256 * 1024;
+
+/// Limits YouTube enforces on video metadata
+const MAX_TITLE_CHARS: usize = 100;
+const MAX_DESCRIPTION_BYTES: usize = 5000;
+const MAX_TAGS_CHARS: usize = 500;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Privacy {
+    Public,
+    Unlisted,
+    Private,
+}
+
+/// What the video is published as
+#[derive(Serialize, Deserialize, Clone)]
+pub struct VideoDetails {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub privacy: Privacy,
+}
+
+/// The token as stored in the keychain
+#[derive(Serialize, Deserialize)]
+struct StoredToken {
+    client_id: String,
+    client_secret: String,
+    access_token: String,
+    refresh_token: String,
+    /// Unix seconds
+    expires_at: u64,
+}
+
+#[derive(Deserialize)]
+struct DeviceCodeResponse {
+    device_code: String,
+    user_code: String,
+    verification_url: String,
+    expires_in: u64,
+    interval: u64,
+}
+
+/// What to show the user while they approve the app
+#[derive(Serialize, Clone)]
+pub struct DeviceAuthorization {
+    pub user_code: String,
+    pub verification_url: String,
+    /// Seconds until the code stops working
+    pub expires_in: u64,
+}
+
+/// Token endpoint reply; pending and failed sign-ins come back as `error`
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct AuthFinished {
+    account: String,
+    /// None when the account was connected
+    error: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+struct UploadProgress {
+    file_path: String,
+    bytes_done: u64,
+    bytes_total: u64,
+}
+
+fn network_error(context: &str, error: reqwest::Error) -> ClipFlowError {
+    ClipFlowError::Network(format!("{}: {}", context, error))
+}
+
+/// Poll the token endpoint until the user approves or the code expires,
+/// then store the token
+async fn wait_for_approval(
+    app: &AppHandle,
+    account: &str,
+    client_id: &str,
+    client_secret: &str,
+    device: DeviceCodeResponse,
+) -> Result<()> {
+    let client = reqwest::Client::new();
+    let deadline = Instant::now() + Duration::from_secs(device.expires_in);
+    let mut interval = Duration::from_secs(device.interval.max(1));
+    while Instant::now() < deadline {
+        tokio::time::sleep(interval).await;
+        let reply: TokenResponse = client
+            .post(TOKEN_URL)
+            .form(&[
+                ("client_id", client_id),
+                ("client_secret", client_secret),
+                ("device_code", device.device_code.as_str()),
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+            ])
+            .send()
+            .await
+            .map_err(|e| network_error("Failed to reach Google", e))?
+            .json()
+            .await
+            .map_err(|e| network_error("Unexpected reply from Google", e))?;
+        match (reply.access_token, reply.error.as_deref()) {
+            (Some(access_token), _) => {
+                let token = StoredToken {
+                    client_id: client_id.to_string(),
+                    client_secret: client_secret.to_string(),
+                    access_token,
+                    refresh_token: reply.refresh_token.unwrap_or_default(),
+                    expires_at: now_secs() + reply.expires_in.unwrap_or(3600),
+                };
+                let secret = serde_json::to_string(&token).map_err(|e| ClipFlowError::io("Failed to serialize token", e))?;
+                return credentials::add_credential(app.clone(), CredentialProvider::YouTube, account.to_string(), secret).await;
+            }
+            (None, Some("authorization_pending")) => {}
+            (None, Some("slow_down")) => interval += Duration::from_secs(5),
+            (None, error) => {
+                return Err(ClipFlowError::Network(format!(
+                    "YouTube sign-in failed: {}",
+                    reply.error_description.as_deref().or(error).unwrap_or("no token returned")
+                )));
+            }
+        }
+    }
+    Err(ClipFlowError::Network("YouTube sign-in code expired before it was approved".to_string()))
+}
+
+/// A current access token for `account`, refreshed if it's about to expire
+async fn access_token(client: &reqwest::Client, account: &str) -> Result<String> {
+    let secret = credentials::get_secret(CredentialProvider::YouTube, account)?
+        .ok_or_else(|| ClipFlowError::not_found(format!("No YouTube account \"{}\" is connected", account)))?;
+    let mut token: StoredToken = serde_json::from_str(&secret)
+        .map_err(|_| ClipFlowError::invalid("The stored YouTube credential isn't a sign-in token; connect the account again"))?;
+    if token.expires_at > now_secs() + 60 {
+        return Ok(token.access_token);
+    }
+
+    let reply: TokenResponse = client
+        .post(TOKEN_URL)
+        .form(&[
+            ("client_id", token.client_id.as_str()),
+            ("client_secret", token.client_secret.as_str()),
+            ("refresh_token", token.refresh_token.as_str()),
+            ("grant_type", "refresh_token"),
+        ])
+        .send()
+        .await
+        .map_err(|e| network_error("Failed to reach Google", e))?
+        .json()
+        .await
+        .map_err(|e| network_error("Unexpected reply from Google", e))?;
+    let access_token = reply.access_token.ok_or_else(|| {
+        ClipFlowError::Network(format!(
+            "YouTube sign-in expired ({}); connect the account again",
+            reply.error.as_deref().unwrap_or("no token returned")
+        ))
+    })?;
+    token.access_token = access_token.clone();
+    token.expires_at = now_secs() + reply.expires_in.unwrap_or(3600);
+    let secret = serde_json::to_string(&token).map_err(|e| ClipFlowError::io("Failed to serialize token", e))?;
+    credentials::set_secret(CredentialProvider::YouTube, account, &secret)?;
+    Ok(access_token)
+}
+
+/// The access token in a stored YouTube secret, for checking it
+/// Secrets added by hand before device sign-in existed are bare tokens.
+pub fn stored_access_token(secret: &str) -> String {
+    serde_json::from_str::<StoredToken>(secret)
+        .map(|token| token.access_token)
+        .unwrap_or_else(|_| secret.to_string())
+}
+
+fn check_details(details: &VideoDetails) -> Result<()> {
+    let title_chars = details.title.trim().chars().count();
+    if title_chars == 0 || title_chars > MAX_TITLE_CHARS {
+        return Err(ClipFlowError::invalid(format!("Title must be 1-{} characters", MAX_TITLE_CHARS)));
+    }
+    if details.description.len() > MAX_DESCRIPTION_BYTES {
+        return Err(ClipFlowError::invalid(format!("Description is over YouTube's {} byte limit", MAX_DESCRIPTION_BYTES)));
+    }
+    // YouTube rejects angle brackets in titles and descriptions
+    if [&details.title, &details.description].iter().any(|text| text.contains(['<', '>'])) {
+        return Err(ClipFlowError::invalid("Title and description can't contain < or >"));
+    }
+    if details.tags.iter().map(|tag| tag.chars().count() + 1).sum::<usize>() > MAX_TAGS_CHARS {
+        return Err(ClipFlowError::invalid(format!("Tags add up to more than {} characters", MAX_TAGS_CHARS)));
+    }
+    Ok(())
+}
+
+/// One resumable upload: the file, its metadata, and its checkpoint
+struct Upload<'a> {
+    app: &'a AppHandle,
+    client: reqwest::Client,
+    account: &'a str,
+    file_path: &'a str,
+    size: u64,
+    /// Modification time of the file, in nanoseconds since the epoch
+    modified: u64,
+    metadata: serde_json::Value,
+    checkpoint_id: String,
+}
+
+/// Error for a response that's neither progress nor success
+async fn unexpected(response: reqwest::Response) -> NetworkError {
+    match retry::check_response(response).await {
+        Ok(response) => NetworkError::Fatal(format!("Unexpected HTTP {} from YouTube", response.status().as_u16())),
+        Err(error) => error,
+    }
+}
+
+/// Bytes YouTube holds, from a 308's Range header ("bytes=0-1234")
+fn confirmed_bytes(response: &reqwest::Response) -> u64 {
+    response
+        .headers()
+        .get(reqwest::header::RANGE)
+        .and_then(|range| range.to_str().ok())
+        .and_then(|range| range.rsplit('-').next()?.parse::<u64>().ok())
+        .map_or(0, |last| last + 1)
+}
+
+async fn video_id(response: reqwest::Response) -> std::result::Result<String, NetworkError> {
+    let video: serde_json::Value = response.json().await?;
+    video["id"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| NetworkError::Fatal("YouTube didn't return a video id".to_string()))
+}
+
+impl Upload<'_> {
+    fn progress(&self, bytes_done: u64) {
+        let _ = self.app.emit("youtube-upload-progress", UploadProgress {
+            file_path: self.file_path.to_string(),
+            bytes_done,
+            bytes_total: self.size,
+        });
+    }
+
+    fn save_checkpoint(&self, session: &str, bytes_done: u64) -> std::result::Result<(), NetworkError> {
+        TransferCheckpoint {
+            id: self.checkpoint_id.clone(),
+            session: Some(session.to_string()),
+            bytes_done,
+            total_bytes: self.size,
+            extra: serde_json::json!({ "modified": self.modified }),
+            ..Default::default()
+        }
+        .save(self.app)
+        .map_err(|e| NetworkError::Fatal(e.to_string()))
+    }
+
+    /// Fetched for every attempt, since a long upload can outlive the token
+    async fn token(&self) -> std::result::Result<String, NetworkError> {
+        access_token(&self.client, self.account).await.map_err(|e| match e {
+            ClipFlowError::Network(message) => NetworkError::Transient(message),
+            e => NetworkError::Fatal(e.to_string()),
+        })
+    }
+
+    /// The saved upload session, or a new one
+    /// A session saved for an earlier version of the file is dropped, since
+    /// YouTube would splice its bytes together with the new ones.
+    async fn session(&self, token: &str) -> std::result::Result<String, NetworkError> {
+        match TransferCheckpoint::load(self.app, &self.checkpoint_id) {
+            Some(checkpoint)
+                if checkpoint.session.is_some()
+                    && checkpoint.total_bytes == self.size
+                    && checkpoint.extra["modified"].as_u64() == Some(self.modified) =>
+            {
+                return Ok(checkpoint.session.unwrap_or_default());
+            }
+            Some(_) => TransferCheckpoint::clear(self.app, &self.checkpoint_id),
+            None => {}
+        }
+        let response = self
+            .client
+            .post(UPLOAD_URL)
+            .bearer_auth(token)
+            .header("X-Upload-Content-Length", self.size)
+            .header("X-Upload-Content-Type", "video/*")
+            .json(&self.metadata)
+            .send()
+            .await?;
+        let response = retry::check_response(response).await?;
+        let session = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| NetworkError::Fatal("YouTube didn't return an upload session".to_string()))?;
+        self.save_checkpoint(&session, 0)?;
+        Ok(session)
+    }
+
+    /// Send whatever YouTube doesn't have yet, returning the video id
+    async fn send_remaining(&self) -> std::result::Result<String, NetworkError> {
+        let token = self.token().await?;
+        let session = self.session(&token).await?;
+
+        // After a dropped connection YouTube may hold more or less than
+        // was sent, so ask before sending anything
+        let response = self
+            .client
+            .put(&session)
+            .bearer_auth(&token)
+            .header(reqwest::header::CONTENT_RANGE, format!("bytes */{}", self.size))
+            .body(Vec::new())
+            .send()
+            .await?;
+        let mut offset = match response.status().as_u16() {
+            200 | 201 => return video_id(response).await,
+            308 => confirmed_bytes(&response),
+            404 | 410 => {
+                TransferCheckpoint::clear(self.app, &self.checkpoint_id);
+                return Err(NetworkError::Transient("Upload session expired; starting a new one".to_string()));
+            }
+            _ => return Err(unexpected(response).await),
+        };
+
+        let mut file = File::open(self.file_path)
+            .map_err(|e| NetworkError::Fatal(format!("Failed to open {}: {}", self.file_path, e)))?;
+        while offset < self.size {
+            let len = CHUNK_SIZE.min(self.size - offset);
+            let mut chunk = vec![0u8; len as usize];
+            file.seek(SeekFrom::Start(offset))
+                .and_then(|_| file.read_exact(&mut chunk))
+                .map_err(|e| NetworkError::Fatal(format!("Failed to read {}: {}", self.file_path, e)))?;
+            let response = self
+                .client
+                .put(&session)
+                .bearer_auth(&token)
+                .header(reqwest::header::CONTENT_RANGE, format!("bytes {}-{}/{}", offset, offset + len - 1, self.size))
+                .body(chunk)
+                .send()
+                .await?;
+            match response.status().as_u16() {
+                200 | 201 => {
+                    self.progress(self.size);
+                    return video_id(response).await;
+                }
+                308 => {
+                    offset = confirmed_bytes(&response);
+                    self.save_checkpoint(&session, offset)?;
+                    self.progress(offset);
+                }
+                _ => return Err(unexpected(response).await),
+            }
+        }
+        Err(NetworkError::Transient("YouTube has every byte but didn't finish the upload".to_string()))
+    }
+}
+
+/// Upload `file_path` to the YouTube account `account`, returning the
+/// video's URL
+pub async fn upload(app: &AppHandle, file_path: &str, details: &VideoDetails, account: &str) -> Result<String> {
+    check_details(details)?;
+    let metadata = std::fs::metadata(file_path)
+        .map_err(|_| ClipFlowError::not_found(format!("File not found: {}", file_path)))?;
+    let size = metadata.len();
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_nanos() as u64);
+    if size == 0 {
+        return Err(ClipFlowError::invalid(format!("{} is empty", file_path)));
+    }
+
+    // 308 is YouTube's "keep going", not a redirect to follow
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| network_error("Failed to set up HTTP client", e))?;
+    let upload = Upload {
+        app,
+        client,
+        account,
+        file_path,
+        size,
+        modified,
+        metadata: serde_json::json!({
+            "snippet": {
+                "title": details.title.trim(),
+                "description": details.description,
+                "tags": details.tags,
+            },
+            "status": { "privacyStatus": details.privacy },
+        }),
+        checkpoint_id: format!("youtube-{}-{}", account, file_path),
+    };
+    let policy = settings::current().network_retry;
+    let id = retry::with_retry(&policy, "YouTube upload", |_| upload.send_remaining()).await?;
+    TransferCheckpoint::clear(app, &upload.checkpoint_id);
+    Ok(format!("https://youtu.be/{}", id))
+}
+
+/// Connect a YouTube account with Google's device flow
+/// Returns the code for the user to enter at the verification URL; a
+/// `youtube-auth` event follows once they approve it (or it fails).
+/// `client_id` and `client_secret` are a Google OAuth client of type "TVs
+/// and Limited Input devices".
+#[tauri::command]
+pub async fn start_youtube_auth(
+    app: AppHandle,
+    client_id: String,
+    client_secret: String,
+    account: Option<String>,
+) -> Result<DeviceAuthorization> {
+    let account = account.unwrap_or_else(|| DEFAULT_ACCOUNT.to_string());
+    let response = reqwest::Client::new()
+        .post(DEVICE_CODE_URL)
+        .form(&[("client_id", client_id.as_str()), ("scope", UPLOAD_SCOPE)])
+        .send()
+        .await
+        .map_err(|e| network_error("Failed to reach Google", e))?;
+    let device: DeviceCodeResponse = retry::check_response(response)
+        .await
+        .map_err(|e| ClipFlowError::Network(format!("Google refused the sign-in request: {}", e.message())))?
+        .json()
+        .await
+        .map_err(|e| network_error("Unexpected reply from Google", e))?;
+    let authorization = DeviceAuthorization {
+        user_code: device.user_code.clone(),
+        verification_url: device.verification_url.clone(),
+        expires_in: device.expires_in,
+    };
+
+    tauri::async_runtime::spawn(async move {
+        let outcome = wait_for_approval(&app, &account, &client_id, &client_secret, device).await;
+        if let Err(e) = &outcome {
+            tracing::warn!(account = %account, error = %e, "YouTube sign-in failed");
+        }
+        let _ = app.emit("youtube-auth", AuthFinished { account, error: outcome.err().map(|e| e.to_string()) });
+    });
+    Ok(authorization)
+}
+
+/// Upload a video to a connected YouTube account, with
+/// `youtube-upload-progress` events as it goes
+/// Returns the video's URL.
+#[tauri::command]
+pub async fn upload_to_youtube(
+    app: AppHandle,
+    file: String,
+    title: String,
+    description: Option<String>,
+    tags: Option<Vec<String>>,
+    privacy: Privacy,
+    account: Option<String>,
+) -> Result<String> {
+    let details = VideoDetails {
+        title,
+        description: description.unwrap_or_default(),
+        tags: tags.unwrap_or_default(),
+        privacy,
+    };
+    upload(&app, &file, &details, account.as_deref().unwrap_or(DEFAULT_ACCOUNT)).await
+}